@@ -26,3 +26,46 @@ impl Cell {
 
 pub type Board = [[Cell; 9]; 9];
 pub type SolutionBoard = [[u8; 9]; 9];
+
+/// The givens of the top-left `size`×`size` grid as one line, row by row,
+/// with `.` for every other cell. Enough to rebuild the puzzle.
+pub fn givens_string(board: &Board, size: usize) -> String {
+    board[..size]
+        .iter()
+        .flat_map(|row| &row[..size])
+        .map(|cell| match cell {
+            Cell::Given(v) => (b'0' + v) as char,
+            _ => '.',
+        })
+        .collect()
+}
+
+/// Read a 9×9 puzzle written as 81 cells row by row, digits for givens and
+/// `.` or `0` for blanks (the format `givens_string` writes). None if the
+/// text isn't exactly that.
+pub fn parse_givens(text: &str) -> Option<Board> {
+    let text = text.trim();
+    if text.chars().count() != 81 {
+        return None;
+    }
+    let mut board = [[Cell::Empty; 9]; 9];
+    for (i, ch) in text.chars().enumerate() {
+        board[i / 9][i % 9] = match ch {
+            '.' | '0' => Cell::Empty,
+            '1'..='9' => Cell::Given(ch as u8 - b'0'),
+            _ => return None,
+        };
+    }
+    Some(board)
+}
+
+/// Cheap FNV-1a hash of the cell values, used to spot client/server drift.
+/// Givens and player entries with the same value hash the same.
+pub fn board_checksum(board: &Board) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for cell in board.iter().flatten() {
+        hash ^= cell.value().unwrap_or(0) as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
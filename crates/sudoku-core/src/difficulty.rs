@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Ordered easiest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Difficulty {
     Easy,
     Medium,
@@ -27,6 +28,17 @@ impl Difficulty {
         }
     }
 
+    /// A quick-match difficulty that suits a player with this Elo rating.
+    /// New accounts start at 1200, so they begin on Easy.
+    pub fn for_rating(rating: i32) -> Difficulty {
+        match rating {
+            ..1300 => Difficulty::Easy,
+            1300..1600 => Difficulty::Medium,
+            1600..1900 => Difficulty::Hard,
+            _ => Difficulty::Expert,
+        }
+    }
+
     pub fn all() -> &'static [Difficulty] {
         &[
             Difficulty::Easy,
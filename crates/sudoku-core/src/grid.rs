@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+
+/// Shape of a Sudoku grid: `size`×`size` cells split into `box_rows`×`box_cols` boxes.
+/// Smaller variants live in the top-left corner of the usual 9×9 arrays; the
+/// cells outside `size` stay empty and are never looked at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridSpec {
+    pub size: usize,
+    pub box_rows: usize,
+    pub box_cols: usize,
+}
+
+impl Default for GridSpec {
+    fn default() -> Self {
+        GridSpec::CLASSIC
+    }
+}
+
+impl GridSpec {
+    pub const CLASSIC: GridSpec = GridSpec {
+        size: 9,
+        box_rows: 3,
+        box_cols: 3,
+    };
+    pub const SIX: GridSpec = GridSpec {
+        size: 6,
+        box_rows: 2,
+        box_cols: 3,
+    };
+    pub const FOUR: GridSpec = GridSpec {
+        size: 4,
+        box_rows: 2,
+        box_cols: 2,
+    };
+
+    pub fn all() -> &'static [GridSpec] {
+        &[GridSpec::CLASSIC, GridSpec::SIX, GridSpec::FOUR]
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}×{}", self.size, self.size)
+    }
+
+    /// The next variant in `all()`, wrapping around.
+    pub fn next(&self) -> GridSpec {
+        let all = GridSpec::all();
+        let idx = all.iter().position(|s| s == self).unwrap_or(0);
+        all[(idx + 1) % all.len()]
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.size * self.size
+    }
+
+    /// Top-left cell of the box containing (row, col).
+    pub fn box_origin(&self, row: usize, col: usize) -> (usize, usize) {
+        (
+            (row / self.box_rows) * self.box_rows,
+            (col / self.box_cols) * self.box_cols,
+        )
+    }
+
+    /// Every cell in the box containing (row, col).
+    pub fn box_cells(&self, row: usize, col: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (box_r, box_c) = self.box_origin(row, col);
+        let box_cols = self.box_cols;
+        (box_r..box_r + self.box_rows)
+            .flat_map(move |r| (box_c..box_c + box_cols).map(move |c| (r, c)))
+    }
+
+    /// Target number of givens, scaled from the 9×9 ranges by cell count.
+    pub fn givens_range(&self, difficulty: Difficulty) -> (usize, usize) {
+        let (min, max) = difficulty.givens_range();
+        if *self == GridSpec::CLASSIC {
+            return (min, max);
+        }
+        let scale = |n: usize| (n * self.cell_count()).div_ceil(81);
+        (scale(min), scale(max))
+    }
+}
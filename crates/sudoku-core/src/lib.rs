@@ -1,11 +1,14 @@
 pub mod board;
 pub mod difficulty;
 pub mod elo;
+pub mod grid;
 pub mod protocol;
 pub mod puzzle;
+pub mod rating;
 pub mod validation;
 
 pub use board::{Board, Cell, SolutionBoard};
 pub use difficulty::Difficulty;
 pub use elo::calculate_elo;
+pub use grid::GridSpec;
 pub use protocol::{ClientMessage, ServerMessage};
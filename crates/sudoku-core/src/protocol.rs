@@ -1,14 +1,53 @@
 use serde::{Deserialize, Serialize};
 
+use crate::board::SolutionBoard;
 use crate::difficulty::Difficulty;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GameMode {
     Race,
     Shared,
 }
 
-/// Messages sent from client to server
+/// Optional handicap for a private room, applied to the higher-rated player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Handicap {
+    /// Race mode only: the stronger player's board has this many fewer givens
+    FewerGivens { count: u8 },
+    /// The stronger player can't place anything for this long after the start
+    TimePenalty { secs: u32 },
+}
+
+impl Handicap {
+    /// Most givens a `FewerGivens` handicap may take off
+    pub const MAX_FEWER_GIVENS: u8 = 10;
+    /// Longest late start a `TimePenalty` handicap may impose
+    pub const MAX_TIME_PENALTY_SECS: u32 = 120;
+
+    /// Whether the server accepts this handicap for a room
+    pub fn is_within_limits(&self) -> bool {
+        match *self {
+            Handicap::FewerGivens { count } => count <= Self::MAX_FEWER_GIVENS,
+            Handicap::TimePenalty { secs } => secs <= Self::MAX_TIME_PENALTY_SECS,
+        }
+    }
+}
+
+/// How much of a race opponent's progress a room shares
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressGranularity {
+    /// The exact number of cells filled (`OpponentProgress`)
+    #[default]
+    Exact,
+    /// Only which quarter of their empty cells they've filled
+    /// (`OpponentProgressCoarse`), for a game played in fog
+    Quartiles,
+}
+
+/// Messages sent from client to server. `req_id`, where present, is any
+/// number the client picks; the server echoes it on the reply (`RoomCreated`,
+/// `RoomSettingsUpdated`, `MoveAccepted`, `MoveRejected` or `Error`) so it can tell which request
+/// an answer is for.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
@@ -18,9 +57,30 @@ pub enum ClientMessage {
     CreateRoom {
         mode: GameMode,
         difficulty: Difficulty,
+        #[serde(default)]
+        handicap: Option<Handicap>,
+        #[serde(default)]
+        progress: ProgressGranularity,
+        /// A specific puzzle to play, as 81 cells (see `parse_givens`).
+        /// `difficulty` is still used for display and ratings.
+        #[serde(default)]
+        puzzle: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
+    },
+    /// Change the mode and difficulty of the room you created, with a fresh
+    /// puzzle when the difficulty changes. An imported puzzle is kept. Only
+    /// while nobody has joined yet.
+    UpdateRoomSettings {
+        mode: GameMode,
+        difficulty: Difficulty,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     JoinRoom {
         code: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     QuickMatch {
         mode: GameMode,
@@ -30,6 +90,8 @@ pub enum ClientMessage {
         row: usize,
         col: usize,
         value: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     EraseNumber {
         row: usize,
@@ -39,9 +101,38 @@ pub enum ClientMessage {
         row: usize,
         col: usize,
     },
+    /// Shared mode: ask for the authoritative board after a checksum mismatch
+    RequestSync,
+    /// Flag the current puzzle as broken (e.g. more than one solution)
+    ReportPuzzle {
+        reason: String,
+    },
     Forfeit,
-    Rematch,
+    /// Ask for (or accept) another game with the same opponent. `difficulty`
+    /// is the one you'd like, None for the one just played; if the two
+    /// players ask for different ones the easier is used.
+    Rematch {
+        #[serde(default)]
+        difficulty: Option<Difficulty>,
+    },
     Ping,
+    /// Watch a game in progress, read-only
+    Spectate {
+        code: String,
+    },
+    /// Watch some game in progress, favouring the highest rated, without
+    /// needing its code. Answered like `Spectate`, or with an `Error` if
+    /// nothing is being played.
+    SpectateRandom,
+    StopSpectating,
+}
+
+/// How long a player whose connection dropped mid-game has to come back
+/// before they forfeit, unless the server is configured otherwise.
+pub const RECONNECT_GRACE_SECS: u64 = 30;
+
+fn default_grace_secs() -> u64 {
+    RECONNECT_GRACE_SECS
 }
 
 /// Messages sent from server to client
@@ -54,6 +145,15 @@ pub enum ServerMessage {
     },
     RoomCreated {
         code: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
+    },
+    /// The room you're waiting in now has these settings
+    RoomSettingsUpdated {
+        mode: GameMode,
+        difficulty: Difficulty,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     WaitingForOpponent,
     MatchStarted {
@@ -63,18 +163,29 @@ pub enum ServerMessage {
         board: Vec<Vec<u8>>,
         opponent_name: String,
         opponent_rating: i32,
+        /// Room the match is played in, whichever way the players were paired
+        #[serde(default)]
+        code: String,
+        /// The room's handicap, sent only to the player it lands on
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        handicap: Option<Handicap>,
     },
     MoveAccepted {
         row: usize,
         col: usize,
         value: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     MoveRejected {
         row: usize,
         col: usize,
         reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
-    /// Board is full but has incorrect cells — keep playing
+    /// Board is full but has incorrect cells — keep playing. In shared mode
+    /// both players get it, since either may own the wrong cells.
     BoardIncomplete {
         wrong_cells: u32,
     },
@@ -83,6 +194,16 @@ pub enum ServerMessage {
         filled_count: u32,
         momentum: f32,
     },
+    /// Race mode in a `ProgressGranularity::Quartiles` room: which quarter of
+    /// their empty cells the opponent has filled, 0 (under 25%) to 3
+    OpponentProgressCoarse {
+        bucket: u8,
+    },
+    /// Race mode: the opponent has just placed a digit that disagrees with
+    /// the solution, making this many so far
+    OpponentMistakes {
+        count: u32,
+    },
     /// Shared mode: opponent placed a number
     OpponentPlaced {
         row: usize,
@@ -98,17 +219,95 @@ pub enum ServerMessage {
         row: usize,
         col: usize,
     },
+    /// Shared mode: periodic `board_checksum` of the server's shared board
+    BoardChecksum {
+        hash: u64,
+    },
+    /// Shared mode: full board in reply to `RequestSync`.
+    /// `owners` is per cell: 0 = given or empty, 1 = you, 2 = opponent.
+    /// `elapsed_secs` is the match clock by the server's count.
+    BoardSync {
+        board: Vec<Vec<u8>>,
+        owners: Vec<Vec<u8>>,
+        #[serde(default)]
+        elapsed_secs: u64,
+    },
     GameEnd {
         won: bool,
         your_score: u32,
         opponent_score: u32,
         elo_change: i32,
         new_rating: i32,
+        /// The puzzle's solution, to look at afterwards. Only ever sent once
+        /// the game is over, so it can't be used to cheat.
+        #[serde(default)]
+        solution: Option<SolutionBoard>,
+        /// Seed the puzzle was generated from, also kept with the match, so
+        /// `generate_puzzle_seeded_in` rebuilds it exactly. None for a puzzle
+        /// the room's creator brought. Like the solution, only sent once the
+        /// game is over.
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    /// Race mode: both finished boards, sent after `GameEnd` (never mid-game).
+    /// Full values, 0 for empty; givens are whatever the puzzle had.
+    FinalBoards {
+        mine: Vec<Vec<u8>>,
+        opponent: Vec<Vec<u8>>,
+    },
+    /// Your opponent's connection dropped; they forfeit unless they're back
+    /// within `grace_secs`.
+    OpponentDisconnected {
+        #[serde(default = "default_grace_secs")]
+        grace_secs: u64,
     },
-    OpponentDisconnected,
+    /// Your opponent reconnected within the grace period.
     OpponentReconnected,
+    /// The same account connected again elsewhere and took over; the server
+    /// closes this connection right after.
+    SessionReplaced,
+    /// A message from the server's operator to everyone connected, such as
+    /// a warning ahead of a restart
+    Announcement {
+        text: String,
+    },
+    /// Nobody has moved for a while; the room is forfeited when this runs out.
+    /// Any placement resets the countdown.
+    IdleWarning {
+        seconds_remaining: u64,
+    },
+    /// Your opponent asked for a rematch at `difficulty`; send `Rematch` to accept
+    RematchOffered {
+        difficulty: Difficulty,
+    },
+    /// Reply to `Spectate`: the room you're now watching. `board` is givens
+    /// only; a shared-mode room follows it with a `BoardSync` of the moves so
+    /// far, owners 1 and 2 being the first and second of `players`.
+    /// `elapsed_secs` is how long the game has been running.
+    Spectating {
+        code: String,
+        mode: GameMode,
+        difficulty: Difficulty,
+        board: Vec<Vec<u8>>,
+        players: Vec<String>,
+        #[serde(default)]
+        elapsed_secs: u64,
+    },
+    /// To both players whenever someone starts or stops watching their room
+    SpectatorCount {
+        count: u32,
+    },
+    /// To spectators when the game they watch ends, or in reply to `Spectate`
+    /// for a room that finished while they were away. They stop watching.
+    SpectatedGameEnded {
+        code: String,
+        winner: Option<String>,
+    },
     Error {
         message: String,
+        /// The request this error answers, if it answers one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        req_id: Option<u64>,
     },
     Pong,
 }
@@ -121,6 +320,12 @@ pub struct LeaderboardEntry {
     pub rating: i32,
     pub wins: u32,
     pub losses: u32,
+    /// Difficulty and mode of most of their finished matches, the latest
+    /// breaking a tie; None until they've finished one
+    #[serde(default)]
+    pub favorite_difficulty: Option<Difficulty>,
+    #[serde(default)]
+    pub favorite_mode: Option<GameMode>,
 }
 
 /// Player profile returned by REST API
@@ -131,6 +336,87 @@ pub struct PlayerProfile {
     pub rating: i32,
     pub wins: u32,
     pub losses: u32,
+    /// Difficulty and mode of most of their finished matches, the latest
+    /// breaking a tie; None until they've finished one
+    #[serde(default)]
+    pub favorite_difficulty: Option<Difficulty>,
+    #[serde(default)]
+    pub favorite_mode: Option<GameMode>,
+}
+
+/// A player's rating right after one finished match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingPoint {
+    pub timestamp: String,
+    pub rating: i32,
+    /// The match's puzzle seed, if it was generated from one and recorded
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// `puzzle::GENERATOR_VERSION` the seed was drawn for; rebuild it with
+    /// `generate_puzzle_seeded_version_in`
+    #[serde(default = "first_generator")]
+    pub generator: u32,
+}
+
+/// Seeds recorded before the generator was versioned came from the first
+fn first_generator() -> u32 {
+    1
+}
+
+/// Everything two players have played against each other. `a_wins` belongs
+/// to the first name in `GET /h2h/{a}/{b}`, `b_wins` to the second.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadToHead {
+    pub a_wins: u32,
+    pub b_wins: u32,
+    pub games: u32,
+    pub avg_duration_secs: u32,
+}
+
+/// A single-player puzzle finished while logged in, for `POST /solo/record`
+/// so the player's stats follow them between machines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoloResult {
+    pub difficulty: Difficulty,
+    pub secs: u64,
+    pub mistakes: u32,
+    pub hints: u32,
+    /// No mistakes and no hints
+    pub flawless: bool,
+}
+
+/// One difficulty's line in `SoloStats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoloDifficultyStats {
+    pub difficulty: Difficulty,
+    pub solved: u32,
+    pub flawless: u32,
+    pub best_secs: u64,
+    pub avg_secs: u64,
+}
+
+/// `GET /solo/stats`: totals over every result the player has recorded,
+/// with a line per difficulty they've solved, easiest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SoloStats {
+    pub solved: u32,
+    pub flawless: u32,
+    pub by_difficulty: Vec<SoloDifficultyStats>,
+}
+
+/// The authenticated player's own profile and leaderboard position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeResponse {
+    pub profile: PlayerProfile,
+    pub rank: u32,
+}
+
+/// How long a device code stays valid when the server doesn't say (GitHub's default).
+pub const DEVICE_CODE_LIFETIME_SECS: u64 = 900;
+
+/// Serde default for an `expires_in` the sender left out
+pub fn default_device_code_lifetime() -> u64 {
+    DEVICE_CODE_LIFETIME_SECS
 }
 
 /// Device auth flow response
@@ -139,6 +425,9 @@ pub struct DeviceAuthResponse {
     pub user_code: String,
     pub verification_uri: String,
     pub interval: u64,
+    /// Seconds until the code expires.
+    #[serde(default = "default_device_code_lifetime")]
+    pub expires_in: u64,
 }
 
 /// Auth poll response
@@ -149,3 +438,52 @@ pub enum AuthPollResponse {
     Complete { token: String, username: String },
     Expired,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{RngExt, SeedableRng};
+
+    const SAMPLES: &[&str] = &[
+        r#"{"type":"CreateRoom","mode":"Race","difficulty":"Easy","handicap":{"TimePenalty":{"secs":30}},"req_id":1}"#,
+        r#"{"type":"PlaceNumber","row":3,"col":4,"value":5,"req_id":7}"#,
+        r#"{"type":"JoinRoom","code":"ABC123"}"#,
+        r#"{"type":"ReportPuzzle","reason":"two solutions"}"#,
+        r#"{"type":"Rematch","difficulty":null}"#,
+        r#"{"type":"Ping"}"#,
+    ];
+
+    /// The server parses whatever a client sends: garbage, mangled messages
+    /// and deep nesting must come back as errors, never panics.
+    #[test]
+    fn hostile_input_never_panics() {
+        for sample in SAMPLES {
+            serde_json::from_str::<ClientMessage>(sample).expect(sample);
+        }
+
+        let mut rng = StdRng::seed_from_u64(1947);
+        for _ in 0..20_000 {
+            let len = rng.random_range(0..64);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+            let _ = serde_json::from_slice::<ClientMessage>(&bytes);
+
+            let mut mangled = SAMPLES[rng.random_range(0..SAMPLES.len())].as_bytes().to_vec();
+            for _ in 0..rng.random_range(1..4) {
+                let i = rng.random_range(0..mangled.len());
+                match rng.random_range(0..3) {
+                    0 => mangled[i] = rng.random(),
+                    1 => mangled.truncate(i),
+                    _ => mangled.insert(i, b"{}[]\":,0-e\\"[rng.random_range(0..11)]),
+                }
+                if mangled.is_empty() {
+                    break;
+                }
+            }
+            let _ = serde_json::from_slice::<ClientMessage>(&mangled);
+        }
+
+        let deep = format!(r#"{{"type":"Ping","x":{}{}}}"#, "[".repeat(100_000), "]".repeat(100_000));
+        assert!(serde_json::from_str::<ClientMessage>(&deep).is_err());
+    }
+}
@@ -1,29 +1,34 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::rng;
-use rand::RngExt;
+use rand::{Rng, RngExt, SeedableRng};
 
 use crate::board::{Board, Cell, SolutionBoard};
 use crate::difficulty::Difficulty;
+use crate::grid::GridSpec;
+use crate::rating::{solve_logically, Technique};
 
 /// Check if placing `val` at (row, col) is valid on a raw u8 grid
-fn is_valid_placement(grid: &[[u8; 9]; 9], row: usize, col: usize, val: u8) -> bool {
-    for c in 0..9 {
+fn is_valid_placement(
+    spec: &GridSpec,
+    grid: &[[u8; 9]; 9],
+    row: usize,
+    col: usize,
+    val: u8,
+) -> bool {
+    for c in 0..spec.size {
         if grid[row][c] == val {
             return false;
         }
     }
-    for r in 0..9 {
+    for r in 0..spec.size {
         if grid[r][col] == val {
             return false;
         }
     }
-    let box_r = (row / 3) * 3;
-    let box_c = (col / 3) * 3;
-    for r in box_r..box_r + 3 {
-        for c in box_c..box_c + 3 {
-            if grid[r][c] == val {
-                return false;
-            }
+    for (r, c) in spec.box_cells(row, col) {
+        if grid[r][c] == val {
+            return false;
         }
     }
     true
@@ -31,13 +36,18 @@ fn is_valid_placement(grid: &[[u8; 9]; 9], row: usize, col: usize, val: u8) -> b
 
 /// Solve the grid in place using backtracking. Returns true if solved.
 pub fn solve(grid: &mut [[u8; 9]; 9]) -> bool {
-    for row in 0..9 {
-        for col in 0..9 {
+    solve_in(&GridSpec::CLASSIC, grid)
+}
+
+/// `solve` for any grid shape
+pub fn solve_in(spec: &GridSpec, grid: &mut [[u8; 9]; 9]) -> bool {
+    for row in 0..spec.size {
+        for col in 0..spec.size {
             if grid[row][col] == 0 {
-                for val in 1..=9 {
-                    if is_valid_placement(grid, row, col, val) {
+                for val in 1..=spec.size as u8 {
+                    if is_valid_placement(spec, grid, row, col, val) {
                         grid[row][col] = val;
-                        if solve(grid) {
+                        if solve_in(spec, grid) {
                             return true;
                         }
                         grid[row][col] = 0;
@@ -51,39 +61,38 @@ pub fn solve(grid: &mut [[u8; 9]; 9]) -> bool {
 }
 
 /// Generate a complete valid Sudoku board
-fn generate_complete_board() -> [[u8; 9]; 9] {
+fn generate_complete_board<R: Rng + ?Sized>(spec: &GridSpec, rng: &mut R) -> [[u8; 9]; 9] {
     let mut grid = [[0u8; 9]; 9];
-    let mut rng = rng();
 
-    for box_idx in 0..3 {
-        let mut nums: Vec<u8> = (1..=9).collect();
-        nums.shuffle(&mut rng);
-        let start = box_idx * 3;
-        let mut idx = 0;
-        for r in start..start + 3 {
-            for c in start..start + 3 {
+    // The three diagonal boxes don't constrain each other, so they can be
+    // filled at random up front. Small grids are quick to fill from scratch,
+    // and a random diagonal there can leave the rest unsolvable.
+    if *spec == GridSpec::CLASSIC {
+        for box_idx in 0..spec.size / spec.box_rows {
+            let mut nums: Vec<u8> = (1..=spec.size as u8).collect();
+            nums.shuffle(rng);
+            let start = box_idx * spec.box_rows;
+            for (idx, (r, c)) in spec.box_cells(start, start).enumerate() {
                 grid[r][c] = nums[idx];
-                idx += 1;
             }
         }
     }
 
-    solve_shuffled(&mut grid);
+    solve_shuffled(spec, &mut grid, rng);
     grid
 }
 
 /// Solve with randomized value ordering for variety
-fn solve_shuffled(grid: &mut [[u8; 9]; 9]) -> bool {
-    let mut rng = rng();
-    for row in 0..9 {
-        for col in 0..9 {
+fn solve_shuffled<R: Rng + ?Sized>(spec: &GridSpec, grid: &mut [[u8; 9]; 9], rng: &mut R) -> bool {
+    for row in 0..spec.size {
+        for col in 0..spec.size {
             if grid[row][col] == 0 {
-                let mut vals: Vec<u8> = (1..=9).collect();
-                vals.shuffle(&mut rng);
+                let mut vals: Vec<u8> = (1..=spec.size as u8).collect();
+                vals.shuffle(rng);
                 for val in vals {
-                    if is_valid_placement(grid, row, col, val) {
+                    if is_valid_placement(spec, grid, row, col, val) {
                         grid[row][col] = val;
-                        if solve_shuffled(grid) {
+                        if solve_shuffled(spec, grid, rng) {
                             return true;
                         }
                         grid[row][col] = 0;
@@ -97,19 +106,19 @@ fn solve_shuffled(grid: &mut [[u8; 9]; 9]) -> bool {
 }
 
 /// Count solutions (up to limit) for uniqueness checking
-fn count_solutions(grid: &mut [[u8; 9]; 9], limit: usize) -> usize {
+fn count_solutions(spec: &GridSpec, grid: &mut [[u8; 9]; 9], limit: usize) -> usize {
     if limit == 0 {
         return 0;
     }
 
-    for row in 0..9 {
-        for col in 0..9 {
+    for row in 0..spec.size {
+        for col in 0..spec.size {
             if grid[row][col] == 0 {
                 let mut count = 0;
-                for val in 1..=9 {
-                    if is_valid_placement(grid, row, col, val) {
+                for val in 1..=spec.size as u8 {
+                    if is_valid_placement(spec, grid, row, col, val) {
                         grid[row][col] = val;
-                        count += count_solutions(grid, limit - count);
+                        count += count_solutions(spec, grid, limit - count);
                         grid[row][col] = 0;
                         if count >= limit {
                             return count;
@@ -123,24 +132,212 @@ fn count_solutions(grid: &mut [[u8; 9]; 9], limit: usize) -> usize {
     1
 }
 
+/// Removal passes tried when one gets stuck above the difficulty's givens range
+const GIVENS_ATTEMPTS: usize = 3;
+
+/// Fewest givens a 9×9 puzzle can have and still solve one way only
+pub const MIN_GIVENS: usize = 17;
+
+/// Most givens a 9×9 puzzle can have and still leave a cell to fill
+pub const MAX_GIVENS: usize = 80;
+
+/// Puzzles generated looking for one whose rating matches the difficulty
+/// asked for, before settling for the last
+const RATING_ATTEMPTS: usize = 20;
+
+/// What a guess counts for in `DifficultyScore::score`, against 1 for a
+/// hidden single
+const GUESS_WEIGHT: usize = 3;
+
+/// How hard a 9×9 puzzle is to solve, from `rate_difficulty`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DifficultyScore {
+    /// Deductions needed beyond naked singles: 1 for each hidden single and
+    /// `GUESS_WEIGHT` for each guess
+    pub score: usize,
+    /// The difficulty that score suits
+    pub difficulty: Difficulty,
+}
+
+/// Rate a 9×9 puzzle by solving it the way a person would, taking naked
+/// then hidden singles and guessing only when neither applies. Two puzzles
+/// with the same number of givens can rate far apart. None if the puzzle has
+/// no solution to rate against.
+pub fn rate_difficulty(board: &Board) -> Option<DifficultyScore> {
+    let mut solution = board.map(|row| row.map(|cell| cell.value().unwrap_or(0)));
+    solve(&mut solution).then(|| rate_against(board, &solution))
+}
+
+fn rate_against(board: &Board, solution: &SolutionBoard) -> DifficultyScore {
+    let trace = solve_logically(board, solution);
+    let score = trace.count(Technique::HiddenSingle) + GUESS_WEIGHT * trace.count(Technique::Guess);
+    let difficulty = match score {
+        0..=1 => Difficulty::Easy,
+        2..=6 => Difficulty::Medium,
+        7..=14 => Difficulty::Hard,
+        _ => Difficulty::Expert,
+    };
+    DifficultyScore { score, difficulty }
+}
+
+/// Revision of the seeded generator. A seed only rebuilds its puzzle under
+/// the revision it was drawn for, so stored seeds keep it alongside. 1 dug
+/// to a givens count alone; 2 also regenerates puzzles rating off-tier.
+pub const GENERATOR_VERSION: u32 = 2;
+
+/// Random solo seeds stay below this, short enough to read out or type
+pub const SEED_LIMIT: u64 = 100_000_000;
+
+/// A fresh seed for `generate_puzzle_seeded_in`, under `SEED_LIMIT`
+pub fn random_seed() -> u64 {
+    rng().random_range(..SEED_LIMIT)
+}
+
 /// Generate a puzzle with the given difficulty
 pub fn generate_puzzle(difficulty: Difficulty) -> (Board, SolutionBoard) {
-    let solution = generate_complete_board();
-    let mut rng = rng();
+    generate_puzzle_seeded(difficulty, random_seed())
+}
+
+/// `generate_puzzle` from a seed, as `generate_puzzle_seeded_in`
+pub fn generate_puzzle_seeded(difficulty: Difficulty, seed: u64) -> (Board, SolutionBoard) {
+    generate_puzzle_seeded_in(&GridSpec::CLASSIC, difficulty, seed)
+}
+
+/// `generate_puzzle` for any grid shape. Cells outside the grid are left
+/// empty in the board and 0 in the solution.
+pub fn generate_puzzle_in(spec: &GridSpec, difficulty: Difficulty) -> (Board, SolutionBoard) {
+    let (board, solution, _) = generate_puzzle_counted_in(spec, difficulty);
+    (board, solution)
+}
+
+/// `generate_puzzle_in`, also returning how many givens the puzzle ended up
+/// with. Removal stops once a cell can't go without losing uniqueness, so a
+/// pass can fall short of its target; when it lands above the difficulty's
+/// range the solution is dug again, keeping the pass with the fewest givens.
+pub fn generate_puzzle_counted_in(
+    spec: &GridSpec,
+    difficulty: Difficulty,
+) -> (Board, SolutionBoard, usize) {
+    generate_rated(spec, difficulty, &mut rng())
+}
+
+/// `generate_puzzle_in` from a seed: the same seed, grid and difficulty
+/// always give the same puzzle, so a board can be shared as a number.
+pub fn generate_puzzle_seeded_in(
+    spec: &GridSpec,
+    difficulty: Difficulty,
+    seed: u64,
+) -> (Board, SolutionBoard) {
+    generate_puzzle_seeded_version_in(GENERATOR_VERSION, spec, difficulty, seed)
+}
+
+/// `generate_puzzle_seeded_in` as generator `version` had it, to rebuild a
+/// puzzle from a seed stored under an earlier `GENERATOR_VERSION`
+pub fn generate_puzzle_seeded_version_in(
+    version: u32,
+    spec: &GridSpec,
+    difficulty: Difficulty,
+    seed: u64,
+) -> (Board, SolutionBoard) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (board, solution, _) = if version < 2 {
+        generate_with(spec, spec.givens_range(difficulty), &mut rng)
+    } else {
+        generate_rated(spec, difficulty, &mut rng)
+    };
+    (board, solution)
+}
+
+/// A 9×9 puzzle dug down to `count` givens, clamped to
+/// `MIN_GIVENS..=MAX_GIVENS`, in place of a difficulty's range. Best effort:
+/// uniqueness can stop the digging short, most often below the mid-20s, and
+/// then the pass with the fewest givens is kept. Returns how many it got.
+pub fn generate_puzzle_with_givens(count: usize) -> (Board, SolutionBoard, usize) {
+    generate_puzzle_with_givens_seeded(count, rng().random())
+}
+
+/// `generate_puzzle_with_givens` from a seed, as `generate_puzzle_seeded_in`
+pub fn generate_puzzle_with_givens_seeded(
+    count: usize,
+    seed: u64,
+) -> (Board, SolutionBoard, usize) {
+    let count = count.clamp(MIN_GIVENS, MAX_GIVENS);
+    generate_with(&GridSpec::CLASSIC, (count, count), &mut StdRng::seed_from_u64(seed))
+}
+
+/// A puzzle in `difficulty`'s givens range that also rates as `difficulty`,
+/// trying up to `RATING_ATTEMPTS` times. Only 9×9 puzzles are rated.
+fn generate_rated<R: Rng + ?Sized>(
+    spec: &GridSpec,
+    difficulty: Difficulty,
+    rng: &mut R,
+) -> (Board, SolutionBoard, usize) {
+    let range = spec.givens_range(difficulty);
+    let mut puzzle = generate_with(spec, range, rng);
+    if *spec != GridSpec::CLASSIC {
+        return puzzle;
+    }
+    for _ in 1..RATING_ATTEMPTS {
+        if rate_against(&puzzle.0, &puzzle.1).difficulty == difficulty {
+            break;
+        }
+        puzzle = generate_with(spec, range, rng);
+    }
+    puzzle
+}
 
-    let (min_givens, max_givens) = difficulty.givens_range();
-    let target_givens = rng.random_range(min_givens..=max_givens);
-    let cells_to_remove = 81 - target_givens;
+fn generate_with<R: Rng + ?Sized>(
+    spec: &GridSpec,
+    (min_givens, max_givens): (usize, usize),
+    rng: &mut R,
+) -> (Board, SolutionBoard, usize) {
+    let solution = generate_complete_board(spec, rng);
 
-    let mut positions: Vec<(usize, usize)> = Vec::with_capacity(81);
+    let mut best: Option<([[u8; 9]; 9], usize)> = None;
+    for _ in 0..GIVENS_ATTEMPTS {
+        let target_givens = rng.random_range(min_givens..=max_givens);
+        let grid = dig_holes(spec, &solution, target_givens, rng);
+        let givens = grid.iter().flatten().filter(|&&v| v != 0).count();
+        if best.is_none_or(|(_, fewest)| givens < fewest) {
+            best = Some((grid, givens));
+        }
+        if givens <= max_givens {
+            break;
+        }
+    }
+    let (puzzle_grid, givens) = best.expect("at least one attempt");
+
+    let mut board = [[Cell::Empty; 9]; 9];
     for r in 0..9 {
         for c in 0..9 {
+            if puzzle_grid[r][c] != 0 {
+                board[r][c] = Cell::Given(puzzle_grid[r][c]);
+            }
+        }
+    }
+
+    (board, solution, givens)
+}
+
+/// Empty cells of `solution` in random order until `target_givens` remain,
+/// skipping any whose removal would allow a second solution.
+fn dig_holes<R: Rng + ?Sized>(
+    spec: &GridSpec,
+    solution: &[[u8; 9]; 9],
+    target_givens: usize,
+    rng: &mut R,
+) -> [[u8; 9]; 9] {
+    let cells_to_remove = spec.cell_count() - target_givens;
+
+    let mut positions: Vec<(usize, usize)> = Vec::with_capacity(spec.cell_count());
+    for r in 0..spec.size {
+        for c in 0..spec.size {
             positions.push((r, c));
         }
     }
-    positions.shuffle(&mut rng);
+    positions.shuffle(rng);
 
-    let mut puzzle_grid = solution;
+    let mut puzzle_grid = *solution;
     let mut removed = 0;
 
     for (r, c) in positions {
@@ -151,21 +348,201 @@ pub fn generate_puzzle(difficulty: Difficulty) -> (Board, SolutionBoard) {
         puzzle_grid[r][c] = 0;
 
         let mut test_grid = puzzle_grid;
-        if count_solutions(&mut test_grid, 2) == 1 {
+        if count_solutions(spec, &mut test_grid, 2) == 1 {
             removed += 1;
         } else {
             puzzle_grid[r][c] = backup;
         }
     }
+    puzzle_grid
+}
 
-    let mut board = [[Cell::Empty; 9]; 9];
+/// The solution of a 9×9 puzzle given from outside (an import), or None if
+/// its givens clash or it has no solution or more than one.
+pub fn unique_solution(board: &Board) -> Option<SolutionBoard> {
+    let spec = GridSpec::CLASSIC;
+    let mut grid = [[0u8; 9]; 9];
     for r in 0..9 {
         for c in 0..9 {
-            if puzzle_grid[r][c] != 0 {
-                board[r][c] = Cell::Given(puzzle_grid[r][c]);
+            let v = board[r][c].value().unwrap_or(0);
+            if v != 0 && !is_valid_placement(&spec, &grid, r, c, v) {
+                return None;
             }
+            grid[r][c] = v;
         }
     }
+    if count_solutions(&spec, &mut grid.clone(), 2) != 1 {
+        return None;
+    }
+    solve_in(&spec, &mut grid).then_some(grid)
+}
 
-    (board, solution)
+/// Take up to `count` more givens off a puzzle, only removing a cell when the
+/// puzzle still has a unique solution. May remove fewer if no more can go.
+pub fn remove_givens(board: &Board, count: usize) -> Board {
+    let mut rng = rng();
+    let mut grid = [[0u8; 9]; 9];
+    let mut positions: Vec<(usize, usize)> = Vec::new();
+    for r in 0..9 {
+        for c in 0..9 {
+            if let Cell::Given(v) = board[r][c] {
+                grid[r][c] = v;
+                positions.push((r, c));
+            }
+        }
+    }
+    positions.shuffle(&mut rng);
+
+    let mut result = *board;
+    let mut removed = 0;
+    for (r, c) in positions {
+        if removed >= count {
+            break;
+        }
+        let backup = grid[r][c];
+        grid[r][c] = 0;
+
+        let mut test_grid = grid;
+        if count_solutions(&GridSpec::CLASSIC, &mut test_grid, 2) == 1 {
+            result[r][c] = Cell::Empty;
+            removed += 1;
+        } else {
+            grid[r][c] = backup;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::is_board_complete_in;
+
+    #[test]
+    fn small_grids_generate_unique_puzzles() {
+        for spec in [GridSpec::FOUR, GridSpec::SIX] {
+            let (board, solution) = generate_puzzle_in(&spec, Difficulty::Medium);
+
+            let mut solved = board;
+            for r in 0..spec.size {
+                for c in 0..spec.size {
+                    solved[r][c] = Cell::Given(solution[r][c]);
+                }
+            }
+            assert!(is_board_complete_in(&spec, &solved));
+
+            let mut grid = [[0u8; 9]; 9];
+            for r in 0..9 {
+                for c in 0..9 {
+                    grid[r][c] = board[r][c].value().unwrap_or(0);
+                    if r >= spec.size || c >= spec.size {
+                        assert_eq!(grid[r][c], 0);
+                        assert_eq!(solution[r][c], 0);
+                    }
+                }
+            }
+            assert_eq!(count_solutions(&spec, &mut grid, 2), 1);
+        }
+    }
+
+    #[test]
+    fn seeds_reproduce_their_puzzle() {
+        let (board, solution) = generate_puzzle_seeded(Difficulty::Medium, 12345);
+        assert_eq!(generate_puzzle_seeded(Difficulty::Medium, 12345), (board, solution));
+        assert_ne!(generate_puzzle_seeded(Difficulty::Medium, 12346).1, solution);
+    }
+
+    #[test]
+    fn easy_puzzles_rate_below_expert_ones() {
+        use crate::board::parse_givens;
+
+        // Seed 1's Easy and Expert puzzles
+        let easy = "1.6...8.985.........986.5..361...48..84..1.7.927..8..67321.4698..82..75.6.53871.4";
+        let expert = "1.6.....9.5.........986....3.1...48......1.7.........67..1.4.....82...5.6..3.71..";
+        let easy = rate_difficulty(&parse_givens(easy).unwrap());
+        let expert = rate_difficulty(&parse_givens(expert).unwrap());
+        assert_eq!(easy, Some(DifficultyScore { score: 0, difficulty: Difficulty::Easy }));
+        assert_eq!(expert, Some(DifficultyScore { score: 21, difficulty: Difficulty::Expert }));
+
+        let (board, _) = generate_puzzle_seeded(Difficulty::Medium, 4);
+        assert_eq!(rate_difficulty(&board).unwrap().difficulty, Difficulty::Medium);
+
+        // The top-left cell sees every digit, so there's no solution to rate
+        let broken = format!(".123456789{}", ".".repeat(71));
+        assert_eq!(rate_difficulty(&parse_givens(&broken).unwrap()), None);
+    }
+
+    #[test]
+    fn seeds_from_before_rating_still_rebuild_their_puzzle() {
+        use crate::board::givens_string;
+
+        // Stored by generator version 1, before puzzles were rated
+        let pinned = [
+            (1972, "..26839...1.4...8.9.8.....418.5..4..4....6...67..1.3.....39.8.....1..537...7....."),
+            (13, "7..3...6....8.29133.26....54...2....18...5...2.....3....3261.84.2.....3.54......1"),
+        ];
+        for (seed, givens) in pinned {
+            let (board, _) =
+                generate_puzzle_seeded_version_in(1, &GridSpec::CLASSIC, Difficulty::Hard, seed);
+            assert_eq!(givens_string(&board, 9), givens);
+        }
+        // Rating turned that first dig down, so the current version differs
+        let (board, _) = generate_puzzle_seeded_in(&GridSpec::CLASSIC, Difficulty::Hard, 13);
+        assert_ne!(givens_string(&board, 9), pinned[1].1);
+    }
+
+    #[test]
+    fn imported_puzzles_need_exactly_one_solution() {
+        use crate::board::{givens_string, parse_givens};
+
+        let (board, solution) = generate_puzzle(Difficulty::Easy);
+        let text = givens_string(&board, 9);
+        assert_eq!(parse_givens(&text), Some(board));
+        assert_eq!(unique_solution(&board), Some(solution));
+
+        assert_eq!(unique_solution(&[[Cell::Empty; 9]; 9]), None);
+        let clash = format!("11{}", &text[2..]);
+        assert_eq!(unique_solution(&parse_givens(&clash).unwrap()), None);
+        assert_eq!(parse_givens(&text[1..]), None);
+    }
+
+    #[test]
+    fn achieved_givens_land_in_each_difficulty_band() {
+        for &difficulty in Difficulty::all() {
+            // Expert is slow to dig in debug builds
+            let samples = if difficulty == Difficulty::Expert { 3 } else { 10 };
+            let (min_givens, max_givens) = difficulty.givens_range();
+            let mut total = 0;
+            for _ in 0..samples {
+                let (board, _, givens) = generate_puzzle_counted_in(&GridSpec::CLASSIC, difficulty);
+                assert_eq!(givens, board.iter().flatten().filter(|c| c.value().is_some()).count());
+                total += givens;
+            }
+            let mean = total as f64 / samples as f64;
+            assert!(
+                (min_givens as f64..=max_givens as f64).contains(&mean),
+                "{} averaged {:.1} givens, outside {}..={}",
+                difficulty.label(),
+                mean,
+                min_givens,
+                max_givens
+            );
+        }
+    }
+
+    #[test]
+    fn custom_givens_are_hit_and_clamped() {
+        let (board, solution, givens) = generate_puzzle_with_givens(30);
+        assert_eq!(givens, 30);
+        assert_eq!(board.iter().flatten().filter(|c| c.value().is_some()).count(), 30);
+        assert_eq!(unique_solution(&board), Some(solution));
+
+        let (_, _, givens) = generate_puzzle_with_givens(200);
+        assert_eq!(givens, MAX_GIVENS);
+        assert_eq!(
+            generate_puzzle_with_givens_seeded(45, 7),
+            generate_puzzle_with_givens_seeded(45, 7)
+        );
+    }
 }
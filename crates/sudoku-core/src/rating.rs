@@ -0,0 +1,285 @@
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+
+use crate::board::{Board, Cell, SolutionBoard};
+use crate::difficulty::Difficulty;
+use crate::grid::GridSpec;
+use crate::puzzle::{generate_puzzle, generate_puzzle_seeded_in, SEED_LIMIT};
+use crate::validation::get_candidates;
+
+/// Puzzles `generate_interesting` picks among
+pub const INTERESTING_CANDIDATES: usize = 8;
+
+/// Deductions the logical solver knows, easiest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    /// Nothing logical applied; the solver copied a value from the solution
+    Guess,
+}
+
+impl Technique {
+    pub fn all() -> &'static [Technique] {
+        &[Technique::NakedSingle, Technique::HiddenSingle, Technique::Guess]
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Technique::NakedSingle => "Naked Single",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::Guess => "Guess",
+        }
+    }
+
+    /// 1 for the simplest technique, rising with each harder one
+    pub fn depth(&self) -> u8 {
+        match self {
+            Technique::NakedSingle => 1,
+            Technique::HiddenSingle => 2,
+            Technique::Guess => 3,
+        }
+    }
+}
+
+/// The technique used for each placement, in solving order
+#[derive(Clone, Debug, Default)]
+pub struct SolveTrace {
+    pub steps: Vec<Technique>,
+}
+
+impl SolveTrace {
+    /// The hardest technique needed, or None for an already-full board
+    pub fn hardest(&self) -> Option<Technique> {
+        self.steps.iter().copied().max()
+    }
+
+    pub fn count(&self, technique: Technique) -> usize {
+        self.steps.iter().filter(|&&t| t == technique).count()
+    }
+
+    pub fn needs_guessing(&self) -> bool {
+        self.steps.contains(&Technique::Guess)
+    }
+}
+
+/// Solve a 9×9 puzzle the way a person would: always take the easiest
+/// available deduction, and only when none applies fall back to a guess on
+/// the cell with the fewest candidates (using `solution`).
+pub fn solve_logically(board: &Board, solution: &SolutionBoard) -> SolveTrace {
+    let mut board = *board;
+    let mut trace = SolveTrace::default();
+    loop {
+        let step = find_naked_single(&board)
+            .map(|p| (p, Technique::NakedSingle))
+            .or_else(|| find_hidden_single(&board).map(|p| (p, Technique::HiddenSingle)))
+            .or_else(|| {
+                find_guess_cell(&board).map(|(r, c)| ((r, c, solution[r][c]), Technique::Guess))
+            });
+        let Some(((r, c, v), technique)) = step else {
+            return trace;
+        };
+        board[r][c] = Cell::UserInput(v);
+        trace.steps.push(technique);
+    }
+}
+
+fn find_naked_single(board: &Board) -> Option<(usize, usize, u8)> {
+    (0..81).map(|i| (i / 9, i % 9)).find_map(|(r, c)| {
+        if board[r][c].value().is_some() {
+            return None;
+        }
+        match get_candidates(board, r, c)[..] {
+            [v] => Some((r, c, v)),
+            _ => None,
+        }
+    })
+}
+
+fn find_hidden_single(board: &Board) -> Option<(usize, usize, u8)> {
+    let spec = GridSpec::CLASSIC;
+    let mut units: Vec<Vec<(usize, usize)>> = Vec::with_capacity(27);
+    for i in 0..9 {
+        units.push((0..9).map(|c| (i, c)).collect());
+        units.push((0..9).map(|r| (r, i)).collect());
+        units.push(spec.box_cells((i / 3) * 3, (i % 3) * 3).collect());
+    }
+    for unit in &units {
+        for v in 1..=9u8 {
+            let mut spots = unit
+                .iter()
+                .filter(|&&(r, c)| board[r][c].value().is_none())
+                .filter(|&&(r, c)| get_candidates(board, r, c).contains(&v));
+            if let (Some(&(r, c)), None) = (spots.next(), spots.next()) {
+                return Some((r, c, v));
+            }
+        }
+    }
+    None
+}
+
+fn find_guess_cell(board: &Board) -> Option<(usize, usize)> {
+    (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| board[r][c].value().is_none())
+        .min_by_key(|&(r, c)| get_candidates(board, r, c).len())
+}
+
+/// How much a solve has going on, for picking a puzzle worth remembering:
+/// mostly how many techniques it needs, then how many hidden singles, the
+/// less obvious kind. None if it can't be finished without guessing.
+pub fn interestingness(trace: &SolveTrace) -> Option<usize> {
+    if trace.needs_guessing() {
+        return None;
+    }
+    let techniques = Technique::all().iter().filter(|&&t| trace.count(t) > 0).count();
+    Some(techniques * 100 + trace.count(Technique::HiddenSingle))
+}
+
+/// A 9×9 puzzle at `difficulty` meant to be memorable, such as a puzzle of
+/// the day: of `INTERESTING_CANDIDATES` drawn from `seed`, the one scoring
+/// highest on `interestingness`. Returns the seed it was generated from, to
+/// share or replay with `generate_puzzle_seeded_in`. The same seed always
+/// gives the same pick; if every candidate needs guessing, the first is kept.
+pub fn generate_interesting(difficulty: Difficulty, seed: u64) -> (Board, SolutionBoard, u64) {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut best: Option<(Option<usize>, (Board, SolutionBoard, u64))> = None;
+    for _ in 0..INTERESTING_CANDIDATES {
+        let seed = seeds.random_range(..SEED_LIMIT);
+        let (board, solution) = generate_puzzle_seeded_in(&GridSpec::CLASSIC, difficulty, seed);
+        let score = interestingness(&solve_logically(&board, &solution));
+        if best.as_ref().is_none_or(|(top, _)| score > *top) {
+            best = Some((score, (board, solution, seed)));
+        }
+    }
+    best.expect("at least one candidate").1
+}
+
+/// How hard a batch of generated puzzles at one difficulty turned out to be.
+/// Depth is `Technique::depth` of the hardest technique each puzzle needed.
+#[derive(Clone, Debug)]
+pub struct DifficultyReport {
+    pub difficulty: Difficulty,
+    pub samples: usize,
+    /// For each technique, how many puzzles needed it at least once
+    pub technique_usage: Vec<(Technique, usize)>,
+    pub min_depth: u8,
+    pub max_depth: u8,
+    pub mean_depth: f64,
+    /// Placements made while solving, guesses included
+    pub min_steps: usize,
+    pub max_steps: usize,
+    pub mean_steps: f64,
+}
+
+impl DifficultyReport {
+    /// Puzzles that could not be finished without guessing
+    pub fn needing_guess(&self) -> usize {
+        self.technique_usage
+            .iter()
+            .find(|(t, _)| *t == Technique::Guess)
+            .map_or(0, |&(_, n)| n)
+    }
+}
+
+impl fmt::Display for DifficultyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} puzzles): depth {}..{} (mean {:.2}), steps {}..{} (mean {:.1})",
+            self.difficulty.label(),
+            self.samples,
+            self.min_depth,
+            self.max_depth,
+            self.mean_depth,
+            self.min_steps,
+            self.max_steps,
+            self.mean_steps
+        )?;
+        for (technique, n) in &self.technique_usage {
+            write!(f, ", {} {}", technique.label(), n)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate `samples` puzzles at `difficulty` and solve each logically, for
+/// tuning the generator. Slow: every sample is a full generate + solve.
+pub fn analyze_difficulty(difficulty: Difficulty, samples: usize) -> DifficultyReport {
+    let traces: Vec<SolveTrace> = (0..samples)
+        .map(|_| {
+            let (board, solution) = generate_puzzle(difficulty);
+            solve_logically(&board, &solution)
+        })
+        .collect();
+
+    let depths: Vec<u8> = traces
+        .iter()
+        .map(|t| t.hardest().map_or(0, |h| h.depth()))
+        .collect();
+    let steps: Vec<usize> = traces.iter().map(|t| t.steps.len()).collect();
+    let mean = |total: f64| if samples == 0 { 0.0 } else { total / samples as f64 };
+
+    DifficultyReport {
+        difficulty,
+        samples,
+        technique_usage: Technique::all()
+            .iter()
+            .map(|&t| (t, traces.iter().filter(|tr| tr.count(t) > 0).count()))
+            .collect(),
+        min_depth: depths.iter().copied().min().unwrap_or(0),
+        max_depth: depths.iter().copied().max().unwrap_or(0),
+        mean_depth: mean(depths.iter().map(|&d| d as f64).sum()),
+        min_steps: steps.iter().copied().min().unwrap_or(0),
+        max_steps: steps.iter().copied().max().unwrap_or(0),
+        mean_steps: mean(steps.iter().sum::<usize>() as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_solve_fills_every_empty_cell() {
+        let (board, solution) = generate_puzzle(Difficulty::Medium);
+        let empties = board.iter().flatten().filter(|c| c.is_empty()).count();
+        let trace = solve_logically(&board, &solution);
+        assert_eq!(trace.steps.len(), empties);
+        assert!(trace.hardest().is_some());
+    }
+
+    #[test]
+    fn easy_puzzles_never_need_guessing() {
+        let report = analyze_difficulty(Difficulty::Easy, 5);
+        assert_eq!(report.samples, 5);
+        assert_eq!(report.needing_guess(), 0);
+        assert!(report.max_depth < Technique::Guess.depth());
+    }
+
+    #[test]
+    fn interesting_picks_replay_from_their_seed_and_solve_logically() {
+        let (board, solution, seed) = generate_interesting(Difficulty::Medium, 12);
+        assert_eq!(generate_interesting(Difficulty::Medium, 12).0, board);
+        assert_eq!(
+            generate_puzzle_seeded_in(&GridSpec::CLASSIC, Difficulty::Medium, seed),
+            (board, solution)
+        );
+        let trace = solve_logically(&board, &solution);
+        assert!(interestingness(&trace).is_some());
+        // Medium candidates mix naked and hidden singles, so the pick should
+        // need more than one technique
+        let needed = Technique::all().iter().filter(|&&t| trace.count(t) > 0).count();
+        assert!(needed >= 2, "pick only needed {needed} technique(s)");
+    }
+
+    #[test]
+    #[ignore = "slow; run with --ignored --nocapture to print the report"]
+    fn print_difficulty_distribution() {
+        for &difficulty in Difficulty::all() {
+            println!("{}", analyze_difficulty(difficulty, 50));
+        }
+    }
+}
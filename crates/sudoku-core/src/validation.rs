@@ -1,46 +1,65 @@
 use crate::board::Board;
+use crate::grid::GridSpec;
 
 /// Check if a value conflicts with any other cell in the same row/col/box
 pub fn has_conflict(board: &Board, row: usize, col: usize) -> bool {
+    has_conflict_in(&GridSpec::CLASSIC, board, row, col)
+}
+
+/// `has_conflict` for any grid shape
+pub fn has_conflict_in(spec: &GridSpec, board: &Board, row: usize, col: usize) -> bool {
+    !conflicting_cells_in(spec, board, row, col).is_empty()
+}
+
+/// The peers in the same row/col/box holding the same value as (row, col).
+/// Each offender is listed once, even if it shares both a row and a box.
+pub fn conflicting_cells_in(
+    spec: &GridSpec,
+    board: &Board,
+    row: usize,
+    col: usize,
+) -> Vec<(usize, usize)> {
     let val = match board[row][col].value() {
         Some(v) => v,
-        None => return false,
+        None => return vec![],
     };
 
-    for c in 0..9 {
+    let mut offenders = Vec::new();
+    for c in 0..spec.size {
         if c != col {
             if board[row][c].value() == Some(val) {
-                return true;
+                offenders.push((row, c));
             }
         }
     }
-    for r in 0..9 {
+    for r in 0..spec.size {
         if r != row {
             if board[r][col].value() == Some(val) {
-                return true;
+                offenders.push((r, col));
             }
         }
     }
-    let box_r = (row / 3) * 3;
-    let box_c = (col / 3) * 3;
-    for r in box_r..box_r + 3 {
-        for c in box_c..box_c + 3 {
-            if r != row || c != col {
-                if board[r][c].value() == Some(val) {
-                    return true;
-                }
+    for (r, c) in spec.box_cells(row, col) {
+        if r != row && c != col {
+            if board[r][c].value() == Some(val) {
+                offenders.push((r, c));
             }
         }
     }
-    false
+    offenders
 }
 
 /// Get all conflicting cell positions
 pub fn get_all_conflicts(board: &Board) -> Vec<(usize, usize)> {
+    get_all_conflicts_in(&GridSpec::CLASSIC, board)
+}
+
+/// `get_all_conflicts` for any grid shape
+pub fn get_all_conflicts_in(spec: &GridSpec, board: &Board) -> Vec<(usize, usize)> {
     let mut conflicts = Vec::new();
-    for r in 0..9 {
-        for c in 0..9 {
-            if board[r][c].value().is_some() && has_conflict(board, r, c) {
+    for r in 0..spec.size {
+        for c in 0..spec.size {
+            if board[r][c].value().is_some() && has_conflict_in(spec, board, r, c) {
                 conflicts.push((r, c));
             }
         }
@@ -50,47 +69,142 @@ pub fn get_all_conflicts(board: &Board) -> Vec<(usize, usize)> {
 
 /// Check if the board is completely and correctly filled
 pub fn is_board_complete(board: &Board) -> bool {
-    for r in 0..9 {
-        for c in 0..9 {
-            if board[r][c].value().is_none() {
-                return false;
-            }
-            if has_conflict(board, r, c) {
-                return false;
-            }
+    is_board_complete_in(&GridSpec::CLASSIC, board)
+}
+
+/// `is_board_complete` for any grid shape. Runs after every placement, so
+/// it looks for a gap first, which is nearly always there mid-game, and only
+/// then checks each row, column and box once for a repeated digit rather
+/// than scanning every cell's peers.
+pub fn is_board_complete_in(spec: &GridSpec, board: &Board) -> bool {
+    let cells = || (0..spec.size).flat_map(|r| (0..spec.size).map(move |c| (r, c)));
+    if cells().any(|(r, c)| board[r][c].value().is_none()) {
+        return false;
+    }
+
+    // One bit per digit seen in each unit
+    let mut rows = [0u16; 9];
+    let mut cols = [0u16; 9];
+    let mut boxes = [0u16; 9];
+    let boxes_across = spec.size / spec.box_cols;
+    for (r, c) in cells() {
+        let bit = 1 << board[r][c].value().unwrap_or(0);
+        let b = (r / spec.box_rows) * boxes_across + c / spec.box_cols;
+        if (rows[r] | cols[c] | boxes[b]) & bit != 0 {
+            return false;
         }
+        rows[r] |= bit;
+        cols[c] |= bit;
+        boxes[b] |= bit;
     }
     true
 }
 
 /// Get candidates (possible values) for an empty cell
 pub fn get_candidates(board: &Board, row: usize, col: usize) -> Vec<u8> {
+    get_candidates_in(&GridSpec::CLASSIC, board, row, col)
+}
+
+/// `get_candidates` for any grid shape; values run 1..=size
+pub fn get_candidates_in(spec: &GridSpec, board: &Board, row: usize, col: usize) -> Vec<u8> {
     if board[row][col].value().is_some() {
         return vec![];
     }
 
-    let mut possible = vec![true; 10];
+    let mut possible = vec![true; spec.size + 1];
     possible[0] = false;
 
-    for c in 0..9 {
+    for c in 0..spec.size {
         if let Some(v) = board[row][c].value() {
             possible[v as usize] = false;
         }
     }
-    for r in 0..9 {
+    for r in 0..spec.size {
         if let Some(v) = board[r][col].value() {
             possible[v as usize] = false;
         }
     }
-    let box_r = (row / 3) * 3;
-    let box_c = (col / 3) * 3;
-    for r in box_r..box_r + 3 {
-        for c in box_c..box_c + 3 {
-            if let Some(v) = board[r][c].value() {
-                possible[v as usize] = false;
+    for (r, c) in spec.box_cells(row, col) {
+        if let Some(v) = board[r][c].value() {
+            possible[v as usize] = false;
+        }
+    }
+
+    (1..=spec.size as u8).filter(|&v| possible[v as usize]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+    use crate::difficulty::Difficulty;
+    use crate::puzzle::generate_puzzle_seeded_in;
+
+    /// The cell-by-cell peer scan `is_board_complete_in` used to do
+    fn full_scan(spec: &GridSpec, board: &Board) -> bool {
+        (0..spec.size).all(|r| {
+            (0..spec.size)
+                .all(|c| board[r][c].value().is_some() && !has_conflict_in(spec, board, r, c))
+        })
+    }
+
+    #[test]
+    fn completeness_agrees_with_the_full_scan() {
+        for spec in GridSpec::all() {
+            for seed in 0..4 {
+                let (_, solution) = generate_puzzle_seeded_in(spec, Difficulty::Easy, seed);
+                let mut solved = [[Cell::Empty; 9]; 9];
+                for r in 0..spec.size {
+                    for c in 0..spec.size {
+                        solved[r][c] = Cell::UserInput(solution[r][c]);
+                    }
+                }
+                let mut boards = vec![solved];
+
+                let mut gap = solved;
+                gap[spec.size - 1][0] = Cell::Empty;
+                boards.push(gap);
+
+                // Rows still hold every digit; columns and boxes don't
+                let mut swapped = solved;
+                swapped[0][0] = solved[0][1];
+                swapped[0][1] = solved[0][0];
+                boards.push(swapped);
+
+                for (i, digit) in (1..=spec.size as u8).enumerate() {
+                    let mut wrong = solved;
+                    wrong[i % spec.size][(i * 2) % spec.size] = Cell::UserInput(digit);
+                    boards.push(wrong);
+                }
+
+                for board in &boards {
+                    assert_eq!(is_board_complete_in(spec, board), full_scan(spec, board));
+                }
+                assert!(is_board_complete_in(spec, &solved));
+                assert!(!is_board_complete_in(spec, &swapped));
             }
         }
     }
 
-    (1..=9).filter(|&v| possible[v as usize]).collect()
+    #[test]
+    fn a_clashing_peer_is_listed_once() {
+        let mut board = [[Cell::Empty; 9]; 9];
+        board[0][0] = Cell::UserInput(4);
+        // Same row and same box as (0, 0): the row scan and the box scan
+        // both reach it
+        board[0][1] = Cell::Given(4);
+        // Same column and same box
+        board[2][0] = Cell::UserInput(4);
+        // Same box only
+        board[1][2] = Cell::UserInput(4);
+        // Elsewhere in the row
+        board[0][8] = Cell::UserInput(4);
+        // A different digit in the box
+        board[1][1] = Cell::UserInput(5);
+
+        let mut offenders = conflicting_cells_in(&GridSpec::CLASSIC, &board, 0, 0);
+        offenders.sort();
+        assert_eq!(offenders, [(0, 1), (0, 8), (1, 2), (2, 0)]);
+        assert!(conflicting_cells_in(&GridSpec::CLASSIC, &board, 1, 1).is_empty());
+    }
 }
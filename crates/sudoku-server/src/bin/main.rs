@@ -2,10 +2,64 @@
 async fn main() {
     let db_url = std::env::var("DATABASE_URL")
         .unwrap_or_else(|_| "sqlite:sudoku.db?mode=rwc".to_string());
-    let (app, _state) = sudoku_server::build_app(&db_url).await;
+    let addr = match sudoku_server::bind_addr(
+        std::env::var("BIND_ADDR").ok().as_deref(),
+        std::env::var("PORT").ok().as_deref(),
+    ) {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let max_message_bytes = match sudoku_server::max_message_bytes(
+        std::env::var("MAX_MESSAGE_BYTES").ok().as_deref(),
+    ) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let timeouts = match sudoku_server::timeouts(|name| std::env::var(name).ok()) {
+        Ok(timeouts) => timeouts,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let puzzle_seed =
+        match sudoku_server::puzzle_seed(std::env::var("PUZZLE_SEED").ok().as_deref()) {
+            Ok(seed) => seed,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+    let (app, state) = sudoku_server::build_app(&db_url, timeouts).await;
 
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let addr = format!("0.0.0.0:{}", port);
+    // Offline admin task: needs direct access to the database, not a login
+    if std::env::args().nth(1).as_deref() == Some("recompute-ratings") {
+        match sudoku_server::db::recompute_ratings(&state.db).await {
+            Ok(count) => println!("Recomputed ratings from {} matches", count),
+            Err(e) => {
+                eprintln!("Recomputing ratings failed, nothing was changed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    state
+        .max_message_bytes
+        .store(max_message_bytes, std::sync::atomic::Ordering::Relaxed);
+    if let Some(seed) = puzzle_seed {
+        let _ = state.puzzle_seed.set(seed);
+    }
+    if let Ok(token) = std::env::var("ADMIN_TOKEN")
+        && !token.is_empty()
+    {
+        let _ = state.admin_token.set(token);
+    }
 
     if std::env::var("GITHUB_CLIENT_ID").is_err() {
         println!("╔══════════════════════════════════════════════════╗");
@@ -16,7 +70,7 @@ async fn main() {
         println!("Run the client with:");
         println!(
             "  SUDOKU_SERVER_URL=ws://localhost:{} cargo run -p sudoku-tui",
-            port
+            addr.port()
         );
         println!();
     }
@@ -1,7 +1,18 @@
 #![allow(unused)]
 
+use std::collections::HashMap;
+
 use sqlx::{Row, SqlitePool};
 
+use sudoku_core::elo::{calculate_elo, DEFAULT_RATING};
+use sudoku_core::puzzle::GENERATOR_VERSION;
+
+/// Cheapest possible round trip, to check the database is reachable.
+pub async fn ping(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
 /// Create all tables if they don't exist.
 pub async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     sqlx::query(
@@ -41,6 +52,10 @@ pub async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
             player1_elo_change INTEGER NOT NULL DEFAULT 0,
             player2_elo_change INTEGER NOT NULL DEFAULT 0,
             duration_secs INTEGER NOT NULL DEFAULT 0,
+            player1_rating_after INTEGER,
+            player2_rating_after INTEGER,
+            seed INTEGER,
+            generator INTEGER,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (player1_id) REFERENCES users(id),
             FOREIGN KEY (player2_id) REFERENCES users(id)
@@ -49,6 +64,51 @@ pub async fn init_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS reports (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            room_code TEXT NOT NULL,
+            difficulty TEXT NOT NULL,
+            puzzle TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS solo_results (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            difficulty TEXT NOT NULL,
+            duration_secs INTEGER NOT NULL,
+            mistakes INTEGER NOT NULL,
+            hints INTEGER NOT NULL,
+            flawless INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (user_id) REFERENCES users(id)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    // Databases created before rating history, puzzle seeds and generator
+    // versions were tracked lack these columns. SQLite has no ADD COLUMN IF
+    // NOT EXISTS, so a duplicate-column error just means it's already there.
+    for column in ["player1_rating_after", "player2_rating_after", "seed", "generator"] {
+        match sqlx::query(&format!("ALTER TABLE matches ADD COLUMN {} INTEGER", column))
+            .execute(pool)
+            .await
+        {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.message().contains("duplicate column name") => {}
+            Err(e) => return Err(e),
+        }
+    }
+
     Ok(())
 }
 
@@ -125,6 +185,15 @@ pub async fn get_session(
     Ok(row.map(|r| (r.get::<i64, _>("user_id"), r.get::<String, _>("username"))))
 }
 
+/// Delete sessions past their expiry, which `get_session` already ignores.
+/// Returns how many went.
+pub async fn purge_expired_sessions(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE expires_at <= datetime('now')")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
 /// Get a user by id.
 pub async fn get_user(
     pool: &SqlitePool,
@@ -194,61 +263,372 @@ pub async fn update_ratings(
     Ok(())
 }
 
-/// Record a completed match.
-pub async fn record_match(
+/// Record a completed match. Its seed is stored with the current
+/// `GENERATOR_VERSION`.
+pub async fn record_match(pool: &SqlitePool, new: &NewMatch<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO matches (player1_id, player2_id, mode, difficulty, winner_id, player1_elo_change, player2_elo_change, duration_secs, player1_rating_after, player2_rating_after, seed, generator)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )
+    .bind(new.player1_id)
+    .bind(new.player2_id)
+    .bind(new.mode)
+    .bind(new.difficulty)
+    .bind(new.winner_id)
+    .bind(new.player1_elo_change)
+    .bind(new.player2_elo_change)
+    .bind(new.duration_secs)
+    .bind(new.player1_rating_after)
+    .bind(new.player2_rating_after)
+    // SQLite integers are signed; the seed is stored bit for bit and cast
+    // back on the way out
+    .bind(new.seed.map(|seed| seed as i64))
+    .bind(new.seed.map(|_| GENERATOR_VERSION))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Rebuild every rating from scratch after a change to the Elo formula:
+/// reset all users to the starting rating, then replay the matches oldest
+/// first with the current `calculate_elo`, rewriting each match's rating
+/// columns and the users' ratings and win/loss counts. Matches without a
+/// winner change nothing. Runs in one transaction, so a failure leaves the
+/// old ratings in place. Returns how many matches were replayed.
+pub async fn recompute_ratings(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let matches = sqlx::query(
+        "SELECT id, player1_id, player2_id, winner_id FROM matches ORDER BY created_at ASC, id ASC",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // user id -> (rating, wins, losses)
+    let mut records: HashMap<i64, (i32, i64, i64)> = HashMap::new();
+    for m in &matches {
+        let (p1, p2): (i64, i64) = (m.get("player1_id"), m.get("player2_id"));
+        let winner: Option<i64> = m.get("winner_id");
+        let r1 = records.get(&p1).map_or(DEFAULT_RATING, |r| r.0);
+        let r2 = records.get(&p2).map_or(DEFAULT_RATING, |r| r.0);
+        let (new1, new2) = match winner {
+            Some(w) if w == p1 => (calculate_elo(r1, r2, true), calculate_elo(r2, r1, false)),
+            Some(w) if w == p2 => (calculate_elo(r1, r2, false), calculate_elo(r2, r1, true)),
+            _ => (r1, r2),
+        };
+        for (id, rating) in [(p1, new1), (p2, new2)] {
+            let record = records.entry(id).or_insert((DEFAULT_RATING, 0, 0));
+            record.0 = rating;
+            match winner {
+                Some(w) if w == id => record.1 += 1,
+                Some(w) if w == p1 || w == p2 => record.2 += 1,
+                _ => {}
+            }
+        }
+
+        sqlx::query(
+            "UPDATE matches SET player1_elo_change = ?1, player2_elo_change = ?2,
+                    player1_rating_after = ?3, player2_rating_after = ?4
+             WHERE id = ?5",
+        )
+        .bind(new1 - r1)
+        .bind(new2 - r2)
+        .bind(new1)
+        .bind(new2)
+        .bind(m.get::<i64, _>("id"))
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE users SET rating = ?1, wins = 0, losses = 0")
+        .bind(DEFAULT_RATING)
+        .execute(&mut *tx)
+        .await?;
+    for (id, (rating, wins, losses)) in records {
+        sqlx::query("UPDATE users SET rating = ?1, wins = ?2, losses = ?3 WHERE id = ?4")
+            .bind(rating)
+            .bind(wins)
+            .bind(losses)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(matches.len())
+}
+
+/// Get a user's post-match ratings with each match's puzzle seed and the
+/// generator version it's for, oldest first. Matches recorded before ratings
+/// were stored fall back to replaying the elo changes from 1200; seeds
+/// recorded before generators were versioned are for version 1.
+pub async fn get_rating_history(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<(String, i32, Option<u64>, u32)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT created_at, seed, generator,
+                CASE WHEN player1_id = ?1 THEN player1_rating_after ELSE player2_rating_after END AS rating_after,
+                CASE WHEN player1_id = ?1 THEN player1_elo_change ELSE player2_elo_change END AS elo_change
+         FROM matches
+         WHERE player1_id = ?1 OR player2_id = ?1
+         ORDER BY created_at ASC, id ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut rating = 1200;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            rating = r
+                .get::<Option<i32>, _>("rating_after")
+                .unwrap_or(rating + r.get::<i32, _>("elo_change"));
+            let seed = r.get::<Option<i64>, _>("seed").map(|seed| seed as u64);
+            let generator = r.get::<Option<u32>, _>("generator").unwrap_or(1);
+            (r.get("created_at"), rating, seed, generator)
+        })
+        .collect())
+}
+
+/// Matches between two users in either seat: (games, a's wins, b's wins,
+/// average duration in seconds).
+pub async fn get_head_to_head(
+    pool: &SqlitePool,
+    a: i64,
+    b: i64,
+) -> Result<(i64, i64, i64, f64), sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS games,
+                COALESCE(SUM(winner_id = ?1), 0) AS a_wins,
+                COALESCE(SUM(winner_id = ?2), 0) AS b_wins,
+                COALESCE(AVG(duration_secs), 0.0) AS avg_duration
+         FROM matches
+         WHERE (player1_id = ?1 AND player2_id = ?2) OR (player1_id = ?2 AND player2_id = ?1)",
+    )
+    .bind(a)
+    .bind(b)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((
+        row.get("games"),
+        row.get("a_wins"),
+        row.get("b_wins"),
+        row.get("avg_duration"),
+    ))
+}
+
+/// Store a player's report of a flawed puzzle. `puzzle` is the givens string.
+pub async fn record_report(
+    pool: &SqlitePool,
+    user_id: i64,
+    room_code: &str,
+    difficulty: &str,
+    puzzle: &str,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO reports (user_id, room_code, difficulty, puzzle, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )
+    .bind(user_id)
+    .bind(room_code)
+    .bind(difficulty)
+    .bind(puzzle)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Store a single-player result a logged-in player finished.
+pub async fn record_solo_result(
     pool: &SqlitePool,
-    player1_id: i64,
-    player2_id: i64,
-    mode: &str,
+    user_id: i64,
     difficulty: &str,
-    winner_id: Option<i64>,
-    player1_elo_change: i32,
-    player2_elo_change: i32,
     duration_secs: i64,
+    mistakes: i64,
+    hints: i64,
+    flawless: bool,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO matches (player1_id, player2_id, mode, difficulty, winner_id, player1_elo_change, player2_elo_change, duration_secs)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO solo_results (user_id, difficulty, duration_secs, mistakes, hints, flawless)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
     )
-    .bind(player1_id)
-    .bind(player2_id)
-    .bind(mode)
+    .bind(user_id)
     .bind(difficulty)
-    .bind(winner_id)
-    .bind(player1_elo_change)
-    .bind(player2_elo_change)
     .bind(duration_secs)
+    .bind(mistakes)
+    .bind(hints)
+    .bind(flawless)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
+/// A user's single-player results summed up per difficulty.
+pub async fn get_solo_stats(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<SoloStatsRow>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT difficulty,
+                COUNT(*) AS solved,
+                SUM(flawless) AS flawless,
+                MIN(duration_secs) AS best_secs,
+                AVG(duration_secs) AS avg_secs
+         FROM solo_results
+         WHERE user_id = ?1
+         GROUP BY difficulty",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SoloStatsRow {
+            difficulty: r.get("difficulty"),
+            solved: r.get("solved"),
+            flawless: r.get("flawless"),
+            best_secs: r.get("best_secs"),
+            avg_secs: r.get("avg_secs"),
+        })
+        .collect())
+}
+
+/// Most recent puzzle reports, newest first.
+pub async fn get_reports(pool: &SqlitePool, limit: i64) -> Result<Vec<ReportRow>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT user_id, difficulty, puzzle, reason FROM reports ORDER BY id DESC LIMIT ?1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| ReportRow {
+            user_id: r.get("user_id"),
+            difficulty: r.get("difficulty"),
+            puzzle: r.get("puzzle"),
+            reason: r.get("reason"),
+        })
+        .collect())
+}
+
 /// Get top users by rating.
 pub async fn get_leaderboard(
     pool: &SqlitePool,
     limit: i64,
 ) -> Result<Vec<LeaderboardRow>, sqlx::Error> {
     let rows = sqlx::query(
-        "SELECT username, rating, wins, losses FROM users ORDER BY rating DESC LIMIT ?1",
+        "SELECT id, username, rating, wins, losses FROM users ORDER BY rating DESC LIMIT ?1",
     )
     .bind(limit)
     .fetch_all(pool)
     .await?;
+    let mut difficulties = most_played(pool, "difficulty", None).await?;
+    let mut modes = most_played(pool, "mode", None).await?;
 
     Ok(rows
         .into_iter()
         .enumerate()
-        .map(|(i, r)| LeaderboardRow {
-            rank: (i + 1) as u32,
-            username: r.get("username"),
-            rating: r.get("rating"),
-            wins: r.get::<i32, _>("wins") as u32,
-            losses: r.get::<i32, _>("losses") as u32,
+        .map(|(i, r)| {
+            let id: i64 = r.get("id");
+            LeaderboardRow {
+                rank: (i + 1) as u32,
+                username: r.get("username"),
+                rating: r.get("rating"),
+                wins: r.get::<i32, _>("wins") as u32,
+                losses: r.get::<i32, _>("losses") as u32,
+                favorite_difficulty: difficulties.remove(&id),
+                favorite_mode: modes.remove(&id),
+            }
         })
         .collect())
 }
 
+/// A user's most played match difficulty and mode, as recorded.
+pub async fn get_favorites(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<(Option<String>, Option<String>), sqlx::Error> {
+    Ok((
+        most_played(pool, "difficulty", Some(user_id)).await?.remove(&user_id),
+        most_played(pool, "mode", Some(user_id)).await?.remove(&user_id),
+    ))
+}
+
+/// The value of `column` ("difficulty" or "mode") in most of each player's
+/// matches, the most recent one breaking a tie. Just `user`'s if given.
+async fn most_played(
+    pool: &SqlitePool,
+    column: &str,
+    user: Option<i64>,
+) -> Result<HashMap<i64, String>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT user_id, {column} AS value FROM (
+             SELECT user_id, {column},
+                    ROW_NUMBER() OVER (
+                        PARTITION BY user_id ORDER BY COUNT(*) DESC, MAX(id) DESC
+                    ) AS place
+             FROM (
+                 SELECT id, player1_id AS user_id, {column} FROM matches
+                 UNION ALL
+                 SELECT id, player2_id AS user_id, {column} FROM matches
+             )
+             WHERE ?1 IS NULL OR user_id = ?1
+             GROUP BY user_id, {column}
+         )
+         WHERE place = 1"
+    ))
+    .bind(user)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.get("user_id"), r.get("value")))
+        .collect())
+}
+
+/// Get a user's 1-based leaderboard rank (ties share the better rank).
+pub async fn get_user_rank(pool: &SqlitePool, user_id: i64) -> Result<Option<u32>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT (SELECT COUNT(*) FROM users o WHERE o.rating > u.rating) + 1 AS rank
+         FROM users u WHERE u.id = ?1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.get::<i64, _>("rank") as u32))
+}
+
+/// A finished match for `record_match`.
+#[derive(Debug, Clone, Default)]
+pub struct NewMatch<'a> {
+    pub player1_id: i64,
+    pub player2_id: i64,
+    /// As sent on the wire, e.g. "Race" and "Hard"
+    pub mode: &'a str,
+    pub difficulty: &'a str,
+    pub winner_id: Option<i64>,
+    pub player1_elo_change: i32,
+    pub player2_elo_change: i32,
+    pub duration_secs: i64,
+    pub player1_rating_after: i32,
+    pub player2_rating_after: i32,
+    /// The puzzle's seed, when it was generated from one
+    pub seed: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserRow {
     pub id: i64,
@@ -267,4 +647,24 @@ pub struct LeaderboardRow {
     pub rating: i32,
     pub wins: u32,
     pub losses: u32,
+    /// As recorded with their matches, e.g. "Hard" and "Race"
+    pub favorite_difficulty: Option<String>,
+    pub favorite_mode: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SoloStatsRow {
+    pub difficulty: String,
+    pub solved: i64,
+    pub flawless: i64,
+    pub best_secs: i64,
+    pub avg_secs: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportRow {
+    pub user_id: i64,
+    pub difficulty: String,
+    pub puzzle: String,
+    pub reason: String,
 }
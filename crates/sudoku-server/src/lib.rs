@@ -5,7 +5,8 @@ pub mod routes;
 pub mod state;
 pub mod ws;
 
-use std::sync::atomic::AtomicU32;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicUsize};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -15,10 +16,102 @@ use dashmap::DashMap;
 use sqlx::sqlite::SqlitePoolOptions;
 use tower_http::cors::CorsLayer;
 
-use crate::state::{AppState, RoomState};
+use sudoku_core::protocol::ServerMessage;
 
-/// Build a fully configured Router + shared state.
-pub async fn build_app(db_url: &str) -> (Router, Arc<AppState>) {
+use crate::state::{AppState, RoomState, Timeouts};
+
+/// Playing rooms with no placement for this long are forfeited, unless
+/// `IDLE_FORFEIT_SECS` says otherwise.
+pub const IDLE_FORFEIT_SECS: u64 = 300;
+/// How long before the idle forfeit both players get an `IdleWarning`.
+pub const IDLE_WARNING_SECS: u64 = 60;
+/// Rooms nobody joined are dropped after this long, unless
+/// `WAITING_TTL_SECS` says otherwise.
+pub const WAITING_TTL_SECS: u64 = 600;
+/// Quick match won't pair two players again within this long of their last
+/// game, unless nobody else has turned up for them.
+pub const RECENT_OPPONENT_SECS: u64 = 300;
+
+/// How often expired login sessions are deleted from the database.
+pub const SESSION_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default cap on an inbound WebSocket message. The largest real one, a
+/// `CreateRoom` with a custom puzzle, is well under 1 KiB.
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 16 * 1024;
+
+/// The inbound message cap from `MAX_MESSAGE_BYTES`, or the default if unset.
+pub fn max_message_bytes(var: Option<&str>) -> Result<usize, String> {
+    let Some(var) = var else {
+        return Ok(DEFAULT_MAX_MESSAGE_BYTES);
+    };
+    match var.parse() {
+        Ok(bytes) if bytes > 0 => Ok(bytes),
+        _ => Err(format!("MAX_MESSAGE_BYTES {var:?} is not a positive number of bytes")),
+    }
+}
+
+/// Timeouts from `GRACE_SECS`, `IDLE_FORFEIT_SECS` and `WAITING_TTL_SECS`,
+/// each falling back to its default when unset. `var` looks a variable up,
+/// so tests needn't touch the environment.
+pub fn timeouts(var: impl Fn(&str) -> Option<String>) -> Result<Timeouts, String> {
+    let defaults = Timeouts::default();
+    let secs = |name: &str, default: u64, min: u64| -> Result<u64, String> {
+        let Some(value) = var(name) else {
+            return Ok(default);
+        };
+        match value.parse() {
+            Ok(secs) if secs >= min => Ok(secs),
+            _ => Err(format!(
+                "{name} {value:?} must be a whole number of seconds, at least {min}"
+            )),
+        }
+    };
+    Ok(Timeouts {
+        // Long enough to ride out a network blip
+        grace_secs: secs("GRACE_SECS", defaults.grace_secs, 5)?,
+        // The idle warning goes out IDLE_WARNING_SECS ahead, so leave room before it
+        idle_forfeit_secs: secs(
+            "IDLE_FORFEIT_SECS",
+            defaults.idle_forfeit_secs,
+            2 * IDLE_WARNING_SECS,
+        )?,
+        // Time to share the code with a friend
+        waiting_ttl_secs: secs("WAITING_TTL_SECS", defaults.waiting_ttl_secs, 60)?,
+    })
+}
+
+/// Address to listen on: `BIND_ADDR` (`host:port`, e.g. `[::]:8080` for
+/// IPv6) if set, otherwise every IPv4 interface on `PORT`, which defaults to
+/// 8080. Takes the two variables as arguments so tests needn't touch the
+/// environment.
+pub fn bind_addr(bind: Option<&str>, port: Option<&str>) -> Result<SocketAddr, String> {
+    if let Some(bind) = bind {
+        return bind.parse().map_err(|_| {
+            format!(
+                "BIND_ADDR {bind:?} is not a host:port address \
+                 (e.g. 0.0.0.0:8080, or [::]:8080 for IPv6)"
+            )
+        });
+    }
+    let port = port.unwrap_or("8080");
+    let port: u16 = port
+        .parse()
+        .map_err(|_| format!("PORT {port:?} is not a port number"))?;
+    Ok(SocketAddr::from(([0, 0, 0, 0], port)))
+}
+
+/// A fixed seed for every match puzzle from `PUZZLE_SEED`, or None if unset.
+pub fn puzzle_seed(var: Option<&str>) -> Result<Option<u64>, String> {
+    let Some(var) = var else {
+        return Ok(None);
+    };
+    var.parse()
+        .map(Some)
+        .map_err(|_| format!("PUZZLE_SEED {var:?} is not a whole number"))
+}
+
+/// Build a fully configured Router + shared state with the given timeouts.
+pub async fn build_app(db_url: &str, timeouts: Timeouts) -> (Router, Arc<AppState>) {
     let pool = SqlitePoolOptions::new()
         .max_connections(5)
         .connect(db_url)
@@ -35,8 +128,13 @@ pub async fn build_app(db_url: &str) -> (Router, Arc<AppState>) {
         sessions: DashMap::new(),
         connections: DashMap::new(),
         matchmaking: DashMap::new(),
+        recent_pairings: DashMap::new(),
         connection_count: AtomicU32::new(0),
+        max_message_bytes: AtomicUsize::new(DEFAULT_MAX_MESSAGE_BYTES),
         max_connections: 100,
+        timeouts,
+        admin_token: std::sync::OnceLock::new(),
+        puzzle_seed: std::sync::OnceLock::new(),
     });
 
     {
@@ -50,12 +148,34 @@ pub async fn build_app(db_url: &str) -> (Router, Arc<AppState>) {
         });
     }
 
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SESSION_PURGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                // Best effort: a failed sweep is retried on the next tick
+                let _ = db::purge_expired_sessions(&state.db).await;
+            }
+        });
+    }
+
     let app = Router::new()
         .route("/health", get(routes::health))
         .route("/auth/device", post(routes::device_auth))
         .route("/auth/poll", post(routes::auth_poll))
+        .route("/auth/cancel", post(routes::auth_cancel))
         .route("/leaderboard", get(routes::leaderboard))
         .route("/profile/{username}", get(routes::profile))
+        .route(
+            "/profile/{username}/rating-history",
+            get(routes::rating_history),
+        )
+        .route("/h2h/{user_a}/{user_b}", get(routes::head_to_head))
+        .route("/me", get(routes::me))
+        .route("/solo/record", post(routes::record_solo))
+        .route("/solo/stats", get(routes::solo_stats))
+        .route("/admin/broadcast", post(routes::broadcast))
         .route("/ws", get(routes::ws_upgrade))
         .layer(CorsLayer::permissive())
         .with_state(state.clone());
@@ -63,22 +183,40 @@ pub async fn build_app(db_url: &str) -> (Router, Arc<AppState>) {
     (app, state)
 }
 
-async fn cleanup(state: &AppState) {
-    let now = Instant::now();
+async fn cleanup(state: &Arc<AppState>) {
+    cleanup_at(state, Instant::now()).await;
+}
+
+/// Periodic sweep: drop stale rooms, warn idle players, forfeit idle games
+/// and pair quick-match players who have waited long enough.
+/// Takes `now` explicitly so tests can fast-forward instead of waiting minutes.
+pub async fn cleanup_at(state: &Arc<AppState>, now: Instant) {
     let mut to_remove = Vec::new();
     let mut to_forfeit = Vec::new();
+    let mut to_warn = Vec::new();
+    let Timeouts { idle_forfeit_secs, waiting_ttl_secs, .. } = state.timeouts;
 
-    for entry in state.rooms.iter() {
-        let room = entry.value();
+    for mut entry in state.rooms.iter_mut() {
+        let room = entry.value_mut();
         match room.state {
             RoomState::Waiting => {
-                if now.duration_since(room.created_at) > Duration::from_secs(600) {
+                if now.duration_since(room.created_at) > Duration::from_secs(waiting_ttl_secs) {
                     to_remove.push(room.code.clone());
                 }
             }
             RoomState::Playing => {
-                if now.duration_since(room.last_activity) > Duration::from_secs(300) {
+                let idle = now.duration_since(room.last_activity);
+                if idle > Duration::from_secs(idle_forfeit_secs) {
                     to_forfeit.push((room.code.clone(), room.player1_id));
+                } else if idle > Duration::from_secs(idle_forfeit_secs - IDLE_WARNING_SECS)
+                    && room.idle_warned_at != Some(room.last_activity)
+                {
+                    room.idle_warned_at = Some(room.last_activity);
+                    let seconds_remaining = idle_forfeit_secs - idle.as_secs();
+                    to_warn.push((room.player1_id, seconds_remaining));
+                    if let Some(p2) = room.player2_id {
+                        to_warn.push((p2, seconds_remaining));
+                    }
                 }
             }
             RoomState::Ended => {
@@ -93,7 +231,17 @@ async fn cleanup(state: &AppState) {
         state.rooms.remove(&code);
     }
 
+    state
+        .recent_pairings
+        .retain(|_, at| now.duration_since(*at) < Duration::from_secs(RECENT_OPPONENT_SECS));
+
+    for (player_id, seconds_remaining) in to_warn {
+        ws::send_to(state, player_id, ServerMessage::IdleWarning { seconds_remaining });
+    }
+
     for (code, player_id) in to_forfeit {
         ws::forfeit_player_public(state, &code, player_id).await;
     }
+
+    ws::pair_long_waiters(state, now).await;
 }
@@ -11,8 +11,11 @@ use axum::Json;
 use serde::Deserialize;
 
 use sudoku_core::protocol::{
-    AuthPollResponse, DeviceAuthResponse, LeaderboardEntry, PlayerProfile,
+    AuthPollResponse, DeviceAuthResponse, HeadToHead, LeaderboardEntry, MeResponse,
+    PlayerProfile, RatingPoint, SoloDifficultyStats, SoloResult, SoloStats,
+    ServerMessage, DEVICE_CODE_LIFETIME_SECS, default_device_code_lifetime,
 };
+use sudoku_core::Difficulty;
 
 use crate::db;
 use crate::state::AppState;
@@ -24,8 +27,19 @@ fn is_dev_mode() -> bool {
 
 // ── Health ──────────────────────────────────────────────────────────────
 
-pub async fn health() -> &'static str {
-    "ok"
+/// How long `/health` waits on the database before calling it down
+const HEALTH_DB_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// "ok" if the database answers a trivial query, 503 otherwise, so a load
+/// balancer stops sending players to a server that can't save their games.
+pub async fn health(
+    State(state): State<Arc<AppState>>,
+) -> Result<&'static str, (StatusCode, &'static str)> {
+    match tokio::time::timeout(HEALTH_DB_TIMEOUT, db::ping(&state.db)).await {
+        Ok(Ok(())) => Ok("ok"),
+        Ok(Err(_)) => Err((StatusCode::SERVICE_UNAVAILABLE, "database unavailable")),
+        Err(_) => Err((StatusCode::SERVICE_UNAVAILABLE, "database not responding")),
+    }
 }
 
 // ── Device Auth (GitHub or Dev Mode) ────────────────────────────────────
@@ -36,6 +50,8 @@ struct GhDeviceCode {
     device_code: String,
     verification_uri: String,
     interval: u64,
+    #[serde(default = "default_device_code_lifetime")]
+    expires_in: u64,
 }
 
 /// Counter for generating unique dev user codes
@@ -63,6 +79,7 @@ pub async fn device_auth(
             user_code,
             verification_uri: "http://localhost (dev mode - no action needed)".to_string(),
             interval: 1,
+            expires_in: DEVICE_CODE_LIFETIME_SECS,
         }));
     }
 
@@ -93,6 +110,7 @@ pub async fn device_auth(
         user_code: body.user_code,
         verification_uri: body.verification_uri,
         interval: body.interval,
+        expires_in: body.expires_in,
     }))
 }
 
@@ -227,11 +245,29 @@ pub async fn auth_poll(
     }
 }
 
+/// Forget a pending device code, e.g. when the client quits mid-login.
+/// Unknown or already-finished codes are fine; there's nothing to undo.
+pub async fn auth_cancel(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<AuthPollRequest>,
+) -> StatusCode {
+    state.sessions.remove(&format!("device:{}", req.user_code));
+    StatusCode::NO_CONTENT
+}
+
 // ── Leaderboard ─────────────────────────────────────────────────────────
 
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    /// "csv" for a spreadsheet-friendly table; JSON otherwise
+    pub format: Option<String>,
+}
+
+/// The top 100, as JSON or, with `?format=csv`, as CSV for charting elsewhere.
 pub async fn leaderboard(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<LeaderboardEntry>>, StatusCode> {
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<axum::response::Response, StatusCode> {
     let rows = db::get_leaderboard(&state.db, 100)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -244,10 +280,44 @@ pub async fn leaderboard(
             rating: r.rating,
             wins: r.wins,
             losses: r.losses,
+            favorite_difficulty: recorded(r.favorite_difficulty),
+            favorite_mode: recorded(r.favorite_mode),
         })
         .collect();
 
-    Ok(Json(entries))
+    match query.format.as_deref() {
+        Some("csv") => Ok((
+            [(axum::http::header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            leaderboard_csv(&entries),
+        )
+            .into_response()),
+        Some("json") | None => Ok(Json(entries).into_response()),
+        Some(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn leaderboard_csv(entries: &[LeaderboardEntry]) -> String {
+    let mut csv = String::from("rank,username,rating,wins,losses\n");
+    for e in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            e.rank,
+            csv_field(&e.username),
+            e.rating,
+            e.wins,
+            e.losses
+        ));
+    }
+    csv
+}
+
+/// Quote a field that would otherwise break the row, doubling inner quotes.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
 }
 
 // ── Profile ─────────────────────────────────────────────────────────────
@@ -260,6 +330,9 @@ pub async fn profile(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
+    let (difficulty, mode) = db::get_favorites(&state.db, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(PlayerProfile {
         username: user.username,
@@ -267,19 +340,222 @@ pub async fn profile(
         rating: user.rating,
         wins: user.wins as u32,
         losses: user.losses as u32,
+        favorite_difficulty: recorded(difficulty),
+        favorite_mode: recorded(mode),
     }))
 }
 
-// ── WebSocket upgrade ───────────────────────────────────────────────────
+/// Read back a difficulty or mode stored with a match under its variant name.
+fn recorded<T: serde::de::DeserializeOwned>(name: Option<String>) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(name?)).ok()
+}
+
+pub async fn rating_history(
+    State(state): State<Arc<AppState>>,
+    Path(username): Path<String>,
+) -> Result<Json<Vec<RatingPoint>>, StatusCode> {
+    let user = db::get_user_by_username(&state.db, &username)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let history = db::get_rating_history(&state.db, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|(timestamp, rating, seed, generator)| RatingPoint {
+                timestamp,
+                rating,
+                seed,
+                generator,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn head_to_head(
+    State(state): State<Arc<AppState>>,
+    Path((user_a, user_b)): Path<(String, String)>,
+) -> Result<Json<HeadToHead>, StatusCode> {
+    let mut ids = [0; 2];
+    for (id, username) in ids.iter_mut().zip([&user_a, &user_b]) {
+        *id = db::get_user_by_username(&state.db, username)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?
+            .id;
+    }
+
+    let (games, a_wins, b_wins, avg_duration) = db::get_head_to_head(&state.db, ids[0], ids[1])
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(HeadToHead {
+        a_wins: a_wins as u32,
+        b_wins: b_wins as u32,
+        games: games as u32,
+        avg_duration_secs: avg_duration.round() as u32,
+    }))
+}
+
+// ── Me ──────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
-pub struct WsQuery {
+pub struct TokenQuery {
     pub token: String,
 }
 
+pub async fn me(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Result<Json<MeResponse>, StatusCode> {
+    let (user_id, _) = db::get_session(&state.db, &query.token)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let user = db::get_user(&state.db, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let rank = db::get_user_rank(&state.db, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let (difficulty, mode) = db::get_favorites(&state.db, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(MeResponse {
+        profile: PlayerProfile {
+            username: user.username,
+            avatar_url: user.avatar_url,
+            rating: user.rating,
+            wins: user.wins as u32,
+            losses: user.losses as u32,
+            favorite_difficulty: recorded(difficulty),
+            favorite_mode: recorded(mode),
+        },
+        rank,
+    }))
+}
+
+// ── Solo results ────────────────────────────────────────────────────────
+
+/// The user a `?token=` belongs to, or 401.
+async fn session_user(state: &AppState, token: &str) -> Result<i64, StatusCode> {
+    let (user_id, _) = db::get_session(&state.db, token)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(user_id)
+}
+
+/// No single-player puzzle takes anywhere near this long; anything beyond
+/// is a broken client, not a result worth averaging in.
+const MAX_SOLO_SECS: u64 = 24 * 60 * 60;
+
+pub async fn record_solo(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+    Json(result): Json<SoloResult>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = session_user(&state, &query.token).await?;
+
+    let flawless = result.mistakes == 0 && result.hints == 0;
+    if result.flawless != flawless || result.secs > MAX_SOLO_SECS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    db::record_solo_result(
+        &state.db,
+        user_id,
+        &format!("{:?}", result.difficulty),
+        result.secs as i64,
+        result.mistakes as i64,
+        result.hints as i64,
+        flawless,
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn solo_stats(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Result<Json<SoloStats>, StatusCode> {
+    let user_id = session_user(&state, &query.token).await?;
+
+    let rows = db::get_solo_stats(&state.db, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut stats = SoloStats::default();
+    for &difficulty in Difficulty::all() {
+        let Some(row) = rows.iter().find(|r| r.difficulty == format!("{:?}", difficulty)) else {
+            continue;
+        };
+        stats.solved += row.solved as u32;
+        stats.flawless += row.flawless as u32;
+        stats.by_difficulty.push(SoloDifficultyStats {
+            difficulty,
+            solved: row.solved as u32,
+            flawless: row.flawless as u32,
+            best_secs: row.best_secs as u64,
+            avg_secs: row.avg_secs.round() as u64,
+        });
+    }
+
+    Ok(Json(stats))
+}
+
+// ── Admin ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct BroadcastRequest {
+    pub text: String,
+}
+
+/// Send an `Announcement` to every connected player, say before a restart.
+/// Needs `?token=` to match `ADMIN_TOKEN`, and is not found without one set.
+/// Returns how many connections it went to.
+pub async fn broadcast(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+    Json(request): Json<BroadcastRequest>,
+) -> Result<Json<usize>, StatusCode> {
+    let admin_token = state.admin_token.get().ok_or(StatusCode::NOT_FOUND)?;
+    if query.token != *admin_token {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let text = request.text.trim();
+    if text.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let sent = state
+        .connections
+        .iter()
+        .filter(|conn| {
+            conn.tx
+                .send(ServerMessage::Announcement { text: text.to_string() })
+                .is_ok()
+        })
+        .count();
+    Ok(Json(sent))
+}
+
+// ── WebSocket upgrade ───────────────────────────────────────────────────
+
 pub async fn ws_upgrade(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<WsQuery>,
+    Query(query): Query<TokenQuery>,
     ws: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, StatusCode> {
     let (user_id, username) = db::get_session(&state.db, &query.token)
@@ -300,8 +576,12 @@ pub async fn ws_upgrade(
         .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let rating = user.rating;
+    let max_bytes = state.max_message_bytes.load(Ordering::Relaxed);
 
-    Ok(ws.on_upgrade(move |socket| {
-        ws::handle_socket(state, socket, user_id, username, rating)
-    }))
+    Ok(ws
+        .max_message_size(max_bytes)
+        .max_frame_size(max_bytes)
+        .on_upgrade(move |socket| {
+            ws::handle_socket(state, socket, user_id, username, rating)
+        }))
 }
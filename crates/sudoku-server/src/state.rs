@@ -1,14 +1,16 @@
 #![allow(unused)]
 
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicU32, AtomicUsize};
 use std::time::Instant;
 
 use dashmap::DashMap;
 use sqlx::SqlitePool;
 use tokio::sync::mpsc;
 
-use sudoku_core::protocol::{GameMode, ServerMessage};
-use sudoku_core::{Board, Cell, Difficulty, SolutionBoard};
+use sudoku_core::protocol::{
+    GameMode, Handicap, ProgressGranularity, ServerMessage, RECONNECT_GRACE_SECS,
+};
+use sudoku_core::{Board, Cell, Difficulty, GridSpec, SolutionBoard};
 
 /// Handle to push messages to a connected WebSocket client.
 #[derive(Debug, Clone)]
@@ -18,6 +20,8 @@ pub struct ConnectionHandle {
     pub rating: i32,
     pub tx: mpsc::UnboundedSender<ServerMessage>,
     pub room_code: Option<String>,
+    /// The room this connection is watching as a spectator.
+    pub spectating: Option<String>,
     /// Messages received in the current second window.
     pub message_count: u32,
     pub rate_limit_window: Instant,
@@ -53,6 +57,8 @@ pub struct Room {
     pub board: Board,
     /// The full solution.
     pub solution: SolutionBoard,
+    /// Seed the puzzle was generated from; None for an imported puzzle.
+    pub seed: Option<u64>,
     /// Per-player boards for race mode: user_id -> board.
     pub player_boards: std::collections::HashMap<i64, Board>,
     /// Cell ownership for shared mode: (row, col) -> user_id who placed it.
@@ -62,6 +68,51 @@ pub struct Room {
     pub created_at: Instant,
     pub last_activity: Instant,
     pub started_at: Option<Instant>,
+    /// Handicap requested by the room creator, applied when the room fills.
+    pub handicap: Option<Handicap>,
+    /// How much of each racer's progress the other is told.
+    pub progress: ProgressGranularity,
+    /// Time-penalty handicap: this player's moves are rejected until the instant.
+    pub penalized_until: Option<(i64, Instant)>,
+    /// The `last_activity` an idle warning was sent for, so each idle stretch warns once.
+    pub idle_warned_at: Option<Instant>,
+    /// user_id -> (correct cells, when that count was first reached); breaks score ties.
+    pub correct_since: std::collections::HashMap<i64, (u32, Instant)>,
+    /// Race mode: user_id -> placements that disagreed with the solution.
+    pub mistakes: std::collections::HashMap<i64, u32>,
+    /// Users watching this room read-only.
+    pub spectators: std::collections::HashSet<i64>,
+    /// Username of the winner once the game has ended, for late spectators.
+    pub winner: Option<String>,
+    /// After the game: the player who asked for a rematch, and at what difficulty.
+    pub rematch_offer: Option<(i64, Difficulty)>,
+}
+
+impl Room {
+    /// Note a player's correct-cell count after a move, keeping the original
+    /// timestamp if the count did not change.
+    pub fn record_correct(&mut self, user_id: i64, correct: u32) {
+        match self.correct_since.get(&user_id) {
+            Some(&(prev, _)) if prev == correct => {}
+            _ => {
+                self.correct_since.insert(user_id, (correct, Instant::now()));
+            }
+        }
+    }
+
+    /// Seconds since the game started; the match clock everyone should see.
+    pub fn elapsed_secs(&self) -> u64 {
+        self.started_at.map_or(0, |t| t.elapsed().as_secs())
+    }
+
+    /// Whether `a` reached their current correct count strictly before `b` did.
+    pub fn reached_first(&self, a: i64, b: i64) -> bool {
+        match (self.correct_since.get(&a), self.correct_since.get(&b)) {
+            (Some((_, ta)), Some((_, tb))) => ta < tb,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
 }
 
 /// A user session backed by the database.
@@ -78,10 +129,49 @@ pub struct AppState {
     pub rooms: DashMap<String, Room>,
     pub sessions: DashMap<String, Session>,
     pub connections: DashMap<i64, ConnectionHandle>,
-    /// Matchmaking queues keyed by "mode:difficulty".
-    pub matchmaking: DashMap<String, Vec<QueueEntry>>,
+    /// Matchmaking queues by mode and difficulty, longest waiting first.
+    pub matchmaking: DashMap<(GameMode, Difficulty), Vec<QueueEntry>>,
+    /// When each pair of players last finished a game together, keyed by
+    /// `pairing_key`, so quick match can avoid instant rematches.
+    pub recent_pairings: DashMap<(i64, i64), Instant>,
     pub connection_count: AtomicU32,
+    /// Largest inbound WebSocket message accepted, in bytes; a connection
+    /// sending anything bigger is closed before it's parsed.
+    pub max_message_bytes: AtomicUsize,
     pub max_connections: u32,
+    pub timeouts: Timeouts,
+    /// Token the admin endpoints want, from `ADMIN_TOKEN`; they're off while unset.
+    pub admin_token: std::sync::OnceLock<String>,
+    /// Seed for every match puzzle, from `PUZZLE_SEED`, so dev and test
+    /// games get known boards. Unset, each match draws a fresh one.
+    pub puzzle_seed: std::sync::OnceLock<u64>,
+}
+
+/// How long the server waits on players, in seconds. Read from the
+/// environment at startup; see `crate::timeouts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// How long a player whose connection dropped mid-game has to come back
+    pub grace_secs: u64,
+    /// Playing rooms with no placement for this long are forfeited
+    pub idle_forfeit_secs: u64,
+    /// Rooms nobody joined are dropped after this long
+    pub waiting_ttl_secs: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            grace_secs: RECONNECT_GRACE_SECS,
+            idle_forfeit_secs: crate::IDLE_FORFEIT_SECS,
+            waiting_ttl_secs: crate::WAITING_TTL_SECS,
+        }
+    }
+}
+
+/// Key for `AppState::recent_pairings`: the two ids in a fixed order.
+pub fn pairing_key(a: i64, b: i64) -> (i64, i64) {
+    (a.min(b), a.max(b))
 }
 
 /// Generate a random 6-character uppercase alphanumeric room code.
@@ -97,6 +187,28 @@ pub fn generate_room_code() -> String {
         .collect()
 }
 
+/// Generate a match puzzle from a fresh seed, or the fixed `puzzle_seed`,
+/// returned alongside it so the match can be recorded and its puzzle
+/// regenerated exactly later. Rated generation can take a good part of a
+/// second at Expert, so it runs on the blocking pool rather than stalling
+/// every connection sharing this worker.
+///
+/// Seeds span the whole u64 range rather than the short ones solo puzzles
+/// are shared by, which are few enough to map every givens to its solution.
+pub async fn generate_match_puzzle(
+    state: &AppState,
+    difficulty: Difficulty,
+) -> (Board, SolutionBoard, u64) {
+    use sudoku_core::puzzle::generate_puzzle_seeded_in;
+    let seed = state.puzzle_seed.get().copied().unwrap_or_else(rand::random);
+    let (board, solution) = tokio::task::spawn_blocking(move || {
+        generate_puzzle_seeded_in(&GridSpec::CLASSIC, difficulty, seed)
+    })
+    .await
+    .expect("puzzle generation panicked");
+    (board, solution, seed)
+}
+
 /// Convert a Board to the Vec<Vec<u8>> wire format (givens only, 0 for empty).
 pub fn board_to_wire(board: &Board) -> Vec<Vec<u8>> {
     board
@@ -112,6 +224,14 @@ pub fn board_to_wire(board: &Board) -> Vec<Vec<u8>> {
         .collect()
 }
 
+/// Convert a Board to the wire format including player entries (0 for empty).
+pub fn board_values_to_wire(board: &Board) -> Vec<Vec<u8>> {
+    board
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.value().unwrap_or(0)).collect())
+        .collect()
+}
+
 /// Count user-placed (non-given, non-empty) cells in a board.
 pub fn filled_count(board: &Board) -> u32 {
     let mut count = 0u32;
@@ -125,6 +245,15 @@ pub fn filled_count(board: &Board) -> u32 {
     count
 }
 
+/// Which quarter of its non-given cells a board has filled, 0 to 3.
+pub fn progress_quartile(board: &Board) -> u8 {
+    let to_fill = board.iter().flatten().filter(|c| !matches!(c, Cell::Given(_))).count() as u32;
+    if to_fill == 0 {
+        return 3;
+    }
+    (filled_count(board) * 4 / to_fill).min(3) as u8
+}
+
 /// Count user-placed cells that match the solution.
 pub fn correct_count(board: &Board, solution: &[[u8; 9]; 9]) -> u32 {
     let mut count = 0u32;
@@ -139,8 +268,3 @@ pub fn correct_count(board: &Board, solution: &[[u8; 9]; 9]) -> u32 {
     }
     count
 }
-
-/// Build matchmaking queue key from mode + difficulty.
-pub fn queue_key(mode: GameMode, difficulty: Difficulty) -> String {
-    format!("{:?}:{:?}", mode, difficulty)
-}
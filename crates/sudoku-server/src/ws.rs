@@ -1,6 +1,6 @@
 #![allow(unused)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -8,14 +8,25 @@ use std::time::{Duration, Instant};
 use axum::extract::ws::{Message, WebSocket};
 use tokio::sync::mpsc;
 
-use sudoku_core::elo::{calculate_elo, elo_change};
-use sudoku_core::protocol::{ClientMessage, GameMode, ServerMessage};
+use sudoku_core::elo::{calculate_elo, elo_change, DEFAULT_RATING};
+use sudoku_core::protocol::{
+    ClientMessage, GameMode, Handicap, ProgressGranularity, ServerMessage,
+};
 use sudoku_core::validation::is_board_complete;
-use sudoku_core::{Board, Cell, Difficulty};
+use sudoku_core::board::{board_checksum, givens_string, parse_givens};
+use sudoku_core::{Board, Cell, Difficulty, SolutionBoard};
 
-use crate::db;
+use crate::{db, RECENT_OPPONENT_SECS};
 use crate::state::*;
 
+/// Puzzle reports keep at most this many characters of the player's reason.
+const MAX_REPORT_REASON_LEN: usize = 200;
+/// After this long in the queue a player takes a wider rating gap, or a
+/// recent opponent.
+const LONG_WAIT_SECS: u64 = 30;
+/// `SpectateRandom` picks among this many of the highest-rated games.
+const SPECTATE_RANDOM_POOL: usize = 3;
+
 /// Top-level WebSocket handler -- spawned per connection.
 pub async fn handle_socket(
     state: Arc<AppState>,
@@ -28,8 +39,9 @@ pub async fn handle_socket(
 
     let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
 
-    // Register connection handle.
-    state.connections.insert(
+    // Register connection handle. One connection per user: a newer one takes
+    // over the room and spectating of the old, which is told and closed.
+    let replaced = state.connections.insert(
         user_id,
         ConnectionHandle {
             user_id,
@@ -37,10 +49,19 @@ pub async fn handle_socket(
             rating,
             tx: tx.clone(),
             room_code: None,
+            spectating: None,
             message_count: 0,
             rate_limit_window: Instant::now(),
         },
     );
+    if let Some(old) = replaced {
+        if let Some(mut conn) = state.connections.get_mut(&user_id) {
+            conn.room_code = old.room_code;
+            conn.spectating = old.spectating;
+        }
+        let _ = old.tx.send(ServerMessage::SessionReplaced);
+    }
+    rejoin_game(&state, user_id);
 
     loop {
         tokio::select! {
@@ -51,6 +72,10 @@ pub async fn handle_socket(
                         break;
                     }
                 }
+                if matches!(msg, ServerMessage::SessionReplaced) {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
             }
             // Inbound: read from the WebSocket.
             maybe_msg = socket.recv() => {
@@ -59,8 +84,9 @@ pub async fn handle_socket(
                         // Rate limiting: max 20 messages per second.
                         {
                             let mut conn = match state.connections.get_mut(&user_id) {
-                                Some(c) => c,
-                                None => break,
+                                Some(c) if c.tx.same_channel(&tx) => c,
+                                // Gone, or replaced by a newer connection
+                                _ => break,
                             };
                             let now = Instant::now();
                             if now.duration_since(conn.rate_limit_window) > Duration::from_secs(1) {
@@ -71,6 +97,7 @@ pub async fn handle_socket(
                             if conn.message_count > 20 {
                                 let _ = conn.tx.send(ServerMessage::Error {
                                     message: "Rate limited".into(),
+                                    req_id: None,
                                 });
                                 continue;
                             }
@@ -81,6 +108,7 @@ pub async fn handle_socket(
                             Err(e) => {
                                 let _ = tx.send(ServerMessage::Error {
                                     message: format!("Invalid message: {}", e),
+                                    req_id: None,
                                 });
                                 continue;
                             }
@@ -88,7 +116,9 @@ pub async fn handle_socket(
 
                         handle_message(&state, user_id, &username, rating, &tx, client_msg).await;
                     }
-                    Some(Ok(Message::Close(_))) | None => {
+                    // Errors include a message over `max_message_bytes`;
+                    // the socket is unusable after any of them
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
                         break;
                     }
                     _ => continue,
@@ -97,23 +127,45 @@ pub async fn handle_socket(
         }
     }
 
+    // A newer connection took over: the game, queue and everything else are
+    // its now.
+    if state
+        .connections
+        .get(&user_id)
+        .is_some_and(|c| !c.tx.same_channel(&tx))
+    {
+        state.connection_count.fetch_sub(1, Ordering::Relaxed);
+        return;
+    }
+
     // Disconnected -- start grace period.
     let room_code = state
         .connections
         .get(&user_id)
         .and_then(|c| c.room_code.clone());
 
+    // A room nobody has joined yet has no game to forfeit: free the code now
+    // instead of leaving it to the idle sweep.
+    let room_code = room_code.filter(|code| {
+        state
+            .rooms
+            .remove_if(code, |_, room| {
+                room.state == RoomState::Waiting && room.player1_id == user_id
+            })
+            .is_none()
+    });
+
     if let Some(code) = room_code {
         // Notify opponent of disconnect.
         if let Some(opponent_id) = get_opponent(&state, &code, user_id) {
-            send_to(&state, opponent_id, ServerMessage::OpponentDisconnected);
+            let grace_secs = state.timeouts.grace_secs;
+            send_to(&state, opponent_id, ServerMessage::OpponentDisconnected { grace_secs });
         }
 
-        // 30-second grace period.
         let grace_state = state.clone();
         let grace_code = code.clone();
         tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_secs(30)).await;
+            tokio::time::sleep(Duration::from_secs(grace_state.timeouts.grace_secs)).await;
             // If still disconnected (connection handle gone), forfeit.
             if !grace_state.connections.contains_key(&user_id) {
                 forfeit_player(&grace_state, &grace_code, user_id).await;
@@ -121,15 +173,41 @@ pub async fn handle_socket(
         });
     }
 
+    stop_spectating(&state, user_id);
+
     // Remove matchmaking entries.
     for mut queue in state.matchmaking.iter_mut() {
         queue.value_mut().retain(|e| e.user_id != user_id);
     }
 
-    state.connections.remove(&user_id);
+    state
+        .connections
+        .remove_if(&user_id, |_, c| c.tx.same_channel(&tx));
     state.connection_count.fetch_sub(1, Ordering::Relaxed);
 }
 
+/// A player back within the grace period picks up the game they dropped out
+/// of, and their opponent hears they're back.
+fn rejoin_game(state: &AppState, user_id: i64) {
+    let Some(code) = state
+        .rooms
+        .iter()
+        .find(|room| {
+            room.state == RoomState::Playing
+                && (room.player1_id == user_id || room.player2_id == Some(user_id))
+        })
+        .map(|room| room.code.clone())
+    else {
+        return;
+    };
+    if let Some(mut conn) = state.connections.get_mut(&user_id) {
+        conn.room_code = Some(code.clone());
+    }
+    if let Some(opponent_id) = get_opponent(state, &code, user_id) {
+        send_to(state, opponent_id, ServerMessage::OpponentReconnected);
+    }
+}
+
 /// Public wrapper so the cleanup task in main.rs can call forfeit.
 pub async fn forfeit_player_public(state: &AppState, room_code: &str, player_id: i64) {
     forfeit_player(state, room_code, player_id).await;
@@ -153,8 +231,51 @@ async fn handle_message(
             });
         }
 
-        ClientMessage::CreateRoom { mode, difficulty } => {
-            let (board, solution) = sudoku_core::puzzle::generate_puzzle(difficulty);
+        ClientMessage::CreateRoom {
+            mode,
+            difficulty,
+            handicap,
+            progress,
+            puzzle,
+            req_id,
+        } => {
+            if handicap.is_some_and(|handicap| !handicap.is_within_limits()) {
+                let _ = tx.send(ServerMessage::Error {
+                    message: format!(
+                        "Handicap can be at most {} fewer givens or a {}s late start",
+                        Handicap::MAX_FEWER_GIVENS,
+                        Handicap::MAX_TIME_PENALTY_SECS
+                    ),
+                    req_id,
+                });
+                return;
+            }
+            let (board, solution, seed) = match puzzle {
+                Some(text) => {
+                    // Solving a sparse import can take a while; keep it off
+                    // the connection's worker
+                    let imported = tokio::task::spawn_blocking(move || {
+                        let board = parse_givens(&text)?;
+                        sudoku_core::puzzle::unique_solution(&board).map(|sol| (board, sol, None))
+                    })
+                    .await
+                    .expect("checking the puzzle panicked");
+                    match imported {
+                        Some(pair) => pair,
+                        None => {
+                            let _ = tx.send(ServerMessage::Error {
+                                message: "Puzzle must be 81 cells with exactly one solution".into(),
+                                req_id,
+                            });
+                            return;
+                        }
+                    }
+                }
+                None => {
+                    let (board, solution, seed) = generate_match_puzzle(state, difficulty).await;
+                    (board, solution, Some(seed))
+                }
+            };
             let code = generate_room_code();
 
             let room = Room {
@@ -166,6 +287,7 @@ async fn handle_message(
                 player2_id: None,
                 board,
                 solution,
+                seed,
                 player_boards: {
                     let mut m = HashMap::new();
                     m.insert(user_id, board);
@@ -176,6 +298,15 @@ async fn handle_message(
                 created_at: Instant::now(),
                 last_activity: Instant::now(),
                 started_at: None,
+                handicap,
+                progress,
+                penalized_until: None,
+                idle_warned_at: None,
+                correct_since: HashMap::new(),
+                mistakes: HashMap::new(),
+                spectators: HashSet::new(),
+                winner: None,
+                rematch_offer: None,
             };
 
             state.rooms.insert(code.clone(), room);
@@ -185,18 +316,102 @@ async fn handle_message(
                 conn.room_code = Some(code.clone());
             }
 
-            let _ = tx.send(ServerMessage::RoomCreated { code });
+            let _ = tx.send(ServerMessage::RoomCreated { code, req_id });
             let _ = tx.send(ServerMessage::WaitingForOpponent);
         }
 
-        ClientMessage::JoinRoom { code } => {
+        ClientMessage::UpdateRoomSettings {
+            mode,
+            difficulty,
+            req_id,
+        } => {
+            let code = state
+                .connections
+                .get(&user_id)
+                .and_then(|c| c.room_code.clone());
+            let error = |message: &str| {
+                let _ = tx.send(ServerMessage::Error {
+                    message: message.into(),
+                    req_id,
+                });
+            };
+            let Some(code) = code.filter(|code| state.rooms.contains_key(code)) else {
+                error("You're not in a room");
+                return;
+            };
+            let mut regenerate = false;
+            if let Some(room) = state.rooms.get(&code) {
+                if room.player1_id != user_id {
+                    error("Only the room's creator can change its settings");
+                    return;
+                }
+                if room.state != RoomState::Waiting {
+                    error("The game has already started");
+                    return;
+                }
+                // An imported puzzle has no seed and is kept whatever the
+                // difficulty says; a generated one is only remade for a new one
+                regenerate = room.seed.is_some() && room.difficulty != difficulty;
+            }
+
+            let puzzle = if regenerate {
+                Some(generate_match_puzzle(state, difficulty).await)
+            } else {
+                None
+            };
+            // Someone may have joined while the puzzle was being made
+            let Some(mut room) = state.rooms.get_mut(&code) else {
+                error("You're not in a room");
+                return;
+            };
+            if room.state != RoomState::Waiting {
+                error("The game has already started");
+                return;
+            }
+            room.mode = mode;
+            room.difficulty = difficulty;
+            if let Some((board, solution, seed)) = puzzle {
+                room.board = board;
+                room.solution = solution;
+                room.seed = Some(seed);
+                room.shared_board = board;
+                room.player_boards.insert(user_id, board);
+            }
+            drop(room);
+
+            let _ = tx.send(ServerMessage::RoomSettingsUpdated {
+                mode,
+                difficulty,
+                req_id,
+            });
+        }
+
+        ClientMessage::JoinRoom { code, req_id } => {
             let code = code.to_uppercase();
+            // Settle the handicap before taking the room for writing: a harder
+            // board means counting solutions, too slow to do under the guard.
+            let Some(setup) = state
+                .rooms
+                .get(&code)
+                .map(|room| (room.handicap, room.mode, room.board, room.player1_id))
+            else {
+                let _ = tx.send(ServerMessage::Error {
+                    message: "Room not found".into(),
+                    req_id,
+                });
+                return;
+            };
+            let (handicap, mode, board, p1_id) = setup;
+            let plan = plan_handicap(state, handicap, mode, board, p1_id, user_id).await;
+            let handicapped = plan.as_ref().map(|plan| (plan.player, plan.handicap));
+
             let start_info = {
                 let mut room = match state.rooms.get_mut(&code) {
                     Some(r) => r,
                     None => {
                         let _ = tx.send(ServerMessage::Error {
                             message: "Room not found".into(),
+                            req_id,
                         });
                         return;
                     }
@@ -205,6 +420,7 @@ async fn handle_message(
                 if room.state != RoomState::Waiting {
                     let _ = tx.send(ServerMessage::Error {
                         message: "Room is not accepting players".into(),
+                        req_id,
                     });
                     return;
                 }
@@ -212,6 +428,15 @@ async fn handle_message(
                 if room.player1_id == user_id {
                     let _ = tx.send(ServerMessage::Error {
                         message: "Cannot join your own room".into(),
+                        req_id,
+                    });
+                    return;
+                }
+
+                if (room.mode, room.board) != (mode, board) {
+                    let _ = tx.send(ServerMessage::Error {
+                        message: "The room changed while you were joining, try again".into(),
+                        req_id,
                     });
                     return;
                 }
@@ -228,15 +453,18 @@ async fn handle_message(
                     conn.room_code = Some(code.clone());
                 }
 
+                apply_handicap(&mut room, plan);
+
                 Some((
                     room.mode,
                     room.difficulty,
-                    board_to_wire(&room.board),
-                    room.player1_id,
+                    board_to_wire(player_board(&room, p1_id)),
+                    board_to_wire(player_board(&room, user_id)),
+                    p1_id,
                 ))
             };
 
-            if let Some((mode, difficulty, wire_board, p1_id)) = start_info {
+            if let Some((mode, difficulty, p1_board, wire_board, p1_id)) = start_info {
                 let p1_name = state
                     .connections
                     .get(&p1_id)
@@ -255,6 +483,8 @@ async fn handle_message(
                     board: wire_board.clone(),
                     opponent_name: p1_name,
                     opponent_rating: p1_rating,
+                    code: code.clone(),
+                    handicap: handicap_of(handicapped, user_id),
                 });
 
                 // Send MatchStarted to player1 (creator).
@@ -264,40 +494,54 @@ async fn handle_message(
                     ServerMessage::MatchStarted {
                         mode,
                         difficulty,
-                        board: wire_board,
+                        board: p1_board,
                         opponent_name: username.to_string(),
                         opponent_rating: rating,
+                        code: code.clone(),
+                        handicap: handicap_of(handicapped, p1_id),
                     },
                 );
 
                 // For race mode, spawn progress broadcaster.
                 if mode == GameMode::Race {
                     spawn_progress_broadcaster(state.clone(), code.clone(), user_id, p1_id);
+                } else {
+                    spawn_checksum_broadcaster(state.clone(), code.clone(), user_id, p1_id);
                 }
             }
         }
 
         ClientMessage::QuickMatch { mode, difficulty } => {
-            let key = queue_key(mode, difficulty);
-
             // Try to find a match first.
             let matched = {
-                let mut queue = state.matchmaking.entry(key.clone()).or_default();
+                let mut queue = state.matchmaking.entry((mode, difficulty)).or_default();
                 let now = Instant::now();
 
+                // Prefer someone we haven't just played; a recent opponent
+                // only once they've waited long enough that nobody else is
+                // likely to come.
                 let mut match_idx = None;
+                let mut rematch_idx = None;
                 for (i, entry) in queue.iter().enumerate() {
                     if entry.user_id == user_id {
                         // Already queued.
                         return;
                     }
                     let wait_secs = now.duration_since(entry.joined_at).as_secs();
-                    let elo_range = if wait_secs > 30 { 400 } else { 200 };
-                    if (rating - entry.rating).abs() <= elo_range {
+                    let long_wait = wait_secs > LONG_WAIT_SECS;
+                    let elo_range = if long_wait { 400 } else { 200 };
+                    if (rating - entry.rating).abs() > elo_range {
+                        continue;
+                    }
+                    if !played_recently(state, user_id, entry.user_id, now) {
                         match_idx = Some(i);
                         break;
                     }
+                    if long_wait && rematch_idx.is_none() {
+                        rematch_idx = Some(i);
+                    }
                 }
+                let match_idx = match_idx.or(rematch_idx);
 
                 if let Some(i) = match_idx {
                     Some(queue.remove(i))
@@ -313,81 +557,31 @@ async fn handle_message(
             };
 
             if let Some(opponent) = matched {
-                // Create a room and start the game.
-                let (board, solution) = sudoku_core::puzzle::generate_puzzle(difficulty);
-                let code = generate_room_code();
-
-                let new_room = Room {
-                    code: code.clone(),
-                    mode,
-                    difficulty,
-                    state: RoomState::Playing,
-                    player1_id: opponent.user_id,
-                    player2_id: Some(user_id),
-                    board,
-                    solution,
-                    player_boards: {
-                        let mut m = HashMap::new();
-                        m.insert(opponent.user_id, board);
-                        m.insert(user_id, board);
-                        m
-                    },
-                    cell_ownership: HashMap::new(),
-                    shared_board: board,
-                    created_at: Instant::now(),
-                    last_activity: Instant::now(),
-                    started_at: Some(Instant::now()),
+                let me = QueueEntry {
+                    user_id,
+                    username: username.to_string(),
+                    rating,
+                    joined_at: Instant::now(),
                 };
-
-                state.rooms.insert(code.clone(), new_room);
-
-                // Associate connections.
-                if let Some(mut c) = state.connections.get_mut(&user_id) {
-                    c.room_code = Some(code.clone());
-                }
-                if let Some(mut c) = state.connections.get_mut(&opponent.user_id) {
-                    c.room_code = Some(code.clone());
-                }
-
-                let wire_board = board_to_wire(&board);
-
-                // Send to opponent (player1).
-                send_to(
-                    state,
-                    opponent.user_id,
-                    ServerMessage::MatchStarted {
-                        mode,
-                        difficulty,
-                        board: wire_board.clone(),
-                        opponent_name: username.to_string(),
-                        opponent_rating: rating,
-                    },
-                );
-
-                // Send to us (player2).
-                let _ = tx.send(ServerMessage::MatchStarted {
-                    mode,
-                    difficulty,
-                    board: wire_board,
-                    opponent_name: opponent.username,
-                    opponent_rating: opponent.rating,
-                });
-
-                if mode == GameMode::Race {
-                    spawn_progress_broadcaster(state.clone(), code, user_id, opponent.user_id);
-                }
+                start_quick_match(state, mode, difficulty, opponent, me).await;
             } else {
                 let _ = tx.send(ServerMessage::WaitingForOpponent);
             }
         }
 
-        ClientMessage::PlaceNumber { row, col, value } => {
+        ClientMessage::PlaceNumber {
+            row,
+            col,
+            value,
+            req_id,
+        } => {
             let room_code =
                 match state.connections.get(&user_id).and_then(|c| c.room_code.clone()) {
                     Some(c) => c,
                     None => {
                         let _ = tx.send(ServerMessage::Error {
                             message: "Not in a room".into(),
+                            req_id,
                         });
                         return;
                     }
@@ -398,6 +592,7 @@ async fn handle_message(
                     row,
                     col,
                     reason: "Invalid position or value".into(),
+                    req_id,
                 });
                 return;
             }
@@ -411,18 +606,30 @@ async fn handle_message(
                 if room.state != RoomState::Playing {
                     let _ = tx.send(ServerMessage::Error {
                         message: "Game is not in progress".into(),
+                        req_id,
                     });
                     return;
                 }
 
                 room.last_activity = Instant::now();
 
+                if let Some(wait) = handicap_wait(&room, user_id) {
+                    let _ = tx.send(ServerMessage::MoveRejected {
+                        row,
+                        col,
+                        reason: format!("Handicap: you can start in {}s", wait.as_secs() + 1),
+                        req_id,
+                    });
+                    return;
+                }
+
                 // Check if the cell is a given.
-                if room.board[row][col].is_given() {
+                if player_board(&room, user_id)[row][col].is_given() {
                     let _ = tx.send(ServerMessage::MoveRejected {
                         row,
                         col,
                         reason: "Cannot modify a given cell".into(),
+                        req_id,
                     });
                     return;
                 }
@@ -447,6 +654,9 @@ async fn handle_message(
                             room.player_boards.insert(user_id, initial_board);
                         }
                         let player_board = room.player_boards.get_mut(&user_id).unwrap();
+                        // A resend of the same digit isn't a second mistake
+                        let wrong = value != solution[row][col]
+                            && player_board[row][col] != Cell::UserInput(value);
                         player_board[row][col] = Cell::UserInput(value);
 
                         let all_filled = player_board.iter().all(|row| {
@@ -454,6 +664,12 @@ async fn handle_message(
                         });
                         let my_filled = filled_count(player_board);
                         let my_correct = correct_count(player_board, &solution);
+                        room.record_correct(user_id, my_correct);
+                        let mistakes = wrong.then(|| {
+                            let count = room.mistakes.entry(user_id).or_insert(0);
+                            *count += 1;
+                            *count
+                        });
                         // All correct = game over. All filled but some wrong = notify player.
                         let all_correct = all_filled && my_correct == my_filled;
 
@@ -482,15 +698,26 @@ async fn handle_message(
                             opp_filled,
                             my_correct,
                             opp_correct,
+                            mistakes,
                         }
                     }
                     GameMode::Shared => {
+                        // A resend of a placement this player already made
+                        // (e.g. replayed after a reconnect) is acked as-is.
+                        if room.cell_ownership.get(&(row, col)) == Some(&user_id)
+                            && room.shared_board[row][col] == Cell::UserInput(value)
+                        {
+                            let _ = tx.send(ServerMessage::MoveAccepted { row, col, value, req_id });
+                            return;
+                        }
+
                         // First-write-wins: if already placed by someone, reject.
                         if room.cell_ownership.contains_key(&(row, col)) {
                             let _ = tx.send(ServerMessage::MoveRejected {
                                 row,
                                 col,
                                 reason: "Cell already claimed".into(),
+                                req_id,
                             });
                             return;
                         }
@@ -502,7 +729,6 @@ async fn handle_message(
                         let all_filled = room.shared_board.iter().all(|row| {
                             row.iter().all(|cell| cell.value().is_some())
                         });
-                        let all_correct = all_filled && is_board_complete(&room.shared_board);
                         let opponent_id = if room.player1_id == user_id {
                             room.player2_id
                         } else {
@@ -523,10 +749,17 @@ async fn handle_message(
                                 oid,
                             ))
                             .unwrap_or(0);
+                        room.record_correct(user_id, my_score);
 
                         let total_user_cells: u32 = room.cell_ownership.len() as u32;
                         let total_correct = my_score + opp_score;
                         let wrong_cells = if all_filled { total_user_cells - total_correct } else { 0 };
+                        // Checked against the solution as well as for conflicts:
+                        // first-write-wins lets wrong values onto the board, and
+                        // a full board isn't a finished game until they're fixed.
+                        let all_correct = all_filled
+                            && wrong_cells == 0
+                            && is_board_complete(&room.shared_board);
 
                         if all_correct {
                             room.state = RoomState::Ended;
@@ -551,7 +784,7 @@ async fn handle_message(
             };
 
             // Send move accepted.
-            let _ = tx.send(ServerMessage::MoveAccepted { row, col, value });
+            let _ = tx.send(ServerMessage::MoveAccepted { row, col, value, req_id });
 
             match result {
                 PlaceResult::Race {
@@ -566,7 +799,11 @@ async fn handle_message(
                     opp_filled: _,
                     my_correct,
                     opp_correct,
+                    mistakes,
                 } => {
+                    if let (Some(count), Some(oid)) = (mistakes, opponent_id) {
+                        send_to(state, oid, ServerMessage::OpponentMistakes { count });
+                    }
                     if complete {
                         // All cells correct — this player wins
                         let opp_id = opponent_id.unwrap_or(user_id);
@@ -597,6 +834,7 @@ async fn handle_message(
                     }
 
                     if complete {
+                        // Equal scores are settled in end_game by who got there first.
                         let (winner_id, loser_id, w_score, l_score) = if my_score >= opp_score {
                             (user_id, opponent_id.unwrap_or(user_id), my_score, opp_score)
                         } else {
@@ -613,7 +851,11 @@ async fn handle_message(
                         )
                         .await;
                     } else if all_filled && wrong_cells > 0 {
+                        // Either player may own the wrong cells, so both hear it
                         let _ = tx.send(ServerMessage::BoardIncomplete { wrong_cells });
+                        if let Some(oid) = opponent_id {
+                            send_to(state, oid, ServerMessage::BoardIncomplete { wrong_cells });
+                        }
                     }
                 }
             }
@@ -642,14 +884,21 @@ async fn handle_message(
 
                 room.last_activity = Instant::now();
 
-                if room.board[row][col].is_given() {
+                if handicap_wait(&room, user_id).is_some()
+                    || player_board(&room, user_id)[row][col].is_given()
+                {
                     return;
                 }
 
+                // Dropping below a count gives up when it was reached, so
+                // the tie-break doesn't credit an earlier time for it
+                let solution = room.solution;
                 match room.mode {
                     GameMode::Race => {
                         if let Some(player_board) = room.player_boards.get_mut(&user_id) {
                             player_board[row][col] = Cell::Empty;
+                            let correct = correct_count(player_board, &solution);
+                            room.record_correct(user_id, correct);
                         }
                         None // No broadcast in race mode.
                     }
@@ -660,6 +909,13 @@ async fn handle_message(
                         }
                         room.shared_board[row][col] = Cell::Empty;
                         room.cell_ownership.remove(&(row, col));
+                        let correct = count_correct_for_player(
+                            &room.cell_ownership,
+                            &room.shared_board,
+                            &solution,
+                            user_id,
+                        );
+                        room.record_correct(user_id, correct);
 
                         if room.player1_id == user_id {
                             room.player2_id
@@ -687,6 +943,62 @@ async fn handle_message(
             }
         }
 
+        ClientMessage::RequestSync => {
+            let room_code =
+                match state.connections.get(&user_id).and_then(|c| c.room_code.clone()) {
+                    Some(c) => c,
+                    None => return,
+                };
+
+            let sync = {
+                let room = match state.rooms.get(&room_code) {
+                    Some(r) => r,
+                    None => return,
+                };
+                if room.mode != GameMode::Shared {
+                    return;
+                }
+                let mut owners = vec![vec![0u8; 9]; 9];
+                for (&(r, c), &owner) in room.cell_ownership.iter() {
+                    owners[r][c] = if owner == user_id { 1 } else { 2 };
+                }
+                ServerMessage::BoardSync {
+                    board: board_values_to_wire(&room.shared_board),
+                    owners,
+                    elapsed_secs: room.elapsed_secs(),
+                }
+            };
+            let _ = tx.send(sync);
+        }
+
+        ClientMessage::ReportPuzzle { reason } => {
+            let room_code =
+                match state.connections.get(&user_id).and_then(|c| c.room_code.clone()) {
+                    Some(c) => c,
+                    None => return,
+                };
+
+            let (difficulty, puzzle) = {
+                let room = match state.rooms.get(&room_code) {
+                    Some(r) => r,
+                    None => return,
+                };
+                (
+                    format!("{:?}", room.difficulty),
+                    givens_string(player_board(&room, user_id), 9),
+                )
+            };
+            let reason: String = reason.chars().take(MAX_REPORT_REASON_LEN).collect();
+
+            println!(
+                "[report] user={} room={} difficulty={} puzzle={} reason={:?}",
+                username, room_code, difficulty, puzzle, reason
+            );
+            let _ =
+                db::record_report(&state.db, user_id, &room_code, &difficulty, &puzzle, &reason)
+                    .await;
+        }
+
         ClientMessage::Forfeit => {
             let room_code =
                 match state.connections.get(&user_id).and_then(|c| c.room_code.clone()) {
@@ -696,7 +1008,7 @@ async fn handle_message(
             forfeit_player(state, &room_code, user_id).await;
         }
 
-        ClientMessage::Rematch => {
+        ClientMessage::Rematch { difficulty } => {
             let room_code =
                 match state.connections.get(&user_id).and_then(|c| c.room_code.clone()) {
                     Some(c) => c,
@@ -704,7 +1016,7 @@ async fn handle_message(
                 };
 
             let new_room_info = {
-                let room = match state.rooms.get(&room_code) {
+                let mut room = match state.rooms.get_mut(&room_code) {
                     Some(r) => r,
                     None => return,
                 };
@@ -716,20 +1028,42 @@ async fn handle_message(
                 } else {
                     Some(room.player1_id)
                 };
-                (room.mode, room.difficulty, opponent_id)
+                let wanted = difficulty.unwrap_or(room.difficulty);
+                // The first ask is an offer; the game starts once the other
+                // player asks too, at the easier of the two difficulties.
+                match room.rematch_offer {
+                    Some((asker, theirs)) if asker != user_id => {
+                        (room.mode, wanted.min(theirs), opponent_id, room.handicap, room.progress)
+                    }
+                    _ => {
+                        room.rematch_offer = Some((user_id, wanted));
+                        drop(room);
+                        if let Some(opponent_id) = opponent_id {
+                            send_to(
+                                state,
+                                opponent_id,
+                                ServerMessage::RematchOffered { difficulty: wanted },
+                            );
+                        }
+                        return;
+                    }
+                }
             };
 
-            let (mode, difficulty, opponent_id) = new_room_info;
+            let (mode, difficulty, opponent_id, handicap, progress) = new_room_info;
             let opponent_id = match opponent_id {
                 Some(id) => id,
                 None => return,
             };
 
             // Generate new puzzle and room.
-            let (board, solution) = sudoku_core::puzzle::generate_puzzle(difficulty);
+            let (board, solution, seed) = generate_match_puzzle(state, difficulty).await;
+            let seed = Some(seed);
+            let plan = plan_handicap(state, handicap, mode, board, user_id, opponent_id).await;
+            let handicapped = plan.as_ref().map(|plan| (plan.player, plan.handicap));
             let new_code = generate_room_code();
 
-            let new_room = Room {
+            let mut new_room = Room {
                 code: new_code.clone(),
                 mode,
                 difficulty,
@@ -738,6 +1072,7 @@ async fn handle_message(
                 player2_id: Some(opponent_id),
                 board,
                 solution,
+                seed,
                 player_boards: {
                     let mut m = HashMap::new();
                     m.insert(user_id, board);
@@ -749,7 +1084,19 @@ async fn handle_message(
                 created_at: Instant::now(),
                 last_activity: Instant::now(),
                 started_at: Some(Instant::now()),
+                handicap,
+                progress,
+                penalized_until: None,
+                idle_warned_at: None,
+                correct_since: HashMap::new(),
+                mistakes: HashMap::new(),
+                spectators: HashSet::new(),
+                winner: None,
+                rematch_offer: None,
             };
+            apply_handicap(&mut new_room, plan);
+            let my_board = board_to_wire(player_board(&new_room, user_id));
+            let opp_board = board_to_wire(player_board(&new_room, opponent_id));
 
             state.rooms.insert(new_code.clone(), new_room);
 
@@ -761,7 +1108,6 @@ async fn handle_message(
                 c.room_code = Some(new_code.clone());
             }
 
-            let wire_board = board_to_wire(&board);
             let opp_name = state
                 .connections
                 .get(&opponent_id)
@@ -776,9 +1122,11 @@ async fn handle_message(
             let _ = tx.send(ServerMessage::MatchStarted {
                 mode,
                 difficulty,
-                board: wire_board.clone(),
+                board: my_board,
                 opponent_name: opp_name,
                 opponent_rating: opp_rating,
+                code: new_code.clone(),
+                handicap: handicap_of(handicapped, user_id),
             });
 
             send_to(
@@ -787,14 +1135,18 @@ async fn handle_message(
                 ServerMessage::MatchStarted {
                     mode,
                     difficulty,
-                    board: wire_board,
+                    board: opp_board,
                     opponent_name: username.to_string(),
                     opponent_rating: rating,
+                    code: new_code.clone(),
+                    handicap: handicap_of(handicapped, opponent_id),
                 },
             );
 
             if mode == GameMode::Race {
                 spawn_progress_broadcaster(state.clone(), new_code, user_id, opponent_id);
+            } else {
+                spawn_checksum_broadcaster(state.clone(), new_code, user_id, opponent_id);
             }
 
             // Clean up old room.
@@ -804,6 +1156,320 @@ async fn handle_message(
         ClientMessage::Ping => {
             let _ = tx.send(ServerMessage::Pong);
         }
+
+        ClientMessage::Spectate { code } => spectate(state, user_id, tx, code.to_uppercase()),
+
+        ClientMessage::SpectateRandom => match pick_game_to_watch(state, user_id) {
+            Some(code) => spectate(state, user_id, tx, code),
+            None => {
+                let _ = tx.send(ServerMessage::Error {
+                    message: "No games being played right now, try again soon".into(),
+                    req_id: None,
+                });
+            }
+        },
+
+        ClientMessage::StopSpectating => {
+            stop_spectating(state, user_id);
+        }
+    }
+}
+
+/// Add `user_id` to the spectators of room `code` and send them what's on
+/// the board so far.
+fn spectate(
+    state: &AppState,
+    user_id: i64,
+    tx: &mpsc::UnboundedSender<ServerMessage>,
+    code: String,
+) {
+    stop_spectating(state, user_id);
+
+    let watched = {
+        let mut room = match state.rooms.get_mut(&code) {
+            Some(r) => r,
+            None => {
+                let _ = tx.send(ServerMessage::Error {
+                    message: "Room not found".into(),
+                    req_id: None,
+                });
+                return;
+            }
+        };
+        match room.state {
+            RoomState::Playing => {}
+            // Came back after the final whistle: just the result
+            RoomState::Ended => {
+                let winner = room.winner.clone();
+                drop(room);
+                let _ = tx.send(ServerMessage::SpectatedGameEnded { code, winner });
+                return;
+            }
+            RoomState::Waiting => {
+                let _ = tx.send(ServerMessage::Error {
+                    message: "No game in progress in that room".into(),
+                    req_id: None,
+                });
+                return;
+            }
+        }
+        if room.player1_id == user_id || room.player2_id == Some(user_id) {
+            let _ = tx.send(ServerMessage::Error {
+                message: "Cannot spectate your own game".into(),
+                req_id: None,
+            });
+            return;
+        }
+        room.spectators.insert(user_id);
+        // Same snapshot a player gets from RequestSync, seen from player 1's side
+        let sync = (room.mode == GameMode::Shared).then(|| {
+            let mut owners = vec![vec![0u8; 9]; 9];
+            for (&(r, c), &owner) in room.cell_ownership.iter() {
+                owners[r][c] = if owner == room.player1_id { 1 } else { 2 };
+            }
+            ServerMessage::BoardSync {
+                board: board_values_to_wire(&room.shared_board),
+                owners,
+                elapsed_secs: room.elapsed_secs(),
+            }
+        });
+        (
+            room.mode,
+            room.difficulty,
+            board_to_wire(&room.board),
+            [Some(room.player1_id), room.player2_id],
+            sync,
+            room.elapsed_secs(),
+        )
+    };
+
+    if let Some(mut conn) = state.connections.get_mut(&user_id) {
+        conn.spectating = Some(code.clone());
+    }
+    let (mode, difficulty, board, player_ids, sync, elapsed_secs) = watched;
+    let players = player_ids
+        .into_iter()
+        .flatten()
+        .filter_map(|id| state.connections.get(&id).map(|c| c.username.clone()))
+        .collect();
+    let _ = tx.send(ServerMessage::Spectating {
+        code: code.clone(),
+        mode,
+        difficulty,
+        board,
+        players,
+        elapsed_secs,
+    });
+    if let Some(sync) = sync {
+        let _ = tx.send(sync);
+    }
+    broadcast_spectator_count(state, &code);
+}
+
+/// A game in progress for `user_id` to watch: one of the few with the
+/// highest-rated players, chosen at random so watchers spread out. None if
+/// nothing is being played apart from their own game.
+fn pick_game_to_watch(state: &AppState, user_id: i64) -> Option<String> {
+    use rand::RngExt;
+    let rating = |id: Option<i64>| {
+        id.and_then(|id| state.connections.get(&id).map(|c| c.rating))
+            .unwrap_or(DEFAULT_RATING)
+    };
+    let mut games: Vec<(i32, String)> = state
+        .rooms
+        .iter()
+        .filter(|room| {
+            room.state == RoomState::Playing
+                && room.player1_id != user_id
+                && room.player2_id != Some(user_id)
+        })
+        .map(|room| (rating(Some(room.player1_id)) + rating(room.player2_id), room.code.clone()))
+        .collect();
+    games.sort_unstable_by(|a, b| b.cmp(a));
+    games.truncate(SPECTATE_RANDOM_POOL);
+    if games.is_empty() {
+        return None;
+    }
+    let pick = rand::rng().random_range(0..games.len());
+    Some(games.swap_remove(pick).1)
+}
+
+/// Take `user_id` off the spectators of whichever room they're watching.
+fn stop_spectating(state: &AppState, user_id: i64) {
+    let code = state
+        .connections
+        .get_mut(&user_id)
+        .and_then(|mut c| c.spectating.take());
+    let Some(code) = code else {
+        return;
+    };
+    if let Some(mut room) = state.rooms.get_mut(&code) {
+        room.spectators.remove(&user_id);
+    }
+    broadcast_spectator_count(state, &code);
+}
+
+/// Start a quick match between a player who was waiting in the queue and
+/// the one they were paired with.
+async fn start_quick_match(
+    state: &Arc<AppState>,
+    mode: GameMode,
+    difficulty: Difficulty,
+    waiting: QueueEntry,
+    joining: QueueEntry,
+) {
+    let (board, solution, seed) = generate_match_puzzle(state, difficulty).await;
+    let seed = Some(seed);
+    let code = generate_room_code();
+
+    let new_room = Room {
+        code: code.clone(),
+        mode,
+        difficulty,
+        state: RoomState::Playing,
+        player1_id: waiting.user_id,
+        player2_id: Some(joining.user_id),
+        board,
+        solution,
+        seed,
+        player_boards: {
+            let mut m = HashMap::new();
+            m.insert(waiting.user_id, board);
+            m.insert(joining.user_id, board);
+            m
+        },
+        cell_ownership: HashMap::new(),
+        shared_board: board,
+        created_at: Instant::now(),
+        last_activity: Instant::now(),
+        started_at: Some(Instant::now()),
+        handicap: None,
+        progress: ProgressGranularity::Exact,
+        penalized_until: None,
+        idle_warned_at: None,
+        correct_since: HashMap::new(),
+        mistakes: HashMap::new(),
+        spectators: HashSet::new(),
+        winner: None,
+        rematch_offer: None,
+    };
+
+    state.rooms.insert(code.clone(), new_room);
+
+    // Associate connections.
+    for player in [&waiting, &joining] {
+        if let Some(mut c) = state.connections.get_mut(&player.user_id) {
+            c.room_code = Some(code.clone());
+        }
+    }
+
+    let wire_board = board_to_wire(&board);
+    for (player, opponent) in [(&waiting, &joining), (&joining, &waiting)] {
+        send_to(
+            state,
+            player.user_id,
+            ServerMessage::MatchStarted {
+                mode,
+                difficulty,
+                board: wire_board.clone(),
+                opponent_name: opponent.username.clone(),
+                opponent_rating: opponent.rating,
+                code: code.clone(),
+                handicap: None,
+            },
+        );
+    }
+
+    if mode == GameMode::Race {
+        spawn_progress_broadcaster(state.clone(), code, joining.user_id, waiting.user_id);
+    } else {
+        spawn_checksum_broadcaster(state.clone(), code, joining.user_id, waiting.user_id);
+    }
+}
+
+/// Pair up quick-match players who have waited long enough to take a wider
+/// rating gap or a recent opponent. A player only looks for a match as they
+/// join, so without this two such players alone in a queue would wait forever.
+pub async fn pair_long_waiters(state: &Arc<AppState>, now: Instant) {
+    let mut pairs = Vec::new();
+    for mut entry in state.matchmaking.iter_mut() {
+        let (mode, difficulty) = *entry.key();
+        let queue = entry.value_mut();
+        let mut i = 0;
+        // The queue is longest waiting first, so stop at the first short wait
+        while i < queue.len()
+            && now.saturating_duration_since(queue[i].joined_at).as_secs() > LONG_WAIT_SECS
+        {
+            let candidates = || {
+                (i + 1..queue.len()).filter(|&j| (queue[i].rating - queue[j].rating).abs() <= 400)
+            };
+            let partner = candidates()
+                .find(|&j| !played_recently(state, queue[i].user_id, queue[j].user_id, now))
+                .or_else(|| candidates().next());
+            match partner {
+                Some(j) => {
+                    let joining = queue.remove(j);
+                    let waiting = queue.remove(i);
+                    pairs.push((mode, difficulty, waiting, joining));
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    for (mode, difficulty, waiting, joining) in pairs {
+        start_quick_match(state, mode, difficulty, waiting, joining).await;
+    }
+}
+
+/// Whether `a` and `b` finished a game together within `RECENT_OPPONENT_SECS`.
+fn played_recently(state: &AppState, a: i64, b: i64, now: Instant) -> bool {
+    state
+        .recent_pairings
+        .get(&pairing_key(a, b))
+        .is_some_and(|at| now.duration_since(*at) < Duration::from_secs(RECENT_OPPONENT_SECS))
+}
+
+/// The watched game is over: tell its spectators who won and let them go.
+/// The winner is kept on the room for anyone who reconnects to watch later.
+fn release_spectators(state: &AppState, room_code: &str, winner_id: i64) {
+    let winner = state.connections.get(&winner_id).map(|c| c.username.clone());
+    let spectators = {
+        let Some(mut room) = state.rooms.get_mut(room_code) else {
+            return;
+        };
+        room.winner = winner.clone();
+        std::mem::take(&mut room.spectators)
+    };
+    for spectator_id in spectators {
+        if let Some(mut conn) = state.connections.get_mut(&spectator_id)
+            && conn.spectating.as_deref() == Some(room_code)
+        {
+            conn.spectating = None;
+        }
+        send_to(
+            state,
+            spectator_id,
+            ServerMessage::SpectatedGameEnded {
+                code: room_code.to_string(),
+                winner: winner.clone(),
+            },
+        );
+    }
+}
+
+/// Tell both players of a room how many people are watching it.
+fn broadcast_spectator_count(state: &AppState, room_code: &str) {
+    let Some((players, count)) = state.rooms.get(room_code).map(|room| {
+        (
+            [Some(room.player1_id), room.player2_id],
+            room.spectators.len() as u32,
+        )
+    }) else {
+        return;
+    };
+    for player_id in players.into_iter().flatten() {
+        send_to(state, player_id, ServerMessage::SpectatorCount { count });
     }
 }
 
@@ -822,6 +1488,8 @@ enum PlaceResult {
         opp_filled: u32,
         my_correct: u32,
         opp_correct: u32,
+        /// The placer's mistake count, if this placement was one
+        mistakes: Option<u32>,
     },
     Shared {
         complete: bool,
@@ -856,7 +1524,92 @@ fn count_correct_for_player(
     count
 }
 
-fn send_to(state: &AppState, user_id: i64, msg: ServerMessage) {
+/// The board a player is solving: their own race board, else the room puzzle.
+fn player_board(room: &Room, user_id: i64) -> &Board {
+    match room.mode {
+        GameMode::Race => room.player_boards.get(&user_id).unwrap_or(&room.board),
+        GameMode::Shared => &room.board,
+    }
+}
+
+/// Who a room's handicap lands on and, for fewer givens, their board.
+struct HandicapPlan {
+    player: i64,
+    handicap: Handicap,
+    board: Option<Board>,
+}
+
+/// Decide who gets the room's handicap: the higher-rated player, or nobody
+/// when ratings are equal. The harder board is made on the blocking pool,
+/// since keeping it unique means counting solutions.
+async fn plan_handicap(
+    state: &AppState,
+    handicap: Option<Handicap>,
+    mode: GameMode,
+    board: Board,
+    p1_id: i64,
+    p2_id: i64,
+) -> Option<HandicapPlan> {
+    let handicap = handicap?;
+    let rating_of = |id: i64| state.connections.get(&id).map(|c| c.rating).unwrap_or(1200);
+    let player = match rating_of(p1_id).cmp(&rating_of(p2_id)) {
+        std::cmp::Ordering::Greater => p1_id,
+        std::cmp::Ordering::Less => p2_id,
+        std::cmp::Ordering::Equal => return None,
+    };
+
+    let board = match handicap {
+        // Boards are shared in shared mode, so there's nothing to make harder.
+        Handicap::FewerGivens { .. } if mode == GameMode::Shared => return None,
+        Handicap::FewerGivens { count } => Some(
+            tokio::task::spawn_blocking(move || {
+                sudoku_core::puzzle::remove_givens(&board, count as usize)
+            })
+            .await
+            .expect("removing givens panicked"),
+        ),
+        Handicap::TimePenalty { .. } => None,
+    };
+    Some(HandicapPlan {
+        player,
+        handicap,
+        board,
+    })
+}
+
+/// Apply a planned handicap once both players have joined.
+fn apply_handicap(room: &mut Room, plan: Option<HandicapPlan>) {
+    let Some(plan) = plan else {
+        return;
+    };
+    match plan.handicap {
+        Handicap::FewerGivens { .. } => {
+            if let Some(board) = plan.board {
+                room.player_boards.insert(plan.player, board);
+            }
+        }
+        Handicap::TimePenalty { secs } => {
+            let start = room.started_at.unwrap_or_else(Instant::now);
+            room.penalized_until = Some((plan.player, start + Duration::from_secs(secs as u64)));
+        }
+    }
+}
+
+/// The handicap for `MatchStarted`, for the player it landed on only.
+fn handicap_of(handicapped: Option<(i64, Handicap)>, user_id: i64) -> Option<Handicap> {
+    handicapped
+        .filter(|&(player, _)| player == user_id)
+        .map(|(_, handicap)| handicap)
+}
+
+/// Time left before a time-penalised player may move, if any.
+fn handicap_wait(room: &Room, user_id: i64) -> Option<Duration> {
+    let (player, until) = room.penalized_until?;
+    let now = Instant::now();
+    (player == user_id && now < until).then(|| until - now)
+}
+
+pub(crate) fn send_to(state: &AppState, user_id: i64, msg: ServerMessage) {
     if let Some(conn) = state.connections.get(&user_id) {
         let _ = conn.tx.send(msg);
     }
@@ -903,10 +1656,11 @@ async fn forfeit_player(state: &AppState, room_code: &str, forfeiter_id: i64) {
             duration,
             room.mode,
             room.difficulty,
+            room.seed,
         )
     };
 
-    let (winner_id, p1_id, p2_id, duration, mode, difficulty) = info;
+    let (winner_id, p1_id, p2_id, duration, mode, difficulty, seed) = info;
     let winner_id = match winner_id {
         Some(id) => id,
         None => return,
@@ -943,24 +1697,31 @@ async fn forfeit_player(state: &AppState, room_code: &str, forfeiter_id: i64) {
     )
     .await;
 
-    let (p1_elo_change, p2_elo_change) = if p1_id == winner_id {
-        (winner_change, loser_change)
+    let (p1_elo_change, p2_elo_change, p1_rating, p2_rating) = if p1_id == winner_id {
+        (winner_change, loser_change, new_winner_rating, new_loser_rating)
     } else {
-        (loser_change, winner_change)
+        (loser_change, winner_change, new_loser_rating, new_winner_rating)
     };
     let _ = db::record_match(
         &state.db,
-        p1_id,
-        p2_id,
-        &format!("{:?}", mode),
-        &format!("{:?}", difficulty),
-        Some(winner_id),
-        p1_elo_change,
-        p2_elo_change,
-        duration,
+        &db::NewMatch {
+            player1_id: p1_id,
+            player2_id: p2_id,
+            mode: &format!("{:?}", mode),
+            difficulty: &format!("{:?}", difficulty),
+            winner_id: Some(winner_id),
+            player1_elo_change: p1_elo_change,
+            player2_elo_change: p2_elo_change,
+            duration_secs: duration,
+            player1_rating_after: p1_rating,
+            player2_rating_after: p2_rating,
+            seed,
+        },
     )
     .await;
 
+    let solution = room_solution(state, room_code);
+
     // Notify winner.
     send_to(
         state,
@@ -971,6 +1732,8 @@ async fn forfeit_player(state: &AppState, room_code: &str, forfeiter_id: i64) {
             opponent_score: 0,
             elo_change: winner_change,
             new_rating: new_winner_rating,
+            solution,
+            seed,
         },
     );
 
@@ -984,9 +1747,17 @@ async fn forfeit_player(state: &AppState, room_code: &str, forfeiter_id: i64) {
             opponent_score: 0,
             elo_change: loser_change,
             new_rating: new_loser_rating,
+            solution,
+            seed,
         },
     );
 
+    send_final_boards(state, room_code);
+    release_spectators(state, room_code, winner_id);
+    state
+        .recent_pairings
+        .insert(pairing_key(p1_id, p2_id), Instant::now());
+
     // Update connection ratings.
     if let Some(mut c) = state.connections.get_mut(&winner_id) {
         c.rating = new_winner_rating;
@@ -1012,6 +1783,18 @@ async fn end_game(
         None => return,
     };
 
+    // On a tie, whoever reached that correct count earliest wins.
+    let (winner_id, loser_id) = if winner_score == loser_score
+        && state
+            .rooms
+            .get(room_code)
+            .is_some_and(|r| r.reached_first(loser_id, winner_id))
+    {
+        (loser_id, winner_id)
+    } else {
+        (winner_id, loser_id)
+    };
+
     let winner_rating = state
         .connections
         .get(&winner_id)
@@ -1040,26 +1823,34 @@ async fn end_game(
     let room_mode = state
         .rooms
         .get(room_code)
-        .map(|r| (r.mode, r.difficulty));
-    if let Some((mode, difficulty)) = room_mode {
-        let (p1_elo_change, p2_elo_change) = if p1_id == winner_id {
-            (winner_change, loser_change)
+        .map(|r| (r.mode, r.difficulty, r.seed));
+    if let Some((mode, difficulty, seed)) = room_mode {
+        let (p1_elo_change, p2_elo_change, p1_rating, p2_rating) = if p1_id == winner_id {
+            (winner_change, loser_change, new_winner_rating, new_loser_rating)
         } else {
-            (loser_change, winner_change)
+            (loser_change, winner_change, new_loser_rating, new_winner_rating)
         };
         let _ = db::record_match(
             &state.db,
-            p1_id,
-            p2_id,
-            &format!("{:?}", mode),
-            &format!("{:?}", difficulty),
-            Some(winner_id),
-            p1_elo_change,
-            p2_elo_change,
-            duration,
+            &db::NewMatch {
+                player1_id: p1_id,
+                player2_id: p2_id,
+                mode: &format!("{:?}", mode),
+                difficulty: &format!("{:?}", difficulty),
+                winner_id: Some(winner_id),
+                player1_elo_change: p1_elo_change,
+                player2_elo_change: p2_elo_change,
+                duration_secs: duration,
+                player1_rating_after: p1_rating,
+                player2_rating_after: p2_rating,
+                seed,
+            },
         )
         .await;
     }
+    let seed = room_mode.and_then(|(_, _, seed)| seed);
+
+    let solution = room_solution(state, room_code);
 
     // Notify winner.
     send_to(
@@ -1071,6 +1862,8 @@ async fn end_game(
             opponent_score: loser_score,
             elo_change: winner_change,
             new_rating: new_winner_rating,
+            solution,
+            seed,
         },
     );
 
@@ -1084,9 +1877,17 @@ async fn end_game(
             opponent_score: winner_score,
             elo_change: loser_change,
             new_rating: new_loser_rating,
+            solution,
+            seed,
         },
     );
 
+    send_final_boards(state, room_code);
+    release_spectators(state, room_code, winner_id);
+    state
+        .recent_pairings
+        .insert(pairing_key(p1_id, p2_id), Instant::now());
+
     // Update connection ratings.
     if let Some(mut c) = state.connections.get_mut(&winner_id) {
         c.rating = new_winner_rating;
@@ -1096,10 +1897,59 @@ async fn end_game(
     }
 }
 
+/// The room's solution for `GameEnd`. None until the room has ended, so it
+/// never gets out while the puzzle is still being played.
+fn room_solution(state: &AppState, room_code: &str) -> Option<SolutionBoard> {
+    let room = state.rooms.get(room_code)?;
+    (room.state == RoomState::Ended).then_some(room.solution)
+}
+
+/// Race mode: once the room has ended, show each player both finished boards.
+fn send_final_boards(state: &AppState, room_code: &str) {
+    let boards = {
+        let room = match state.rooms.get(room_code) {
+            Some(r) => r,
+            None => return,
+        };
+        // Revealing a board mid-game would leak the opponent's progress.
+        if room.mode != GameMode::Race || room.state != RoomState::Ended {
+            return;
+        }
+        let p2_id = match room.player2_id {
+            Some(id) => id,
+            None => return,
+        };
+        let board_of = |id: i64| {
+            board_values_to_wire(room.player_boards.get(&id).unwrap_or(&room.board))
+        };
+        (room.player1_id, board_of(room.player1_id), p2_id, board_of(p2_id))
+    };
+
+    let (p1_id, p1_board, p2_id, p2_board) = boards;
+    send_to(
+        state,
+        p1_id,
+        ServerMessage::FinalBoards {
+            mine: p1_board.clone(),
+            opponent: p2_board.clone(),
+        },
+    );
+    send_to(
+        state,
+        p2_id,
+        ServerMessage::FinalBoards {
+            mine: p2_board,
+            opponent: p1_board,
+        },
+    );
+}
+
 /// Spawn a task that broadcasts OpponentProgress every 2 seconds for race mode.
 fn spawn_progress_broadcaster(state: Arc<AppState>, room_code: String, p1: i64, p2: i64) {
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(2));
+        // Skip the immediate first tick; both boards are empty at match start.
+        let period = Duration::from_secs(2);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
         loop {
             interval.tick().await;
 
@@ -1112,38 +1962,51 @@ fn spawn_progress_broadcaster(state: Arc<AppState>, room_code: String, p1: i64,
                 break;
             }
 
-            let p1_filled = room
-                .player_boards
-                .get(&p1)
-                .map(|b| filled_count(b))
-                .unwrap_or(0);
-            let p2_filled = room
-                .player_boards
-                .get(&p2)
-                .map(|b| filled_count(b))
-                .unwrap_or(0);
+            // In fog only the quarter each racer has reached goes out.
+            let progress = |player: i64| {
+                let board = room.player_boards.get(&player);
+                match room.progress {
+                    ProgressGranularity::Exact => ServerMessage::OpponentProgress {
+                        filled_count: board.map(filled_count).unwrap_or(0),
+                        momentum: 0.0,
+                    },
+                    ProgressGranularity::Quartiles => ServerMessage::OpponentProgressCoarse {
+                        bucket: board.map(progress_quartile).unwrap_or(0),
+                    },
+                }
+            };
+            let p1_progress = progress(p1);
+            let p2_progress = progress(p2);
 
             drop(room);
 
             // Send p2's progress to p1.
-            send_to(
-                &state,
-                p1,
-                ServerMessage::OpponentProgress {
-                    filled_count: p2_filled,
-                    momentum: 0.0,
-                },
-            );
+            send_to(&state, p1, p2_progress);
 
             // Send p1's progress to p2.
-            send_to(
-                &state,
-                p2,
-                ServerMessage::OpponentProgress {
-                    filled_count: p1_filled,
-                    momentum: 0.0,
-                },
-            );
+            send_to(&state, p2, p1_progress);
+        }
+    });
+}
+
+/// Spawn a task that sends the shared board's checksum every 5 seconds so
+/// clients can detect a dropped update and ask for a resync.
+fn spawn_checksum_broadcaster(state: Arc<AppState>, room_code: String, p1: i64, p2: i64) {
+    tokio::spawn(async move {
+        let period = Duration::from_secs(5);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+
+            let hash = match state.rooms.get(&room_code) {
+                Some(room) if room.state == RoomState::Playing => {
+                    board_checksum(&room.shared_board)
+                }
+                _ => break,
+            };
+
+            send_to(&state, p1, ServerMessage::BoardChecksum { hash });
+            send_to(&state, p2, ServerMessage::BoardChecksum { hash });
         }
     });
 }
@@ -0,0 +1,224 @@
+//! Shared setup for the integration tests: a server per test, dev logins,
+//! raw WebSocket helpers, and `TestHarness` / `ClientHandle` on top of them
+//! for the usual two-player flows.
+
+// Each test binary that pulls this in uses only some of it
+#![allow(dead_code)]
+
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use sudoku_core::protocol::{AuthPollResponse, DeviceAuthResponse, GameMode};
+use sudoku_core::Difficulty;
+use sudoku_server::state::{AppState, Timeouts};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+pub type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Spin up a test server on a random port, return the base URL.
+pub async fn start_server() -> String {
+    start_server_with_state().await.0
+}
+
+/// Like `start_server`, but also hand back the shared state for tests that
+/// need to poke at server internals.
+pub async fn start_server_with_state() -> (String, Arc<AppState>) {
+    // In-memory SQLite so tests don't clash.
+    serve(sudoku_server::build_app("sqlite::memory:", Timeouts::default()).await).await
+}
+
+/// Like `start_server_with_state`, with the given timeouts instead of the
+/// defaults.
+pub async fn start_server_with_timeouts(timeouts: Timeouts) -> (String, Arc<AppState>) {
+    serve(sudoku_server::build_app("sqlite::memory:", timeouts).await).await
+}
+
+async fn serve((app, state): (axum::Router, Arc<AppState>)) -> (String, Arc<AppState>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    // Give the server a moment to start.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    (format!("http://127.0.0.1:{}", port), state)
+}
+
+/// Authenticate a dev user, return (token, username).
+pub async fn dev_auth(base: &str) -> (String, String) {
+    let client = reqwest::Client::new();
+
+    let resp: DeviceAuthResponse = client
+        .post(format!("{}/auth/device", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let poll: AuthPollResponse = client
+        .post(format!("{}/auth/poll", base))
+        .json(&json!({ "user_code": resp.user_code }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    match poll {
+        AuthPollResponse::Complete { token, username } => (token, username),
+        other => panic!("Expected Complete, got {:?}", other),
+    }
+}
+
+/// Connect a WebSocket client, return the split stream.
+pub async fn ws_connect(base: &str, token: &str) -> (WsSink, WsStream) {
+    let ws_url = base.replace("http://", "ws://");
+    let url = format!("{}/ws?token={}", ws_url, token);
+    let (stream, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    stream.split()
+}
+
+/// Send a JSON message over the WebSocket.
+pub async fn ws_send(
+    sink: &mut WsSink,
+    msg: serde_json::Value,
+) {
+    sink.send(Message::Text(msg.to_string().into()))
+        .await
+        .unwrap();
+}
+
+/// Receive messages until we get one matching the expected type.
+pub async fn ws_recv_type(
+    stream: &mut WsStream,
+    msg_type: &str,
+) -> serde_json::Value {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let remaining = deadline - tokio::time::Instant::now();
+        if remaining.is_zero() {
+            panic!("Timed out waiting for message type: {}", msg_type);
+        }
+        let msg = tokio::time::timeout(remaining, stream.next())
+            .await
+            .unwrap_or_else(|_| panic!("Timed out waiting for {}", msg_type))
+            .unwrap()
+            .unwrap();
+
+        if let Message::Text(text) = msg {
+            let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if parsed["type"].as_str() == Some(msg_type) {
+                return parsed;
+            }
+        }
+    }
+}
+
+// ── Harness ─────────────────────────────────────────────────────────────
+
+/// A running server plus what tests need to reach it.
+pub struct TestHarness {
+    pub base: String,
+    pub state: Arc<AppState>,
+}
+
+impl TestHarness {
+    pub async fn start() -> Self {
+        let (base, state) = start_server_with_state().await;
+        TestHarness { base, state }
+    }
+
+    pub async fn with_timeouts(timeouts: Timeouts) -> Self {
+        let (base, state) = start_server_with_timeouts(timeouts).await;
+        TestHarness { base, state }
+    }
+
+    /// A freshly logged-in dev user with a live connection.
+    pub async fn client(&self) -> ClientHandle {
+        let (token, username) = dev_auth(&self.base).await;
+        let (sink, stream) = ws_connect(&self.base, &token).await;
+        ClientHandle {
+            token,
+            username,
+            board: Vec::new(),
+            sink,
+            stream,
+        }
+    }
+
+    /// Two new players quick-matched into the same game. The first is queued
+    /// before the second asks, so which one waited is always the same.
+    pub async fn two_matched_players(
+        &self,
+        mode: GameMode,
+        difficulty: Difficulty,
+    ) -> (ClientHandle, ClientHandle) {
+        let mut a = self.client().await;
+        let mut b = self.client().await;
+        let quick_match = json!({"type": "QuickMatch", "mode": mode, "difficulty": difficulty});
+
+        a.send(quick_match.clone()).await;
+        a.expect("WaitingForOpponent").await;
+        b.send(quick_match).await;
+        for player in [&mut a, &mut b] {
+            let started = player.expect("MatchStarted").await;
+            player.board = serde_json::from_value(started["board"].clone()).unwrap();
+        }
+        (a, b)
+    }
+}
+
+/// One player's connection, with the puzzle from their last `MatchStarted`.
+pub struct ClientHandle {
+    pub token: String,
+    pub username: String,
+    /// Givens, 0 for empty; empty until a match starts
+    pub board: Vec<Vec<u8>>,
+    pub sink: WsSink,
+    pub stream: WsStream,
+}
+
+impl ClientHandle {
+    pub async fn send(&mut self, msg: serde_json::Value) {
+        ws_send(&mut self.sink, msg).await;
+    }
+
+    /// The next message of `msg_type`, skipping any others.
+    pub async fn expect(&mut self, msg_type: &str) -> serde_json::Value {
+        ws_recv_type(&mut self.stream, msg_type).await
+    }
+
+    pub async fn place(&mut self, row: usize, col: usize, value: u8) {
+        self.send(json!({"type": "PlaceNumber", "row": row, "col": col, "value": value}))
+            .await;
+    }
+
+    pub async fn forfeit(&mut self) {
+        self.send(json!({"type": "Forfeit"})).await;
+    }
+
+    /// The first empty cell of the puzzle, in reading order.
+    pub fn first_empty(&self) -> (usize, usize) {
+        all_cells().find(|&(r, c)| self.board[r][c] == 0).unwrap()
+    }
+
+    /// The first given cell of the puzzle, in reading order.
+    pub fn first_given(&self) -> (usize, usize) {
+        all_cells().find(|&(r, c)| self.board[r][c] != 0).unwrap()
+    }
+}
+
+fn all_cells() -> impl Iterator<Item = (usize, usize)> {
+    (0..9).flat_map(|r| (0..9).map(move |c| (r, c)))
+}
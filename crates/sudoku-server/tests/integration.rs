@@ -1,131 +1,63 @@
-use futures_util::{SinkExt, StreamExt};
+use futures_util::StreamExt;
 use serde_json::json;
-use std::time::Duration;
-use sudoku_core::protocol::{AuthPollResponse, DeviceAuthResponse, LeaderboardEntry};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use sudoku_core::board::board_checksum;
+use sudoku_core::protocol::{
+    AuthPollResponse, DeviceAuthResponse, LeaderboardEntry, MeResponse, PlayerProfile, RatingPoint,
+    SoloStats,
+};
+use sudoku_core::protocol::GameMode;
+use sudoku_core::puzzle::{generate_puzzle_seeded_in, GENERATOR_VERSION, SEED_LIMIT};
+use sudoku_core::{Cell, Difficulty, GridSpec};
+use sudoku_server::db::NewMatch;
+use sudoku_server::state::{board_to_wire, RoomState, Timeouts};
 use tokio::net::TcpListener;
 use tokio_tungstenite::tungstenite::Message;
 
-/// Spin up a test server on a random port, return the base URL.
-async fn start_server() -> String {
-    // In-memory SQLite so tests don't clash.
-    let (app, _state) = sudoku_server::build_app("sqlite::memory:").await;
+mod common;
 
-    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let port = listener.local_addr().unwrap().port();
-
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
-
-    // Give the server a moment to start.
-    tokio::time::sleep(Duration::from_millis(50)).await;
-
-    format!("http://127.0.0.1:{}", port)
-}
-
-/// Authenticate a dev user, return (token, username).
-async fn dev_auth(base: &str) -> (String, String) {
-    let client = reqwest::Client::new();
+use common::*;
 
-    let resp: DeviceAuthResponse = client
-        .post(format!("{}/auth/device", base))
-        .send()
-        .await
-        .unwrap()
-        .json()
-        .await
-        .unwrap();
+// ── Tests ───────────────────────────────────────────────────────────────
 
-    let poll: AuthPollResponse = client
-        .post(format!("{}/auth/poll", base))
-        .json(&json!({ "user_code": resp.user_code }))
-        .send()
+#[tokio::test]
+async fn test_health() {
+    let base = start_server().await;
+    let resp = reqwest::get(format!("{}/health", base))
         .await
         .unwrap()
-        .json()
+        .text()
         .await
         .unwrap();
-
-    match poll {
-        AuthPollResponse::Complete { token, username } => (token, username),
-        other => panic!("Expected Complete, got {:?}", other),
-    }
+    assert_eq!(resp, "ok");
 }
 
-/// Connect a WebSocket client, return the split stream.
-async fn ws_connect(
-    base: &str,
-    token: &str,
-) -> (
-    futures_util::stream::SplitSink<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-        Message,
-    >,
-    futures_util::stream::SplitStream<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-    >,
-) {
-    let ws_url = base.replace("http://", "ws://");
-    let url = format!("{}/ws?token={}", ws_url, token);
-    let (stream, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
-    stream.split()
-}
-
-/// Send a JSON message over the WebSocket.
-async fn ws_send(
-    sink: &mut futures_util::stream::SplitSink<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-        Message,
-    >,
-    msg: serde_json::Value,
-) {
-    sink.send(Message::Text(msg.to_string().into()))
-        .await
-        .unwrap();
-}
+#[tokio::test]
+async fn test_health_reports_a_lost_database() {
+    let (base, state) = start_server_with_state().await;
+    state.db.close().await;
 
-/// Receive messages until we get one matching the expected type.
-async fn ws_recv_type(
-    stream: &mut futures_util::stream::SplitStream<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-    >,
-    msg_type: &str,
-) -> serde_json::Value {
-    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
-    loop {
-        let remaining = deadline - tokio::time::Instant::now();
-        if remaining.is_zero() {
-            panic!("Timed out waiting for message type: {}", msg_type);
-        }
-        let msg = tokio::time::timeout(remaining, stream.next())
-            .await
-            .unwrap_or_else(|_| panic!("Timed out waiting for {}", msg_type))
-            .unwrap()
-            .unwrap();
-
-        if let Message::Text(text) = msg {
-            let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
-            if parsed["type"].as_str() == Some(msg_type) {
-                return parsed;
-            }
-        }
-    }
+    let resp = reqwest::get(format!("{}/health", base)).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.text().await.unwrap(), "database unavailable");
 }
 
-// ── Tests ───────────────────────────────────────────────────────────────
-
 #[tokio::test]
-async fn test_health() {
-    let base = start_server().await;
-    let resp = reqwest::get(format!("{}/health", base))
+async fn test_serves_on_an_ipv6_bind_address() {
+    let addr = sudoku_server::bind_addr(Some("[::1]:0"), Some("9999")).unwrap();
+    assert!(addr.is_ipv6());
+    assert!(sudoku_server::bind_addr(Some("::1:8080"), None).is_err());
+    assert!(sudoku_server::bind_addr(None, Some("http")).is_err());
+
+    let (app, _state) = sudoku_server::build_app("sqlite::memory:", Default::default()).await;
+    let listener = TcpListener::bind(addr).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let resp = reqwest::get(format!("http://[::1]:{}/health", port))
         .await
         .unwrap()
         .text()
@@ -134,6 +66,37 @@ async fn test_health() {
     assert_eq!(resp, "ok");
 }
 
+#[tokio::test]
+async fn test_oversized_messages_close_the_connection() {
+    assert_eq!(
+        sudoku_server::max_message_bytes(None),
+        Ok(sudoku_server::DEFAULT_MAX_MESSAGE_BYTES)
+    );
+    assert_eq!(sudoku_server::max_message_bytes(Some("1024")), Ok(1024));
+    assert!(sudoku_server::max_message_bytes(Some("0")).is_err());
+
+    let (base, state) = start_server_with_state().await;
+    state.max_message_bytes.store(1024, Ordering::Relaxed);
+    let (t1, _) = dev_auth(&base).await;
+    let (mut sink, mut stream) = ws_connect(&base, &t1).await;
+
+    ws_send(&mut sink, json!({"type": "Ping"})).await;
+    let _ = ws_recv_type(&mut stream, "Pong").await;
+
+    let reason = "x".repeat(2000);
+    ws_send(&mut sink, json!({"type": "ReportPuzzle", "reason": reason})).await;
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match stream.next().await {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "oversized message should end the connection");
+}
+
 #[tokio::test]
 async fn test_dev_auth_creates_unique_users() {
     let base = start_server().await;
@@ -147,6 +110,40 @@ async fn test_dev_auth_creates_unique_users() {
     assert!(u2.starts_with("dev_player_"));
 }
 
+#[tokio::test]
+async fn test_cancelled_device_code_expires() {
+    let base = start_server().await;
+    let client = reqwest::Client::new();
+
+    let resp: DeviceAuthResponse = client
+        .post(format!("{}/auth/device", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let cancel = client
+        .post(format!("{}/auth/cancel", base))
+        .json(&json!({ "user_code": resp.user_code }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cancel.status(), reqwest::StatusCode::NO_CONTENT);
+
+    let poll: AuthPollResponse = client
+        .post(format!("{}/auth/poll", base))
+        .json(&json!({ "user_code": resp.user_code }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(matches!(poll, AuthPollResponse::Expired), "got {:?}", poll);
+}
+
 #[tokio::test]
 async fn test_create_and_join_room() {
     let base = start_server().await;
@@ -170,9 +167,185 @@ async fn test_create_and_join_room() {
 
     let p2_match = ws_recv_type(&mut stream2, "MatchStarted").await;
     assert_eq!(p2_match["opponent_name"].as_str().unwrap(), u1);
+    assert_eq!(p2_match["code"], code);
 
     let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
     assert_eq!(p1_match["opponent_name"].as_str().unwrap(), u2);
+    assert_eq!(p1_match["code"], code);
+}
+
+#[tokio::test]
+async fn test_waiting_room_is_freed_when_creator_disconnects() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    assert!(state.rooms.contains_key(&code));
+
+    drop((sink1, stream1));
+    for _ in 0..100 {
+        if !state.rooms.contains_key(&code) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!state.rooms.contains_key(&code));
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let err = ws_recv_type(&mut stream2, "Error").await;
+    assert_eq!(err["message"], "Room not found");
+}
+
+#[tokio::test]
+async fn test_second_connection_replaces_the_first() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink_old, mut stream_old) = ws_connect(&base, &t1).await;
+    ws_send(&mut sink_old, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream_old, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+
+    // Same token again: the old socket is told, then closed
+    let (_sink_new, mut stream_new) = ws_connect(&base, &t1).await;
+    let _ = ws_recv_type(&mut stream_old, "SessionReplaced").await;
+    let rest = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(Ok(msg)) = stream_old.next().await {
+            if let Message::Close(_) = msg {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(rest.is_ok(), "old connection should be closed");
+
+    // The new connection kept the room
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(state.rooms.contains_key(&code));
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream_new, "MatchStarted").await;
+    assert_eq!(state.connection_count.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn test_host_can_change_settings_while_waiting() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+
+    ws_send(
+        &mut sink1,
+        json!({"type": "UpdateRoomSettings", "mode": "Shared", "difficulty": "Hard", "req_id": 4}),
+    )
+    .await;
+    let updated = ws_recv_type(&mut stream1, "RoomSettingsUpdated").await;
+    assert_eq!(updated["difficulty"], "Hard");
+    assert_eq!(updated["mode"], "Shared");
+    assert_eq!(updated["req_id"], 4);
+
+    // Someone who isn't in the room can't change it
+    ws_send(&mut sink2, json!({"type": "UpdateRoomSettings", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream2, "Error").await;
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let started = ws_recv_type(&mut stream2, "MatchStarted").await;
+    assert_eq!(started["difficulty"], "Hard");
+    assert_eq!(started["mode"], "Shared");
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+
+    // Too late once the game is on
+    ws_send(&mut sink1, json!({"type": "UpdateRoomSettings", "mode": "Race", "difficulty": "Easy"})).await;
+    let err = ws_recv_type(&mut stream1, "Error").await;
+    assert_eq!(err["message"], "The game has already started");
+}
+
+#[tokio::test]
+async fn test_create_room_with_imported_puzzle() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    let puzzle = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    // Only the first row: far too few givens for a unique solution
+    let ambiguous = format!("{}{}", &puzzle[..9], ".".repeat(72));
+    ws_send(
+        &mut sink1,
+        json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Hard", "puzzle": ambiguous}),
+    )
+    .await;
+    let err = ws_recv_type(&mut stream1, "Error").await;
+    assert!(err["message"].as_str().unwrap().contains("exactly one solution"));
+
+    ws_send(
+        &mut sink1,
+        json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Hard", "puzzle": puzzle}),
+    )
+    .await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap();
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+
+    let expected: Vec<Vec<u8>> = puzzle
+        .as_bytes()
+        .chunks(9)
+        .map(|row| row.iter().map(|&b| if b == b'.' { 0 } else { b - b'0' }).collect())
+        .collect();
+    for started in [
+        ws_recv_type(&mut stream1, "MatchStarted").await,
+        ws_recv_type(&mut stream2, "MatchStarted").await,
+    ] {
+        assert_eq!(started["difficulty"], "Hard");
+        let board: Vec<Vec<u8>> = serde_json::from_value(started["board"].clone()).unwrap();
+        assert_eq!(board, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_changing_settings_keeps_an_imported_puzzle() {
+    let harness = TestHarness::start().await;
+    let mut host = harness.client().await;
+    let mut guest = harness.client().await;
+
+    let puzzle = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    host.send(json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Hard", "puzzle": puzzle}))
+        .await;
+    let created = host.expect("RoomCreated").await;
+
+    host.send(json!({"type": "UpdateRoomSettings", "mode": "Shared", "difficulty": "Easy"}))
+        .await;
+    host.expect("RoomSettingsUpdated").await;
+
+    guest.send(json!({"type": "JoinRoom", "code": created["code"]})).await;
+    let started = guest.expect("MatchStarted").await;
+    assert_eq!(started["mode"], "Shared");
+    assert_eq!(started["difficulty"], "Easy");
+    let board: Vec<Vec<u8>> = serde_json::from_value(started["board"].clone()).unwrap();
+    assert_eq!(board[0], [5, 3, 0, 0, 7, 0, 0, 0, 0]);
+    assert_eq!(board[8], [0, 0, 0, 0, 8, 0, 0, 7, 9]);
 }
 
 #[tokio::test]
@@ -212,6 +385,91 @@ async fn test_quick_match_pairs_two_players() {
     assert_eq!(p1_match["opponent_name"].as_str().unwrap(), u2);
 }
 
+#[tokio::test]
+async fn test_quick_matches_name_their_room() {
+    let h = TestHarness::start().await;
+    let mut a = h.client().await;
+    let mut b = h.client().await;
+    let quick_match = json!({"type": "QuickMatch", "mode": "Shared", "difficulty": "Easy"});
+    a.send(quick_match.clone()).await;
+    a.expect("WaitingForOpponent").await;
+    b.send(quick_match).await;
+
+    let a_code = a.expect("MatchStarted").await["code"].clone();
+    let b_code = b.expect("MatchStarted").await["code"].clone();
+    assert_eq!(a_code, b_code);
+    assert!(h.state.rooms.contains_key(a_code.as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn test_quick_match_prefers_a_fresh_opponent() {
+    let base = start_server().await;
+
+    let (ta, ua) = dev_auth(&base).await;
+    let (tb, _) = dev_auth(&base).await;
+    let (tc, uc) = dev_auth(&base).await;
+
+    let (mut sink_a, mut stream_a) = ws_connect(&base, &ta).await;
+    let (mut sink_b, mut stream_b) = ws_connect(&base, &tb).await;
+    let (mut sink_c, mut stream_c) = ws_connect(&base, &tc).await;
+
+    // A and B play a game, which A forfeits
+    ws_send(&mut sink_a, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream_a, "WaitingForOpponent").await;
+    ws_send(&mut sink_b, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream_b, "MatchStarted").await;
+    ws_send(&mut sink_a, json!({"type": "Forfeit"})).await;
+    let _ = ws_recv_type(&mut stream_a, "GameEnd").await;
+    let _ = ws_recv_type(&mut stream_b, "GameEnd").await;
+
+    // Both queue again: B waits rather than facing A straight away
+    ws_send(&mut sink_a, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream_a, "WaitingForOpponent").await;
+    ws_send(&mut sink_b, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream_b, "WaitingForOpponent").await;
+
+    // C gets the first fresh opponent in the queue, A
+    ws_send(&mut sink_c, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let c_match = ws_recv_type(&mut stream_c, "MatchStarted").await;
+    assert_eq!(c_match["opponent_name"].as_str().unwrap(), ua);
+    let a_match = ws_recv_type(&mut stream_a, "MatchStarted").await;
+    assert_eq!(a_match["opponent_name"].as_str().unwrap(), uc);
+}
+
+#[tokio::test]
+async fn test_recent_opponents_alone_in_the_queue_are_paired_after_a_wait() {
+    let harness = TestHarness::start().await;
+    let (mut a, mut b) = harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
+    a.forfeit().await;
+    a.expect("GameEnd").await;
+    b.expect("GameEnd").await;
+
+    let quick_match = json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"});
+    a.send(quick_match.clone()).await;
+    a.expect("WaitingForOpponent").await;
+    b.send(quick_match).await;
+    b.expect("WaitingForOpponent").await;
+
+    // Still too soon for a rematch
+    sudoku_server::cleanup_at(&harness.state, Instant::now()).await;
+    let queued = |h: &TestHarness| {
+        h.state
+            .matchmaking
+            .get(&(GameMode::Race, Difficulty::Easy))
+            .map_or(0, |queue| queue.len())
+    };
+    assert_eq!(queued(&harness), 2);
+
+    // Once A has waited long enough and nobody else came, they play again
+    sudoku_server::cleanup_at(&harness.state, Instant::now() + Duration::from_secs(31)).await;
+    assert_eq!(queued(&harness), 0);
+    let a_match = a.expect("MatchStarted").await;
+    let b_match = b.expect("MatchStarted").await;
+    assert_eq!(a_match["opponent_name"].as_str().unwrap(), b.username);
+    assert_eq!(b_match["opponent_name"].as_str().unwrap(), a.username);
+    assert_eq!(a_match["code"], b_match["code"]);
+}
+
 #[tokio::test]
 async fn test_place_number_and_progress() {
     let base = start_server().await;
@@ -249,7 +507,7 @@ async fn test_place_number_and_progress() {
 }
 
 #[tokio::test]
-async fn test_cannot_place_on_given_cell() {
+async fn test_replies_echo_the_request_id() {
     let base = start_server().await;
 
     let (t1, _) = dev_auth(&base).await;
@@ -258,46 +516,67 @@ async fn test_cannot_place_on_given_cell() {
     let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
     let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
 
-    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
-    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
-    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
-
-    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
-    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
-    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": "ZZZZZZ", "req_id": 4})).await;
+    let err = ws_recv_type(&mut stream2, "Error").await;
+    assert_eq!(err["req_id"].as_u64(), Some(4));
 
-    // Find a given cell
-    let (gr, gc) = (0..9)
-        .flat_map(|r| (0..9).map(move |c| (r, c)))
-        .find(|(r, c)| board[*r][*c] != 0)
-        .unwrap();
+    ws_send(
+        &mut sink1,
+        json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy", "req_id": 1}),
+    )
+    .await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    assert_eq!(created["req_id"].as_u64(), Some(1));
+    let code = created["code"].as_str().unwrap();
 
-    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": gr, "col": gc, "value": 5})).await;
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let started = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(started["board"].clone()).unwrap();
+    let cells = || (0..9).flat_map(|r| (0..9).map(move |c| (r, c)));
+    let (gr, gc) = cells().find(|&(r, c)| board[r][c] != 0).unwrap();
+    let (er, ec) = cells().find(|&(r, c)| board[r][c] == 0).unwrap();
+
+    ws_send(
+        &mut sink1,
+        json!({"type": "PlaceNumber", "row": gr, "col": gc, "value": 5, "req_id": 2}),
+    )
+    .await;
     let rejected = ws_recv_type(&mut stream1, "MoveRejected").await;
-    assert!(rejected["reason"].as_str().unwrap().contains("given"));
-}
+    assert_eq!(rejected["req_id"].as_u64(), Some(2));
 
-#[tokio::test]
-async fn test_forfeit_updates_elo() {
-    let base = start_server().await;
+    ws_send(
+        &mut sink1,
+        json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 5, "req_id": 3}),
+    )
+    .await;
+    let accepted = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    assert_eq!(accepted["req_id"].as_u64(), Some(3));
 
-    let (t1, u1) = dev_auth(&base).await;
-    let (t2, u2) = dev_auth(&base).await;
+    // Requests without an id get replies without one
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 6})).await;
+    let accepted = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    assert!(accepted.get("req_id").is_none());
+}
 
-    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
-    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+#[tokio::test]
+async fn test_cannot_place_on_given_cell() {
+    let harness = TestHarness::start().await;
+    let (mut p1, _p2) = harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
 
-    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
-    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
-    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
-    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
-    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+    let (gr, gc) = p1.first_given();
+    p1.place(gr, gc, 5).await;
+    let rejected = p1.expect("MoveRejected").await;
+    assert!(rejected["reason"].as_str().unwrap().contains("given"));
+}
 
-    // P1 forfeits
-    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+#[tokio::test]
+async fn test_forfeit_updates_elo() {
+    let harness = TestHarness::start().await;
+    let (mut p1, mut p2) = harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
 
-    let end1 = ws_recv_type(&mut stream1, "GameEnd").await;
-    let end2 = ws_recv_type(&mut stream2, "GameEnd").await;
+    p1.forfeit().await;
+    let end1 = p1.expect("GameEnd").await;
+    let end2 = p2.expect("GameEnd").await;
 
     assert_eq!(end1["won"].as_bool().unwrap(), false);
     assert!(end1["elo_change"].as_i64().unwrap() < 0);
@@ -305,15 +584,15 @@ async fn test_forfeit_updates_elo() {
     assert!(end2["elo_change"].as_i64().unwrap() > 0);
 
     // Verify leaderboard
-    let lb: Vec<LeaderboardEntry> = reqwest::get(format!("{}/leaderboard", base))
+    let lb: Vec<LeaderboardEntry> = reqwest::get(format!("{}/leaderboard", harness.base))
         .await
         .unwrap()
         .json()
         .await
         .unwrap();
 
-    let winner = lb.iter().find(|e| e.username == u2).unwrap();
-    let loser = lb.iter().find(|e| e.username == u1).unwrap();
+    let winner = lb.iter().find(|e| e.username == p2.username).unwrap();
+    let loser = lb.iter().find(|e| e.username == p1.username).unwrap();
     assert_eq!(winner.wins, 1);
     assert!(winner.rating > 1200);
     assert_eq!(loser.losses, 1);
@@ -321,7 +600,38 @@ async fn test_forfeit_updates_elo() {
 }
 
 #[tokio::test]
-async fn test_wrong_number_accepted_in_race_mode() {
+async fn test_leaderboard_exports_as_csv() {
+    let harness = TestHarness::start().await;
+    let (mut p1, mut p2) = harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
+    p1.forfeit().await;
+    p1.expect("GameEnd").await;
+    p2.expect("GameEnd").await;
+
+    let lb: Vec<LeaderboardEntry> = reqwest::get(format!("{}/leaderboard", harness.base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let resp = reqwest::get(format!("{}/leaderboard?format=csv", harness.base))
+        .await
+        .unwrap();
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/csv"));
+    let csv = resp.text().await.unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "rank,username,rating,wins,losses");
+    assert_eq!(lines.len(), lb.len() + 1);
+    let winner = lb.iter().find(|e| e.username == p2.username).unwrap();
+    assert!(lines.contains(&format!("{},{},{},1,0", winner.rank, winner.username, winner.rating).as_str()));
+
+    let resp = reqwest::get(format!("{}/leaderboard?format=xml", harness.base))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_rematch_waits_for_both_and_takes_the_easier_difficulty() {
     let base = start_server().await;
 
     let (t1, _) = dev_auth(&base).await;
@@ -333,67 +643,1027 @@ async fn test_wrong_number_accepted_in_race_mode() {
     ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
     let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
     ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
-
-    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
-    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
     let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
 
-    // Find an empty cell
-    let (er, ec) = (0..9)
-        .flat_map(|r| (0..9).map(move |c| (r, c)))
-        .find(|(r, c)| board[*r][*c] == 0)
-        .unwrap();
-
-    // Place any number — should be accepted even if wrong
-    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 1})).await;
-    let result = ws_recv_type(&mut stream1, "MoveAccepted").await;
-    assert_eq!(result["type"].as_str().unwrap(), "MoveAccepted");
+    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+    let _ = ws_recv_type(&mut stream1, "GameEnd").await;
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
+
+    // The first ask only reaches the opponent as an offer
+    ws_send(&mut sink1, json!({"type": "Rematch", "difficulty": "Hard"})).await;
+    let offer = ws_recv_type(&mut stream2, "RematchOffered").await;
+    assert_eq!(offer["difficulty"], "Hard");
+
+    ws_send(&mut sink2, json!({"type": "Rematch", "difficulty": "Medium"})).await;
+    let p1 = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let p2 = ws_recv_type(&mut stream2, "MatchStarted").await;
+    assert_eq!(p1["difficulty"], "Medium");
+    assert_eq!(p2["difficulty"], "Medium");
 }
 
 #[tokio::test]
-async fn test_shared_mode_first_write_wins() {
-    let base = start_server().await;
+async fn test_idle_warning_precedes_forfeit() {
+    let (base, state) = start_server_with_state().await;
 
-    let (t1, _) = dev_auth(&base).await;
-    let (t2, _) = dev_auth(&base).await;
+    let (t1, _u1) = dev_auth(&base).await;
+    let (t2, _u2) = dev_auth(&base).await;
 
     let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
     let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
 
-    // Create shared mode room
-    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
-    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
-    let code = created["code"].as_str().unwrap();
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
     let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
-
-    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
-    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
-    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
     let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
 
-    // Find an empty cell
-    let (er, ec) = (0..9)
-        .flat_map(|r| (0..9).map(move |c| (r, c)))
+    let last_activity = state.rooms.iter().next().unwrap().last_activity;
+    let idle_for = |secs: u64| last_activity + Duration::from_secs(secs);
+
+    // Inside the warning window: both players are told how long they have, once.
+    sudoku_server::cleanup_at(&state, idle_for(250)).await;
+    let warn1 = ws_recv_type(&mut stream1, "IdleWarning").await;
+    let warn2 = ws_recv_type(&mut stream2, "IdleWarning").await;
+    assert_eq!(warn1["seconds_remaining"].as_u64().unwrap(), 50);
+    assert_eq!(warn2["seconds_remaining"].as_u64().unwrap(), 50);
+
+    // Past the threshold the room is forfeited.
+    sudoku_server::cleanup_at(&state, idle_for(310)).await;
+    let end1 = ws_recv_type(&mut stream1, "GameEnd").await;
+    let end2 = ws_recv_type(&mut stream2, "GameEnd").await;
+    assert!(!end1["won"].as_bool().unwrap());
+    assert!(end2["won"].as_bool().unwrap());
+}
+
+#[tokio::test]
+async fn test_report_puzzle_is_stored() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _u1) = dev_auth(&base).await;
+    let (t2, _u2) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let started = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    ws_send(&mut sink1, json!({"type": "ReportPuzzle", "reason": "Multiple solutions"})).await;
+    // Messages are handled in order, so the Pong means the report is in.
+    ws_send(&mut sink1, json!({"type": "Ping"})).await;
+    let _ = ws_recv_type(&mut stream1, "Pong").await;
+
+    let reports = sudoku_server::db::get_reports(&state.db, 10).await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].reason, "Multiple solutions");
+    assert_eq!(reports[0].difficulty, "Easy");
+    assert_eq!(reports[0].puzzle.len(), 81);
+    assert_eq!(
+        reports[0].puzzle.chars().filter(|&c| c != '.').count(),
+        count_givens(&started["board"])
+    );
+}
+
+#[tokio::test]
+async fn test_purging_sessions_drops_only_expired_ones() {
+    let (base, state) = start_server_with_state().await;
+    let (token, name) = dev_auth(&base).await;
+    let user_id = sudoku_server::db::get_user_by_username(&state.db, &name)
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    sqlx::query(
+        "INSERT INTO sessions (token, user_id, expires_at)
+         VALUES ('stale', ?1, datetime('now', '-1 day'))",
+    )
+    .bind(user_id)
+    .execute(&state.db)
+    .await
+    .unwrap();
+
+    assert_eq!(sudoku_server::db::purge_expired_sessions(&state.db).await.unwrap(), 1);
+    let left: Vec<String> = sqlx::query_scalar("SELECT token FROM sessions WHERE user_id = ?1")
+        .bind(user_id)
+        .fetch_all(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(left, vec![token.clone()]);
+    assert!(sudoku_server::db::get_session(&state.db, &token).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_head_to_head_counts_both_seats() {
+    let (base, state) = start_server_with_state().await;
+
+    let (_, u1) = dev_auth(&base).await;
+    let (_, u2) = dev_auth(&base).await;
+    let (_, u3) = dev_auth(&base).await;
+    let mut ids = Vec::new();
+    for name in [&u1, &u2, &u3] {
+        let user = sudoku_server::db::get_user_by_username(&state.db, name).await.unwrap();
+        ids.push(user.unwrap().id);
+    }
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+
+    // a beats b twice from either seat, b wins once, and a game against c doesn't count
+    for (p1, p2, winner, secs) in [(a, b, a, 100), (b, a, a, 200), (a, b, b, 300), (a, c, c, 50)] {
+        let new = NewMatch {
+            player1_id: p1,
+            player2_id: p2,
+            mode: "Race",
+            difficulty: "Easy",
+            winner_id: Some(winner),
+            duration_secs: secs,
+            ..Default::default()
+        };
+        sudoku_server::db::record_match(&state.db, &new).await.unwrap();
+    }
+
+    let h2h: serde_json::Value = reqwest::get(format!("{}/h2h/{}/{}", base, u2, u1))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(h2h["games"], 3);
+    assert_eq!(h2h["a_wins"], 1);
+    assert_eq!(h2h["b_wins"], 2);
+    assert_eq!(h2h["avg_duration_secs"], 200);
+
+    let missing = reqwest::get(format!("{}/h2h/{}/nobody", base, u1)).await.unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_profiles_show_the_most_played_difficulty_and_mode() {
+    let (base, state) = start_server_with_state().await;
+
+    let (_, u1) = dev_auth(&base).await;
+    let (_, u2) = dev_auth(&base).await;
+    let (_, u3) = dev_auth(&base).await;
+    let mut ids = Vec::new();
+    for name in [&u1, &u2, &u3] {
+        let user = sudoku_server::db::get_user_by_username(&state.db, name).await.unwrap();
+        ids.push(user.unwrap().id);
+    }
+    let (a, b) = (ids[0], ids[1]);
+
+    // a plays Hard twice and Shared twice; b's Race and Shared tie, the later winning
+    for (p1, p2, mode, difficulty) in [
+        (a, b, "Race", "Hard"),
+        (b, a, "Shared", "Easy"),
+        (a, ids[2], "Shared", "Hard"),
+    ] {
+        let new = NewMatch {
+            player1_id: p1,
+            player2_id: p2,
+            mode,
+            difficulty,
+            winner_id: Some(p1),
+            duration_secs: 60,
+            ..Default::default()
+        };
+        sudoku_server::db::record_match(&state.db, &new).await.unwrap();
+    }
+
+    let profile: PlayerProfile = reqwest::get(format!("{}/profile/{}", base, u1))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(profile.favorite_difficulty, Some(Difficulty::Hard));
+    assert_eq!(profile.favorite_mode, Some(GameMode::Shared));
+
+    let lb: Vec<LeaderboardEntry> = reqwest::get(format!("{}/leaderboard", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let entry = |name: &str| lb.iter().find(|e| e.username == name).unwrap().clone();
+    assert_eq!(entry(&u2).favorite_difficulty, Some(Difficulty::Easy));
+    assert_eq!(entry(&u2).favorite_mode, Some(GameMode::Shared));
+
+    // Nothing to go on before a first match
+    let (_, newcomer) = dev_auth(&base).await;
+    let profile: PlayerProfile = reqwest::get(format!("{}/profile/{}", base, newcomer))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(profile.favorite_difficulty, None);
+    assert_eq!(profile.favorite_mode, None);
+}
+
+#[tokio::test]
+async fn test_recompute_ratings_replays_matches_in_order() {
+    let (base, state) = start_server_with_state().await;
+
+    let (_, u1) = dev_auth(&base).await;
+    let (_, u2) = dev_auth(&base).await;
+    let (_, u3) = dev_auth(&base).await;
+    let mut ids = Vec::new();
+    for name in [&u1, &u2, &u3] {
+        let user = sudoku_server::db::get_user_by_username(&state.db, name).await.unwrap();
+        ids.push(user.unwrap().id);
+    }
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+
+    // Stored with made-up rating changes, as if from an older formula
+    for (p1, p2, winner) in [(a, b, Some(a)), (c, a, Some(a)), (b, c, Some(c)), (a, c, None)] {
+        let new = NewMatch {
+            player1_id: p1,
+            player2_id: p2,
+            mode: "Race",
+            difficulty: "Easy",
+            winner_id: winner,
+            player1_elo_change: 99,
+            player2_elo_change: -99,
+            duration_secs: 60,
+            player1_rating_after: 1299,
+            player2_rating_after: 1101,
+            seed: None,
+        };
+        sudoku_server::db::record_match(&state.db, &new).await.unwrap();
+    }
+
+    let replayed = sudoku_server::db::recompute_ratings(&state.db).await.unwrap();
+    assert_eq!(replayed, 4);
+
+    // By hand, K=32 from 1200: a beats b (1216/1184), a beats c (1231/1185),
+    // c beats b (1201/1168); the match with no winner changes nothing
+    let mut records = Vec::new();
+    for name in [&u1, &u2, &u3] {
+        let user = sudoku_server::db::get_user_by_username(&state.db, name).await.unwrap().unwrap();
+        records.push((user.rating, user.wins, user.losses));
+    }
+    assert_eq!(records, [(1231, 2, 0), (1168, 0, 2), (1201, 1, 1)]);
+
+    let history = sudoku_server::db::get_rating_history(&state.db, a).await.unwrap();
+    let ratings: Vec<i32> = history.iter().map(|(_, rating, _, _)| *rating).collect();
+    assert_eq!(ratings, [1216, 1231, 1231]);
+}
+
+#[tokio::test]
+async fn test_rating_history_follows_matches() {
+    let base = start_server().await;
+
+    let (t1, u1) = dev_auth(&base).await;
+    let (t2, _u2) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    let history_url = format!("{}/profile/{}/rating-history", base, u1);
+    let empty: Vec<RatingPoint> = reqwest::get(&history_url).await.unwrap().json().await.unwrap();
+    assert!(empty.is_empty());
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+    let end1 = ws_recv_type(&mut stream1, "GameEnd").await;
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
+
+    let history: Vec<RatingPoint> = reqwest::get(&history_url).await.unwrap().json().await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(
+        history[0].rating as i64,
+        1200 + end1["elo_change"].as_i64().unwrap()
+    );
+    assert!(!history[0].timestamp.is_empty());
+
+    let missing = reqwest::get(format!("{}/profile/nobody/rating-history", base))
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_match_seeds_rebuild_the_puzzle_and_are_recorded() {
+    let base = start_server().await;
+
+    let (t1, u1) = dev_auth(&base).await;
+    let (t2, _u2) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Medium"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Medium"})).await;
+    let started = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+    // The seed rebuilds the solution, so it stays secret until the end
+    assert!(started.get("seed").is_none());
+
+    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+    let ended = ws_recv_type(&mut stream1, "GameEnd").await;
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
+
+    let seed = ended["seed"].as_u64().unwrap();
+    // Drawn from the whole u64 range, not the short solo seeds
+    assert!(seed >= SEED_LIMIT);
+    let (rebuilt, _) = generate_puzzle_seeded_in(&GridSpec::CLASSIC, Difficulty::Medium, seed);
+    let board: Vec<Vec<u8>> = serde_json::from_value(started["board"].clone()).unwrap();
+    assert_eq!(board, board_to_wire(&rebuilt));
+
+    let history_url = format!("{}/profile/{}/rating-history", base, u1);
+    let history: Vec<RatingPoint> = reqwest::get(&history_url).await.unwrap().json().await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].seed, Some(seed));
+    assert_eq!(history[0].generator, GENERATOR_VERSION);
+}
+
+#[tokio::test]
+async fn test_race_end_sends_both_final_boards() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let started = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // P1 fills the first empty cell, then forfeits
+    let board = started["board"].as_array().unwrap();
+    let (row, col) = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .find(|&(r, c)| board[r][c].as_u64() == Some(0))
+        .unwrap();
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": row, "col": col, "value": 7})).await;
+    let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
+    let boards2 = ws_recv_type(&mut stream2, "FinalBoards").await;
+    assert_eq!(boards2["opponent"][row][col].as_u64(), Some(7));
+    assert_eq!(boards2["mine"][row][col].as_u64(), Some(0));
+
+    let boards1 = ws_recv_type(&mut stream1, "FinalBoards").await;
+    assert_eq!(boards1["mine"][row][col].as_u64(), Some(7));
+}
+
+fn count_givens(board: &serde_json::Value) -> usize {
+    board
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|row| row.as_array().unwrap())
+        .filter(|v| v.as_u64() != Some(0))
+        .count()
+}
+
+#[tokio::test]
+async fn test_handicap_gives_stronger_player_fewer_givens() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    // Play one forfeited game so P2 ends up rated above P1
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+    ws_send(&mut sink1, json!({"type": "Forfeit"})).await;
+    let _ = ws_recv_type(&mut stream1, "GameEnd").await;
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
+
+    // The weaker player creates a handicapped room, the stronger one joins
+    ws_send(
+        &mut sink1,
+        json!({
+            "type": "CreateRoom",
+            "mode": "Race",
+            "difficulty": "Easy",
+            "handicap": {"FewerGivens": {"count": 5}}
+        }),
+    )
+    .await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+
+    let weaker = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let stronger = ws_recv_type(&mut stream2, "MatchStarted").await;
+    assert_eq!(
+        count_givens(&stronger["board"]) + 5,
+        count_givens(&weaker["board"])
+    );
+}
+
+#[tokio::test]
+async fn test_time_penalty_holds_back_the_stronger_player() {
+    let harness = TestHarness::start().await;
+    let (mut weaker, mut stronger) =
+        harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
+
+    // A forfeit leaves the second player rated above the first
+    weaker.forfeit().await;
+    weaker.expect("GameEnd").await;
+    stronger.expect("GameEnd").await;
+
+    // Handicaps past the limits are refused
+    for handicap in [
+        json!({"TimePenalty": {"secs": u32::MAX}}),
+        json!({"FewerGivens": {"count": 255}}),
+    ] {
+        weaker
+            .send(json!({
+                "type": "CreateRoom",
+                "mode": "Race",
+                "difficulty": "Easy",
+                "handicap": handicap
+            }))
+            .await;
+        let error = weaker.expect("Error").await;
+        assert!(error["message"].as_str().unwrap().starts_with("Handicap"));
+    }
+
+    weaker
+        .send(json!({
+            "type": "CreateRoom",
+            "mode": "Race",
+            "difficulty": "Easy",
+            "handicap": {"TimePenalty": {"secs": 30}}
+        }))
+        .await;
+    let created = weaker.expect("RoomCreated").await;
+    stronger
+        .send(json!({"type": "JoinRoom", "code": created["code"]}))
+        .await;
+
+    // Only the penalised player is told about the handicap
+    let weaker_start = weaker.expect("MatchStarted").await;
+    let stronger_start = stronger.expect("MatchStarted").await;
+    assert!(weaker_start.get("handicap").is_none());
+    assert_eq!(stronger_start["handicap"], json!({"TimePenalty": {"secs": 30}}));
+    weaker.board = serde_json::from_value(weaker_start["board"].clone()).unwrap();
+
+    let (r, c) = weaker.first_empty();
+    stronger.place(r, c, 1).await;
+    let rejected = stronger.expect("MoveRejected").await;
+    assert!(rejected["reason"].as_str().unwrap().starts_with("Handicap"));
+    weaker.place(r, c, 1).await;
+    weaker.expect("MoveAccepted").await;
+}
+
+#[tokio::test]
+async fn test_shared_mode_checksum_and_resync() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let p2_match = ws_recv_type(&mut stream2, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p2_match["board"].clone()).unwrap();
+
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .find(|(r, c)| board[*r][*c] == 0)
+        .unwrap();
+
+    // P1 places; P2 pretends the OpponentPlaced update never arrived
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 3})).await;
+    let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+
+    let mut stale = [[Cell::Empty; 9]; 9];
+    for r in 0..9 {
+        for c in 0..9 {
+            if board[r][c] != 0 {
+                stale[r][c] = Cell::Given(board[r][c]);
+            }
+        }
+    }
+    let checksum = ws_recv_type(&mut stream2, "BoardChecksum").await;
+    assert_ne!(checksum["hash"].as_u64().unwrap(), board_checksum(&stale));
+
+    ws_send(&mut sink2, json!({"type": "RequestSync"})).await;
+    let sync = ws_recv_type(&mut stream2, "BoardSync").await;
+    assert_eq!(sync["board"][er][ec].as_u64(), Some(3));
+    assert_eq!(sync["owners"][er][ec].as_u64(), Some(2));
+
+    stale[er][ec] = Cell::UserInput(3);
+    assert_eq!(checksum["hash"].as_u64().unwrap(), board_checksum(&stale));
+}
+
+#[tokio::test]
+async fn test_snapshots_carry_the_match_clock() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+    let (t3, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    let (mut sink3, mut stream3) = ws_connect(&base, &t3).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // The game has been going for a minute and a half
+    state.rooms.get_mut(&code).unwrap().started_at =
+        Some(Instant::now() - Duration::from_secs(90));
+
+    ws_send(&mut sink2, json!({"type": "RequestSync"})).await;
+    let sync = ws_recv_type(&mut stream2, "BoardSync").await;
+    assert!((90..95).contains(&sync["elapsed_secs"].as_u64().unwrap()));
+
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let watching = ws_recv_type(&mut stream3, "Spectating").await;
+    assert!((90..95).contains(&watching["elapsed_secs"].as_u64().unwrap()));
+}
+
+#[tokio::test]
+async fn test_wrong_number_accepted_in_race_mode() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+
+    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Find an empty cell
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .find(|(r, c)| board[*r][*c] == 0)
+        .unwrap();
+
+    // Place any number — should be accepted even if wrong
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 1})).await;
+    let result = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    assert_eq!(result["type"].as_str().unwrap(), "MoveAccepted");
+}
+
+#[tokio::test]
+async fn test_shared_mode_first_write_wins() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    // Create shared mode room
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap();
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Find an empty cell
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .find(|(r, c)| board[*r][*c] == 0)
+        .unwrap();
+
+    // P1 claims the cell
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 3})).await;
+    let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+
+    // P2 should see OpponentPlaced
+    let opp = ws_recv_type(&mut stream2, "OpponentPlaced").await;
+    assert_eq!(opp["row"].as_u64().unwrap(), er as u64);
+
+    // P2 tries same cell — should be rejected
+    ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 5})).await;
+    let rejected = ws_recv_type(&mut stream2, "MoveRejected").await;
+    assert!(rejected["reason"].as_str().unwrap().contains("claimed"));
+}
+
+#[tokio::test]
+async fn test_players_hear_the_spectator_count() {
+    let base = start_server().await;
+
+    let (t1, name1) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+    let (t3, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    let (mut sink3, mut stream3) = ws_connect(&base, &t3).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+
+    // Nothing to watch until the game starts
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let err = ws_recv_type(&mut stream3, "Error").await;
+    assert!(err["message"].as_str().unwrap().contains("No game"));
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let watching = ws_recv_type(&mut stream3, "Spectating").await;
+    assert_eq!(watching["code"], code.as_str());
+    assert_eq!(watching["players"][0], name1.as_str());
+    for stream in [&mut stream1, &mut stream2] {
+        let count = ws_recv_type(stream, "SpectatorCount").await;
+        assert_eq!(count["count"], 1);
+    }
+
+    ws_send(&mut sink3, json!({"type": "StopSpectating"})).await;
+    for stream in [&mut stream1, &mut stream2] {
+        let count = ws_recv_type(stream, "SpectatorCount").await;
+        assert_eq!(count["count"], 0);
+    }
+
+    // Disconnecting counts as leaving
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let count = ws_recv_type(&mut stream2, "SpectatorCount").await;
+    assert_eq!(count["count"], 1);
+    drop((sink3, stream3));
+    let count = ws_recv_type(&mut stream2, "SpectatorCount").await;
+    assert_eq!(count["count"], 0);
+}
+
+#[tokio::test]
+async fn test_spectate_random_finds_a_game_without_its_code() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+    let (t3, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    let (mut sink3, mut stream3) = ws_connect(&base, &t3).await;
+
+    ws_send(&mut sink3, json!({"type": "SpectateRandom"})).await;
+    let err = ws_recv_type(&mut stream3, "Error").await;
+    assert!(err["message"].as_str().unwrap().contains("No games"));
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Players aren't sent to watch their own game
+    ws_send(&mut sink1, json!({"type": "SpectateRandom"})).await;
+    let _ = ws_recv_type(&mut stream1, "Error").await;
+
+    ws_send(&mut sink3, json!({"type": "SpectateRandom"})).await;
+    let watching = ws_recv_type(&mut stream3, "Spectating").await;
+    assert_eq!(watching["code"], code.as_str());
+    let count = ws_recv_type(&mut stream2, "SpectatorCount").await;
+    assert_eq!(count["count"], 1);
+}
+
+#[tokio::test]
+async fn test_player_back_within_grace_rejoins_the_game() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+    ws_send(&mut sink2, json!({"type": "QuickMatch", "mode": "Race", "difficulty": "Easy"})).await;
+    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    drop((sink2, stream2));
+    let _ = ws_recv_type(&mut stream1, "OpponentDisconnected").await;
+    // Let the old connection finish cleaning up before the new one registers
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+    let _ = ws_recv_type(&mut stream1, "OpponentReconnected").await;
+
+    // The returning player's moves count in the same room
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
         .find(|(r, c)| board[*r][*c] == 0)
         .unwrap();
+    ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 5})).await;
+    let _ = ws_recv_type(&mut stream2, "MoveAccepted").await;
+    let prog = ws_recv_type(&mut stream1, "OpponentProgress").await;
+    assert_eq!(prog["filled_count"].as_u64().unwrap(), 1);
+}
 
-    // P1 claims the cell
+#[tokio::test]
+async fn test_spectators_can_reconnect_and_hear_the_result() {
+    let base = start_server().await;
+
+    let (t1, name1) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+    let (t3, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .find(|(r, c)| board[*r][*c] == 0)
+        .unwrap();
     ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 3})).await;
     let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
 
-    // P2 should see OpponentPlaced
-    let opp = ws_recv_type(&mut stream2, "OpponentPlaced").await;
-    assert_eq!(opp["row"].as_u64().unwrap(), er as u64);
+    // Watch, drop the connection, and pick the game back up by code
+    let (mut sink3, mut stream3) = ws_connect(&base, &t3).await;
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let _ = ws_recv_type(&mut stream3, "Spectating").await;
+    let count = ws_recv_type(&mut stream1, "SpectatorCount").await;
+    assert_eq!(count["count"], 1);
+    drop((sink3, stream3));
+    // Let the old connection finish cleaning up before the new one registers
+    let count = ws_recv_type(&mut stream1, "SpectatorCount").await;
+    assert_eq!(count["count"], 0);
+    tokio::time::sleep(Duration::from_millis(50)).await;
 
-    // P2 tries same cell — should be rejected
-    ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 5})).await;
-    let rejected = ws_recv_type(&mut stream2, "MoveRejected").await;
+    let (mut sink3, mut stream3) = ws_connect(&base, &t3).await;
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let watching = ws_recv_type(&mut stream3, "Spectating").await;
+    assert_eq!(watching["code"], code.as_str());
+    let sync = ws_recv_type(&mut stream3, "BoardSync").await;
+    assert_eq!(sync["board"][er][ec], 3);
+    assert_eq!(sync["owners"][er][ec], 1);
+
+    ws_send(&mut sink2, json!({"type": "Forfeit"})).await;
+    let ended = ws_recv_type(&mut stream3, "SpectatedGameEnded").await;
+    assert_eq!(ended["winner"], name1.as_str());
+
+    // Coming back after the game only gets the result
+    ws_send(&mut sink3, json!({"type": "Spectate", "code": code})).await;
+    let ended = ws_recv_type(&mut stream3, "SpectatedGameEnded").await;
+    assert_eq!(ended["code"], code.as_str());
+    assert_eq!(ended["winner"], name1.as_str());
+}
+
+#[tokio::test]
+async fn test_shared_mode_repeated_placement_is_acked() {
+    let base = start_server().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap();
+    let _ = ws_recv_type(&mut stream1, "WaitingForOpponent").await;
+
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    let (er, ec) = (0..9)
+        .flat_map(|r| (0..9).map(move |c| (r, c)))
+        .find(|(r, c)| board[*r][*c] == 0)
+        .unwrap();
+
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 3, "req_id": 1})).await;
+    let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+
+    // Same cell, same value, same player: a duplicate, not a conflict
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 3, "req_id": 2})).await;
+    let resent = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    assert_eq!(resent["req_id"], 2);
+
+    // A different value on your own cell is still a claimed cell
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": 4, "req_id": 3})).await;
+    let rejected = ws_recv_type(&mut stream1, "MoveRejected").await;
     assert!(rejected["reason"].as_str().unwrap().contains("claimed"));
+    assert_eq!(rejected["req_id"], 3);
+}
+
+#[tokio::test]
+async fn test_shared_mode_tie_goes_to_first_to_reach_score() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Fill in all but four empty cells so each player can score two and tie,
+    // without tripping the per-second message limit.
+    let (solution, mut empties) = {
+        let mut room = state.rooms.get_mut(&code).unwrap();
+        let mut empties: Vec<(usize, usize)> = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| room.shared_board[r][c].value().is_none())
+            .collect();
+        for (r, c) in empties.split_off(4) {
+            let given = Cell::Given(room.solution[r][c]);
+            room.shared_board[r][c] = given;
+            room.board[r][c] = given;
+        }
+        (room.solution, empties)
+    };
+
+    // P1 reaches the tied score first; P2 gets there later and completes the board.
+    let p2_cells = empties.split_off(empties.len() / 2);
+    for (r, c) in empties {
+        ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r, "col": c, "value": solution[r][c]})).await;
+        let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    }
+    for (r, c) in p2_cells {
+        ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": r, "col": c, "value": solution[r][c]})).await;
+        let _ = ws_recv_type(&mut stream2, "MoveAccepted").await;
+    }
+
+    let end1 = ws_recv_type(&mut stream1, "GameEnd").await;
+    let end2 = ws_recv_type(&mut stream2, "GameEnd").await;
+    assert_eq!(end1["your_score"], end1["opponent_score"]);
+    assert!(end1["won"].as_bool().unwrap());
+    assert!(!end2["won"].as_bool().unwrap());
+}
+
+#[tokio::test]
+async fn test_erasing_gives_up_the_time_a_score_was_reached() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Leave four empty cells, two for each player
+    let (solution, mut empties) = {
+        let mut room = state.rooms.get_mut(&code).unwrap();
+        let mut empties: Vec<(usize, usize)> = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| room.shared_board[r][c].value().is_none())
+            .collect();
+        for (r, c) in empties.split_off(4) {
+            let given = Cell::Given(room.solution[r][c]);
+            room.shared_board[r][c] = given;
+            room.board[r][c] = given;
+        }
+        (room.solution, empties)
+    };
+
+    // P1 reaches two first, then drops back to one
+    let p2_cells = empties.split_off(2);
+    for &(r, c) in &empties {
+        ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r, "col": c, "value": solution[r][c]})).await;
+        let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    }
+    let (er, ec) = empties[0];
+    ws_send(&mut sink1, json!({"type": "EraseNumber", "row": er, "col": ec})).await;
+    let _ = ws_recv_type(&mut stream2, "OpponentErased").await;
+
+    // P2 reaches two while P1 is down, and P1's re-entry finishes the board
+    for (r, c) in p2_cells {
+        ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": r, "col": c, "value": solution[r][c]})).await;
+        let _ = ws_recv_type(&mut stream2, "MoveAccepted").await;
+    }
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": er, "col": ec, "value": solution[er][ec]})).await;
+
+    let end1 = ws_recv_type(&mut stream1, "GameEnd").await;
+    let end2 = ws_recv_type(&mut stream2, "GameEnd").await;
+    assert_eq!(end1["your_score"], end1["opponent_score"]);
+    assert!(!end1["won"].as_bool().unwrap());
+    assert!(end2["won"].as_bool().unwrap());
+}
+
+#[tokio::test]
+async fn test_shared_board_full_of_mistakes_plays_on_until_fixed() {
+    let (base, state) = start_server_with_state().await;
+
+    let (t1, _) = dev_auth(&base).await;
+    let (t2, _) = dev_auth(&base).await;
+
+    let (mut sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let (mut sink2, mut stream2) = ws_connect(&base, &t2).await;
+
+    ws_send(&mut sink1, json!({"type": "CreateRoom", "mode": "Shared", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream1, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+    ws_send(&mut sink2, json!({"type": "JoinRoom", "code": code})).await;
+    let _ = ws_recv_type(&mut stream1, "MatchStarted").await;
+    let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
+
+    // Leave two empty cells, one for each player
+    let (solution, mut empties) = {
+        let mut room = state.rooms.get_mut(&code).unwrap();
+        let mut empties: Vec<(usize, usize)> = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| room.shared_board[r][c].value().is_none())
+            .collect();
+        for (r, c) in empties.split_off(2) {
+            let given = Cell::Given(room.solution[r][c]);
+            room.shared_board[r][c] = given;
+            room.board[r][c] = given;
+        }
+        (room.solution, empties)
+    };
+    let (r2, c2) = empties.pop().unwrap();
+    let (r1, c1) = empties.pop().unwrap();
+    let wrong = solution[r1][c1] % 9 + 1;
+
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r1, "col": c1, "value": wrong})).await;
+    let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
+    ws_send(&mut sink2, json!({"type": "PlaceNumber", "row": r2, "col": c2, "value": solution[r2][c2]})).await;
+
+    // The board is full, but the game goes on and both players are told
+    let incomplete2 = ws_recv_type(&mut stream2, "BoardIncomplete").await;
+    let incomplete1 = ws_recv_type(&mut stream1, "BoardIncomplete").await;
+    assert_eq!(incomplete1["wrong_cells"], 1);
+    assert_eq!(incomplete2["wrong_cells"], 1);
+    assert_eq!(state.rooms.get(&code).unwrap().state, RoomState::Playing);
+
+    // The owner fixes their cell and that finishes it
+    ws_send(&mut sink1, json!({"type": "EraseNumber", "row": r1, "col": c1})).await;
+    let _ = ws_recv_type(&mut stream2, "OpponentErased").await;
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r1, "col": c1, "value": solution[r1][c1]})).await;
+    let end = ws_recv_type(&mut stream1, "GameEnd").await;
+    assert!(end["won"].is_boolean());
+    let _ = ws_recv_type(&mut stream2, "GameEnd").await;
 }
 
 #[tokio::test]
 async fn test_race_game_ends_when_board_full_even_with_wrong_numbers() {
-    let base = start_server().await;
+    assert_eq!(sudoku_server::puzzle_seed(None), Ok(None));
+    assert_eq!(sudoku_server::puzzle_seed(Some("1972")), Ok(Some(1972)));
+    assert!(sudoku_server::puzzle_seed(Some("-1")).is_err());
+
+    let (base, state) = start_server_with_state().await;
+    state.puzzle_seed.set(1972).unwrap();
+    let (givens, solution) = generate_puzzle_seeded_in(&GridSpec::CLASSIC, Difficulty::Easy, 1972);
 
     let (t1, _) = dev_auth(&base).await;
     let (t2, _) = dev_auth(&base).await;
@@ -407,25 +1677,299 @@ async fn test_race_game_ends_when_board_full_even_with_wrong_numbers() {
 
     let p1_match = ws_recv_type(&mut stream1, "MatchStarted").await;
     let board: Vec<Vec<u8>> = serde_json::from_value(p1_match["board"].clone()).unwrap();
+    assert_eq!(board, board_to_wire(&givens));
     let _ = ws_recv_type(&mut stream2, "MatchStarted").await;
 
-    // P1 fills every empty cell with value 1 (mostly wrong)
+    // P1 fills the board correctly except for one wrong number at the end
     let empty_cells: Vec<(usize, usize)> = (0..9)
         .flat_map(|r| (0..9).map(move |c| (r, c)))
-        .filter(|(r, c)| board[*r][*c] == 0)
+        .filter(|&(r, c)| givens[r][c] == Cell::Empty)
         .collect();
+    let (last, rest) = empty_cells.split_last().unwrap();
 
-    assert!(!empty_cells.is_empty());
-
-    for (r, c) in &empty_cells {
-        ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r, "col": c, "value": 1})).await;
+    for &(r, c) in rest {
+        ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r, "col": c, "value": solution[r][c]})).await;
         // Small delay to avoid rate limiting (20 msg/s)
         tokio::time::sleep(Duration::from_millis(60)).await;
-        let msg = ws_recv_type(&mut stream1, "MoveAccepted").await;
-        assert_eq!(msg["type"].as_str().unwrap(), "MoveAccepted");
+        let _ = ws_recv_type(&mut stream1, "MoveAccepted").await;
     }
+    let (r, c) = *last;
+    let wrong = solution[r][c] % 9 + 1;
+    ws_send(&mut sink1, json!({"type": "PlaceNumber", "row": r, "col": c, "value": wrong})).await;
 
-    // Board is full but mostly wrong — should get BoardIncomplete, NOT GameEnd
+    // Board is full but one cell is wrong — should get BoardIncomplete, NOT GameEnd
     let incomplete = ws_recv_type(&mut stream1, "BoardIncomplete").await;
-    assert!(incomplete["wrong_cells"].as_u64().unwrap() > 0);
+    assert_eq!(incomplete["wrong_cells"], 1);
+}
+
+#[tokio::test]
+async fn test_full_server_rejects_upgrade_with_503() {
+    let (base, state) = start_server_with_state().await;
+    let (t1, _) = dev_auth(&base).await;
+
+    // Pretend every slot is taken.
+    state
+        .connection_count
+        .store(state.max_connections, Ordering::Relaxed);
+
+    let url = format!("{}/ws?token={}", base.replace("http://", "ws://"), t1);
+    match tokio_tungstenite::connect_async(&url).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => {
+            assert_eq!(resp.status().as_u16(), 503);
+        }
+        Err(other) => panic!("Expected HTTP 503, got {:?}", other),
+        Ok(_) => panic!("Expected the upgrade to be refused"),
+    }
+}
+
+#[tokio::test]
+async fn test_me_returns_own_profile_and_rank() {
+    let base = start_server().await;
+    let (token, username) = dev_auth(&base).await;
+
+    let me: MeResponse = reqwest::get(format!("{}/me?token={}", base, token))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(me.profile.username, username);
+    assert_eq!(me.profile.rating, 1200);
+    assert_eq!(me.rank, 1);
+
+    let resp = reqwest::get(format!("{}/me?token=not-a-real-token", base))
+        .await
+        .unwrap();
+    assert_eq!(resp.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn test_configured_timeouts_are_validated_and_applied() {
+    let vars = |name: &str| match name {
+        "GRACE_SECS" => Some("5".to_string()),
+        "WAITING_TTL_SECS" => Some("60".to_string()),
+        _ => None,
+    };
+    let timeouts = sudoku_server::timeouts(vars).unwrap();
+    assert_eq!(timeouts.grace_secs, 5);
+    assert_eq!(timeouts.idle_forfeit_secs, sudoku_server::IDLE_FORFEIT_SECS);
+    assert_eq!(timeouts.waiting_ttl_secs, 60);
+    assert_eq!(sudoku_server::timeouts(|_| None).unwrap(), Timeouts::default());
+    let too_short = [
+        ("GRACE_SECS", "1"),
+        ("IDLE_FORFEIT_SECS", "60"),
+        ("WAITING_TTL_SECS", "soon"),
+    ];
+    for (name, value) in too_short {
+        let err = sudoku_server::timeouts(|n| (n == name).then(|| value.to_string()))
+            .unwrap_err();
+        assert!(err.contains(name), "{}", err);
+    }
+
+    let (base, state) = start_server_with_timeouts(timeouts).await;
+    let (token, _) = dev_auth(&base).await;
+    let (mut sink, mut stream) = ws_connect(&base, &token).await;
+    ws_send(&mut sink, json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let created = ws_recv_type(&mut stream, "RoomCreated").await;
+    let code = created["code"].as_str().unwrap().to_string();
+
+    sudoku_server::cleanup_at(&state, Instant::now() + Duration::from_secs(30)).await;
+    assert!(state.rooms.contains_key(&code));
+    // A minute is enough here, where the default would keep it for ten
+    sudoku_server::cleanup_at(&state, Instant::now() + Duration::from_secs(61)).await;
+    assert!(!state.rooms.contains_key(&code));
+}
+
+#[tokio::test]
+async fn test_configured_grace_decides_a_disconnect() {
+    let timeouts = Timeouts { grace_secs: 5, ..Timeouts::default() };
+    let harness = TestHarness::with_timeouts(timeouts).await;
+    let (mut p1, p2) = harness.two_matched_players(GameMode::Race, Difficulty::Easy).await;
+
+    let started = Instant::now();
+    drop(p2);
+    let gone = p1.expect("OpponentDisconnected").await;
+    assert_eq!(gone["grace_secs"], 5);
+    let end = p1.expect("GameEnd").await;
+    assert!(end["won"].as_bool().unwrap());
+    assert!(started.elapsed() >= Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_solo_results_follow_the_player_between_machines() {
+    let (base, state) = start_server_with_state().await;
+    let (token, name) = dev_auth(&base).await;
+    let client = reqwest::Client::new();
+    let record = |token: String, body: serde_json::Value| {
+        let request = client
+            .post(format!("{}/solo/record", base))
+            .query(&[("token", token)])
+            .json(&body);
+        async move { request.send().await.unwrap().status() }
+    };
+
+    for (difficulty, secs, mistakes, hints) in
+        [("Easy", 300, 0, 0), ("Easy", 200, 1, 0), ("Hard", 900, 0, 2)]
+    {
+        let body = json!({
+            "difficulty": difficulty,
+            "secs": secs,
+            "mistakes": mistakes,
+            "hints": hints,
+            "flawless": mistakes == 0 && hints == 0,
+        });
+        assert_eq!(record(token.clone(), body).await, reqwest::StatusCode::NO_CONTENT);
+    }
+    // Claiming a flawless solve with mistakes in it
+    let bogus = json!({"difficulty": "Easy", "secs": 60, "mistakes": 3, "hints": 0, "flawless": true});
+    assert_eq!(record(token.clone(), bogus.clone()).await, reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(record("nope".into(), bogus).await, reqwest::StatusCode::UNAUTHORIZED);
+
+    // Another machine logs in separately and sees the same numbers
+    let user_id = sudoku_server::db::get_user_by_username(&state.db, &name)
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    let other_token = sudoku_server::db::create_session(&state.db, user_id).await.unwrap();
+    let stats: SoloStats = client
+        .get(format!("{}/solo/stats", base))
+        .query(&[("token", other_token)])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!((stats.solved, stats.flawless), (3, 1));
+    let lines: Vec<_> = stats
+        .by_difficulty
+        .iter()
+        .map(|d| (d.difficulty.label().to_string(), d.solved, d.flawless, d.best_secs, d.avg_secs))
+        .collect();
+    assert_eq!(
+        lines,
+        [("Easy".to_string(), 2, 1, 200, 250), ("Hard".to_string(), 1, 0, 900, 900)]
+    );
+}
+
+#[tokio::test]
+async fn test_game_end_reveals_the_solution() {
+    let harness = TestHarness::start().await;
+    let (mut p1, mut p2) = harness.two_matched_players(GameMode::Shared, Difficulty::Easy).await;
+
+    p1.forfeit().await;
+    let end1 = p1.expect("GameEnd").await;
+    let end2 = p2.expect("GameEnd").await;
+
+    let solution: [[u8; 9]; 9] = serde_json::from_value(end1["solution"].clone()).unwrap();
+    assert_eq!(end2["solution"], end1["solution"]);
+    let board = solution.map(|row| row.map(Cell::Given));
+    assert!(sudoku_core::validation::is_board_complete(&board));
+    for (givens, values) in p1.board.iter().zip(&solution) {
+        for (&given, &value) in givens.iter().zip(values) {
+            assert!(given == 0 || given == value);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_race_mistakes_are_counted_and_shown_to_the_opponent() {
+    let harness = TestHarness::start().await;
+    let mut host = harness.client().await;
+    let mut guest = harness.client().await;
+
+    host.send(json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy"})).await;
+    let code = host.expect("RoomCreated").await["code"].as_str().unwrap().to_string();
+    guest.send(json!({"type": "JoinRoom", "code": code})).await;
+    let started = host.expect("MatchStarted").await;
+    host.board = serde_json::from_value(started["board"].clone()).unwrap();
+    let solution = harness.state.rooms.get(&code).unwrap().solution;
+    let (r, c) = host.first_empty();
+    let wrong = solution[r][c] % 9 + 1;
+
+    host.place(r, c, wrong).await;
+    host.expect("MoveAccepted").await;
+    assert_eq!(guest.expect("OpponentMistakes").await["count"], 1);
+
+    // Sending the same digit again isn't another mistake, but a second wrong one is
+    host.place(r, c, wrong).await;
+    host.expect("MoveAccepted").await;
+    host.place(r, c, wrong % 9 + 1).await;
+    host.expect("MoveAccepted").await;
+    assert_eq!(guest.expect("OpponentMistakes").await["count"], 2);
+
+    let host_id = harness.state.rooms.get(&code).unwrap().player1_id;
+    assert_eq!(harness.state.rooms.get(&code).unwrap().mistakes[&host_id], 2);
+}
+
+#[tokio::test]
+async fn test_fog_rooms_only_share_a_quarter() {
+    let harness = TestHarness::start().await;
+    let mut host = harness.client().await;
+    let mut guest = harness.client().await;
+
+    host.send(json!({"type": "CreateRoom", "mode": "Race", "difficulty": "Easy", "progress": "Quartiles"}))
+        .await;
+    let code = host.expect("RoomCreated").await["code"].as_str().unwrap().to_string();
+    guest.send(json!({"type": "JoinRoom", "code": code})).await;
+    let started = host.expect("MatchStarted").await;
+    host.board = serde_json::from_value(started["board"].clone()).unwrap();
+    let solution = harness.state.rooms.get(&code).unwrap().solution;
+
+    // Filling half the empty cells puts the host in the third quarter
+    let empties: Vec<(usize, usize)> = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| host.board[r][c] == 0)
+        .collect();
+    for &(r, c) in &empties[..empties.len().div_ceil(2)] {
+        host.place(r, c, solution[r][c]).await;
+        host.expect("MoveAccepted").await;
+        // Stay under the 20 messages a second rate limit
+        tokio::time::sleep(Duration::from_millis(60)).await;
+    }
+    loop {
+        let progress = guest.expect("OpponentProgressCoarse").await;
+        if progress["bucket"] == 2 {
+            break;
+        }
+        assert_eq!(progress["bucket"], 0);
+    }
+}
+
+#[tokio::test]
+async fn test_admin_broadcast_reaches_connected_players() {
+    let (base, state) = start_server_with_state().await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/admin/broadcast", base);
+    let body = json!({"text": "Server restarting in 2 minutes, finish your game"});
+
+    // Off until a token is configured
+    let off = client.post(format!("{}?token=x", url)).json(&body).send().await.unwrap();
+    assert_eq!(off.status(), reqwest::StatusCode::NOT_FOUND);
+    state.admin_token.set("s3cret".to_string()).unwrap();
+
+    let (t1, _) = dev_auth(&base).await;
+    let (_sink1, mut stream1) = ws_connect(&base, &t1).await;
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while state.connections.is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let wrong = client.post(format!("{}?token=nope", url)).json(&body).send().await.unwrap();
+    assert_eq!(wrong.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let sent: usize = client
+        .post(format!("{}?token=s3cret", url))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sent, 1);
+    let announcement = ws_recv_type(&mut stream1, "Announcement").await;
+    assert_eq!(announcement["text"], "Server restarting in 2 minutes, finish your game");
 }
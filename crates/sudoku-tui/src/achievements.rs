@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sudoku_core::Difficulty;
+
+/// A badge for something done in single-player. Each is earned once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Achievement {
+    FirstSolve,
+    FlawlessExpert,
+    QuickMedium,
+    TenDayStreak,
+    Centurion,
+}
+
+impl Achievement {
+    pub const ALL: &'static [Achievement] = &[
+        Achievement::FirstSolve,
+        Achievement::FlawlessExpert,
+        Achievement::QuickMedium,
+        Achievement::TenDayStreak,
+        Achievement::Centurion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstSolve => "First Solve",
+            Achievement::FlawlessExpert => "Flawless Expert",
+            Achievement::QuickMedium => "Sub-3-minute Medium",
+            Achievement::TenDayStreak => "10-day Streak",
+            Achievement::Centurion => "Centurion",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstSolve => "Solve a puzzle",
+            Achievement::FlawlessExpert => "Solve an Expert puzzle with no mistakes and no hints",
+            Achievement::QuickMedium => "Solve a Medium puzzle in under 3 minutes",
+            Achievement::TenDayStreak => "Solve a puzzle on 10 days in a row",
+            Achievement::Centurion => "Solve 100 puzzles",
+        }
+    }
+
+    /// Whether `solve` earns this, judged on `stats` already counting it
+    fn earned_by(&self, stats: &Stats, solve: &Solve) -> bool {
+        match self {
+            Achievement::FirstSolve => true,
+            Achievement::FlawlessExpert => {
+                solve.difficulty == Difficulty::Expert && solve.mistakes == 0 && solve.hints == 0
+            }
+            Achievement::QuickMedium => solve.difficulty == Difficulty::Medium && solve.secs < 180,
+            Achievement::TenDayStreak => stats.streak_days >= 10,
+            Achievement::Centurion => stats.solved >= 100,
+        }
+    }
+}
+
+/// One finished single-player puzzle
+#[derive(Clone, Copy, Debug)]
+pub struct Solve {
+    pub difficulty: Difficulty,
+    pub secs: u64,
+    pub mistakes: u32,
+    pub hints: u32,
+}
+
+/// Single-player tallies the achievements are judged on, and the ones
+/// already earned so each is announced once. Kept next to the settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub solved: u32,
+    /// Consecutive days with a solve, up to `last_solve_day`
+    pub streak_days: u32,
+    /// Day of the latest solve, counted in days since the Unix epoch (UTC)
+    pub last_solve_day: Option<u64>,
+    pub earned: Vec<Achievement>,
+}
+
+fn stats_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("stats.json")
+}
+
+/// Today, in days since the Unix epoch (UTC)
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400)
+}
+
+impl Stats {
+    /// Load stats from disk, starting afresh if missing or unreadable
+    pub fn load() -> Self {
+        std::fs::read_to_string(stats_file_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = stats_file_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.earned.contains(&achievement)
+    }
+
+    /// Count `solve`, made on `day`, and return the achievements it earns
+    /// for the first time.
+    pub fn record(&mut self, solve: &Solve, day: u64) -> Vec<Achievement> {
+        self.solved += 1;
+        self.streak_days = match self.last_solve_day {
+            Some(last) if last == day => self.streak_days.max(1),
+            Some(last) if last + 1 == day => self.streak_days + 1,
+            _ => 1,
+        };
+        self.last_solve_day = Some(day);
+
+        let new: Vec<Achievement> = Achievement::ALL
+            .iter()
+            .copied()
+            .filter(|a| !self.has(*a) && a.earned_by(self, solve))
+            .collect();
+        self.earned.extend(&new);
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(difficulty: Difficulty, secs: u64, mistakes: u32, hints: u32) -> Solve {
+        Solve { difficulty, secs, mistakes, hints }
+    }
+
+    #[test]
+    fn badges_are_earned_once() {
+        let mut stats = Stats::default();
+        let sloppy = solve(Difficulty::Expert, 900, 2, 0);
+        assert_eq!(stats.record(&sloppy, 100), [Achievement::FirstSolve]);
+
+        let clean = solve(Difficulty::Expert, 900, 0, 0);
+        assert_eq!(stats.record(&clean, 100), [Achievement::FlawlessExpert]);
+        assert!(stats.record(&clean, 100).is_empty());
+        assert!(stats.has(Achievement::FlawlessExpert));
+    }
+
+    #[test]
+    fn streaks_count_consecutive_days() {
+        let mut stats = Stats::default();
+        let easy = solve(Difficulty::Easy, 300, 0, 0);
+        for day in 10..19 {
+            stats.record(&easy, day);
+            stats.record(&easy, day);
+        }
+        assert_eq!(stats.streak_days, 9);
+        assert_eq!(stats.record(&easy, 19), [Achievement::TenDayStreak]);
+
+        // A missed day starts over
+        stats.record(&easy, 21);
+        assert_eq!(stats.streak_days, 1);
+    }
+}
@@ -1,7 +1,8 @@
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::clipboard::CopyToClipboard;
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -9,33 +10,51 @@ use crossterm::terminal::{
 use futures_util::StreamExt;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use tokio::task::JoinHandle;
 
-use crate::game::{Game, GameState};
-use crate::net::NetworkClient;
+use crate::achievements::Stats;
+use crate::game::{CellFlag, EndView, Game, GameState, PuzzleRequest, Unit, Watching};
+use crate::history;
+use crate::mp_menu::MenuAction;
+use crate::net::client::PendingAuth;
+use crate::net::outbox::OUTBOX_LEN;
+use crate::net::trace::TracedMessage;
+use crate::net::{Backend, NetError, NetworkClient};
+use crate::report::{self, REPORT_REASONS};
+use crate::settings::{SettingItem, Settings};
+use crate::tasks::{TaskKind, Tasks};
 use crate::ui;
 use sudoku_core::protocol::{
-    AuthPollResponse, ClientMessage, DeviceAuthResponse, GameMode, LeaderboardEntry, ServerMessage,
+    AuthPollResponse, ClientMessage, DeviceAuthResponse, GameMode, Handicap, HeadToHead,
+    LeaderboardEntry, MeResponse, RatingPoint, ServerMessage, SoloStats,
 };
-use sudoku_core::Cell;
+use sudoku_core::board::board_checksum;
+use sudoku_core::{Board, Cell, Difficulty, SolutionBoard};
 
 /// Result types for background async operations
 enum AsyncResult {
-    AuthStarted(Result<DeviceAuthResponse, String>),
-    Connected(Result<NetworkClient, String>),
-    DevConnected(Result<(NetworkClient, String), String>),
-    LeaderboardLoaded(Result<Vec<LeaderboardEntry>, String>),
+    AuthStarted(Result<DeviceAuthResponse, NetError>),
+    Connected(Result<NetworkClient, NetError>),
+    DevConnected(Result<(NetworkClient, String, String), NetError>),
+    LeaderboardLoaded(Result<Vec<LeaderboardEntry>, NetError>),
+    MeLoaded(Result<MeResponse, NetError>),
+    RatingHistoryLoaded(Result<Vec<RatingPoint>, NetError>),
+    HeadToHeadLoaded(Result<HeadToHead, NetError>),
+    SoloRecorded,
+    SoloStatsLoaded(Result<SoloStats, NetError>),
+    PuzzleGenerated(PuzzleRequest, Board, SolutionBoard),
 }
 
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// `debug` starts with the network trace overlay showing; `network` is where
+/// every network call goes.
+pub fn run(debug: bool, network: Backend) -> Result<(), Box<dyn std::error::Error>> {
     // Install rustls crypto provider before any TLS usage
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
 
     let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async_run())
+    rt.block_on(async_run(debug, network))
 }
 
-async fn async_run() -> Result<(), Box<dyn std::error::Error>> {
+async fn async_run(debug: bool, network: Backend) -> Result<(), Box<dyn std::error::Error>> {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         let _ = disable_raw_mode();
@@ -50,13 +69,22 @@ async fn async_run() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     let mut game = Game::new();
+    game.settings = Settings::load();
+    game.stats = Stats::load();
+    game.history = history::load();
+    game.show_net_trace = debug;
     let mut net_client: Option<NetworkClient> = None;
     let mut username: Option<String> = None;
     let mut saved_token: Option<String> = None;
 
-    if let Some((token, name)) = NetworkClient::load_token() {
+    if let Some((token, name)) = network.load_token() {
         username = Some(name);
         saved_token = Some(token);
+    } else if let Some(pending) = network.load_pending_auth() {
+        // A login was still in progress when the app last closed; pick it up.
+        begin_auth_polling(&mut game, &pending);
+        game.state = GameState::AuthScreen;
+        game.auth_status = Some("Resuming login — enter the code below at the URL".to_string());
     }
 
     let result = run_loop(
@@ -65,6 +93,7 @@ async fn async_run() -> Result<(), Box<dyn std::error::Error>> {
         &mut net_client,
         &mut username,
         &mut saved_token,
+        network,
     )
     .await;
 
@@ -81,107 +110,206 @@ async fn run_loop(
     net_client: &mut Option<NetworkClient>,
     username: &mut Option<String>,
     saved_token: &mut Option<String>,
+    network: Backend,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut event_stream = EventStream::new();
-    let tick_rate = Duration::from_millis(250);
-    let mut auth_poll_deadline = tokio::time::Instant::now() + Duration::from_secs(60);
+    let tick_rate = tick_rate();
+    // Set by anything that changes what's on screen; idle screens are only
+    // repainted when it is, animated ones on every tick.
+    let mut needs_redraw = true;
+    // A resumed login polls straight away.
+    let mut auth_poll_deadline = if game.auth_polling {
+        tokio::time::Instant::now()
+    } else {
+        tokio::time::Instant::now() + Duration::from_secs(60)
+    };
 
-    // In-flight background task (only one at a time)
-    let mut inflight: Option<JoinHandle<AsyncResult>> = None;
+    // Background tasks, one of each kind at a time
+    let mut tasks: Tasks<AsyncResult> = Tasks::default();
 
     loop {
-        terminal.draw(|f| ui::draw(f, game))?;
+        game.advance_bot();
+
+        if std::mem::take(&mut game.stats_dirty) {
+            // Best effort, like settings: badges still show for this session
+            let _ = game.stats.save();
+        }
+        if std::mem::take(&mut game.history_dirty) {
+            let _ = history::save(&game.history);
+        }
 
         // Spawn background tasks for pending async operations.
         // These run concurrently so the UI stays responsive.
-        if game.pending_auth_start && inflight.is_none() {
+        if game.pending_auth_start && !tasks.is_running(TaskKind::Auth) {
             game.pending_auth_start = false;
             game.state = GameState::AuthScreen;
             game.auth_status = Some("Connecting to server...".to_string());
 
-            inflight = Some(tokio::spawn(async {
+            tasks.spawn(TaskKind::Auth, async {
                 AsyncResult::AuthStarted(
                     NetworkClient::start_device_auth()
-                        .await
-                        .map_err(|e| e.to_string()),
+                        .await,
                 )
-            }));
+            });
         }
 
-        if game.pending_connect && inflight.is_none() {
+        if game.pending_connect && !tasks.is_running(TaskKind::Connect) {
             game.pending_connect = false;
             game.auth_status = Some("Connecting...".to_string());
 
-            if crate::net::client::is_local() && saved_token.is_none() {
-                inflight = Some(tokio::spawn(async {
+            if network.is_local() && saved_token.is_none() {
+                tasks.spawn(TaskKind::Connect, async move {
                     AsyncResult::DevConnected(
-                        NetworkClient::dev_auth_and_connect()
-                            .await
-                            .map_err(|e| e.to_string()),
+                        network.dev_auth_and_connect()
+                            .await,
                     )
-                }));
+                });
             } else if let Some(token) = saved_token.clone() {
-                inflight = Some(tokio::spawn(async move {
+                tasks.spawn(TaskKind::Connect, async move {
                     AsyncResult::Connected(
-                        NetworkClient::connect(&token)
-                            .await
-                            .map_err(|e| e.to_string()),
+                        network.connect(&token)
+                            .await,
                     )
-                }));
+                });
             }
         }
 
-        if game.pending_leaderboard && inflight.is_none() {
+        if game.pending_leaderboard && !tasks.is_running(TaskKind::Leaderboard) {
             game.pending_leaderboard = false;
             game.auth_status = Some("Loading leaderboard...".to_string());
 
-            inflight = Some(tokio::spawn(async {
+            tasks.spawn(TaskKind::Leaderboard, async move {
                 AsyncResult::LeaderboardLoaded(
-                    NetworkClient::fetch_leaderboard()
-                        .await
-                        .map_err(|e| e.to_string()),
+                    network.fetch_leaderboard()
+                        .await,
                 )
-            }));
+            });
+        }
+
+        if game.pending_me && !tasks.is_running(TaskKind::Profile) {
+            game.pending_me = false;
+
+            if let Some(token) = saved_token.clone() {
+                tasks.spawn(TaskKind::Profile, async move {
+                    AsyncResult::MeLoaded(
+                        network.fetch_me(&token)
+                            .await,
+                    )
+                });
+            }
+        }
+
+        if game.pending_rating_history && !tasks.is_running(TaskKind::Profile) {
+            game.pending_rating_history = false;
+
+            if let Some(username) = game.my_profile.as_ref().map(|me| me.profile.username.clone()) {
+                tasks.spawn(TaskKind::Profile, async move {
+                    AsyncResult::RatingHistoryLoaded(
+                        network.fetch_rating_history(&username)
+                            .await,
+                    )
+                });
+            }
         }
 
-        // Build a future that resolves when the inflight task completes,
-        // or pends forever if there is no inflight task.
-        let inflight_fut = async {
-            match &mut inflight {
-                Some(handle) => handle.await,
-                None => std::future::pending().await,
+        if game.pending_head_to_head && !tasks.is_running(TaskKind::Profile) {
+            game.pending_head_to_head = false;
+
+            let me = game.my_profile.as_ref().map(|me| me.profile.username.clone());
+            let opponent = game.multiplayer.as_ref().map(|mp| mp.opponent_name.clone());
+            if let (Some(me), Some(opponent)) = (me, opponent) {
+                tasks.spawn(TaskKind::Profile, async move {
+                    AsyncResult::HeadToHeadLoaded(
+                        network.fetch_head_to_head(&me, &opponent)
+                            .await,
+                    )
+                });
             }
-        };
+        }
+
+        if !game.pending_solo_results.is_empty() && !tasks.is_running(TaskKind::SoloSync) {
+            // Only a connected player's solves follow them to other machines
+            let result = game.pending_solo_results.remove(0);
+            if let (Some(_), Some(token)) = (net_client.as_ref(), saved_token.clone()) {
+                tasks.spawn(TaskKind::SoloSync, async move {
+                    // Best effort: the local stats already have it
+                    let _ = network.record_solo(&token, &result).await;
+                    AsyncResult::SoloRecorded
+                });
+            } else {
+                game.pending_solo_results.clear();
+            }
+        }
+
+        if game.pending_solo_stats && !tasks.is_running(TaskKind::SoloSync) {
+            game.pending_solo_stats = false;
+
+            if let Some(token) = saved_token.clone() {
+                tasks.spawn(TaskKind::SoloSync, async move {
+                    AsyncResult::SoloStatsLoaded(
+                        network.fetch_solo_stats(&token)
+                            .await,
+                    )
+                });
+            }
+        }
+
+        // A newer request replaces one still being generated
+        if let Some(request) = game.pending_puzzle.take() {
+            tasks.spawn(TaskKind::Puzzle, async move {
+                let (board, solution) = tokio::task::spawn_blocking(move || request.generate())
+                    .await
+                    .expect("generating the puzzle panicked");
+                AsyncResult::PuzzleGenerated(request, board, solution)
+            });
+        }
+
+        if needs_redraw || game.is_animating() {
+            terminal.draw(|f| ui::draw(f, game))?;
+            needs_redraw = false;
+        }
 
         tokio::select! {
             maybe_event = event_stream.next() => {
+                // Resizes and focus changes need a repaint as much as keys
+                needs_redraw = true;
                 if let Some(Ok(Event::Key(key))) = maybe_event {
                     if key.kind != KeyEventKind::Press {
                         continue;
                     }
-                    // Allow Esc to cancel in-flight operations
-                    if key.code == KeyCode::Esc && inflight.is_some() {
-                        if let Some(handle) = inflight.take() {
-                            handle.abort();
-                        }
+                    if key.code == KeyCode::F(12) {
+                        game.show_net_trace = !game.show_net_trace;
+                        continue;
+                    }
+                    // Esc cancels whatever the player is waiting on; background
+                    // fetches carry on
+                    if key.code == KeyCode::Esc && tasks.cancel_foreground() {
                         game.auth_status = None;
                         game.pending_menu_action = None;
                         game.state = GameState::MultiplayerMenu;
                         continue;
                     }
-                    if handle_key(game, key, net_client, username, saved_token) {
+                    if handle_key(game, key, net_client, username, saved_token, network) {
+                        // Nothing started from here should outlive the UI
+                        tasks.cancel_all();
+                        if let Some(code) = cancel_login(game) {
+                            let _ = tokio::time::timeout(
+                                AUTH_CANCEL_TIMEOUT,
+                                NetworkClient::cancel_device_auth(&code),
+                            )
+                            .await;
+                        }
                         return Ok(());
                     }
                 }
             }
-            result = inflight_fut => {
-                inflight = None;
+            (_, result) = tasks.next() => {
+                needs_redraw = true;
                 match result {
                     Ok(AsyncResult::AuthStarted(Ok(resp))) => {
-                        game.auth_code = Some(resp.user_code);
-                        game.auth_uri = Some(resp.verification_uri);
-                        game.auth_poll_interval = resp.interval.max(5);
-                        game.auth_polling = true;
+                        let pending = PendingAuth::from_response(&resp);
+                        let _ = NetworkClient::save_pending_auth(&pending);
+                        begin_auth_polling(game, &pending);
                         auth_poll_deadline = tokio::time::Instant::now()
                             + Duration::from_secs(game.auth_poll_interval);
                         game.auth_status =
@@ -191,31 +319,65 @@ async fn run_loop(
                         game.auth_status = Some(format!("Auth failed: {}", e));
                         game.auth_polling = false;
                     }
-                    Ok(AsyncResult::Connected(Ok(client))) => {
+                    Ok(AsyncResult::Connected(Ok(mut client))) => {
+                        client.attach_trace(game.net_trace.clone());
+                        let resumed = game.resume_after_reconnect(&client);
                         *net_client = Some(client);
+                        game.connected_as = username.clone();
                         game.auth_status = None;
-                        if let Some(action) = game.pending_menu_action.take() {
+                        if resumed {
+                            game.notice = Some("Reconnected".to_string());
+                        } else if let Some(action) = game.pending_menu_action.take() {
+                            game.pending_me = true;
                             execute_menu_action(game, action, net_client);
                         } else {
                             // Post-auth connect: return to multiplayer menu
+                            game.pending_me = true;
                             game.state = GameState::MultiplayerMenu;
                         }
                     }
-                    Ok(AsyncResult::Connected(Err(e))) => {
+                    Ok(AsyncResult::Connected(Err(e)))
+                        if !matches!(e, NetError::Unauthorized)
+                            && game.reconnect_deadline().is_some() =>
+                    {
+                        // Still inside the grace period: keep trying
+                        if let Some(token) = saved_token.clone() {
+                            spawn_reconnect(&mut tasks, network, token, RECONNECT_RETRY);
+                        }
+                    }
+                    Ok(AsyncResult::Connected(Err(NetError::Unauthorized))) => {
                         // Clear stale token so next attempt triggers re-auth
                         // (e.g. server DB was wiped on redeploy)
                         *saved_token = None;
                         *username = None;
+                        game.connected_as = None;
                         NetworkClient::clear_token();
-                        game.error_message = Some(format!("Connection failed: {} — please try again to re-authenticate", e));
+                        game.error_message = Some(
+                            "Session expired — please try again to re-authenticate".to_string(),
+                        );
+                        game.pending_menu_action = None;
+                        game.auth_status = None;
+                        game.state = GameState::MultiplayerMenu;
+                    }
+                    Ok(AsyncResult::Connected(Err(e))) => {
+                        // The token is still good (server full, network down,
+                        // cold start...), so keep it and let the user retry
+                        game.error_message = Some(match e {
+                            NetError::ServerFull => e.to_string(),
+                            _ => format!("Connection failed: {}", e),
+                        });
                         game.pending_menu_action = None;
                         game.auth_status = None;
                         game.state = GameState::MultiplayerMenu;
                     }
-                    Ok(AsyncResult::DevConnected(Ok((client, name)))) => {
+                    Ok(AsyncResult::DevConnected(Ok((mut client, name, token)))) => {
                         *username = Some(name);
+                        *saved_token = Some(token);
+                        client.attach_trace(game.net_trace.clone());
                         *net_client = Some(client);
+                        game.connected_as = username.clone();
                         game.auth_status = None;
+                        game.pending_me = true;
                         if let Some(action) = game.pending_menu_action.take() {
                             execute_menu_action(game, action, net_client);
                         }
@@ -235,6 +397,36 @@ async fn run_loop(
                         game.error_message = Some(format!("Failed to load: {}", e));
                         game.auth_status = None;
                     }
+                    Ok(AsyncResult::MeLoaded(Ok(me))) => {
+                        game.my_profile = Some(me);
+                        game.pending_rating_history = true;
+                    }
+                    Ok(AsyncResult::MeLoaded(Err(_))) => {
+                        // Not worth an error banner; the header shows just the name
+                        game.my_profile = None;
+                        game.rating_history.clear();
+                    }
+                    Ok(AsyncResult::RatingHistoryLoaded(Ok(history))) => {
+                        game.rating_history = history.into_iter().map(|p| p.rating).collect();
+                    }
+                    Ok(AsyncResult::RatingHistoryLoaded(Err(_))) => {
+                        game.rating_history.clear();
+                    }
+                    Ok(AsyncResult::HeadToHeadLoaded(Ok(h2h))) => {
+                        if let Some(mp) = &mut game.multiplayer {
+                            mp.series = Some((h2h.a_wins, h2h.b_wins));
+                        }
+                    }
+                    // The end screen just goes without the series line
+                    Ok(AsyncResult::HeadToHeadLoaded(Err(_))) => {}
+                    Ok(AsyncResult::SoloRecorded) => {}
+                    Ok(AsyncResult::SoloStatsLoaded(Ok(stats))) => {
+                        game.solo_stats = Some(stats);
+                    }
+                    Ok(AsyncResult::SoloStatsLoaded(Err(_))) => game.solo_stats = None,
+                    Ok(AsyncResult::PuzzleGenerated(request, board, solution)) => {
+                        game.puzzle_ready(request, board, solution);
+                    }
                     Err(_) => {
                         // JoinHandle error (task panicked or was cancelled)
                         game.error_message = Some("Operation failed".to_string());
@@ -244,15 +436,31 @@ async fn run_loop(
                 }
             }
             server_msg = recv_server_msg(net_client) => {
+                needs_redraw = true;
                 if let Some(msg) = server_msg {
-                    handle_server_message(game, msg);
+                    game.net_trace.record(TracedMessage::Received(msg.clone()));
+                    if matches!(msg, ServerMessage::SessionReplaced) {
+                        // The server is closing this connection
+                        *net_client = None;
+                        game.connected_as = None;
+                    }
+                    handle_server_message(game, msg, net_client);
+                } else if let Some(token) = saved_token.clone()
+                    && game.start_reconnecting()
+                {
+                    // The connection dropped mid-match; the server holds our
+                    // place for a while, so get back in before it gives up
+                    *net_client = None;
+                    spawn_reconnect(&mut tasks, network, token, Duration::ZERO);
                 }
             }
             _ = tokio::time::sleep_until(auth_poll_deadline), if game.auth_polling => {
+                needs_redraw = true;
                 if let Some(code) = game.auth_code.clone() {
                     match NetworkClient::poll_auth(&code).await {
                         Ok(AuthPollResponse::Complete { token, username: name }) => {
                             let _ = NetworkClient::save_token(&token, &name);
+                            NetworkClient::clear_pending_auth();
                             *username = Some(name.clone());
                             *saved_token = Some(token.clone());
                             game.auth_polling = false;
@@ -261,13 +469,12 @@ async fn run_loop(
 
                             // Spawn connect as a background task too
                             let t = token.clone();
-                            inflight = Some(tokio::spawn(async move {
+                            tasks.spawn(TaskKind::Connect, async move {
                                 AsyncResult::Connected(
-                                    NetworkClient::connect(&t)
-                                        .await
-                                        .map_err(|e| e.to_string()),
+                                    network.connect(&t)
+                                        .await,
                                 )
-                            }));
+                            });
                             game.auth_status = Some(format!("Logged in as {} — connecting...", name));
                         }
                         Ok(AuthPollResponse::Pending) => {
@@ -275,6 +482,7 @@ async fn run_loop(
                                 + Duration::from_secs(game.auth_poll_interval);
                         }
                         Ok(AuthPollResponse::Expired) => {
+                            NetworkClient::clear_pending_auth();
                             game.auth_polling = false;
                             game.auth_status = Some("Auth code expired. Try again.".to_string());
                         }
@@ -291,6 +499,20 @@ async fn run_loop(
     }
 }
 
+/// Open a new connection after `delay`, to get back into a match whose
+/// connection dropped.
+fn spawn_reconnect(
+    tasks: &mut Tasks<AsyncResult>,
+    network: Backend,
+    token: String,
+    delay: Duration,
+) {
+    tasks.spawn(TaskKind::Reconnect, async move {
+        tokio::time::sleep(delay).await;
+        AsyncResult::Connected(network.connect(&token).await)
+    });
+}
+
 async fn recv_server_msg(net_client: &mut Option<NetworkClient>) -> Option<ServerMessage> {
     match net_client {
         Some(client) => client.receiver.recv().await,
@@ -298,15 +520,31 @@ async fn recv_server_msg(net_client: &mut Option<NetworkClient>) -> Option<Serve
     }
 }
 
-fn handle_server_message(game: &mut Game, msg: ServerMessage) {
+/// A board from the wire as givens, 0 being empty
+fn givens_board(data: &[Vec<u8>]) -> Board {
+    let mut board = [[Cell::Empty; 9]; 9];
+    for (row, values) in board.iter_mut().zip(data) {
+        for (cell, &v) in row.iter_mut().zip(values) {
+            if v != 0 {
+                *cell = Cell::Given(v);
+            }
+        }
+    }
+    board
+}
+
+fn handle_server_message(game: &mut Game, msg: ServerMessage, net_client: &Option<NetworkClient>) {
     match msg {
         ServerMessage::AuthOk { username, rating } => {
             game.auth_status = Some(format!("Logged in as {} ({})", username, rating));
         }
-        ServerMessage::RoomCreated { code } => {
+        ServerMessage::RoomCreated { code, .. } => {
             game.room_code = Some(code);
             game.state = GameState::Lobby;
         }
+        ServerMessage::RoomSettingsUpdated { mode, difficulty, .. } => {
+            game.lobby_room = Some((mode, difficulty));
+        }
         ServerMessage::WaitingForOpponent => {
             game.state = GameState::Lobby;
         }
@@ -316,24 +554,36 @@ fn handle_server_message(game: &mut Game, msg: ServerMessage) {
             board: board_data,
             opponent_name,
             opponent_rating,
+            code,
+            handicap,
         } => {
-            let mut board = [[Cell::Empty; 9]; 9];
-            let mut solution = [[0u8; 9]; 9];
-            for r in 0..9 {
-                for c in 0..9 {
-                    let v = board_data[r][c];
-                    if v != 0 {
-                        board[r][c] = Cell::Given(v);
-                    }
-                    solution[r][c] = v;
+            let board = givens_board(&board_data);
+            game.difficulty = difficulty;
+            // The server keeps the solution to itself until `GameEnd`
+            game.start_multiplayer_game(board, None, mode, opponent_name, opponent_rating);
+            // Older servers leave the code out
+            game.room_code = Some(code).filter(|code| !code.is_empty());
+            game.notice = handicap.map(|handicap| match handicap {
+                Handicap::FewerGivens { count } => {
+                    format!("Handicap: your board has {} fewer givens", count)
+                }
+                Handicap::TimePenalty { secs } => {
+                    format!("Handicap: you can start placing after {}s", secs)
                 }
+            });
+        }
+        ServerMessage::MoveAccepted { req_id, .. } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.settle_placement(req_id);
             }
-            game.difficulty = difficulty;
-            game.start_multiplayer_game(board, solution, mode, opponent_name, opponent_rating);
         }
-        ServerMessage::MoveAccepted { .. } => {}
-        ServerMessage::MoveRejected { row, col, reason } => {
-            game.board[row][col] = Cell::Empty;
+        ServerMessage::MoveRejected {
+            row,
+            col,
+            reason,
+            req_id,
+        } => {
+            game.roll_back_placement(row, col, req_id);
             game.error_message = Some(reason);
         }
         ServerMessage::OpponentProgress {
@@ -341,19 +591,34 @@ fn handle_server_message(game: &mut Game, msg: ServerMessage) {
             momentum,
         } => {
             if let Some(mp) = &mut game.multiplayer {
-                mp.opponent_filled = filled_count;
-                mp.opponent_momentum = momentum;
+                mp.update_opponent_progress(filled_count, momentum);
+            }
+        }
+        ServerMessage::OpponentMistakes { count } => {
+            if let Some(mp) = &mut game.multiplayer {
+                // Only a placement can add a mistake
+                if mp.opponent_mistakes != Some(count) {
+                    mp.opponent_moved();
+                }
+                mp.opponent_mistakes = Some(count);
+            }
+        }
+        ServerMessage::OpponentProgressCoarse { bucket } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.update_opponent_quarter(bucket);
             }
         }
         ServerMessage::OpponentPlaced { row, col, value } => {
             if let Some(mp) = &mut game.multiplayer {
                 mp.cell_owner[row][col] = crate::game::CellOwner::Opponent;
+                mp.opponent_moved();
             }
             game.board[row][col] = Cell::UserInput(value);
         }
         ServerMessage::OpponentErased { row, col } => {
             if let Some(mp) = &mut game.multiplayer {
                 mp.cell_owner[row][col] = crate::game::CellOwner::None;
+                mp.opponent_moved();
             }
             game.board[row][col] = Cell::Empty;
         }
@@ -362,12 +627,30 @@ fn handle_server_message(game: &mut Game, msg: ServerMessage) {
                 mp.opponent_cursor = Some((row, col));
             }
         }
+        ServerMessage::BoardChecksum { hash } => {
+            // A dropped OpponentPlaced/Erased leaves us out of step; fetch the real board
+            if board_checksum(&game.board) != hash
+                && let Some(client) = net_client.as_ref()
+            {
+                client.send(ClientMessage::RequestSync);
+            }
+        }
+        ServerMessage::BoardSync { board, elapsed_secs, .. } if game.state == GameState::Spectating => {
+            game.apply_watched_board(&board);
+            game.sync_clock(elapsed_secs);
+        }
+        ServerMessage::BoardSync { board, owners, elapsed_secs } => {
+            game.apply_board_sync(&board, &owners);
+            game.sync_clock(elapsed_secs);
+        }
         ServerMessage::GameEnd {
             won,
             your_score,
             opponent_score,
             elo_change,
             new_rating,
+            solution,
+            ..
         } => {
             if let Some(start) = game.timer_start {
                 game.elapsed_secs = game.paused_elapsed + start.elapsed().as_secs();
@@ -380,15 +663,118 @@ fn handle_server_message(game: &mut Game, msg: ServerMessage) {
                     elo_change,
                     new_rating,
                 });
+                mp.solution = solution.map(|values| {
+                    game.solution = values;
+                    game.solution_known = true;
+                    let mut board = [[Cell::Empty; 9]; 9];
+                    for r in 0..9 {
+                        for c in 0..9 {
+                            board[r][c] = match game.board[r][c] {
+                                Cell::Given(v) => Cell::Given(v),
+                                _ => Cell::UserInput(values[r][c]),
+                            };
+                        }
+                    }
+                    board
+                });
             }
             game.state = GameState::MultiplayerEnd;
+            game.pending_head_to_head = true;
+        }
+        ServerMessage::FinalBoards { mine, opponent } => {
+            // Both boards started from our puzzle, so its givens tell them apart
+            let to_board = |data: &[Vec<u8>]| {
+                let mut board = [[Cell::Empty; 9]; 9];
+                for r in 0..9 {
+                    for c in 0..9 {
+                        board[r][c] = match data[r][c] {
+                            0 => Cell::Empty,
+                            v if game.board[r][c].is_given() => Cell::Given(v),
+                            v => Cell::UserInput(v),
+                        };
+                    }
+                }
+                board
+            };
+            let final_boards = crate::game::FinalBoards {
+                mine: to_board(&mine),
+                opponent: to_board(&opponent),
+            };
+            if let Some(mp) = &mut game.multiplayer {
+                mp.final_boards = Some(final_boards);
+            }
         }
         ServerMessage::BoardIncomplete { wrong_cells } => {
             game.error_message = Some(format!("{} cells are incorrect — fix them!", wrong_cells));
         }
-        ServerMessage::OpponentDisconnected => {}
-        ServerMessage::OpponentReconnected => {}
-        ServerMessage::Error { message } => {
+        ServerMessage::OpponentDisconnected { grace_secs } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.opponent_gone_until = Some(Instant::now() + Duration::from_secs(grace_secs));
+            }
+        }
+        ServerMessage::OpponentReconnected => {
+            if let Some(mp) = &mut game.multiplayer
+                && mp.opponent_gone_until.take().is_some()
+            {
+                game.notice = Some("Opponent reconnected".to_string());
+            }
+        }
+        ServerMessage::SessionReplaced => {
+            if game.multiplayer.is_some() || game.state == GameState::Lobby {
+                game.state = GameState::MultiplayerMenu;
+                game.multiplayer = None;
+            }
+            game.error_message =
+                Some("Signed in somewhere else, so this window was disconnected".to_string());
+        }
+        ServerMessage::Announcement { text } => {
+            game.announcement = Some((text, Instant::now()));
+        }
+        ServerMessage::IdleWarning { seconds_remaining } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.idle_deadline =
+                    Some(Instant::now() + Duration::from_secs(seconds_remaining));
+            }
+        }
+        ServerMessage::RematchOffered { difficulty } => {
+            if let Some(mp) = &mut game.multiplayer {
+                // Pressing r then accepts exactly what was offered
+                mp.rematch_offer = Some(difficulty);
+                if !mp.rematch_requested {
+                    mp.rematch_difficulty = difficulty;
+                }
+            }
+        }
+        ServerMessage::SpectatorCount { count } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.spectators = count;
+            }
+        }
+        ServerMessage::Spectating {
+            code,
+            mode,
+            difficulty,
+            board: board_data,
+            players,
+            elapsed_secs,
+        } => {
+            let board = givens_board(&board_data);
+            let watching = Watching { code, mode, difficulty, players };
+            game.start_watching(watching, board, elapsed_secs);
+        }
+        ServerMessage::SpectatedGameEnded { winner, .. } => {
+            if game.watching.take().is_some() {
+                game.state = GameState::MultiplayerMenu;
+                game.notice = Some(match winner {
+                    Some(name) => format!("The game you were watching ended: {} won", name),
+                    None => "The game you were watching ended".to_string(),
+                });
+            }
+        }
+        ServerMessage::Error { message, req_id } => {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.settle_placement(req_id);
+            }
             game.error_message = Some(message);
         }
         ServerMessage::Pong => {}
@@ -402,6 +788,7 @@ fn handle_key(
     net_client: &mut Option<NetworkClient>,
     username: &mut Option<String>,
     saved_token: &mut Option<String>,
+    network: Backend,
 ) -> bool {
     match game.state {
         GameState::Menu => handle_menu_key(game, key),
@@ -409,24 +796,105 @@ fn handle_key(
         GameState::Paused => handle_paused_key(game, key),
         GameState::Won => handle_won_key(game, key),
         GameState::MultiplayerMenu => {
-            handle_multiplayer_menu_key(game, key, net_client, username, saved_token)
+            handle_multiplayer_menu_key(game, key, net_client, username, saved_token, network)
         }
         GameState::AuthScreen => handle_auth_key(game, key),
-        GameState::Lobby => handle_lobby_key(game, key),
+        GameState::Lobby => handle_lobby_key(game, key, net_client),
+        // Bot races never touch the server, even while connected
+        GameState::MultiplayerPlaying if game.bot.is_some() => {
+            handle_multiplayer_playing_key(game, key, &mut None)
+        }
+        GameState::MultiplayerEnd if game.bot.is_some() => {
+            handle_multiplayer_end_key(game, key, &mut None)
+        }
         GameState::MultiplayerPlaying => handle_multiplayer_playing_key(game, key, net_client),
         GameState::MultiplayerEnd => handle_multiplayer_end_key(game, key, net_client),
         GameState::Leaderboard => handle_leaderboard_key(game, key),
+        GameState::Settings => handle_settings_key(game, key),
+        GameState::MarathonSummary => handle_marathon_summary_key(game, key),
+        GameState::Badges => handle_badges_key(game, key),
+        GameState::History => handle_history_key(game, key),
+        GameState::Spectating => handle_spectating_key(game, key, net_client),
+        GameState::Profile => handle_profile_key(game, key),
     }
 }
 
 fn handle_menu_key(game: &mut Game, key: KeyEvent) -> bool {
+    game.notice = None;
+    if let Some(input) = &mut game.seed_input {
+        match key.code {
+            KeyCode::Char(c @ '0'..='9') if input.len() < MAX_SEED_DIGITS => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                if let Ok(seed) = input.parse() {
+                    game.seed_input = None;
+                    game.marathon = None;
+                    game.start_seeded_game(seed);
+                }
+            }
+            KeyCode::Esc => game.seed_input = None,
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(input) = &mut game.givens_input {
+        match key.code {
+            KeyCode::Char(c @ '0'..='9') if input.len() < 2 => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                if let Ok(count) = input.parse() {
+                    game.givens_input = None;
+                    game.marathon = None;
+                    game.set_custom_givens(count);
+                    game.start_new_game();
+                }
+            }
+            KeyCode::Esc => game.givens_input = None,
+            _ => {}
+        }
+        return false;
+    }
     match key.code {
-        KeyCode::Up | KeyCode::Left => game.difficulty = game.difficulty.prev(),
-        KeyCode::Down | KeyCode::Right => game.difficulty = game.difficulty.next(),
-        KeyCode::Enter => game.start_new_game(),
+        KeyCode::Up | KeyCode::Left => {
+            game.custom_givens = None;
+            game.difficulty = game.difficulty.prev();
+        }
+        KeyCode::Down | KeyCode::Right => {
+            game.custom_givens = None;
+            game.difficulty = game.difficulty.next();
+        }
+        KeyCode::Enter => {
+            game.marathon = None;
+            game.start_new_game();
+        }
+        KeyCode::Char('g') | KeyCode::Char('G') => {
+            game.custom_givens = None;
+            game.grid = game.grid.next();
+        }
+        KeyCode::Char('#') => game.seed_input = Some(String::new()),
+        KeyCode::Char('c') | KeyCode::Char('C') => game.givens_input = Some(String::new()),
+        KeyCode::Char('r') | KeyCode::Char('R') => game.start_marathon(),
+        KeyCode::Char('b') | KeyCode::Char('B') => game.start_bot_race(),
+        KeyCode::Char('h') | KeyCode::Char('H') => game.start_ghost_race(),
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            game.state = GameState::History;
+            game.history_selection = 0;
+        }
+        KeyCode::Char('a') | KeyCode::Char('A') => {
+            game.state = GameState::Badges;
+            game.pending_solo_stats = true;
+        }
         KeyCode::Char('m') | KeyCode::Char('M') => {
             game.state = GameState::MultiplayerMenu;
-            game.menu_selection = 0;
+            game.mp_menu = Default::default();
+        }
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            game.state = GameState::Settings;
+            game.settings_selection = 0;
         }
         KeyCode::Char('q') | KeyCode::Esc => return true,
         _ => {}
@@ -443,6 +911,14 @@ fn handle_playing_key(game: &mut Game, key: KeyEvent) -> bool {
         return false;
     }
 
+    if game.show_reroll_confirm {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => game.reroll(),
+            _ => game.show_reroll_confirm = false,
+        }
+        return false;
+    }
+
     if game.active_hint.is_some() {
         match key.code {
             KeyCode::Char('?') => game.request_hint(),
@@ -452,13 +928,49 @@ fn handle_playing_key(game: &mut Game, key: KeyEvent) -> bool {
         return false;
     }
 
+    game.notice = None;
+    if game.awaiting_report_reason {
+        if let Some(reason) = pick_report_reason(game, key.code) {
+            game.notice = Some(match report::save_local_report(game, reason) {
+                Ok(path) => format!("Puzzle saved to {}", path.display()),
+                Err(e) => format!("Couldn't save report: {}", e),
+            });
+        }
+        return false;
+    }
+
+    if game.locked_digit.is_some() && handle_focus_key(game, key.code) {
+        return false;
+    }
+
+    // Shift+arrows stretch a selection for digits to pencil in across;
+    // plain arrows drop it
+    let arrow = match key.code {
+        KeyCode::Up => Some((-1, 0)),
+        KeyCode::Down => Some((1, 0)),
+        KeyCode::Left => Some((0, -1)),
+        KeyCode::Right => Some((0, 1)),
+        _ => None,
+    };
+    if let Some((dr, dc)) = arrow {
+        if key.modifiers.contains(KeyModifiers::SHIFT) {
+            game.extend_selection(dr, dc);
+        } else {
+            game.selection_anchor = None;
+            game.move_cursor(dr, dc);
+        }
+        return false;
+    }
+
     match key.code {
-        KeyCode::Up => game.move_cursor(-1, 0),
-        KeyCode::Down => game.move_cursor(1, 0),
-        KeyCode::Left => game.move_cursor(0, -1),
-        KeyCode::Right => game.move_cursor(0, 1),
         KeyCode::Char(c) => return handle_playing_char(game, c, key.modifiers),
         KeyCode::Delete | KeyCode::Backspace => game.erase(),
+        KeyCode::Enter => {
+            game.promote_pencil_mark();
+        }
+        KeyCode::Esc if game.awaiting_fill_unit => game.awaiting_fill_unit = false,
+        KeyCode::Esc if game.awaiting_lock_digit => game.awaiting_lock_digit = false,
+        KeyCode::Esc if game.selection().is_some() => game.selection_anchor = None,
         KeyCode::Esc => game.show_quit_confirm = true,
         _ => {}
     }
@@ -466,23 +978,131 @@ fn handle_playing_key(game: &mut Game, key: KeyEvent) -> bool {
 }
 
 fn handle_playing_char(game: &mut Game, c: char, modifiers: KeyModifiers) -> bool {
+    if game.awaiting_fill_unit {
+        game.awaiting_fill_unit = false;
+        match c {
+            'r' | 'R' => game.fill_candidates_unit(Unit::Row),
+            'c' | 'C' => game.fill_candidates_unit(Unit::Column),
+            'b' | 'B' => game.fill_candidates_unit(Unit::Box),
+            _ => {}
+        }
+        return false;
+    }
+
+    if game.awaiting_lock_digit {
+        game.awaiting_lock_digit = false;
+        if let Some(digit) = c.to_digit(10).filter(|&d| d > 0) {
+            game.toggle_locked_digit(digit as u8);
+        }
+        return false;
+    }
+
     match c {
+        '1'..='9' if game.flag_mode => {
+            if let Some(flag) = CellFlag::from_digit(c as u8 - b'0') {
+                game.toggle_flag(flag);
+            }
+        }
         '1'..='9' => game.place_number(c as u8 - b'0'),
         '0' => game.erase(),
-        'p' | 'P' => game.pencil_mode = !game.pencil_mode,
+        'p' | 'P' => {
+            game.pencil_mode = !game.pencil_mode;
+            game.flag_mode = false;
+        }
+        'f' | 'F' => {
+            game.flag_mode = !game.flag_mode;
+            game.pencil_mode = false;
+        }
+        'a' | 'A' => game.awaiting_fill_unit = true,
+        'x' | 'X' => {
+            let removed = game.prune_pencil_marks();
+            game.notice = Some(match removed {
+                0 => "No impossible pencil marks".to_string(),
+                1 => "Removed 1 impossible pencil mark".to_string(),
+                n => format!("Removed {} impossible pencil marks", n),
+            });
+        }
+        'l' | 'L' => game.awaiting_lock_digit = true,
+        '!' => game.awaiting_report_reason = true,
         '?' => game.request_hint(),
         'u' | 'U' => game.undo(),
         'z' if modifiers.contains(KeyModifiers::CONTROL) => game.undo(),
         'v' | 'V' => game.validate(),
+        'h' | 'H' => game.open_hint_log(),
+        'g' | 'G' => game.peek(),
+        'n' | 'N' => game.reroll(),
+        'c' | 'C' => copy_seed(game),
         ' ' => game.toggle_pause(),
+        'i' | 'I' => toggle_info_panel(game),
         'q' | 'Q' => game.show_quit_confirm = true,
         _ => {}
     }
     false
 }
 
+/// Hide or show the side panel, keeping the choice for next time
+fn toggle_info_panel(game: &mut Game) {
+    game.settings.show_info_panel = !game.settings.show_info_panel;
+    let _ = game.settings.save();
+}
+
+/// Put the puzzle's seed on the clipboard. Goes through the terminal
+/// (OSC 52), which most pass on to the system clipboard, so it works over
+/// SSH too; where it doesn't, the seed is on screen to copy by hand.
+fn copy_seed(game: &mut Game) {
+    let Some(seed) = game.seed else {
+        return;
+    };
+    game.notice = Some(
+        match execute!(io::stdout(), CopyToClipboard::to_clipboard_from(seed.to_string())) {
+            Ok(()) => format!("Copied seed {}", seed),
+            Err(e) => format!("Couldn't copy the seed: {}", e),
+        },
+    );
+}
+
+/// Close the report prompt opened with '!'. 1-3 picks a reason from
+/// `REPORT_REASONS`; any other key cancels.
+fn pick_report_reason(game: &mut Game, code: KeyCode) -> Option<&'static str> {
+    game.awaiting_report_reason = false;
+    let KeyCode::Char(c) = code else {
+        return None;
+    };
+    let index = c.to_digit(10)?.checked_sub(1)?;
+    REPORT_REASONS.get(index as usize).copied()
+}
+
+/// Keys that mean something different while a digit is locked. Returns false
+/// for everything else so undo, hints, pause etc. keep working.
+fn handle_focus_key(game: &mut Game, code: KeyCode) -> bool {
+    let Some(digit) = game.locked_digit else {
+        return false;
+    };
+    match code {
+        KeyCode::Up => game.move_to_candidate(-1, 0),
+        KeyCode::Down => game.move_to_candidate(1, 0),
+        KeyCode::Left => game.move_to_candidate(0, -1),
+        KeyCode::Right => game.move_to_candidate(0, 1),
+        KeyCode::Enter => game.place_number(digit),
+        KeyCode::Esc => game.locked_digit = None,
+        KeyCode::Char(c @ '1'..='9') => game.toggle_locked_digit(c as u8 - b'0'),
+        _ => return false,
+    }
+    true
+}
+
 fn handle_paused_key(game: &mut Game, key: KeyEvent) -> bool {
+    if game.show_hint_log {
+        match key.code {
+            KeyCode::Up => game.scroll_hint_log(-1),
+            KeyCode::Down => game.scroll_hint_log(1),
+            KeyCode::Char('h') | KeyCode::Char('H') | KeyCode::Esc => game.show_hint_log = false,
+            _ => {}
+        }
+        return false;
+    }
     match key.code {
+        KeyCode::Char('h') | KeyCode::Char('H') => game.open_hint_log(),
         KeyCode::Char(' ') | KeyCode::Esc | KeyCode::Enter => game.toggle_pause(),
         _ => {}
     }
@@ -491,22 +1111,105 @@ fn handle_paused_key(game: &mut Game, key: KeyEvent) -> bool {
 
 fn handle_won_key(game: &mut Game, key: KeyEvent) -> bool {
     match key.code {
-        KeyCode::Enter | KeyCode::Char('n') => game.state = GameState::Menu,
+        KeyCode::Enter | KeyCode::Char('n') => {
+            game.state = GameState::Menu;
+            game.notice = None;
+        }
+        KeyCode::Char('h') | KeyCode::Char('H') => game.start_ghost_race(),
         KeyCode::Char('q') | KeyCode::Esc => return true,
         _ => {}
     }
     false
 }
 
+fn handle_marathon_summary_key(game: &mut Game, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter | KeyCode::Esc => game.end_marathon(),
+        KeyCode::Char('q') => return true,
+        _ => {}
+    }
+    false
+}
+
+fn handle_badges_key(game: &mut Game, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => game.state = GameState::Menu,
+        _ => {}
+    }
+    false
+}
+
+fn handle_profile_key(game: &mut Game, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+            game.state = GameState::MultiplayerMenu;
+        }
+        _ => {}
+    }
+    false
+}
+
+fn handle_history_key(game: &mut Game, key: KeyEvent) -> bool {
+    let count = game.history.len();
+    match key.code {
+        KeyCode::Up if count > 0 => {
+            game.history_selection = (game.history_selection + count - 1) % count;
+        }
+        KeyCode::Down if count > 0 => {
+            game.history_selection = (game.history_selection + 1) % count;
+        }
+        KeyCode::Enter if count > 0 => game.replay(game.history_selection),
+        KeyCode::Esc | KeyCode::Char('q') => game.state = GameState::Menu,
+        _ => {}
+    }
+    false
+}
+
+fn handle_settings_key(game: &mut Game, key: KeyEvent) -> bool {
+    let count = SettingItem::ALL.len();
+    match key.code {
+        KeyCode::Up => {
+            game.settings_selection = (game.settings_selection + count - 1) % count;
+        }
+        KeyCode::Down => {
+            game.settings_selection = (game.settings_selection + 1) % count;
+        }
+        KeyCode::Enter | KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => {
+            game.settings.cycle(SettingItem::ALL[game.settings_selection]);
+        }
+        KeyCode::Esc | KeyCode::Char('q') => {
+            // Best effort: a read-only config dir shouldn't stop the game
+            let _ = game.settings.save();
+            game.state = GameState::Menu;
+        }
+        _ => {}
+    }
+    false
+}
+
 // ── Multiplayer key handlers ────────────────────────────────────────────
 
-const MP_MENU_ITEMS: &[&str] = &[
-    "Create Room",
-    "Join Room",
-    "Quick Match",
-    "Leaderboard",
-    "Back",
-];
+/// How long quitting waits for the server to drop an abandoned device code
+const AUTH_CANCEL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause between attempts to get back into a match after the connection drops
+const RECONNECT_RETRY: Duration = Duration::from_secs(1);
+
+/// Longest seed the main menu takes: any u64, so match seeds fit too
+const MAX_SEED_DIGITS: usize = 20;
+
+/// How often animated screens repaint, unless SUDOKU_TICK_MS overrides it.
+const DEFAULT_TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Shortest tick SUDOKU_TICK_MS may ask for, so a typo can't pin a core.
+const MIN_TICK_RATE: Duration = Duration::from_millis(16);
+
+fn tick_rate() -> Duration {
+    std::env::var("SUDOKU_TICK_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map_or(DEFAULT_TICK_RATE, |ms| Duration::from_millis(ms).max(MIN_TICK_RATE))
+}
 
 fn handle_multiplayer_menu_key(
     game: &mut Game,
@@ -514,69 +1217,40 @@ fn handle_multiplayer_menu_key(
     net_client: &mut Option<NetworkClient>,
     username: &mut Option<String>,
     saved_token: &mut Option<String>,
+    network: Backend,
 ) -> bool {
     game.error_message = None;
+    game.notice = None;
 
-    if game.joining_room {
-        match key.code {
-            KeyCode::Char(c) if c.is_ascii_alphanumeric() && game.room_input.len() < 6 => {
-                game.room_input.push(c.to_ascii_uppercase());
-            }
-            KeyCode::Backspace => {
-                game.room_input.pop();
-            }
-            KeyCode::Enter if game.room_input.len() == 6 => {
-                if let Some(client) = net_client.as_ref() {
-                    client.send(ClientMessage::JoinRoom {
-                        code: game.room_input.clone(),
-                    });
-                }
-                game.joining_room = false;
-            }
-            KeyCode::Esc => {
-                game.joining_room = false;
-                game.room_input.clear();
+    match game.mp_menu.handle_key(key) {
+        // Items 0-5 require auth + connection
+        Some(MenuAction::Activate(item)) if item < 6 && net_client.is_none() => {
+            if network.is_local() {
+                // Dev mode: silent auto-auth+connect
+                game.pending_connect = true;
+                game.pending_menu_action = Some(item);
+            } else if username.is_none() {
+                // Production: GitHub device flow
+                game.pending_auth_start = true;
+            } else if saved_token.is_some() {
+                // Already authed, just need to connect
+                game.pending_connect = true;
+                game.pending_menu_action = Some(item);
             }
-            _ => {}
         }
-        return false;
-    }
-
-    match key.code {
-        KeyCode::Up => {
-            if game.menu_selection > 0 {
-                game.menu_selection -= 1;
-            } else {
-                game.menu_selection = MP_MENU_ITEMS.len() - 1;
-            }
-        }
-        KeyCode::Down => {
-            game.menu_selection = (game.menu_selection + 1) % MP_MENU_ITEMS.len();
-        }
-        KeyCode::Enter => {
-            // Items 0-3 require auth + connection
-            if game.menu_selection < 4 && net_client.is_none() {
-                if crate::net::client::is_local() {
-                    // Dev mode: silent auto-auth+connect
-                    game.pending_connect = true;
-                    game.pending_menu_action = Some(game.menu_selection);
-                } else if username.is_none() {
-                    // Production: GitHub device flow
-                    game.pending_auth_start = true;
-                } else if saved_token.is_some() {
-                    // Already authed, just need to connect
-                    game.pending_connect = true;
-                    game.pending_menu_action = Some(game.menu_selection);
-                }
-                return false;
+        Some(MenuAction::Activate(item)) => execute_menu_action(game, item, net_client),
+        Some(MenuAction::Join(code)) => {
+            if let Some(client) = net_client.as_ref() {
+                client.send(ClientMessage::JoinRoom { code, req_id: None });
             }
-
-            execute_menu_action(game, game.menu_selection, net_client);
         }
-        KeyCode::Esc | KeyCode::Char('q') => {
-            game.state = GameState::Menu;
+        Some(MenuAction::StepQuickDifficulty(forward)) => {
+            let current = game.quick_match_difficulty();
+            let stepped = if forward { current.next() } else { current.prev() };
+            game.mp_menu.quick_override = Some(stepped);
         }
-        _ => {}
+        Some(MenuAction::Leave) => game.state = GameState::Menu,
+        None => {}
     }
     false
 }
@@ -594,30 +1268,49 @@ fn execute_menu_action(
                 client.send(ClientMessage::CreateRoom {
                     mode: GameMode::Race,
                     difficulty: game.difficulty,
+                    handicap: game.settings.room_handicap,
+                    progress: game.settings.room_progress,
+                    puzzle: None,
+                    req_id: None,
                 });
+                game.lobby_room = Some((GameMode::Race, game.difficulty));
+                game.waiting_since = Some(Instant::now());
             }
         }
         1 => {
             // Join Room
-            game.joining_room = true;
-            game.room_input.clear();
+            game.mp_menu.enter_room_code();
         }
         2 => {
             // Quick Match
+            let difficulty = game.quick_match_difficulty();
             if let Some(client) = net_client.as_ref() {
                 client.send(ClientMessage::QuickMatch {
                     mode: GameMode::Race,
-                    difficulty: game.difficulty,
+                    difficulty,
                 });
             }
             game.state = GameState::Lobby;
             game.room_code = None;
+            game.lobby_room = Some((GameMode::Race, difficulty));
+            game.waiting_since = Some(Instant::now());
         }
         3 => {
             // Leaderboard — defer to async
             game.pending_leaderboard = true;
         }
         4 => {
+            // Watch a Game
+            if let Some(client) = net_client.as_ref() {
+                client.send(ClientMessage::SpectateRandom);
+            }
+        }
+        5 => {
+            // Profile, fetched afresh in case a match just ended
+            game.state = GameState::Profile;
+            game.pending_me = true;
+        }
+        6 => {
             // Back
             game.state = GameState::Menu;
         }
@@ -625,17 +1318,103 @@ fn execute_menu_action(
     }
 }
 
+fn handle_spectating_key(
+    game: &mut Game,
+    key: KeyEvent,
+    net_client: &mut Option<NetworkClient>,
+) -> bool {
+    match key.code {
+        // Asking again brings the board up to date, moves and all
+        KeyCode::Char('r') => {
+            if let (Some(client), Some(watching)) = (net_client.as_ref(), &game.watching) {
+                client.send(ClientMessage::Spectate { code: watching.code.clone() });
+            }
+        }
+        KeyCode::Esc | KeyCode::Char('q') => {
+            if let Some(client) = net_client.as_ref() {
+                client.send(ClientMessage::StopSpectating);
+            }
+            game.watching = None;
+            game.state = GameState::MultiplayerMenu;
+        }
+        _ => {}
+    }
+    false
+}
+
 fn handle_auth_key(game: &mut Game, key: KeyEvent) -> bool {
-    if let KeyCode::Esc = key.code {
-        game.state = GameState::MultiplayerMenu;
-        game.auth_code = None;
-        game.auth_uri = None;
-        game.auth_polling = false;
+    if game.show_quit_confirm {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => return true,
+            _ => game.show_quit_confirm = false,
+        }
+        return false;
+    }
+    match key.code {
+        KeyCode::Esc => {
+            game.state = GameState::MultiplayerMenu;
+            if let Some(code) = cancel_login(game) {
+                tokio::spawn(async move {
+                    let _ = NetworkClient::cancel_device_auth(&code).await;
+                });
+            }
+        }
+        KeyCode::Char('q') | KeyCode::Char('Q') => game.show_quit_confirm = true,
+        _ => {}
     }
     false
 }
 
-fn handle_lobby_key(game: &mut Game, key: KeyEvent) -> bool {
+/// Abandon an in-progress login: stop polling and forget the saved code.
+/// Returns the device code if it was still being polled, so the caller can
+/// ask the server to drop it too.
+fn cancel_login(game: &mut Game) -> Option<String> {
+    let code = game.auth_code.take();
+    game.auth_uri = None;
+    if !std::mem::take(&mut game.auth_polling) {
+        return None;
+    }
+    NetworkClient::clear_pending_auth();
+    code
+}
+
+/// Show a device code and start polling for it, whether freshly issued or
+/// resumed from disk.
+fn begin_auth_polling(game: &mut Game, pending: &PendingAuth) {
+    game.auth_code = Some(pending.user_code.clone());
+    game.auth_uri = Some(pending.verification_uri.clone());
+    game.auth_poll_interval = pending.interval.max(5);
+    game.auth_polling = true;
+}
+
+fn handle_lobby_key(
+    game: &mut Game,
+    key: KeyEvent,
+    net_client: &mut Option<NetworkClient>,
+) -> bool {
+    // Hosts of a private room can change its settings until someone joins;
+    // the lobby shows the new ones once the server confirms
+    let hosted = game.room_code.as_ref().and(game.lobby_room);
+    let change = |(mode, difficulty): (GameMode, Difficulty)| match key.code {
+        KeyCode::Left => Some((mode, difficulty.prev())),
+        KeyCode::Right => Some((mode, difficulty.next())),
+        KeyCode::Char('m') => Some(match mode {
+            GameMode::Race => (GameMode::Shared, difficulty),
+            GameMode::Shared => (GameMode::Race, difficulty),
+        }),
+        _ => None,
+    };
+    if let Some((mode, difficulty)) = hosted.and_then(change) {
+        if let Some(client) = net_client.as_ref() {
+            client.send(ClientMessage::UpdateRoomSettings {
+                mode,
+                difficulty,
+                req_id: None,
+            });
+        }
+        return false;
+    }
+
     match key.code {
         KeyCode::Esc | KeyCode::Char('q') => {
             game.state = GameState::MultiplayerMenu;
@@ -678,7 +1457,12 @@ fn handle_multiplayer_playing_key(
                 if let Some(client) = net_client.as_ref() {
                     client.send(ClientMessage::Forfeit);
                 }
-                game.state = GameState::MultiplayerMenu;
+                game.state = if game.bot.take().is_some() {
+                    GameState::Menu
+                } else {
+                    GameState::MultiplayerMenu
+                };
+                game.notice = None;
                 game.show_quit_confirm = false;
                 return false;
             }
@@ -688,6 +1472,26 @@ fn handle_multiplayer_playing_key(
     }
 
     game.error_message = None;
+    game.notice = None;
+
+    if game.awaiting_report_reason {
+        if let Some(reason) = pick_report_reason(game, key.code) {
+            if game.bot.is_some() {
+                game.notice = Some(match report::save_local_report(game, reason) {
+                    Ok(path) => format!("Puzzle saved to {}", path.display()),
+                    Err(e) => format!("Couldn't save report: {}", e),
+                });
+                return false;
+            }
+            if let Some(client) = net_client.as_ref() {
+                client.send(ClientMessage::ReportPuzzle {
+                    reason: reason.to_string(),
+                });
+            }
+            game.notice = Some("Puzzle reported — thanks!".to_string());
+        }
+        return false;
+    }
 
     match key.code {
         KeyCode::Up => {
@@ -713,14 +1517,28 @@ fn handle_multiplayer_playing_key(
 
             if game.pencil_mode {
                 game.place_number(num);
-            } else {
+            } else if game.precheck_placement(r, c, num) {
                 game.place_number(num);
-                if let Some(client) = net_client.as_ref() {
-                    client.send(ClientMessage::PlaceNumber {
-                        row: r,
-                        col: c,
-                        value: num,
-                    });
+                send_placement(game, net_client, r, c, num);
+                clear_idle_warning(game);
+                if (game.selected_row, game.selected_col) != (r, c) {
+                    send_cursor_update(game, net_client);
+                }
+            }
+        }
+        KeyCode::Enter => {
+            let r = game.selected_row;
+            let c = game.selected_col;
+            if let Some(value) = game.pencil_marks[r][c].single()
+                && !game.precheck_placement(r, c, value)
+            {
+                return false;
+            }
+            if let Some(value) = game.promote_pencil_mark() {
+                send_placement(game, net_client, r, c, value);
+                clear_idle_warning(game);
+                if (game.selected_row, game.selected_col) != (r, c) {
+                    send_cursor_update(game, net_client);
                 }
             }
         }
@@ -728,13 +1546,14 @@ fn handle_multiplayer_playing_key(
             let r = game.selected_row;
             let c = game.selected_col;
             game.erase();
-            if let Some(client) = net_client.as_ref() {
-                client.send(ClientMessage::EraseNumber { row: r, col: c });
-            }
+            send_or_hold(game, net_client, ClientMessage::EraseNumber { row: r, col: c });
+            clear_idle_warning(game);
         }
         KeyCode::Char('p') | KeyCode::Char('P') => {
             game.pencil_mode = !game.pencil_mode;
         }
+        KeyCode::Char('!') => game.awaiting_report_reason = true,
+        KeyCode::Char('i') | KeyCode::Char('I') => toggle_info_panel(game),
         KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
             game.show_quit_confirm = true;
         }
@@ -743,26 +1562,136 @@ fn handle_multiplayer_playing_key(
     false
 }
 
+/// Send a placement with a fresh request id, so its answer can be matched to
+/// it even if the cell has changed again since.
+fn send_placement(
+    game: &mut Game,
+    net_client: &Option<NetworkClient>,
+    row: usize,
+    col: usize,
+    value: u8,
+) {
+    let Some(mp) = game.multiplayer.as_mut() else {
+        return;
+    };
+    if net_client.is_none() && mp.reconnecting_until.is_none() {
+        return;
+    }
+    let req_id = Some(mp.track_placement(row, col, value));
+    send_or_hold(
+        game,
+        net_client,
+        ClientMessage::PlaceNumber {
+            row,
+            col,
+            value,
+            req_id,
+        },
+    );
+}
+
+/// Send a move, or if our connection is down mid-match, hold it to send once
+/// we're back. Warns when too many are already held to keep another.
+fn send_or_hold(game: &mut Game, net_client: &Option<NetworkClient>, msg: ClientMessage) {
+    if net_client.as_ref().is_some_and(|client| client.send(msg.clone())) {
+        return;
+    }
+    let Some(mp) = game.multiplayer.as_mut() else {
+        return;
+    };
+    // Without a connection or a reconnect under way it's a local match
+    if net_client.is_none() && mp.reconnecting_until.is_none() {
+        return;
+    }
+    if !mp.outbox.push(msg) {
+        game.error_message = Some(format!(
+            "Offline — only {} moves are kept until we're back; that one was dropped",
+            OUTBOX_LEN
+        ));
+    }
+}
+
 fn handle_multiplayer_end_key(
     game: &mut Game,
     key: KeyEvent,
     net_client: &mut Option<NetworkClient>,
 ) -> bool {
     match key.code {
+        KeyCode::Char('r') | KeyCode::Char('R') if game.ghost_race.is_some() => game.rematch_ghost(),
+        KeyCode::Char('r') | KeyCode::Char('R') if game.bot.is_some() => game.start_bot_race(),
         KeyCode::Char('r') | KeyCode::Char('R') => {
-            if let Some(client) = net_client.as_ref() {
-                client.send(ClientMessage::Rematch);
+            if let Some(mp) = &mut game.multiplayer
+                && let Some(client) = net_client.as_ref()
+            {
+                client.send(ClientMessage::Rematch {
+                    difficulty: Some(mp.rematch_difficulty),
+                });
+                mp.rematch_requested = true;
+            }
+        }
+        KeyCode::Left | KeyCode::Right if game.bot.is_none() => {
+            if let Some(mp) = &mut game.multiplayer
+                && !mp.rematch_requested
+            {
+                mp.rematch_difficulty = if key.code == KeyCode::Left {
+                    mp.rematch_difficulty.prev()
+                } else {
+                    mp.rematch_difficulty.next()
+                };
+            }
+        }
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            if let Some(mp) = &mut game.multiplayer
+                && mp.final_boards.is_some()
+            {
+                mp.end_view = mp.end_view.next();
             }
         }
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            if let Some(mp) = &mut game.multiplayer
+                && mp.solution.is_some()
+            {
+                mp.end_view = if mp.end_view == EndView::Solution {
+                    EndView::Summary
+                } else {
+                    EndView::Solution
+                };
+            }
+        }
+        KeyCode::Esc
+            if game
+                .multiplayer
+                .as_ref()
+                .is_some_and(|mp| mp.end_view != EndView::Summary) =>
+        {
+            if let Some(mp) = &mut game.multiplayer {
+                mp.end_view = EndView::Summary;
+            }
+        }
+        KeyCode::Enter | KeyCode::Char('q') | KeyCode::Esc if game.bot.is_some() => {
+            game.state = GameState::Menu;
+            game.multiplayer = None;
+            game.bot = None;
+            game.notice = None;
+        }
         KeyCode::Enter | KeyCode::Char('q') | KeyCode::Esc => {
             game.state = GameState::MultiplayerMenu;
             game.multiplayer = None;
+            // Rating changed, refresh the header
+            game.pending_me = true;
         }
         _ => {}
     }
     false
 }
 
+/// Our own move resets the server's idle timer, so drop any countdown.
+fn clear_idle_warning(game: &mut Game) {
+    if let Some(mp) = &mut game.multiplayer {
+        mp.idle_deadline = None;
+    }
+}
+
 fn send_cursor_update(game: &Game, net_client: &mut Option<NetworkClient>) {
     if let Some(client) = net_client.as_ref() {
         client.send(ClientMessage::UpdateCursor {
@@ -0,0 +1,240 @@
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use sudoku_core::{Board, Cell, SolutionBoard};
+
+use crate::ghost::{Ghost, GhostMove};
+
+/// How hard the local race bot pushes: its pace and how often it slips up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotLevel {
+    Relaxed,
+    Steady,
+    Quick,
+}
+
+impl BotLevel {
+    pub fn all() -> &'static [BotLevel] {
+        &[BotLevel::Relaxed, BotLevel::Steady, BotLevel::Quick]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BotLevel::Relaxed => "Relaxed",
+            BotLevel::Steady => "Steady",
+            BotLevel::Quick => "Quick",
+        }
+    }
+
+    /// The next level in `all()`, wrapping around.
+    pub fn next(&self) -> BotLevel {
+        let all = BotLevel::all();
+        let idx = all.iter().position(|l| l == self).unwrap_or(0);
+        all[(idx + 1) % all.len()]
+    }
+
+    /// Average placements per minute
+    pub fn cells_per_min(&self) -> f32 {
+        match self {
+            BotLevel::Relaxed => 4.0,
+            BotLevel::Steady => 8.0,
+            BotLevel::Quick => 15.0,
+        }
+    }
+
+    /// Chance that a placement is wrong (and has to be fixed later)
+    pub fn error_rate(&self) -> f64 {
+        match self {
+            BotLevel::Relaxed => 0.15,
+            BotLevel::Steady => 0.08,
+            BotLevel::Quick => 0.03,
+        }
+    }
+
+    /// Shown next to the bot's name, like a player's rating.
+    pub fn rating(&self) -> i32 {
+        match self {
+            BotLevel::Relaxed => 1000,
+            BotLevel::Steady => 1200,
+            BotLevel::Quick => 1450,
+        }
+    }
+}
+
+/// What decides a `BotOpponent`'s moves
+enum Pace {
+    /// Fill cells at the level's speed, now and then wrongly
+    Level(BotLevel),
+    /// Replay a recorded solve, timed from `started_at`. Next move last.
+    Script { moves: Vec<GhostMove>, started_at: Instant },
+}
+
+/// A local opponent for single-player races. It works through its own copy
+/// of the puzzle using the solution, at the pace of its level, sometimes
+/// placing a wrong value that it only goes back to once the board is full.
+/// As a ghost it instead replays one of your past solves move for move.
+pub struct BotOpponent {
+    pace: Pace,
+    pub board: Board,
+    solution: SolutionBoard,
+    /// Empty cells still to fill, popped from the back
+    todo: Vec<(usize, usize)>,
+    /// Cells it filled wrongly and still has to fix
+    wrong: Vec<(usize, usize)>,
+    next_move_at: Instant,
+    /// When each recent placement happened, for the momentum readout
+    recent: Vec<Instant>,
+}
+
+impl BotOpponent {
+    pub fn new(level: BotLevel, board: Board, solution: SolutionBoard, now: Instant) -> Self {
+        let mut todo: Vec<(usize, usize)> = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| board[r][c] == Cell::Empty)
+            .collect();
+        todo.shuffle(&mut rand::rng());
+        let mut bot = BotOpponent {
+            pace: Pace::Level(level),
+            board,
+            solution,
+            todo,
+            wrong: Vec::new(),
+            next_move_at: now,
+            recent: Vec::new(),
+        };
+        bot.next_move_at = now + bot.move_delay();
+        bot
+    }
+
+    /// Race `ghost` from `now`, replaying its moves at their recorded times.
+    pub fn replay(ghost: &Ghost, board: Board, solution: SolutionBoard, now: Instant) -> Self {
+        let mut moves = ghost.moves.clone();
+        moves.reverse();
+        BotOpponent {
+            pace: Pace::Script { moves, started_at: now },
+            board,
+            solution,
+            todo: Vec::new(),
+            wrong: Vec::new(),
+            next_move_at: now,
+            recent: Vec::new(),
+        }
+    }
+
+    /// Gap before the next placement: the level's pace, give or take a third.
+    fn move_delay(&self) -> Duration {
+        let Pace::Level(level) = self.pace else {
+            return Duration::ZERO;
+        };
+        let base = 60.0 / level.cells_per_min();
+        let jitter = rand::rng().random_range(0.67..1.33);
+        Duration::from_secs_f32(base * jitter)
+    }
+
+    /// Make every placement that has come due by `now`. Returns true if the
+    /// board changed.
+    pub fn advance(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        match &mut self.pace {
+            Pace::Script { moves, started_at } => {
+                while let Some(mv) = moves.last().copied() {
+                    let at = *started_at + Duration::from_millis(mv.at_ms);
+                    if now < at {
+                        break;
+                    }
+                    moves.pop();
+                    self.board[mv.row][mv.col] = match mv.value {
+                        0 => Cell::Empty,
+                        v => Cell::UserInput(v),
+                    };
+                    self.recent.push(at);
+                    changed = true;
+                }
+            }
+            Pace::Level(_) => {
+                while !self.is_finished() && now >= self.next_move_at {
+                    self.place_one();
+                    self.recent.push(self.next_move_at);
+                    self.next_move_at += self.move_delay();
+                    changed = true;
+                }
+            }
+        }
+        self.recent.retain(|&t| now.duration_since(t) < Duration::from_secs(60));
+        changed
+    }
+
+    fn place_one(&mut self) {
+        let Pace::Level(level) = self.pace else {
+            return;
+        };
+        if let Some((r, c)) = self.todo.pop() {
+            let answer = self.solution[r][c];
+            if rand::rng().random_bool(level.error_rate()) {
+                self.board[r][c] = Cell::UserInput(answer % 9 + 1);
+                self.wrong.push((r, c));
+            } else {
+                self.board[r][c] = Cell::UserInput(answer);
+            }
+        } else if let Some((r, c)) = self.wrong.pop() {
+            self.board[r][c] = Cell::UserInput(self.solution[r][c]);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.pace {
+            Pace::Level(_) => self.todo.is_empty() && self.wrong.is_empty(),
+            Pace::Script { moves, .. } => moves.is_empty(),
+        }
+    }
+
+    /// Placed (non-given) cells, right or wrong
+    pub fn filled(&self) -> u32 {
+        self.board
+            .iter()
+            .flatten()
+            .filter(|cell| matches!(cell, Cell::UserInput(_)))
+            .count() as u32
+    }
+
+    /// Placed cells that match the solution
+    pub fn correct(&self) -> u32 {
+        (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| self.board[r][c] == Cell::UserInput(self.solution[r][c]))
+            .count() as u32
+    }
+
+    /// Placements over the last minute
+    pub fn momentum(&self) -> f32 {
+        self.recent.len() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sudoku_core::puzzle::generate_puzzle;
+    use sudoku_core::Difficulty;
+
+    #[test]
+    fn bot_fixes_its_mistakes_and_finishes_on_the_solution() {
+        let (board, solution) = generate_puzzle(Difficulty::Easy);
+        let empties = board.iter().flatten().filter(|c| **c == Cell::Empty).count() as u32;
+        let start = Instant::now();
+        let mut bot = BotOpponent::new(BotLevel::Relaxed, board, solution, start);
+
+        assert!(!bot.advance(start));
+        assert_eq!(bot.filled(), 0);
+
+        // Long enough for every cell plus every fix, even at the slowest pace
+        assert!(bot.advance(start + Duration::from_secs(3600)));
+        assert!(bot.is_finished());
+        assert_eq!(bot.filled(), empties);
+        assert_eq!(bot.correct(), empties);
+        let mut cells = bot.board.iter().flatten().zip(solution.iter().flatten());
+        assert!(cells.all(|(cell, &answer)| cell.value() == Some(answer)));
+    }
+}
@@ -1,9 +1,73 @@
-use crate::hint::{find_hint, Hint, HintStage};
-use sudoku_core::protocol::LeaderboardEntry;
-use sudoku_core::puzzle::generate_puzzle;
-use sudoku_core::validation::{get_all_conflicts, get_candidates, is_board_complete};
-use sudoku_core::{Board, Cell, Difficulty, SolutionBoard};
-use std::time::Instant;
+use crate::achievements::{self, Achievement, Solve, Stats};
+use crate::bot::BotOpponent;
+use crate::ghost::{self, Ghost, GhostMove};
+use crate::history::{self, Outcome, Played};
+use crate::hint::{find_hint, Hint, HintStage, HintTechnique};
+use crate::net::outbox::Outbox;
+use crate::net::NetworkClient;
+use crate::net::trace::NetTrace;
+use crate::mp_menu::MultiplayerMenu;
+use crate::pencil::{MarkOrigin, PencilMarks};
+use crate::settings::{AutoAdvance, Settings};
+use sudoku_core::protocol::{
+    GameMode, LeaderboardEntry, MeResponse, SoloResult, SoloStats, RECONNECT_GRACE_SECS,
+};
+use sudoku_core::board::{givens_string, parse_givens};
+use sudoku_core::elo::elo_change;
+use sudoku_core::puzzle::{
+    generate_puzzle_seeded_in, generate_puzzle_with_givens_seeded, random_seed, unique_solution, GENERATOR_VERSION, MAX_GIVENS, MIN_GIVENS,
+};
+use sudoku_core::validation::{
+    conflicting_cells_in, get_all_conflicts_in, get_candidates_in, has_conflict_in,
+    is_board_complete_in,
+};
+use sudoku_core::{Board, Cell, Difficulty, GridSpec, SolutionBoard};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a row, column or box lights up after the placement that completes it
+pub const UNIT_FLASH: Duration = Duration::from_millis(800);
+
+/// How long a server announcement stays up, unless a newer one replaces it
+pub const ANNOUNCEMENT_SHOWN: Duration = Duration::from_secs(120);
+
+/// How long a peek shows the solution. Key repeat from holding the key keeps
+/// it up, since most terminals never report the release.
+pub const PEEK_SHOWN: Duration = Duration::from_secs(1);
+
+/// How many hints a peek at the whole solution counts as in the stats
+pub const PEEK_WEIGHT: u32 = 5;
+
+/// What a `PuzzleRequest` is generated for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleFor {
+    Solo,
+    BotRace,
+}
+
+/// A puzzle to generate on the blocking pool rather than the event loop:
+/// rating can throw away and dig a puzzle many times over, which takes long
+/// enough at the harder tiers to freeze the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PuzzleRequest {
+    pub grid: GridSpec,
+    pub difficulty: Difficulty,
+    pub custom_givens: Option<usize>,
+    pub seed: u64,
+    pub purpose: PuzzleFor,
+}
+
+impl PuzzleRequest {
+    pub fn generate(&self) -> (Board, SolutionBoard) {
+        match self.custom_givens {
+            Some(count) if self.grid == GridSpec::CLASSIC => {
+                let (board, solution, _) = generate_puzzle_with_givens_seeded(count, self.seed);
+                (board, solution)
+            }
+            _ => generate_puzzle_seeded_in(&self.grid, self.difficulty, self.seed),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GameState {
@@ -17,6 +81,12 @@ pub enum GameState {
     MultiplayerPlaying,
     MultiplayerEnd,
     Leaderboard,
+    Settings,
+    MarathonSummary,
+    Badges,
+    History,
+    Spectating,
+    Profile,
 }
 
 #[derive(Clone, Debug)]
@@ -26,6 +96,8 @@ pub enum Move {
         col: usize,
         old: Cell,
         new: Cell,
+        /// Whether this placement added to `mistakes`
+        mistake: bool,
     },
     Erase {
         row: usize,
@@ -36,7 +108,30 @@ pub enum Move {
         row: usize,
         col: usize,
         value: u8,
+        /// The mark's origin before the toggle; None if it wasn't there
+        origin: Option<MarkOrigin>,
     },
+    /// Pencil marks rewritten for several cells at once; holds what they were before
+    FillPencilMarks {
+        previous: Vec<(usize, usize, PencilMarks)>,
+    },
+}
+
+/// Row, column or box containing the cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Row,
+    Column,
+    Box,
+}
+
+/// Units a placement at (row, col) just completed, each full and free of
+/// repeats; drawn highlighted for `UNIT_FLASH`.
+pub struct UnitFlash {
+    pub units: Vec<Unit>,
+    pub row: usize,
+    pub col: usize,
+    pub at: Instant,
 }
 
 /// Multiplayer-specific state
@@ -48,12 +143,173 @@ pub struct MultiplayerState {
     pub opponent_filled: u32,
     /// Race mode: opponent's momentum (placements/min)
     pub opponent_momentum: f32,
+    /// Race mode in a fog room: which quarter of their empty cells the
+    /// opponent has filled (0-3), sent instead of `opponent_filled`
+    pub opponent_quarter: Option<u8>,
+    /// Race mode: opponent's correct cells, only known when racing the local bot
+    pub opponent_correct: Option<u32>,
+    /// Race mode: opponent's wrong placements as counted by the server; None
+    /// until they make one, and always against the bot
+    pub opponent_mistakes: Option<u32>,
     /// Shared mode: opponent's cursor position
     pub opponent_cursor: Option<(usize, usize)>,
     /// Shared mode: cell ownership (who placed what)
     pub cell_owner: [[CellOwner; 9]; 9],
     /// Game result
     pub result: Option<GameResult>,
+    /// Race mode: both finished boards, sent by the server after the game ends
+    pub final_boards: Option<FinalBoards>,
+    /// The solved board from `GameEnd`, givens marked, for the end screen
+    pub solution: Option<Board>,
+    /// What the end screen is currently showing
+    pub end_view: EndView,
+    /// When the server will forfeit the room for inactivity, after an `IdleWarning`
+    pub idle_deadline: Option<Instant>,
+    /// While the opponent's connection is down: when the server forfeits them
+    /// unless they're back
+    pub opponent_gone_until: Option<Instant>,
+    /// How many people are watching this game
+    pub spectators: u32,
+    /// Difficulty to ask for in a rematch; starts as the one just played
+    pub rematch_difficulty: Difficulty,
+    /// The opponent asked for a rematch at this difficulty
+    pub rematch_offer: Option<Difficulty>,
+    /// We asked for a rematch and are waiting on the opponent
+    pub rematch_requested: bool,
+    /// Wins (ours, theirs) over every game against this opponent, fetched
+    /// for the end screen
+    pub series: Option<(u32, u32)>,
+    /// Placements sent to the server but not yet answered, by request id
+    pub pending_moves: HashMap<u64, (usize, usize, u8)>,
+    next_req_id: u64,
+    /// While our connection is down: how long we keep trying to get back
+    /// before giving the match up
+    pub reconnecting_until: Option<Instant>,
+    /// Moves made while the connection is down, sent once it's back
+    pub outbox: Outbox,
+}
+
+impl MultiplayerState {
+    /// The opponent did something that resets the server's idle timer, so an
+    /// idle warning no longer applies
+    pub fn opponent_moved(&mut self) {
+        self.idle_deadline = None;
+    }
+
+    /// Race mode: the opponent's latest progress. It comes on a timer whether
+    /// they moved or not, so only a change counts as a move.
+    pub fn update_opponent_progress(&mut self, filled: u32, momentum: f32) {
+        if filled != self.opponent_filled {
+            self.opponent_moved();
+        }
+        self.opponent_filled = filled;
+        self.opponent_momentum = momentum;
+    }
+
+    /// Fog rooms: which quarter of their empty cells the opponent has filled
+    pub fn update_opponent_quarter(&mut self, bucket: u8) {
+        let quarter = bucket.min(3);
+        if self.opponent_quarter.is_some_and(|q| q != quarter) {
+            self.opponent_moved();
+        }
+        self.opponent_quarter = Some(quarter);
+    }
+
+    /// Seconds left on an idle warning, or None if there's nothing to show
+    pub fn idle_seconds_remaining(&self) -> Option<u64> {
+        self.idle_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs())
+            .filter(|&secs| secs > 0)
+    }
+
+    /// Seconds the disconnected opponent has left to come back, or None if
+    /// they're connected. Stays at 0 until the server's forfeit arrives.
+    pub fn opponent_grace_remaining(&self) -> Option<u64> {
+        self.opponent_gone_until
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs())
+    }
+
+    /// Remember a placement about to be sent; returns the request id to send
+    /// it with.
+    pub fn track_placement(&mut self, row: usize, col: usize, value: u8) -> u64 {
+        self.next_req_id += 1;
+        self.pending_moves.insert(self.next_req_id, (row, col, value));
+        self.next_req_id
+    }
+
+    /// Forget a placement the server has answered, returning what it was.
+    pub fn settle_placement(&mut self, req_id: Option<u64>) -> Option<(usize, usize, u8)> {
+        self.pending_moves.remove(&req_id?)
+    }
+}
+
+pub struct FinalBoards {
+    pub mine: Board,
+    pub opponent: Board,
+}
+
+/// End screen: the result summary, or one of the final boards or the
+/// solution read-only
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndView {
+    Summary,
+    MyBoard,
+    OpponentBoard,
+    Solution,
+}
+
+impl EndView {
+    pub fn next(&self) -> EndView {
+        match self {
+            EndView::Summary => EndView::MyBoard,
+            EndView::MyBoard => EndView::OpponentBoard,
+            EndView::OpponentBoard | EndView::Solution => EndView::Summary,
+        }
+    }
+}
+
+/// Back-to-back single-player run: one running timer with a split per puzzle
+pub struct Marathon {
+    pub length: usize,
+    pub ladder: bool,
+    /// Difficulty the run started at, restored when it ends
+    pub start_difficulty: Difficulty,
+    /// Difficulty and solve time of each finished puzzle
+    pub splits: Vec<(Difficulty, u64)>,
+}
+
+impl Marathon {
+    /// 1-based index of the puzzle currently being played
+    pub fn current(&self) -> usize {
+        self.splits.len() + 1
+    }
+
+    pub fn total_secs(&self) -> u64 {
+        self.splits.iter().map(|(_, secs)| secs).sum()
+    }
+}
+
+/// Colour marker a player can stick on a cell while working through chains.
+/// Purely cosmetic: never affects validation, hints or undo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellFlag {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl CellFlag {
+    /// Map a digit key (1-4) in flag mode to its colour.
+    pub fn from_digit(d: u8) -> Option<CellFlag> {
+        match d {
+            1 => Some(CellFlag::Red),
+            2 => Some(CellFlag::Green),
+            3 => Some(CellFlag::Blue),
+            4 => Some(CellFlag::Yellow),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -64,6 +320,14 @@ pub enum CellOwner {
     Given,
 }
 
+/// The game being watched as a spectator
+pub struct Watching {
+    pub code: String,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub players: Vec<String>,
+}
+
 pub struct GameResult {
     pub won: bool,
     pub your_score: u32,
@@ -74,13 +338,46 @@ pub struct GameResult {
 
 pub struct Game {
     pub board: Board,
+    // Only trustworthy while `solution_known`: single player, bot and ghost
+    // races, and a server match once `GameEnd` has brought the real one.
+    // During a server match the client never sees it, so it's all zeros.
     pub solution: SolutionBoard,
-    pub pencil_marks: [[Vec<u8>; 9]; 9],
+    pub solution_known: bool,
+    pub pencil_marks: [[PencilMarks; 9]; 9],
+    pub cell_flags: [[Option<CellFlag>; 9]; 9],
     pub difficulty: Difficulty,
+    // Shape of the single-player grid; multiplayer is always 9×9
+    pub grid: GridSpec,
+    // Seed the single-player puzzle was generated from, so it can be shared;
+    // None for puzzles that came from elsewhere (multiplayer, bots, ghosts)
+    pub seed: Option<u64>,
+    // Digits typed so far after '#' on the main menu, to play a given seed
+    pub seed_input: Option<String>,
+    // Givens count picked with 'c' on the main menu, used in place of the
+    // difficulty's range; 9×9 only. Custom puzzles don't count towards stats
+    pub custom_givens: Option<usize>,
+    // Digits typed so far after 'c' on the main menu
+    pub givens_input: Option<String>,
+    // Asking before throwing away a game in progress for a fresh puzzle
+    pub show_reroll_confirm: bool,
     pub selected_row: usize,
     pub selected_col: usize,
     pub state: GameState,
     pub pencil_mode: bool,
+    pub flag_mode: bool,
+    // Waiting for r/c/b after 'a' to pick the unit to fill candidates for
+    pub awaiting_fill_unit: bool,
+    // Corner the Shift+arrow selection rectangle was stretched from; the
+    // cursor is the opposite corner. None when nothing is selected
+    pub selection_anchor: Option<(usize, usize)>,
+    // Focus mode: dim everything but this digit; arrows jump between its candidates
+    pub locked_digit: Option<u8>,
+    // Waiting for a digit after 'l' to lock focus onto
+    pub awaiting_lock_digit: bool,
+    // Waiting for 1-3 after '!' to pick why the puzzle is being reported
+    pub awaiting_report_reason: bool,
+    // Short confirmation shown in the status bar (cleared on next key)
+    pub notice: Option<String>,
     pub mistakes: u32,
     pub move_history: Vec<Move>,
     pub timer_start: Option<Instant>,
@@ -88,26 +385,51 @@ pub struct Game {
     pub paused_elapsed: u64,
     pub conflicts: Vec<(usize, usize)>,
     pub show_conflicts: bool,
+    // When conflicts were last switched on; drives the red pulse
+    pub conflicts_shown_at: Option<Instant>,
+    // Rows, columns and boxes the last placement completed
+    pub unit_flash: Option<UnitFlash>,
     pub active_hint: Option<Hint>,
     pub hint_stage: HintStage,
     pub hints_used: u32,
+    // Glances at the whole solution, and until when the current one shows
+    pub peeks: u32,
+    pub peek_until: Option<Instant>,
+    // Explanations of this game's hints, oldest first, for the 'h' overlay
+    pub hint_log: Vec<String>,
+    pub show_hint_log: bool,
+    pub hint_log_scroll: usize,
     pub show_quit_confirm: bool,
+    // Recent network traffic, and whether it's drawn over the screen (F12 / --debug)
+    pub net_trace: NetTrace,
+    pub show_net_trace: bool,
+    pub marathon: Option<Marathon>,
     // Multiplayer
     pub multiplayer: Option<MultiplayerState>,
-    // Menu selection index for multiplayer menu
-    pub menu_selection: usize,
+    // Local race opponent; when set, the multiplayer screens run without a server
+    pub bot: Option<BotOpponent>,
+    // The ghost being raced, when the bot is replaying one of your solves
+    pub ghost_race: Option<Ghost>,
+    // Cell changes of the current 9×9 solve, stored as a ghost once it's won
+    pub ghost_moves: Vec<GhostMove>,
+    // Multiplayer menu: selection and the room code being typed
+    pub mp_menu: MultiplayerMenu,
+    // The game being watched; its board is shown in `board`
+    pub watching: Option<Watching>,
     // Auth
     pub auth_code: Option<String>,
     pub auth_uri: Option<String>,
     pub auth_status: Option<String>,
     // Lobby
     pub room_code: Option<String>,
-    // Room code input buffer for joining
-    pub room_input: String,
-    // Joining mode active
-    pub joining_room: bool,
+    // What the room or queue being waited on is for, and since when
+    pub lobby_room: Option<(GameMode, Difficulty)>,
+    pub waiting_since: Option<Instant>,
     // Error message to display (cleared on next action)
     pub error_message: Option<String>,
+    // Latest operator announcement and when it arrived, shown as a banner
+    // over every screen for `ANNOUNCEMENT_SHOWN`
+    pub announcement: Option<(String, Instant)>,
     // Auth polling state
     pub auth_polling: bool,
     pub auth_poll_interval: u64,
@@ -115,11 +437,41 @@ pub struct Game {
     pub pending_auth_start: bool,
     pub pending_connect: bool,
     pub pending_leaderboard: bool,
+    pub pending_me: bool,
+    pub pending_head_to_head: bool,
+    pub pending_rating_history: bool,
+    // A puzzle to generate before its game can start
+    pub pending_puzzle: Option<PuzzleRequest>,
     // What menu action to resume after connecting
     pub pending_menu_action: Option<usize>,
     // Leaderboard
     pub leaderboard_entries: Vec<LeaderboardEntry>,
     pub leaderboard_scroll: usize,
+    // Who the open connection is logged in as; the header falls back to
+    // this alone if the profile couldn't be fetched
+    pub connected_as: Option<String>,
+    // Own profile + rank, shown in the multiplayer menu header
+    pub my_profile: Option<MeResponse>,
+    // Own rating after each recent match, drawn as a sparkline on the profile screen
+    pub rating_history: Vec<i32>,
+    // Persisted preferences and the highlighted row on the settings screen
+    pub settings: Settings,
+    pub settings_selection: usize,
+    // Single-player tallies and badges; the app writes them out when dirty
+    pub stats: Stats,
+    pub stats_dirty: bool,
+    // Badges the last solve earned, announced on the win screen
+    pub new_badges: Vec<Achievement>,
+    // Solves waiting to be posted to the server; dropped if not connected
+    pub pending_solo_results: Vec<SoloResult>,
+    // Results synced from every machine, shown on the badges screen when logged in
+    pub solo_stats: Option<SoloStats>,
+    pub pending_solo_stats: bool,
+    // Recently played 9×9 puzzles, newest last; written out when dirty
+    pub history: Vec<Played>,
+    pub history_dirty: bool,
+    // Highlighted row on the history screen, counted from the newest
+    pub history_selection: usize,
 }
 
 impl Game {
@@ -127,12 +479,27 @@ impl Game {
         Self {
             board: [[Cell::Empty; 9]; 9],
             solution: [[0u8; 9]; 9],
-            pencil_marks: std::array::from_fn(|_| std::array::from_fn(|_| Vec::new())),
+            solution_known: false,
+            pencil_marks: Default::default(),
+            cell_flags: [[None; 9]; 9],
             difficulty: Difficulty::Easy,
+            grid: GridSpec::CLASSIC,
+            seed: None,
+            seed_input: None,
+            custom_givens: None,
+            givens_input: None,
+            show_reroll_confirm: false,
             selected_row: 4,
             selected_col: 4,
             state: GameState::Menu,
             pencil_mode: false,
+            flag_mode: false,
+            awaiting_fill_unit: false,
+            selection_anchor: None,
+            locked_digit: None,
+            awaiting_lock_digit: false,
+            awaiting_report_reason: false,
+            notice: None,
             mistakes: 0,
             move_history: Vec::new(),
             timer_start: None,
@@ -140,39 +507,154 @@ impl Game {
             paused_elapsed: 0,
             conflicts: Vec::new(),
             show_conflicts: false,
+            conflicts_shown_at: None,
+            unit_flash: None,
             active_hint: None,
             hint_stage: HintStage::ShowTechnique,
             hints_used: 0,
+            peeks: 0,
+            peek_until: None,
+            hint_log: Vec::new(),
+            show_hint_log: false,
+            hint_log_scroll: 0,
             show_quit_confirm: false,
+            net_trace: NetTrace::default(),
+            show_net_trace: false,
+            marathon: None,
             multiplayer: None,
-            menu_selection: 0,
+            bot: None,
+            ghost_race: None,
+            ghost_moves: Vec::new(),
+            mp_menu: MultiplayerMenu::default(),
+            watching: None,
             auth_code: None,
             auth_uri: None,
             auth_status: None,
             room_code: None,
-            room_input: String::new(),
-            joining_room: false,
+            lobby_room: None,
+            waiting_since: None,
             error_message: None,
+            announcement: None,
             auth_polling: false,
             auth_poll_interval: 5,
             pending_auth_start: false,
             pending_connect: false,
             pending_leaderboard: false,
+            pending_me: false,
+            pending_head_to_head: false,
+            pending_rating_history: false,
+            pending_puzzle: None,
             pending_menu_action: None,
             leaderboard_entries: Vec::new(),
             leaderboard_scroll: 0,
+            connected_as: None,
+            my_profile: None,
+            rating_history: Vec::new(),
+            settings: Settings::default(),
+            settings_selection: 0,
+            stats: Stats::default(),
+            stats_dirty: false,
+            new_badges: Vec::new(),
+            pending_solo_results: Vec::new(),
+            solo_stats: None,
+            pending_solo_stats: false,
+            history: Vec::new(),
+            history_dirty: false,
+            history_selection: 0,
         }
     }
 
     pub fn start_new_game(&mut self) {
-        let (board, solution) = generate_puzzle(self.difficulty);
+        self.start_seeded_game(random_seed());
+    }
+
+    /// Start the puzzle `seed` gives at the current grid and difficulty, once
+    /// it has been generated
+    pub fn start_seeded_game(&mut self, seed: u64) {
+        self.pending_puzzle = Some(PuzzleRequest {
+            grid: self.grid,
+            difficulty: self.difficulty,
+            custom_givens: self.custom_givens,
+            seed,
+            purpose: PuzzleFor::Solo,
+        });
+        self.notice = Some("Generating puzzle...".to_string());
+    }
+
+    /// Start the game `request` was for, now its puzzle is ready
+    pub fn puzzle_ready(&mut self, request: PuzzleRequest, board: Board, solution: SolutionBoard) {
+        self.grid = request.grid;
+        self.difficulty = request.difficulty;
+        self.custom_givens = request.custom_givens;
+        match request.purpose {
+            PuzzleFor::Solo => self.start_puzzle(board, solution, Some(request.seed)),
+            PuzzleFor::BotRace => self.race_bot(board, solution),
+        }
+    }
+
+    /// Generate the pending puzzle here and now, where tests would otherwise
+    /// go through the event loop
+    #[cfg(test)]
+    pub fn finish_pending_puzzle(&mut self) {
+        if let Some(request) = self.pending_puzzle.take() {
+            let (board, solution) = request.generate();
+            self.puzzle_ready(request, board, solution);
+        }
+    }
+
+    /// Play a puzzle from the history again, at the difficulty it had.
+    /// `index` counts from the newest, as the history screen lists them.
+    pub fn replay(&mut self, index: usize) {
+        let Some(played) = self.history.iter().rev().nth(index).cloned() else {
+            return;
+        };
+        let Some(board) = parse_givens(&played.givens) else {
+            return;
+        };
+        let Some(solution) = unique_solution(&board) else {
+            return;
+        };
+        self.difficulty = played.difficulty;
+        self.grid = GridSpec::CLASSIC;
+        self.custom_givens = None;
+        self.marathon = None;
+        self.start_puzzle(board, solution, played.shareable_seed());
+    }
+
+    /// Reset everything for a fresh single-player game on `board`
+    fn start_puzzle(&mut self, board: Board, solution: SolutionBoard, seed: Option<u64>) {
+        // Custom-givens puzzles stay out, as they do for stats and ghosts
+        if self.grid == GridSpec::CLASSIC && self.custom_givens.is_none() {
+            history::remember(
+                &mut self.history,
+                Played {
+                    difficulty: self.difficulty,
+                    givens: givens_string(&board, 9),
+                    seed,
+                    generator: GENERATOR_VERSION,
+                    outcome: Outcome::Unfinished,
+                },
+            );
+            self.history_dirty = true;
+        }
+        self.seed = seed;
+        self.show_reroll_confirm = false;
+        self.new_badges.clear();
         self.board = board;
         self.solution = solution;
-        self.pencil_marks = std::array::from_fn(|_| std::array::from_fn(|_| Vec::new()));
-        self.selected_row = 4;
-        self.selected_col = 4;
+        self.solution_known = true;
+        self.pencil_marks = Default::default();
+        self.cell_flags = [[None; 9]; 9];
+        self.place_cursor_near_center();
         self.state = GameState::Playing;
         self.pencil_mode = false;
+        self.flag_mode = false;
+        self.awaiting_fill_unit = false;
+        self.selection_anchor = None;
+        self.locked_digit = None;
+        self.awaiting_lock_digit = false;
+        self.awaiting_report_reason = false;
+        self.notice = None;
         self.mistakes = 0;
         self.move_history.clear();
         self.timer_start = Some(Instant::now());
@@ -180,27 +662,88 @@ impl Game {
         self.paused_elapsed = 0;
         self.conflicts.clear();
         self.show_conflicts = false;
+        self.conflicts_shown_at = None;
         self.active_hint = None;
         self.hints_used = 0;
+        self.peeks = 0;
+        self.peek_until = None;
+        self.hint_log.clear();
+        self.show_hint_log = false;
+        self.hint_log_scroll = 0;
         self.show_quit_confirm = false;
         self.multiplayer = None;
+        self.bot = None;
+        self.ghost_race = None;
+        self.ghost_moves.clear();
     }
 
+    /// A fresh random puzzle at the same grid and difficulty, after asking
+    /// if the current one has been played on. Not mid-marathon, where it
+    /// would skip a leg.
+    pub fn reroll(&mut self) {
+        if self.marathon.is_some() {
+            self.notice = Some("No re-rolls during a marathon".to_string());
+        } else if self.move_history.is_empty() || self.show_reroll_confirm {
+            self.start_new_game();
+        } else {
+            self.show_reroll_confirm = true;
+        }
+    }
+
+    /// Play puzzles with `count` givens, clamped to what a 9×9 grid allows,
+    /// instead of the difficulty's range until a difficulty is picked again
+    pub fn set_custom_givens(&mut self, count: usize) {
+        self.custom_givens = Some(count.clamp(MIN_GIVENS, MAX_GIVENS));
+        self.grid = GridSpec::CLASSIC;
+    }
+
+    pub fn start_marathon(&mut self) {
+        self.custom_givens = None;
+        self.marathon = Some(Marathon {
+            length: self.settings.marathon_length,
+            ladder: self.settings.marathon_ladder,
+            start_difficulty: self.difficulty,
+            splits: Vec::new(),
+        });
+        self.start_new_game();
+    }
+
+    /// Leave the marathon summary, putting back the difficulty the run started at
+    pub fn end_marathon(&mut self) {
+        if let Some(marathon) = self.marathon.take() {
+            self.difficulty = marathon.start_difficulty;
+        }
+        self.state = GameState::Menu;
+    }
+
+    /// Start a race or shared game on `board`. `solution` is None for a
+    /// server match, where it only arrives with `GameEnd`.
     pub fn start_multiplayer_game(
         &mut self,
         board: Board,
-        solution: SolutionBoard,
+        solution: Option<SolutionBoard>,
         mode: sudoku_core::protocol::GameMode,
         opponent_name: String,
         opponent_rating: i32,
     ) {
+        // Bots and ghosts play outside any room; a server match sets its
+        // code once this returns
+        self.room_code = None;
+        // Multiplayer is 9×9 only
+        self.grid = GridSpec::CLASSIC;
+        self.seed = None;
         self.board = board;
-        self.solution = solution;
-        self.pencil_marks = std::array::from_fn(|_| std::array::from_fn(|_| Vec::new()));
-        self.selected_row = 4;
-        self.selected_col = 4;
+        self.solution_known = solution.is_some();
+        self.solution = solution.unwrap_or([[0u8; 9]; 9]);
+        self.pencil_marks = Default::default();
+        self.cell_flags = [[None; 9]; 9];
+        self.place_cursor_near_center();
+        self.selection_anchor = None;
         self.state = GameState::MultiplayerPlaying;
+        self.awaiting_report_reason = false;
+        self.notice = None;
         self.pencil_mode = false;
+        self.flag_mode = false;
         self.mistakes = 0;
         self.move_history.clear();
         self.timer_start = Some(Instant::now());
@@ -208,9 +751,18 @@ impl Game {
         self.paused_elapsed = 0;
         self.conflicts.clear();
         self.show_conflicts = false;
+        self.conflicts_shown_at = None;
         self.active_hint = None;
         self.hints_used = 0;
+        self.peeks = 0;
+        self.peek_until = None;
+        self.hint_log.clear();
+        self.show_hint_log = false;
+        self.hint_log_scroll = 0;
         self.show_quit_confirm = false;
+        self.bot = None;
+        self.ghost_race = None;
+        self.ghost_moves.clear();
 
         let mut cell_owner = [[CellOwner::None; 9]; 9];
         for r in 0..9 {
@@ -227,27 +779,461 @@ impl Game {
             mode,
             opponent_filled: 0,
             opponent_momentum: 0.0,
+            opponent_quarter: None,
+            opponent_correct: None,
+            opponent_mistakes: None,
             opponent_cursor: None,
             cell_owner,
             result: None,
+            final_boards: None,
+            solution: None,
+            end_view: EndView::Summary,
+            idle_deadline: None,
+            opponent_gone_until: None,
+            spectators: 0,
+            rematch_difficulty: self.difficulty,
+            rematch_offer: None,
+            rematch_requested: false,
+            series: None,
+            pending_moves: HashMap::new(),
+            next_req_id: 0,
+            reconnecting_until: None,
+            outbox: Outbox::default(),
+        });
+    }
+
+    /// Race the local bot on a fresh 9×9 puzzle at the current difficulty,
+    /// reusing the multiplayer race screens with no server involved.
+    pub fn start_bot_race(&mut self) {
+        self.pending_puzzle = Some(PuzzleRequest {
+            grid: GridSpec::CLASSIC,
+            difficulty: self.difficulty,
+            custom_givens: None,
+            seed: random_seed(),
+            purpose: PuzzleFor::BotRace,
+        });
+        self.notice = Some("Generating puzzle...".to_string());
+    }
+
+    fn race_bot(&mut self, board: Board, solution: SolutionBoard) {
+        let level = self.settings.bot_level;
+        self.start_multiplayer_game(
+            board,
+            Some(solution),
+            sudoku_core::protocol::GameMode::Race,
+            format!("{} bot", level.label()),
+            level.rating(),
+        );
+        self.bot = Some(BotOpponent::new(level, board, solution, Instant::now()));
+        if let Some(ref mut mp) = self.multiplayer {
+            mp.opponent_correct = Some(0);
+        }
+    }
+
+    /// Race your ghost on the puzzle you most recently solved (or solved
+    /// fastest) at the current difficulty.
+    pub fn start_ghost_race(&mut self) {
+        match ghost::latest(self.difficulty) {
+            Some(ghost) => self.race_ghost(ghost),
+            None => {
+                self.notice = Some(format!(
+                    "No {} ghost yet: solve a 9×9 puzzle first",
+                    self.difficulty.label()
+                ));
+            }
+        }
+    }
+
+    /// Race the same puzzle's ghost again, which is your own run if you just
+    /// beat it.
+    pub fn rematch_ghost(&mut self) {
+        if let Some(raced) = self.ghost_race.take() {
+            let ghost = ghost::find(raced.difficulty, &raced.givens).unwrap_or(raced);
+            self.race_ghost(ghost);
+        }
+    }
+
+    fn race_ghost(&mut self, ghost: Ghost) {
+        let Some(board) = parse_givens(&ghost.givens) else {
+            return;
+        };
+        let Some(solution) = unique_solution(&board) else {
+            return;
+        };
+        let rating = self.my_profile.as_ref().map_or(1200, |me| me.profile.rating);
+        self.difficulty = ghost.difficulty;
+        self.custom_givens = None;
+        self.start_multiplayer_game(board, Some(solution), GameMode::Race, "Your ghost".to_string(), rating);
+        self.bot = Some(BotOpponent::replay(&ghost, board, solution, Instant::now()));
+        if let Some(ref mut mp) = self.multiplayer {
+            mp.opponent_correct = Some(0);
+        }
+        self.ghost_race = Some(ghost);
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.paused_elapsed * 1000 + self.timer_start.map_or(0, |t| t.elapsed().as_millis() as u64)
+    }
+
+    /// Note a cell change for this solve's ghost. Only 9×9 solo games and
+    /// ghost races are recorded.
+    fn record_ghost_move(&mut self, row: usize, col: usize) {
+        let recording = self.grid == GridSpec::CLASSIC
+            && (self.state == GameState::Playing || self.ghost_race.is_some());
+        if recording {
+            self.ghost_moves.push(GhostMove {
+                at_ms: self.elapsed_ms(),
+                row,
+                col,
+                value: self.board[row][col].value().unwrap_or(0),
+            });
+        }
+    }
+
+    /// Count a solved 9×9 puzzle towards the stats and note any badges it
+    /// earns for the win screen.
+    fn record_solve(&mut self) {
+        if self.grid != GridSpec::CLASSIC || self.custom_givens.is_some() {
+            return;
+        }
+        let solve = Solve {
+            difficulty: self.difficulty,
+            secs: self.elapsed_secs,
+            mistakes: self.mistakes,
+            hints: self.hints_used + self.peeks * PEEK_WEIGHT,
+        };
+        self.new_badges = self.stats.record(&solve, achievements::today());
+        self.stats_dirty = true;
+        self.pending_solo_results.push(SoloResult {
+            difficulty: solve.difficulty,
+            secs: solve.secs,
+            mistakes: solve.mistakes,
+            hints: solve.hints,
+            flawless: solve.mistakes == 0 && solve.hints == 0,
+        });
+    }
+
+    /// Store the solve just finished as a ghost, if it beats any earlier one
+    /// of the same puzzle.
+    fn save_ghost(&mut self) {
+        if self.grid != GridSpec::CLASSIC || self.custom_givens.is_some() {
+            return;
+        }
+        ghost::record(Ghost {
+            difficulty: self.difficulty,
+            givens: givens_string(&self.board, 9),
+            ms: self.elapsed_ms(),
+            moves: std::mem::take(&mut self.ghost_moves),
         });
     }
 
+    /// Whether the screen changes on its own (the clock, a bot's progress,
+    /// the lobby spinner, the conflict pulse, a watched game's clock, an
+    /// announcement running out), so it has to be redrawn on every tick
+    /// rather than only when something happens.
+    pub fn is_animating(&self) -> bool {
+        self.current_announcement().is_some()
+            || matches!(
+                self.state,
+                GameState::Playing
+                    | GameState::MultiplayerPlaying
+                    | GameState::Lobby
+                    | GameState::Spectating
+            )
+    }
+
+    /// Our connection dropped. In a server match this starts the window for
+    /// getting back into it and returns true; anywhere else there's nothing
+    /// to get back to.
+    pub fn start_reconnecting(&mut self) -> bool {
+        if self.state != GameState::MultiplayerPlaying {
+            return false;
+        }
+        let Some(mp) = self.multiplayer.as_mut() else {
+            return false;
+        };
+        mp.reconnecting_until
+            .get_or_insert_with(|| Instant::now() + Duration::from_secs(RECONNECT_GRACE_SECS));
+        true
+    }
+
+    /// While we're trying to get back into the match we dropped out of: when
+    /// we give up. None once that's passed, even if an attempt is still hanging.
+    pub fn reconnect_deadline(&self) -> Option<Instant> {
+        if self.state != GameState::MultiplayerPlaying {
+            return None;
+        }
+        self.multiplayer
+            .as_ref()?
+            .reconnecting_until
+            .filter(|&until| until > Instant::now())
+    }
+
+    /// We're connected again: send whatever was held while we weren't.
+    /// Returns whether we're still in the match that dropped, which the
+    /// server has put us back into.
+    pub fn resume_after_reconnect(&mut self, client: &NetworkClient) -> bool {
+        let Some(mp) = self.multiplayer.as_mut() else {
+            return false;
+        };
+        if mp.reconnecting_until.take().is_none() {
+            return false;
+        }
+        mp.outbox.flush(client);
+        self.state == GameState::MultiplayerPlaying
+    }
+
+    /// Let the bot make any moves that are due and mirror its progress into
+    /// the race panel. The bot finishing first ends the race.
+    pub fn advance_bot(&mut self) {
+        if self.state != GameState::MultiplayerPlaying {
+            return;
+        }
+        let (Some(bot), Some(mp)) = (self.bot.as_mut(), self.multiplayer.as_mut()) else {
+            return;
+        };
+        bot.advance(Instant::now());
+        mp.opponent_filled = bot.filled();
+        mp.opponent_correct = Some(bot.correct());
+        mp.opponent_momentum = bot.momentum();
+        if bot.is_finished() {
+            self.finish_bot_race(false);
+        }
+    }
+
+    /// Stop the clock and fill in the end screen for a race against the bot.
+    /// Bot races are unrated.
+    fn finish_bot_race(&mut self, won: bool) {
+        if won && self.ghost_race.is_some() {
+            self.save_ghost();
+        }
+        let Some(ref bot) = self.bot else {
+            return;
+        };
+        if let Some(start) = self.timer_start {
+            self.elapsed_secs = self.paused_elapsed + start.elapsed().as_secs();
+        }
+        let your_score = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| self.board[r][c] == Cell::UserInput(self.solution[r][c]))
+            .count() as u32;
+        let rating = self.my_profile.as_ref().map_or(0, |me| me.profile.rating);
+        if let Some(ref mut mp) = self.multiplayer {
+            mp.result = Some(GameResult {
+                won,
+                your_score,
+                opponent_score: bot.correct(),
+                elo_change: 0,
+                new_rating: rating,
+            });
+            mp.final_boards = Some(FinalBoards {
+                mine: self.board,
+                opponent: bot.board,
+            });
+        }
+        self.state = GameState::MultiplayerEnd;
+    }
+
+    /// Rating a forfeit would cost in the current match, worked out the same
+    /// way the server scores a loss. None for bot races, which aren't rated,
+    /// or while our own rating is still unknown.
+    pub fn forfeit_cost(&self) -> Option<i32> {
+        if self.bot.is_some() {
+            return None;
+        }
+        let mp = self.multiplayer.as_ref()?;
+        let mine = self.my_profile.as_ref()?.profile.rating;
+        Some(elo_change(mine, mp.opponent_rating, false))
+    }
+
+    /// Difficulty Quick Match will ask for: suited to our rating unless
+    /// changed by hand in the menu, and the main menu's before we know it
+    pub fn quick_match_difficulty(&self) -> Difficulty {
+        let rating = self.my_profile.as_ref().map(|me| me.profile.rating);
+        self.mp_menu.quick_difficulty(rating, self.difficulty)
+    }
+
+    /// Watch someone else's game, starting from its givens and clock.
+    pub fn start_watching(&mut self, watching: Watching, givens: Board, elapsed_secs: u64) {
+        self.grid = GridSpec::CLASSIC;
+        self.seed = None;
+        self.board = givens;
+        self.solution_known = false;
+        self.pencil_marks = Default::default();
+        self.cell_flags = [[None; 9]; 9];
+        self.conflicts.clear();
+        self.active_hint = None;
+        self.multiplayer = None;
+        self.paused_elapsed = elapsed_secs;
+        self.timer_start = Some(Instant::now());
+        self.watching = Some(watching);
+        self.state = GameState::Spectating;
+    }
+
+    /// The moves so far in a watched shared-mode game
+    pub fn apply_watched_board(&mut self, board: &[Vec<u8>]) {
+        for (row, values) in self.board.iter_mut().zip(board) {
+            for (cell, &v) in row.iter_mut().zip(values) {
+                if !cell.is_given() {
+                    *cell = if v == 0 { Cell::Empty } else { Cell::UserInput(v) };
+                }
+            }
+        }
+    }
+
+    /// Shared mode: replace our copy of the board with the server's after a
+    /// checksum mismatch. `owners` uses 1 for us and 2 for the opponent.
+    pub fn apply_board_sync(&mut self, board: &[Vec<u8>], owners: &[Vec<u8>]) {
+        let Some(ref mut mp) = self.multiplayer else {
+            return;
+        };
+        for r in 0..9 {
+            for c in 0..9 {
+                if self.board[r][c].is_given() {
+                    continue;
+                }
+                self.board[r][c] = match board[r][c] {
+                    0 => Cell::Empty,
+                    v => Cell::UserInput(v),
+                };
+                mp.cell_owner[r][c] = match owners[r][c] {
+                    1 => CellOwner::Mine,
+                    2 => CellOwner::Opponent,
+                    _ => CellOwner::None,
+                };
+                if self.board[r][c] != Cell::Empty {
+                    self.pencil_marks[r][c].clear();
+                }
+            }
+        }
+        self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
+    }
+
     pub fn move_cursor(&mut self, dr: i32, dc: i32) {
-        let new_row = (self.selected_row as i32 + dr).rem_euclid(9) as usize;
-        let new_col = (self.selected_col as i32 + dc).rem_euclid(9) as usize;
+        let size = self.grid.size as i32;
+        let new_row = (self.selected_row as i32 + dr).rem_euclid(size) as usize;
+        let new_col = (self.selected_col as i32 + dc).rem_euclid(size) as usize;
         self.selected_row = new_row;
         self.selected_col = new_col;
     }
 
+    /// Shift+arrow: stretch the selection rectangle, starting one at the
+    /// cursor if there isn't one. Stops at the edge rather than wrapping,
+    /// which would flip the rectangle inside out.
+    pub fn extend_selection(&mut self, dr: i32, dc: i32) {
+        let anchor = (self.selected_row, self.selected_col);
+        self.selection_anchor.get_or_insert(anchor);
+        let last = self.grid.size as i32 - 1;
+        self.selected_row = (self.selected_row as i32 + dr).clamp(0, last) as usize;
+        self.selected_col = (self.selected_col as i32 + dc).clamp(0, last) as usize;
+    }
+
+    /// Top-left and bottom-right corners of the selection, if it covers more
+    /// than the cursor's cell
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (ar, ac) = self.selection_anchor?;
+        let (r, c) = (self.selected_row, self.selected_col);
+        if (ar, ac) == (r, c) {
+            return None;
+        }
+        Some(((ar.min(r), ac.min(c)), (ar.max(r), ac.max(c))))
+    }
+
+    pub fn in_selection(&self, row: usize, col: usize) -> bool {
+        self.selection()
+            .is_some_and(|((r0, c0), (r1, c1))| (r0..=r1).contains(&row) && (c0..=c1).contains(&col))
+    }
+
+    /// Start the cursor on the empty cell closest to the centre, so the
+    /// player can type straight away. Ties go to the first in reading order;
+    /// a board with no empty cells leaves it on the centre.
+    fn place_cursor_near_center(&mut self) {
+        let n = self.grid.size;
+        let mid = n / 2;
+        let (row, col) = (0..n * n)
+            .map(|idx| (idx / n, idx % n))
+            .filter(|&(r, c)| self.board[r][c] == Cell::Empty)
+            .min_by_key(|&(r, c)| r.abs_diff(mid).pow(2) + c.abs_diff(mid).pow(2))
+            .unwrap_or((mid, mid));
+        self.selected_row = row;
+        self.selected_col = col;
+    }
+
+    /// After a placement, move on as the auto-advance setting says. Both
+    /// modes run in reading order and wrap from the last cell to the first.
+    fn advance_cursor(&mut self) {
+        let n = self.grid.size;
+        let start = self.selected_row * n + self.selected_col;
+        let next = match self.settings.auto_advance {
+            AutoAdvance::Off => return,
+            AutoAdvance::Right => Some((start + 1) % (n * n)),
+            AutoAdvance::NextEmpty => (1..n * n)
+                .map(|i| (start + i) % (n * n))
+                .find(|&idx| self.board[idx / n][idx % n] == Cell::Empty),
+        };
+        if let Some(idx) = next {
+            self.selected_row = idx / n;
+            self.selected_col = idx % n;
+        }
+    }
+
+    /// Lock focus onto `digit`, or unlock if it is already the locked one.
+    pub fn toggle_locked_digit(&mut self, digit: u8) {
+        if digit as usize > self.grid.size {
+            return;
+        }
+        self.locked_digit = if self.locked_digit == Some(digit) {
+            None
+        } else {
+            Some(digit)
+        };
+    }
+
+    /// Whether the cell shows `digit`, either placed or as a pencil mark
+    pub fn cell_shows_digit(&self, row: usize, col: usize, digit: u8) -> bool {
+        self.board[row][col].value() == Some(digit) || self.pencil_marks[row][col].contains(digit)
+    }
+
+    /// Focus mode navigation: move to the next empty cell where the locked
+    /// digit is still a candidate. ←/→ scan row by row, ↑/↓ column by column,
+    /// wrapping around the grid. Stays put if there is nowhere left to go.
+    pub fn move_to_candidate(&mut self, dr: i32, dc: i32) {
+        let Some(digit) = self.locked_digit else {
+            return;
+        };
+        let n = self.grid.size;
+        let total = (n * n) as i32;
+        let vertical = dr != 0;
+        let step = dr + dc;
+        let start = if vertical {
+            self.selected_col * n + self.selected_row
+        } else {
+            self.selected_row * n + self.selected_col
+        } as i32;
+
+        for i in 1..total {
+            let idx = (start + step * i).rem_euclid(total) as usize;
+            let (r, c) = if vertical { (idx % n, idx / n) } else { (idx / n, idx % n) };
+            if get_candidates_in(&self.grid, &self.board, r, c).contains(&digit) {
+                self.selected_row = r;
+                self.selected_col = c;
+                return;
+            }
+        }
+    }
+
     pub fn place_number(&mut self, num: u8) {
         if self.state != GameState::Playing && self.state != GameState::MultiplayerPlaying {
             return;
         }
+        if self.state == GameState::Playing && self.selection().is_some() {
+            self.toggle_selection_pencil_mark(num);
+            return;
+        }
         let r = self.selected_row;
         let c = self.selected_col;
 
-        if self.board[r][c].is_given() {
+        if self.board[r][c].is_given() || num as usize > self.grid.size {
             return;
         }
 
@@ -264,24 +1250,39 @@ impl Game {
             self.toggle_pencil_mark(num);
             return;
         }
+        if self.settings.strict_placement && self.clashes(r, c, num) {
+            self.error_message = Some(format!("{} is already in this row, column or box", num));
+            return;
+        }
 
         let old = self.board[r][c];
         let new = Cell::UserInput(num);
         self.board[r][c] = new;
         self.pencil_marks[r][c].clear();
         self.clear_related_pencil_marks(r, c, num);
+        let mistake = self.solution_known && self.solution[r][c] != num;
+        let units: Vec<Unit> = [Unit::Row, Unit::Column, Unit::Box]
+            .into_iter()
+            .filter(|&unit| self.unit_is_solved(unit, r, c))
+            .collect();
+        if !units.is_empty() {
+            self.unit_flash = Some(UnitFlash { units, row: r, col: c, at: Instant::now() });
+        }
         self.move_history.push(Move::PlaceNumber {
             row: r,
             col: c,
             old,
             new,
+            mistake,
         });
+        self.record_ghost_move(r, c);
 
-        if self.solution[r][c] != num {
+        if mistake {
             self.mistakes += 1;
         }
 
-        self.conflicts = get_all_conflicts(&self.board);
+        self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
+        self.advance_cursor();
 
         // Mark cell ownership in multiplayer
         if let Some(ref mut mp) = self.multiplayer {
@@ -290,27 +1291,82 @@ impl Game {
             }
         }
 
-        if self.state == GameState::Playing && is_board_complete(&self.board) {
-            self.state = GameState::Won;
-            if let Some(start) = self.timer_start {
-                self.elapsed_secs = self.paused_elapsed + start.elapsed().as_secs();
+        if self.state == GameState::Playing && is_board_complete_in(&self.grid, &self.board) {
+            self.finish_puzzle();
+        } else if self.bot.is_some() {
+            self.check_bot_race_board();
+        }
+    }
+
+    /// Place the selected cell's only pencil mark, whatever the pencil mode.
+    /// Returns the digit placed, or None if the cell has zero or several marks.
+    pub fn promote_pencil_mark(&mut self) -> Option<u8> {
+        let (r, c) = (self.selected_row, self.selected_col);
+        if self.board[r][c] != Cell::Empty {
+            return None;
+        }
+        let digit = self.pencil_marks[r][c].single()?;
+        let pencil_mode = std::mem::replace(&mut self.pencil_mode, false);
+        self.place_number(digit);
+        self.pencil_mode = pencil_mode;
+        Some(digit)
+    }
+
+    /// Bot race: a full board either wins or, like the server would, says how
+    /// many cells are still wrong.
+    fn check_bot_race_board(&mut self) {
+        if self.board.iter().flatten().any(|cell| cell.value().is_none()) {
+            return;
+        }
+        let wrong = (0..9)
+            .flat_map(|r| (0..9).map(move |c| (r, c)))
+            .filter(|&(r, c)| self.board[r][c].value() != Some(self.solution[r][c]))
+            .count();
+        if wrong == 0 {
+            self.finish_bot_race(true);
+        } else {
+            self.error_message = Some(format!("{} cells are incorrect — fix them!", wrong));
+        }
+    }
+
+    /// Stop the clock on a solved single-player board. In a marathon this
+    /// records the split and moves straight on to the next puzzle.
+    fn finish_puzzle(&mut self) {
+        self.save_ghost();
+        self.state = GameState::Won;
+        if let Some(start) = self.timer_start {
+            self.elapsed_secs = self.paused_elapsed + start.elapsed().as_secs();
+        }
+        self.record_solve();
+        if self.grid == GridSpec::CLASSIC {
+            let givens = givens_string(&self.board, 9);
+            history::mark_solved(&mut self.history, &givens, self.elapsed_secs);
+            self.history_dirty = true;
+        }
+
+        let Some(ref mut marathon) = self.marathon else {
+            return;
+        };
+        marathon.splits.push((self.difficulty, self.elapsed_secs));
+        if marathon.splits.len() < marathon.length {
+            if marathon.ladder && self.difficulty != Difficulty::Expert {
+                self.difficulty = self.difficulty.next();
             }
+            self.start_new_game();
+        } else {
+            self.state = GameState::MarathonSummary;
         }
     }
 
     fn clear_related_pencil_marks(&mut self, row: usize, col: usize, val: u8) {
-        for c in 0..9 {
-            self.pencil_marks[row][c].retain(|&v| v != val);
+        for c in 0..self.grid.size {
+            self.pencil_marks[row][c].remove(val);
         }
-        for r in 0..9 {
-            self.pencil_marks[r][col].retain(|&v| v != val);
+        for r in 0..self.grid.size {
+            self.pencil_marks[r][col].remove(val);
         }
-        let box_r = (row / 3) * 3;
-        let box_c = (col / 3) * 3;
-        for r in box_r..box_r + 3 {
-            for c in box_c..box_c + 3 {
-                self.pencil_marks[r][c].retain(|&v| v != val);
-            }
+        for (r, c) in self.grid.box_cells(row, col) {
+            self.pencil_marks[r][c].remove(val);
         }
     }
 
@@ -326,13 +1382,38 @@ impl Game {
             row: r,
             col: c,
             value: num,
+            origin: self.pencil_marks[r][c].origin(num),
         });
+        self.pencil_marks[r][c].toggle(num);
+    }
 
-        if self.pencil_marks[r][c].contains(&num) {
-            self.pencil_marks[r][c].retain(|&v| v != num);
-        } else {
-            self.pencil_marks[r][c].push(num);
-            self.pencil_marks[r][c].sort();
+    /// Toggle `num` as a pencil mark across the selection's empty cells: off
+    /// if they all have it, otherwise on wherever it's missing. A digit can't
+    /// go twice in one row or column, so a selection never places it. One
+    /// move, so one undo puts it back.
+    fn toggle_selection_pencil_mark(&mut self, num: u8) {
+        let Some(((r0, c0), (r1, c1))) = self.selection() else {
+            return;
+        };
+        if num as usize > self.grid.size {
+            return;
+        }
+        let empty: Vec<(usize, usize)> = (r0..=r1)
+            .flat_map(|r| (c0..=c1).map(move |c| (r, c)))
+            .filter(|&(r, c)| self.board[r][c] == Cell::Empty)
+            .collect();
+        let all_marked = empty.iter().all(|&(r, c)| self.pencil_marks[r][c].contains(num));
+
+        let mut previous = Vec::new();
+        for (r, c) in empty {
+            let marks = &mut self.pencil_marks[r][c];
+            if all_marked || !marks.contains(num) {
+                previous.push((r, c, marks.clone()));
+                marks.toggle(num);
+            }
+        }
+        if !previous.is_empty() {
+            self.move_history.push(Move::FillPencilMarks { previous });
         }
     }
 
@@ -356,17 +1437,34 @@ impl Game {
             }
         }
 
+        self.cell_flags[r][c] = None;
+
         if let Cell::UserInput(_) = self.board[r][c] {
             let old = self.board[r][c];
             self.board[r][c] = Cell::Empty;
             self.move_history
                 .push(Move::Erase { row: r, col: c, old });
-            self.conflicts = get_all_conflicts(&self.board);
+            self.record_ghost_move(r, c);
+            self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
         } else if !self.pencil_marks[r][c].is_empty() {
             self.pencil_marks[r][c].clear();
         }
     }
 
+    /// Set the flag on the selected cell, or clear it if it already has that colour.
+    pub fn toggle_flag(&mut self, flag: CellFlag) {
+        if self.state != GameState::Playing {
+            return;
+        }
+        let r = self.selected_row;
+        let c = self.selected_col;
+        if self.cell_flags[r][c] == Some(flag) {
+            self.cell_flags[r][c] = None;
+        } else {
+            self.cell_flags[r][c] = Some(flag);
+        }
+    }
+
     pub fn undo(&mut self) {
         if self.state != GameState::Playing {
             return;
@@ -374,28 +1472,63 @@ impl Game {
 
         if let Some(mv) = self.move_history.pop() {
             match mv {
-                Move::PlaceNumber { row, col, old, .. } => {
+                Move::PlaceNumber {
+                    row,
+                    col,
+                    old,
+                    mistake,
+                    ..
+                } => {
                     self.board[row][col] = old;
+                    self.record_ghost_move(row, col);
+                    if mistake && !self.settings.permanent_mistakes {
+                        self.mistakes = self.mistakes.saturating_sub(1);
+                    }
                 }
                 Move::Erase { row, col, old } => {
                     self.board[row][col] = old;
+                    self.record_ghost_move(row, col);
                 }
-                Move::TogglePencilMark { row, col, value } => {
-                    if self.pencil_marks[row][col].contains(&value) {
-                        self.pencil_marks[row][col].retain(|&v| v != value);
-                    } else {
-                        self.pencil_marks[row][col].push(value);
-                        self.pencil_marks[row][col].sort();
+                Move::TogglePencilMark {
+                    row,
+                    col,
+                    value,
+                    origin,
+                } => match origin {
+                    Some(origin) => self.pencil_marks[row][col].insert(value, origin),
+                    None => self.pencil_marks[row][col].remove(value),
+                },
+                Move::FillPencilMarks { previous } => {
+                    for (row, col, marks) in previous {
+                        self.pencil_marks[row][col] = marks;
                     }
                 }
             }
-            self.conflicts = get_all_conflicts(&self.board);
+            self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
         }
     }
 
     pub fn validate(&mut self) {
         self.show_conflicts = true;
-        self.conflicts = get_all_conflicts(&self.board);
+        self.conflicts_shown_at = Some(Instant::now());
+        self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
+    }
+
+    /// Show the whole solution faintly for a moment. Holding the key keeps it
+    /// up as one peek; each fresh one counts against the solve. Single player
+    /// only, and only while the solution is one we can trust.
+    pub fn peek(&mut self) {
+        if self.state != GameState::Playing || !self.solution_known {
+            return;
+        }
+        if !self.is_peeking() {
+            self.peeks += 1;
+        }
+        self.peek_until = Some(Instant::now() + PEEK_SHOWN);
+    }
+
+    pub fn is_peeking(&self) -> bool {
+        self.state == GameState::Playing && self.peek_until.is_some_and(|t| Instant::now() < t)
     }
 
     pub fn request_hint(&mut self) {
@@ -403,7 +1536,14 @@ impl Game {
             return;
         }
 
-        if self.active_hint.is_some() {
+        let stuck = self
+            .active_hint
+            .as_ref()
+            .is_some_and(|h| h.technique == HintTechnique::Stuck);
+        if stuck {
+            // Nothing to reveal; a second press just closes the message
+            self.dismiss_hint();
+        } else if self.active_hint.is_some() {
             match self.hint_stage {
                 HintStage::ShowTechnique => {
                     self.hint_stage = HintStage::RevealValue;
@@ -415,16 +1555,13 @@ impl Game {
                         let v = hint.value;
                         if self.board[r][c] == Cell::Empty {
                             self.board[r][c] = Cell::UserInput(v);
+                            self.record_ghost_move(r, c);
                             self.pencil_marks[r][c].clear();
                             self.clear_related_pencil_marks(r, c, v);
-                            self.conflicts = get_all_conflicts(&self.board);
-
-                            if is_board_complete(&self.board) {
-                                self.state = GameState::Won;
-                                if let Some(start) = self.timer_start {
-                                    self.elapsed_secs =
-                                        self.paused_elapsed + start.elapsed().as_secs();
-                                }
+                            self.conflicts = get_all_conflicts_in(&self.grid, &self.board);
+
+                            if is_board_complete_in(&self.grid, &self.board) {
+                                self.finish_puzzle();
                             }
                         }
                     }
@@ -433,10 +1570,13 @@ impl Game {
                 }
             }
         } else {
-            if let Some(hint) = find_hint(&self.board, &self.solution) {
+            if let Some(hint) = find_hint(&self.grid, &self.board, &self.solution, &self.settings.hints) {
+                if hint.technique != HintTechnique::Stuck {
+                    self.hints_used += 1;
+                    self.hint_log.push(hint.explanation.clone());
+                }
                 self.active_hint = Some(hint);
                 self.hint_stage = HintStage::ShowTechnique;
-                self.hints_used += 1;
             }
         }
     }
@@ -446,6 +1586,23 @@ impl Game {
         self.hint_stage = HintStage::ShowTechnique;
     }
 
+    /// Pause and show the hints used so far. Closing it leaves the game
+    /// paused, so reading back doesn't cost time.
+    pub fn open_hint_log(&mut self) {
+        if self.state == GameState::Playing {
+            self.toggle_pause();
+        }
+        if self.state == GameState::Paused {
+            self.show_hint_log = true;
+            self.hint_log_scroll = 0;
+        }
+    }
+
+    pub fn scroll_hint_log(&mut self, delta: isize) {
+        let last = self.hint_log.len().saturating_sub(1);
+        self.hint_log_scroll = self.hint_log_scroll.saturating_add_signed(delta).min(last);
+    }
+
     pub fn toggle_pause(&mut self) {
         match self.state {
             GameState::Playing => {
@@ -463,18 +1620,29 @@ impl Game {
         }
     }
 
+    /// Multiplayer: take the server's match clock over our own count, which
+    /// drifts or starts late (e.g. after a resync).
+    pub fn sync_clock(&mut self, elapsed_secs: u64) {
+        if matches!(self.state, GameState::MultiplayerPlaying | GameState::Spectating) {
+            self.paused_elapsed = elapsed_secs;
+            self.timer_start = Some(Instant::now());
+        }
+    }
+
     pub fn get_elapsed_secs(&self) -> u64 {
         match self.state {
             GameState::Won | GameState::MultiplayerEnd => self.elapsed_secs,
             GameState::Paused => self.paused_elapsed,
-            GameState::Playing | GameState::MultiplayerPlaying => {
+            GameState::Playing | GameState::MultiplayerPlaying | GameState::Spectating => {
                 self.paused_elapsed
                     + self
                         .timer_start
                         .map(|s| s.elapsed().as_secs())
                         .unwrap_or(0)
             }
-            GameState::Menu | GameState::MultiplayerMenu | GameState::AuthScreen | GameState::Lobby | GameState::Leaderboard => 0,
+            GameState::Menu | GameState::MultiplayerMenu | GameState::AuthScreen | GameState::Lobby | GameState::Leaderboard
+            | GameState::Settings | GameState::MarathonSummary | GameState::Badges
+            | GameState::History | GameState::Profile => 0,
         }
     }
 
@@ -485,24 +1653,200 @@ impl Game {
         format!("{:02}:{:02}", mins, s)
     }
 
+    /// The peer cells (row, col) whose value clashes with the one at (r, c).
+    pub fn conflict_reason(&self, r: usize, c: usize) -> Vec<(usize, usize)> {
+        conflicting_cells_in(&self.grid, &self.board, r, c)
+    }
+
+    /// With instant feedback on, whether the entry at (r, c) matches the
+    /// solution. None for givens, empty cells, or when feedback is off or
+    /// this is a race.
+    pub fn entry_correct(&self, r: usize, c: usize) -> Option<bool> {
+        if !self.settings.instant_feedback || self.is_multiplayer() || !self.solution_known {
+            return None;
+        }
+        match self.board[r][c] {
+            Cell::UserInput(v) => Some(v == self.solution[r][c]),
+            _ => None,
+        }
+    }
+
+    /// Undo an optimistic placement the server rejected. The cell is only
+    /// cleared if it still shows the rejected value and no later placement
+    /// there is waiting on an answer, so a re-entry made in the meantime
+    /// survives. Without a known `req_id`, (row, col) is cleared as before.
+    pub fn roll_back_placement(&mut self, row: usize, col: usize, req_id: Option<u64>) {
+        let Some(mp) = &mut self.multiplayer else {
+            return;
+        };
+        let Some((row, col, value)) = mp.settle_placement(req_id) else {
+            self.board[row][col] = Cell::Empty;
+            return;
+        };
+        let superseded = mp.pending_moves.values().any(|&(r, c, _)| (r, c) == (row, col));
+        if !superseded && self.board[row][col] == Cell::UserInput(value) {
+            self.board[row][col] = Cell::Empty;
+        }
+    }
+
+    /// Early filter for a multiplayer placement before it goes to the server:
+    /// `value` repeating a digit already in its row, column or box can't be
+    /// right. Shared mode would lock the cell to that wrong answer, so it's
+    /// refused here, as it is anywhere with strict placement on; otherwise race
+    /// mode lets wrong values stand, so it goes ahead with a warning. The
+    /// server still has the final word. Returns whether to place and send it.
+    pub fn precheck_placement(&mut self, row: usize, col: usize, value: u8) -> bool {
+        if self.board[row][col].is_given() || !self.clashes(row, col, value) {
+            return true;
+        }
+        let shared = self.multiplayer.as_ref().is_some_and(|mp| mp.mode == GameMode::Shared);
+        if shared || self.settings.strict_placement {
+            self.error_message = Some(format!("{} is already in this row, column or box", value));
+            false
+        } else {
+            self.notice = Some(format!("{} clashes with this row, column or box", value));
+            true
+        }
+    }
+
+    /// Whether `value` at (row, col) would repeat a digit in its row, column or box
+    fn clashes(&self, row: usize, col: usize, value: u8) -> bool {
+        let mut board = self.board;
+        board[row][col] = Cell::UserInput(value);
+        has_conflict_in(&self.grid, &board, row, col)
+    }
+
     pub fn selected_value(&self) -> Option<u8> {
         self.board[self.selected_row][self.selected_col].value()
     }
 
     pub fn auto_pencil_marks(&mut self) {
-        for r in 0..9 {
-            for c in 0..9 {
+        for r in 0..self.grid.size {
+            for c in 0..self.grid.size {
                 if self.board[r][c] == Cell::Empty {
-                    self.pencil_marks[r][c] = get_candidates(&self.board, r, c);
+                    let candidates = get_candidates_in(&self.grid, &self.board, r, c);
+                    self.pencil_marks[r][c] = self.pencil_marks[r][c].filled_from(&candidates);
+                }
+            }
+        }
+    }
+
+    /// The cells of the row, column or box containing (row, col)
+    fn unit_cells(&self, unit: Unit, row: usize, col: usize) -> Vec<(usize, usize)> {
+        match unit {
+            Unit::Row => (0..self.grid.size).map(|c| (row, c)).collect(),
+            Unit::Column => (0..self.grid.size).map(|r| (r, col)).collect(),
+            Unit::Box => self.grid.box_cells(row, col).collect(),
+        }
+    }
+
+    /// Whether the unit through (row, col) is full with every digit once.
+    /// Only looks at that unit, so it's cheap enough to run on each placement.
+    fn unit_is_solved(&self, unit: Unit, row: usize, col: usize) -> bool {
+        let mut seen = 0u16;
+        for (r, c) in self.unit_cells(unit, row, col) {
+            match self.board[r][c].value() {
+                Some(v) if seen & (1 << v) == 0 => seen |= 1 << v,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Whether (row, col) is in a unit that's still flashing
+    pub fn is_flashing(&self, row: usize, col: usize) -> bool {
+        let Some(flash) = &self.unit_flash else {
+            return false;
+        };
+        flash.at.elapsed() < UNIT_FLASH
+            && flash
+                .units
+                .iter()
+                .any(|&unit| self.unit_cells(unit, flash.row, flash.col).contains(&(row, col)))
+    }
+
+    /// Fill candidate pencil marks for the empty cells of the unit under the
+    /// cursor. Recorded as a single move so one undo puts everything back.
+    pub fn fill_candidates_unit(&mut self, unit: Unit) {
+        if self.state != GameState::Playing {
+            return;
+        }
+        let (row, col) = (self.selected_row, self.selected_col);
+        let mut previous = Vec::new();
+        for (r, c) in self.unit_cells(unit, row, col) {
+            if self.board[r][c] != Cell::Empty {
+                continue;
+            }
+            let candidates = get_candidates_in(&self.grid, &self.board, r, c);
+            if !self.pencil_marks[r][c].digits().eq(candidates.iter().copied()) {
+                let filled = self.pencil_marks[r][c].filled_from(&candidates);
+                let old = std::mem::replace(&mut self.pencil_marks[r][c], filled);
+                previous.push((r, c, old));
+            }
+        }
+
+        if !previous.is_empty() {
+            self.move_history.push(Move::FillPencilMarks { previous });
+        }
+    }
+
+    /// Drop pencil marks that can no longer go in their cell, across the whole
+    /// board. Recorded as a single move; returns how many marks went.
+    pub fn prune_pencil_marks(&mut self) -> usize {
+        if self.state != GameState::Playing {
+            return 0;
+        }
+        let mut previous = Vec::new();
+        let mut removed = 0;
+        for r in 0..self.grid.size {
+            for c in 0..self.grid.size {
+                if self.board[r][c] != Cell::Empty || self.pencil_marks[r][c].is_empty() {
+                    continue;
+                }
+                let candidates = get_candidates_in(&self.grid, &self.board, r, c);
+                let mut kept = self.pencil_marks[r][c].clone();
+                kept.retain(|v| candidates.contains(&v));
+                if kept.len() != self.pencil_marks[r][c].len() {
+                    removed += self.pencil_marks[r][c].len() - kept.len();
+                    let old = std::mem::replace(&mut self.pencil_marks[r][c], kept);
+                    previous.push((r, c, old));
                 }
             }
         }
+
+        if !previous.is_empty() {
+            self.move_history.push(Move::FillPencilMarks { previous });
+        }
+        removed
+    }
+
+    /// The operator's latest announcement, while it's still up
+    pub fn current_announcement(&self) -> Option<&str> {
+        self.announcement
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < ANNOUNCEMENT_SHOWN)
+            .map(|(text, _)| text.as_str())
     }
 
     pub fn is_multiplayer(&self) -> bool {
         self.multiplayer.is_some()
     }
 
+    /// Cells still empty when the board is at least 90% filled and free of
+    /// conflicts; `None` otherwise. Single player only.
+    pub fn almost_solved_remaining(&self) -> Option<usize> {
+        if self.state != GameState::Playing || self.is_multiplayer() || !self.conflicts.is_empty() {
+            return None;
+        }
+        let cells = self.grid.cell_count();
+        let empty = self.board[..self.grid.size]
+            .iter()
+            .flat_map(|row| &row[..self.grid.size])
+            .filter(|cell| **cell == Cell::Empty)
+            .count();
+        (empty > 0 && (cells - empty) * 10 >= cells * 9).then_some(empty)
+    }
+
     /// Count filled (non-given, non-empty) cells on the board
     pub fn filled_count(&self) -> u32 {
         let mut count = 0u32;
@@ -516,3 +1860,604 @@ impl Game {
         count
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sudoku_core::puzzle::generate_puzzle_in;
+
+    fn race() -> Game {
+        let mut game = Game::new();
+        let (board, solution) = generate_puzzle_in(&GridSpec::CLASSIC, Difficulty::Easy);
+        game.start_multiplayer_game(board, Some(solution), GameMode::Race, "opp".into(), 1200);
+        game
+    }
+
+    #[test]
+    fn a_forfeit_costs_what_the_server_would_take_for_a_loss() {
+        let mut game = race();
+        assert_eq!(game.forfeit_cost(), None);
+        game.my_profile = Some(crate::net::offline::me());
+        let mine = game.my_profile.as_ref().unwrap().profile.rating;
+
+        // The favourite has more to lose than the underdog
+        game.multiplayer.as_mut().unwrap().opponent_rating = mine - 300;
+        let favourite = game.forfeit_cost().unwrap();
+        assert_eq!(favourite, elo_change(mine, mine - 300, false));
+        game.multiplayer.as_mut().unwrap().opponent_rating = mine + 300;
+        let underdog = game.forfeit_cost().unwrap();
+        assert_eq!(underdog, elo_change(mine, mine + 300, false));
+        assert!(favourite < underdog && underdog < 0, "{favourite} {underdog}");
+
+        // Bot races aren't rated
+        game.start_bot_race();
+        game.finish_pending_puzzle();
+        assert_eq!(game.forfeit_cost(), None);
+    }
+
+    #[test]
+    fn server_matches_judge_nothing_until_the_solution_arrives() {
+        let mut game = Game::new();
+        let (board, solution) = generate_puzzle_in(&GridSpec::CLASSIC, Difficulty::Easy);
+        game.start_multiplayer_game(board, None, GameMode::Race, "opp".into(), 1200);
+        assert!(!game.solution_known);
+
+        // A right answer used to count as a mistake against the givens
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .unwrap();
+        game.selected_row = r;
+        game.selected_col = c;
+        game.place_number(solution[r][c]);
+        assert_eq!(game.mistakes, 0);
+        assert_eq!(game.entry_correct(r, c), None);
+
+        assert!(race().solution_known);
+    }
+
+    #[test]
+    fn a_held_peek_counts_once_and_weighs_on_the_solve() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.peek();
+        game.peek();
+        assert!(game.is_peeking());
+        assert_eq!(game.peeks, 1);
+
+        game.peek_until = Some(Instant::now());
+        game.peek();
+        assert_eq!(game.peeks, 2);
+
+        for r in 0..9 {
+            for c in 0..9 {
+                if game.board[r][c] == Cell::Empty {
+                    game.board[r][c] = Cell::UserInput(game.solution[r][c]);
+                }
+            }
+        }
+        game.finish_puzzle();
+        let result = game.pending_solo_results.last().unwrap();
+        assert_eq!(result.hints, 2 * PEEK_WEIGHT);
+        assert!(!result.flawless);
+
+        // Nothing to peek at in a server match before the solution arrives
+        let (board, _) = generate_puzzle_in(&GridSpec::CLASSIC, Difficulty::Easy);
+        game.start_multiplayer_game(board, None, GameMode::Race, "opp".into(), 1200);
+        game.peek();
+        assert_eq!(game.peeks, 0);
+        assert!(!game.is_peeking());
+    }
+
+    #[test]
+    fn rejection_after_a_re_entry_keeps_the_new_value() {
+        let mut game = race();
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .unwrap();
+        let mp = game.multiplayer.as_mut().unwrap();
+        let first = mp.track_placement(r, c, 1);
+        // The player overwrites the cell before the first answer arrives
+        game.board[r][c] = Cell::UserInput(2);
+        let second = game.multiplayer.as_mut().unwrap().track_placement(r, c, 2);
+
+        game.roll_back_placement(r, c, Some(first));
+        assert_eq!(game.board[r][c], Cell::UserInput(2));
+
+        game.roll_back_placement(r, c, Some(second));
+        assert_eq!(game.board[r][c], Cell::Empty);
+        assert!(game.multiplayer.as_ref().unwrap().pending_moves.is_empty());
+    }
+
+    #[test]
+    fn clashing_placements_are_caught_before_they_are_sent() {
+        let mut game = race();
+        let given_in_row = |game: &Game, r: usize| (0..9).find_map(|c| game.board[r][c].value());
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| game.board[r][c] == Cell::Empty && given_in_row(&game, r).is_some())
+            .unwrap();
+        let clash = given_in_row(&game, r).unwrap();
+        let right = game.solution[r][c];
+
+        // Race: wrong values are the player's business, so it only warns
+        assert!(game.precheck_placement(r, c, right));
+        assert!(game.notice.is_none());
+        assert!(game.precheck_placement(r, c, clash));
+        assert!(game.notice.is_some());
+
+        // Shared: it would claim the cell with a wrong answer, so it's refused
+        game.multiplayer.as_mut().unwrap().mode = GameMode::Shared;
+        assert!(!game.precheck_placement(r, c, clash));
+        assert!(game.error_message.is_some());
+        assert!(game.precheck_placement(r, c, right));
+    }
+
+    #[test]
+    fn new_games_start_on_the_empty_cell_nearest_the_centre() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let (row, col) = (game.selected_row, game.selected_col);
+        assert_eq!(game.board[row][col], Cell::Empty);
+        let dist = |r: usize, c: usize| r.abs_diff(4).pow(2) + c.abs_diff(4).pow(2);
+        let closest = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .map(|(r, c)| dist(r, c))
+            .min()
+            .unwrap();
+        assert_eq!(dist(row, col), closest);
+    }
+
+    #[test]
+    fn hint_log_keeps_each_explanation_until_the_next_game() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.request_hint();
+        let explanation = game.active_hint.as_ref().unwrap().explanation.clone();
+        game.request_hint();
+        game.request_hint();
+        game.request_hint();
+        assert_eq!(game.hint_log.len(), 2);
+        assert_eq!(game.hint_log[0], explanation);
+
+        game.open_hint_log();
+        assert_eq!(game.state, GameState::Paused);
+        assert!(game.show_hint_log);
+
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        assert!(game.hint_log.is_empty());
+        assert!(!game.show_hint_log);
+    }
+
+    #[test]
+    fn pencil_marks_remember_who_added_them() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let (r, c) = (game.selected_row, game.selected_col);
+        game.fill_candidates_unit(Unit::Row);
+        let marks = game.pencil_marks[r][c].clone();
+        let first = marks.digits().next().unwrap();
+        assert!(marks.iter().all(|(_, origin)| origin == MarkOrigin::Auto));
+
+        // Taking a filled-in mark away and undoing brings it back as filled-in
+        game.pencil_mode = true;
+        game.place_number(first);
+        assert!(!game.pencil_marks[r][c].contains(first));
+        game.undo();
+        assert_eq!(game.pencil_marks[r][c], marks);
+
+        let missing = (1..=9).find(|&d| !marks.contains(d)).unwrap();
+        game.place_number(missing);
+        assert_eq!(game.pencil_marks[r][c].origin(missing), Some(MarkOrigin::Manual));
+    }
+
+    #[test]
+    fn flags_toggle_per_colour_and_stay_out_of_undo() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let (r, c) = (game.selected_row, game.selected_col);
+
+        game.toggle_flag(CellFlag::Red);
+        assert_eq!(game.cell_flags[r][c], Some(CellFlag::Red));
+        // Another colour replaces the flag, the same colour clears it
+        game.toggle_flag(CellFlag::Blue);
+        assert_eq!(game.cell_flags[r][c], Some(CellFlag::Blue));
+        game.toggle_flag(CellFlag::Blue);
+        assert_eq!(game.cell_flags[r][c], None);
+        assert!(game.move_history.is_empty());
+
+        // Erasing the cell takes its flag with it
+        game.toggle_flag(CellFlag::Green);
+        game.place_number(game.solution[r][c]);
+        assert_eq!(game.cell_flags[r][c], Some(CellFlag::Green));
+        game.erase();
+        assert_eq!(game.cell_flags[r][c], None);
+    }
+
+    #[test]
+    fn a_new_game_clears_flags() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let (r, c) = (game.selected_row, game.selected_col);
+        game.flag_mode = true;
+        game.toggle_flag(CellFlag::Yellow);
+        assert_eq!(game.cell_flags[r][c], Some(CellFlag::Yellow));
+
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        assert!(game.cell_flags.iter().flatten().all(Option::is_none));
+        assert!(!game.flag_mode);
+    }
+
+    #[test]
+    fn a_selection_toggles_a_pencil_mark_across_its_empty_cells() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        (game.selected_row, game.selected_col) = (0, 0);
+        game.extend_selection(1, 0);
+        game.extend_selection(0, 2);
+        // Clamped at the edge instead of wrapping round
+        game.extend_selection(-5, 0);
+        assert_eq!(game.selection(), Some(((0, 0), (0, 2))));
+        let empty: Vec<(usize, usize)> =
+            (0..3).map(|c| (0, c)).filter(|&(r, c)| game.board[r][c] == Cell::Empty).collect();
+
+        let board = game.board;
+        game.place_number(5);
+        assert!(empty.iter().all(|&(r, c)| game.pencil_marks[r][c].contains(5)));
+        assert_eq!(game.board, board);
+        game.place_number(5);
+        assert!(empty.iter().all(|&(r, c)| !game.pencil_marks[r][c].contains(5)));
+
+        game.place_number(5);
+        game.undo();
+        assert!(empty.iter().all(|&(r, c)| game.pencil_marks[r][c].is_empty()));
+    }
+
+    #[test]
+    fn auto_advance_skips_to_the_next_empty_cell() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.settings.auto_advance = AutoAdvance::NextEmpty;
+        let empties: Vec<(usize, usize)> = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .filter(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .collect();
+        let (r, c) = empties[0];
+        (game.selected_row, game.selected_col) = (r, c);
+
+        game.place_number(game.solution[r][c]);
+        assert_eq!((game.selected_row, game.selected_col), empties[1]);
+
+        // A given can't be placed on, so the cursor stays put
+        let given = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| matches!(game.board[r][c], Cell::Given(_)))
+            .unwrap();
+        (game.selected_row, game.selected_col) = given;
+        game.place_number(1);
+        assert_eq!((game.selected_row, game.selected_col), given);
+
+        game.settings.auto_advance = AutoAdvance::Right;
+        (game.selected_row, game.selected_col) = (r, c);
+        game.place_number(game.solution[r][c]);
+        let expected = if c == 8 { (r + 1, 0) } else { (r, c + 1) };
+        assert_eq!((game.selected_row, game.selected_col), expected);
+    }
+
+    #[test]
+    fn completing_a_row_flashes_it_unless_it_repeats_a_digit() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let r = (0..9).find(|&r| (0..9).any(|c| game.board[r][c] == Cell::Empty)).unwrap();
+        let empty: Vec<usize> = (0..9).filter(|&c| game.board[r][c] == Cell::Empty).collect();
+        let (&last, rest) = empty.split_last().unwrap();
+        for &c in rest {
+            game.board[r][c] = Cell::UserInput(game.solution[r][c]);
+        }
+
+        // A full row with a repeat isn't done
+        let wrong = (1..=9).find(|&v| v != game.solution[r][last]).unwrap();
+        (game.selected_row, game.selected_col) = (r, last);
+        game.place_number(wrong);
+        assert!(game.unit_flash.as_ref().is_none_or(|f| !f.units.contains(&Unit::Row)));
+
+        (game.selected_row, game.selected_col) = (r, last);
+        game.place_number(game.solution[r][last]);
+        assert!(game.unit_flash.as_ref().unwrap().units.contains(&Unit::Row));
+        assert!((0..9).all(|c| game.is_flashing(r, c)));
+    }
+
+    #[test]
+    fn undoing_a_wrong_placement_takes_back_the_mistake() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .unwrap();
+        (game.selected_row, game.selected_col) = (r, c);
+        let wrong = game.solution[r][c] % 9 + 1;
+
+        game.place_number(wrong);
+        assert_eq!(game.mistakes, 1);
+        game.undo();
+        assert_eq!(game.mistakes, 0);
+        assert_eq!(game.board[r][c], Cell::Empty);
+
+        game.settings.permanent_mistakes = true;
+        game.place_number(wrong);
+        game.undo();
+        assert_eq!(game.mistakes, 1);
+    }
+
+    #[test]
+    fn only_ticking_screens_redraw_while_idle() {
+        let mut game = Game::new();
+        assert!(!game.is_animating());
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        assert!(game.is_animating());
+        game.toggle_pause();
+        assert!(!game.is_animating());
+
+        // A watched game's clock runs with no messages coming in
+        let watching = Watching {
+            code: "ABC123".to_string(),
+            mode: GameMode::Shared,
+            difficulty: Difficulty::Easy,
+            players: vec!["alice".to_string(), "bob".to_string()],
+        };
+        game.start_watching(watching, [[Cell::Empty; 9]; 9], 30);
+        assert!(game.is_animating());
+
+        // The banner has to come down on time on a still screen too
+        game.state = GameState::Menu;
+        assert!(!game.is_animating());
+        game.announcement = Some(("Restarting soon".to_string(), Instant::now()));
+        assert!(game.is_animating());
+        game.announcement = Some((
+            "Restarting soon".to_string(),
+            Instant::now() - ANNOUNCEMENT_SHOWN,
+        ));
+        assert!(!game.is_animating());
+    }
+
+    #[test]
+    fn an_opponent_move_ends_the_idle_warning() {
+        let mut game = race();
+        let mp = game.multiplayer.as_mut().unwrap();
+        let warned = Some(Instant::now() + Duration::from_secs(30));
+        mp.idle_deadline = warned;
+
+        // The same count again is just the broadcast timer
+        mp.update_opponent_progress(0, 0.0);
+        assert_eq!(mp.idle_deadline, warned);
+        mp.update_opponent_progress(1, 2.0);
+        assert_eq!(mp.idle_deadline, None);
+        assert!(mp.idle_seconds_remaining().is_none());
+
+        mp.idle_deadline = warned;
+        mp.update_opponent_quarter(0);
+        assert_eq!(mp.idle_deadline, warned);
+        mp.update_opponent_quarter(1);
+        assert_eq!(mp.idle_deadline, None);
+    }
+
+    #[test]
+    fn the_reconnect_banner_goes_once_the_grace_period_is_over() {
+        let mut game = race();
+        assert!(game.start_reconnecting());
+        assert!(game.reconnect_deadline().is_some());
+
+        // A connect attempt hanging past the deadline doesn't keep it up
+        game.multiplayer.as_mut().unwrap().reconnecting_until = Some(Instant::now());
+        assert!(game.reconnect_deadline().is_none());
+    }
+
+    #[test]
+    fn a_clean_expert_solve_earns_flawless_expert() {
+        let mut game = Game::new();
+        game.difficulty = Difficulty::Expert;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        for (r, c) in (0..81).map(|i| (i / 9, i % 9)) {
+            if game.board[r][c] == Cell::Empty {
+                game.selected_row = r;
+                game.selected_col = c;
+                game.place_number(game.solution[r][c]);
+            }
+        }
+        assert_eq!(game.state, GameState::Won);
+        assert!(game.new_badges.contains(&Achievement::FlawlessExpert));
+        assert!(game.stats.has(Achievement::FlawlessExpert));
+        assert!(game.stats_dirty);
+    }
+
+    #[test]
+    fn a_ladder_marathon_climbs_a_tier_per_puzzle() {
+        let mut game = Game::new();
+        game.settings.marathon_length = 2;
+        game.settings.marathon_ladder = true;
+        game.start_marathon();
+        game.finish_pending_puzzle();
+        for leg in 1..=2 {
+            assert_eq!(game.marathon.as_ref().unwrap().current(), leg);
+            for (r, c) in (0..81).map(|i| (i / 9, i % 9)) {
+                if game.board[r][c] == Cell::Empty {
+                    game.selected_row = r;
+                    game.selected_col = c;
+                    game.place_number(game.solution[r][c]);
+                }
+            }
+            game.finish_pending_puzzle();
+        }
+        assert_eq!(game.state, GameState::MarathonSummary);
+        let tiers: Vec<Difficulty> =
+            game.marathon.as_ref().unwrap().splits.iter().map(|&(d, _)| d).collect();
+        assert_eq!(tiers, [Difficulty::Easy, Difficulty::Medium]);
+
+        game.end_marathon();
+        assert_eq!(game.difficulty, Difficulty::Easy);
+        assert!(game.marathon.is_none());
+    }
+
+    #[test]
+    fn pruning_clears_a_placed_digit_from_its_row_column_and_box_only() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.board = [[Cell::Empty; 9]; 9];
+        for marks in game.pencil_marks.iter_mut().flatten() {
+            marks.insert(5, MarkOrigin::Manual);
+            marks.insert(7, MarkOrigin::Manual);
+        }
+        game.board[4][4] = Cell::UserInput(5);
+
+        assert_eq!(game.prune_pencil_marks(), 20);
+        for (r, c) in (0..81).map(|i| (i / 9, i % 9)).filter(|&cell| cell != (4, 4)) {
+            let sees = r == 4 || c == 4 || (r / 3 == 1 && c / 3 == 1);
+            assert_eq!(game.pencil_marks[r][c].contains(5), !sees, "R{}C{}", r + 1, c + 1);
+            assert!(game.pencil_marks[r][c].contains(7), "R{}C{}", r + 1, c + 1);
+        }
+        // Nothing left to prune
+        assert_eq!(game.prune_pencil_marks(), 0);
+    }
+
+    #[test]
+    fn a_marathon_totals_its_splits() {
+        let marathon = Marathon {
+            length: 3,
+            ladder: false,
+            start_difficulty: Difficulty::Hard,
+            splits: vec![(Difficulty::Hard, 95), (Difficulty::Hard, 200)],
+        };
+        assert_eq!(marathon.current(), 3);
+        assert_eq!(marathon.total_secs(), 295);
+    }
+
+    #[test]
+    fn custom_givens_stand_in_for_the_difficulty_but_earn_nothing() {
+        let mut game = Game::new();
+        game.grid = GridSpec::SIX;
+        game.set_custom_givens(5);
+        assert_eq!(game.custom_givens, Some(MIN_GIVENS));
+        assert_eq!(game.grid, GridSpec::CLASSIC);
+
+        game.set_custom_givens(60);
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let givens = game.board.iter().flatten().filter(|c| matches!(c, Cell::Given(_))).count();
+        assert_eq!(givens, 60);
+        for (r, c) in (0..81).map(|i| (i / 9, i % 9)) {
+            if game.board[r][c] == Cell::Empty {
+                game.selected_row = r;
+                game.selected_col = c;
+                game.place_number(game.solution[r][c]);
+            }
+        }
+        assert_eq!(game.state, GameState::Won);
+        assert_eq!(game.stats.solved, 0);
+        assert!(game.pending_solo_results.is_empty());
+    }
+
+    #[test]
+    fn history_replays_a_solved_puzzle_from_its_givens() {
+        let mut game = Game::new();
+        game.difficulty = Difficulty::Hard;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let board = game.board;
+        for (r, c) in (0..81).map(|i| (i / 9, i % 9)) {
+            if game.board[r][c] == Cell::Empty {
+                game.selected_row = r;
+                game.selected_col = c;
+                game.place_number(game.solution[r][c]);
+            }
+        }
+        assert!(matches!(game.history[0].outcome, Outcome::Solved { .. }));
+
+        game.difficulty = Difficulty::Easy;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        assert_eq!(game.history.len(), 2);
+
+        game.replay(1);
+        assert_eq!(game.board, board);
+        assert_eq!(game.difficulty, Difficulty::Hard);
+        assert_eq!(game.history.len(), 2);
+        assert!(matches!(game.history[1].outcome, Outcome::Solved { .. }));
+    }
+
+    #[test]
+    fn strict_placement_refuses_clashes_but_not_wrong_digits() {
+        let mut game = Game::new();
+        game.settings.strict_placement = true;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        // A cell with a wrong candidate alongside the right one
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| get_candidates_in(&game.grid, &game.board, r, c).len() > 1)
+            .unwrap();
+        game.selected_row = r;
+        game.selected_col = c;
+
+        let clash = (1..=9).find(|&v| game.clashes(r, c, v)).unwrap();
+        game.place_number(clash);
+        assert_eq!(game.board[r][c], Cell::Empty);
+        assert_eq!(game.mistakes, 0);
+        assert!(game.error_message.is_some());
+
+        let candidates = get_candidates_in(&game.grid, &game.board, r, c);
+        let wrong = candidates.into_iter().find(|&v| v != game.solution[r][c]).unwrap();
+        game.place_number(wrong);
+        assert_eq!(game.board[r][c], Cell::UserInput(wrong));
+        assert_eq!(game.mistakes, 1);
+    }
+
+    #[test]
+    fn a_seed_replays_the_same_puzzle() {
+        let mut game = Game::new();
+        game.difficulty = Difficulty::Medium;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        let seed = game.seed.unwrap();
+        let board = game.board;
+
+        let mut friend = Game::new();
+        friend.difficulty = Difficulty::Medium;
+        friend.start_seeded_game(seed);
+        friend.finish_pending_puzzle();
+        assert_eq!(friend.board, board);
+        assert_eq!(friend.solution, game.solution);
+
+        // Re-rolling asks first once the game has been played on
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| game.board[r][c] == Cell::Empty)
+            .unwrap();
+        (game.selected_row, game.selected_col) = (r, c);
+        game.place_number(game.solution[r][c]);
+        game.reroll();
+        assert!(game.show_reroll_confirm);
+        assert_eq!(game.board[r][c], Cell::UserInput(game.solution[r][c]));
+        game.reroll();
+        game.finish_pending_puzzle();
+        assert!(!game.show_reroll_confirm);
+        assert!(game.move_history.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sudoku_core::Difficulty;
+
+/// Ghosts kept on disk; the oldest go first
+const MAX_GHOSTS: usize = 20;
+
+/// One cell change in a recorded solve. `value` 0 means the cell was cleared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GhostMove {
+    pub at_ms: u64,
+    pub row: usize,
+    pub col: usize,
+    pub value: u8,
+}
+
+/// Your best solve of one 9×9 puzzle, replayed as the opponent in a ghost race.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ghost {
+    pub difficulty: Difficulty,
+    /// The puzzle, as written by `givens_string`
+    pub givens: String,
+    /// Solve time
+    pub ms: u64,
+    pub moves: Vec<GhostMove>,
+}
+
+impl Ghost {
+    pub fn secs(&self) -> u64 {
+        self.ms / 1000
+    }
+}
+
+fn ghosts_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("ghosts.json")
+}
+
+pub fn load_ghosts() -> Vec<Ghost> {
+    std::fs::read_to_string(ghosts_file_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_ghosts(ghosts: &[Ghost]) -> std::io::Result<()> {
+    let path = ghosts_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(ghosts)?)
+}
+
+/// Add `ghost` unless the same puzzle already has a faster one. The newest
+/// ghost goes last. Returns true if it was kept.
+fn keep_best(ghosts: &mut Vec<Ghost>, ghost: Ghost) -> bool {
+    let same_puzzle = |g: &Ghost| g.difficulty == ghost.difficulty && g.givens == ghost.givens;
+    if ghosts.iter().any(|g| same_puzzle(g) && g.ms <= ghost.ms) {
+        return false;
+    }
+    ghosts.retain(|g| !same_puzzle(g));
+    ghosts.push(ghost);
+    if ghosts.len() > MAX_GHOSTS {
+        ghosts.drain(..ghosts.len() - MAX_GHOSTS);
+    }
+    true
+}
+
+/// Store a finished solve if it's the first or fastest of its puzzle.
+/// Returns true if it was stored.
+pub fn record(ghost: Ghost) -> bool {
+    let mut ghosts = load_ghosts();
+    keep_best(&mut ghosts, ghost) && save_ghosts(&ghosts).is_ok()
+}
+
+/// The most recently stored ghost at `difficulty`
+pub fn latest(difficulty: Difficulty) -> Option<Ghost> {
+    load_ghosts().into_iter().rev().find(|g| g.difficulty == difficulty)
+}
+
+/// The stored ghost for one puzzle
+pub fn find(difficulty: Difficulty, givens: &str) -> Option<Ghost> {
+    load_ghosts()
+        .into_iter()
+        .find(|g| g.difficulty == difficulty && g.givens == givens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ghost(givens: &str, ms: u64) -> Ghost {
+        Ghost {
+            difficulty: Difficulty::Easy,
+            givens: givens.to_string(),
+            ms,
+            moves: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_faster_solves_replace_a_ghost() {
+        let mut ghosts = vec![ghost("a", 90_000), ghost("b", 60_000)];
+
+        assert!(!keep_best(&mut ghosts, ghost("a", 95_000)));
+        assert!(keep_best(&mut ghosts, ghost("a", 80_000)));
+        assert_eq!(ghosts, vec![ghost("b", 60_000), ghost("a", 80_000)]);
+
+        for i in 0..MAX_GHOSTS {
+            keep_best(&mut ghosts, ghost(&i.to_string(), 1000));
+        }
+        assert_eq!(ghosts.len(), MAX_GHOSTS);
+        assert!(ghosts.iter().all(|g| g.givens != "a" && g.givens != "b"));
+    }
+}
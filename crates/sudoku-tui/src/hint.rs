@@ -1,5 +1,6 @@
-use sudoku_core::{Board, Cell};
-use sudoku_core::validation::get_candidates;
+use serde::{Deserialize, Serialize};
+use sudoku_core::{Board, Cell, GridSpec};
+use sudoku_core::validation::get_candidates_in;
 
 #[derive(Clone, Debug)]
 pub struct Hint {
@@ -11,11 +12,13 @@ pub struct Hint {
     pub explanation: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HintTechnique {
     NakedSingle,
     HiddenSingle,
     DirectReveal,
+    /// No enabled technique applies and revealing is not allowed
+    Stuck,
 }
 
 impl HintTechnique {
@@ -24,6 +27,24 @@ impl HintTechnique {
             HintTechnique::NakedSingle => "Naked Single",
             HintTechnique::HiddenSingle => "Hidden Single",
             HintTechnique::DirectReveal => "Direct Reveal",
+            HintTechnique::Stuck => "Stuck",
+        }
+    }
+}
+
+/// Which deduction techniques the hint engine may use, in the order it tries
+/// them. `allow_reveal` controls the solution-peeking fallback.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HintConfig {
+    pub enabled: Vec<HintTechnique>,
+    pub allow_reveal: bool,
+}
+
+impl Default for HintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: vec![HintTechnique::NakedSingle, HintTechnique::HiddenSingle],
+            allow_reveal: true,
         }
     }
 }
@@ -34,45 +55,55 @@ pub enum HintStage {
     RevealValue,
 }
 
-pub fn find_hint(board: &Board, solution: &[[u8; 9]; 9]) -> Option<Hint> {
-    if let Some(hint) = find_naked_single(board) {
-        return Some(hint);
+pub fn find_hint(
+    grid: &GridSpec,
+    board: &Board,
+    solution: &[[u8; 9]; 9],
+    config: &HintConfig,
+) -> Option<Hint> {
+    for technique in &config.enabled {
+        let hint = match technique {
+            HintTechnique::NakedSingle => find_naked_single(grid, board),
+            HintTechnique::HiddenSingle => find_hidden_single(grid, board),
+            // Fallbacks, never tried as regular techniques
+            HintTechnique::DirectReveal | HintTechnique::Stuck => None,
+        };
+        if hint.is_some() {
+            return hint;
+        }
     }
-    if let Some(hint) = find_hidden_single(board) {
-        return Some(hint);
+    if config.allow_reveal {
+        find_direct_reveal(grid, board, solution)
+    } else {
+        find_stuck(grid, board)
     }
-    find_direct_reveal(board, solution)
 }
 
-fn find_naked_single(board: &Board) -> Option<Hint> {
-    for r in 0..9 {
-        for c in 0..9 {
+fn find_naked_single(grid: &GridSpec, board: &Board) -> Option<Hint> {
+    for r in 0..grid.size {
+        for c in 0..grid.size {
             if board[r][c] != Cell::Empty {
                 continue;
             }
-            let candidates = get_candidates(board, r, c);
+            let candidates = get_candidates_in(grid, board, r, c);
             if candidates.len() == 1 {
                 let val = candidates[0];
                 let mut highlighted = Vec::new();
 
-                for cc in 0..9 {
+                for cc in 0..grid.size {
                     if cc != c && board[r][cc].value().is_some() {
                         highlighted.push((r, cc));
                     }
                 }
-                for rr in 0..9 {
+                for rr in 0..grid.size {
                     if rr != r && board[rr][c].value().is_some() {
                         highlighted.push((rr, c));
                     }
                 }
-                let box_r = (r / 3) * 3;
-                let box_c = (c / 3) * 3;
-                for rr in box_r..box_r + 3 {
-                    for cc in box_c..box_c + 3 {
-                        if (rr != r || cc != c) && board[rr][cc].value().is_some() {
-                            if !highlighted.contains(&(rr, cc)) {
-                                highlighted.push((rr, cc));
-                            }
+                for (rr, cc) in grid.box_cells(r, c) {
+                    if (rr != r || cc != c) && board[rr][cc].value().is_some() {
+                        if !highlighted.contains(&(rr, cc)) {
+                            highlighted.push((rr, cc));
                         }
                     }
                 }
@@ -94,22 +125,24 @@ fn find_naked_single(board: &Board) -> Option<Hint> {
     None
 }
 
-fn find_hidden_single(board: &Board) -> Option<Hint> {
-    for r in 0..9 {
-        for val in 1..=9u8 {
-            if (0..9).any(|c| board[r][c].value() == Some(val)) {
+fn find_hidden_single(grid: &GridSpec, board: &Board) -> Option<Hint> {
+    let n = grid.size;
+    for r in 0..n {
+        for val in 1..=n as u8 {
+            if (0..n).any(|c| board[r][c].value() == Some(val)) {
                 continue;
             }
-            let possible_cols: Vec<usize> = (0..9)
+            let possible_cols: Vec<usize> = (0..n)
                 .filter(|&c| {
-                    board[r][c] == Cell::Empty && get_candidates(board, r, c).contains(&val)
+                    board[r][c] == Cell::Empty
+                        && get_candidates_in(grid, board, r, c).contains(&val)
                 })
                 .collect();
 
             if possible_cols.len() == 1 {
                 let c = possible_cols[0];
                 let highlighted: Vec<(usize, usize)> =
-                    (0..9).filter(|&cc| cc != c).map(|cc| (r, cc)).collect();
+                    (0..n).filter(|&cc| cc != c).map(|cc| (r, cc)).collect();
 
                 return Some(Hint {
                     technique: HintTechnique::HiddenSingle,
@@ -129,21 +162,22 @@ fn find_hidden_single(board: &Board) -> Option<Hint> {
         }
     }
 
-    for c in 0..9 {
-        for val in 1..=9u8 {
-            if (0..9).any(|r| board[r][c].value() == Some(val)) {
+    for c in 0..n {
+        for val in 1..=n as u8 {
+            if (0..n).any(|r| board[r][c].value() == Some(val)) {
                 continue;
             }
-            let possible_rows: Vec<usize> = (0..9)
+            let possible_rows: Vec<usize> = (0..n)
                 .filter(|&r| {
-                    board[r][c] == Cell::Empty && get_candidates(board, r, c).contains(&val)
+                    board[r][c] == Cell::Empty
+                        && get_candidates_in(grid, board, r, c).contains(&val)
                 })
                 .collect();
 
             if possible_rows.len() == 1 {
                 let r = possible_rows[0];
                 let highlighted: Vec<(usize, usize)> =
-                    (0..9).filter(|&rr| rr != r).map(|rr| (rr, c)).collect();
+                    (0..n).filter(|&rr| rr != r).map(|rr| (rr, c)).collect();
 
                 return Some(Hint {
                     technique: HintTechnique::HiddenSingle,
@@ -163,32 +197,28 @@ fn find_hidden_single(board: &Board) -> Option<Hint> {
         }
     }
 
-    for box_r in (0..9).step_by(3) {
-        for box_c in (0..9).step_by(3) {
-            for val in 1..=9u8 {
-                let mut found = false;
-                for r in box_r..box_r + 3 {
-                    for c in box_c..box_c + 3 {
-                        if board[r][c].value() == Some(val) {
-                            found = true;
-                        }
-                    }
-                }
-                if found {
+    for box_r in (0..n).step_by(grid.box_rows) {
+        for box_c in (0..n).step_by(grid.box_cols) {
+            for val in 1..=n as u8 {
+                if grid
+                    .box_cells(box_r, box_c)
+                    .any(|(r, c)| board[r][c].value() == Some(val))
+                {
                     continue;
                 }
 
-                let possible: Vec<(usize, usize)> = (box_r..box_r + 3)
-                    .flat_map(|r| (box_c..box_c + 3).map(move |c| (r, c)))
+                let possible: Vec<(usize, usize)> = grid
+                    .box_cells(box_r, box_c)
                     .filter(|&(r, c)| {
-                        board[r][c] == Cell::Empty && get_candidates(board, r, c).contains(&val)
+                        board[r][c] == Cell::Empty
+                            && get_candidates_in(grid, board, r, c).contains(&val)
                     })
                     .collect();
 
                 if possible.len() == 1 {
                     let (r, c) = possible[0];
-                    let highlighted: Vec<(usize, usize)> = (box_r..box_r + 3)
-                        .flat_map(|rr| (box_c..box_c + 3).map(move |cc| (rr, cc)))
+                    let highlighted: Vec<(usize, usize)> = grid
+                        .box_cells(box_r, box_c)
                         .filter(|&(rr, cc)| rr != r || cc != c)
                         .collect();
 
@@ -199,10 +229,12 @@ fn find_hidden_single(board: &Board) -> Option<Hint> {
                         value: val,
                         highlighted_cells: highlighted,
                         explanation: format!(
-                            "Hidden Single: {} can only go in R{}C{} within its 3×3 box",
+                            "Hidden Single: {} can only go in R{}C{} within its {}×{} box",
                             val,
                             r + 1,
-                            c + 1
+                            c + 1,
+                            grid.box_rows,
+                            grid.box_cols
                         ),
                     });
                 }
@@ -213,9 +245,9 @@ fn find_hidden_single(board: &Board) -> Option<Hint> {
     None
 }
 
-fn find_direct_reveal(board: &Board, solution: &[[u8; 9]; 9]) -> Option<Hint> {
-    for r in 0..9 {
-        for c in 0..9 {
+fn find_direct_reveal(grid: &GridSpec, board: &Board, solution: &[[u8; 9]; 9]) -> Option<Hint> {
+    for r in 0..grid.size {
+        for c in 0..grid.size {
             if board[r][c] == Cell::Empty {
                 return Some(Hint {
                     technique: HintTechnique::DirectReveal,
@@ -235,3 +267,48 @@ fn find_direct_reveal(board: &Board, solution: &[[u8; 9]; 9]) -> Option<Hint> {
     }
     None
 }
+
+fn find_stuck(grid: &GridSpec, board: &Board) -> Option<Hint> {
+    for (r, row) in board.iter().enumerate().take(grid.size) {
+        for (c, cell) in row.iter().enumerate().take(grid.size) {
+            if *cell == Cell::Empty {
+                return Some(Hint {
+                    technique: HintTechnique::Stuck,
+                    target_row: r,
+                    target_col: c,
+                    value: 0,
+                    highlighted_cells: Vec::new(),
+                    explanation: "No simple technique applies here — look for pairs, pointing or other advanced patterns".to_string(),
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sudoku_core::puzzle::generate_puzzle;
+    use sudoku_core::Difficulty;
+
+    #[test]
+    fn disabled_techniques_are_skipped() {
+        // One empty cell: a naked single and a hidden single both find it
+        let (_, solution) = generate_puzzle(Difficulty::Easy);
+        let mut board = solution.map(|row| row.map(Cell::Given));
+        board[4][4] = Cell::Empty;
+        let grid = GridSpec::CLASSIC;
+        let hint_with = |enabled: Vec<HintTechnique>, allow_reveal: bool| {
+            let config = HintConfig { enabled, allow_reveal };
+            let hint = find_hint(&grid, &board, &solution, &config).unwrap();
+            assert_eq!((hint.target_row, hint.target_col), (4, 4));
+            hint.technique
+        };
+
+        assert_eq!(hint_with(HintConfig::default().enabled, true), HintTechnique::NakedSingle);
+        assert_eq!(hint_with(vec![HintTechnique::HiddenSingle], true), HintTechnique::HiddenSingle);
+        assert_eq!(hint_with(Vec::new(), true), HintTechnique::DirectReveal);
+        assert_eq!(hint_with(Vec::new(), false), HintTechnique::Stuck);
+    }
+}
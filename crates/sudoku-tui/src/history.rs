@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sudoku_core::puzzle::GENERATOR_VERSION;
+use sudoku_core::Difficulty;
+
+/// Puzzles kept in the history; the oldest go first
+pub const MAX_HISTORY: usize = 15;
+
+/// How a played puzzle ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// Left before it was solved, or still being played
+    Unfinished,
+    /// Solved in this many seconds
+    Solved { secs: u64 },
+}
+
+/// A 9×9 single-player puzzle played recently, kept so it can be replayed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Played {
+    pub difficulty: Difficulty,
+    /// The puzzle, as written by `givens_string`
+    pub givens: String,
+    /// Seed it was generated from, to share; None if it came from elsewhere
+    pub seed: Option<u64>,
+    /// `GENERATOR_VERSION` the seed was generated under
+    #[serde(default = "first_generator")]
+    pub generator: u32,
+    pub outcome: Outcome,
+}
+
+/// Entries saved before the generator was versioned came from the first
+fn first_generator() -> u32 {
+    1
+}
+
+impl Played {
+    /// The seed, if it still gives this puzzle. One from an older generator
+    /// would hand a friend a different board, so it isn't offered.
+    pub fn shareable_seed(&self) -> Option<u64> {
+        self.seed.filter(|_| self.generator == GENERATOR_VERSION)
+    }
+}
+
+fn history_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("history.json")
+}
+
+/// Load the history, starting afresh if missing or unreadable
+pub fn load() -> Vec<Played> {
+    std::fs::read_to_string(history_file_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(history: &[Played]) -> std::io::Result<()> {
+    let path = history_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(history)?)
+}
+
+/// Add a puzzle just started as the newest entry. Playing one again moves
+/// it to the end rather than listing it twice, keeping a solve it already has.
+pub fn remember(history: &mut Vec<Played>, played: Played) {
+    let earlier = history.iter().position(|p| p.givens == played.givens);
+    let outcome = earlier.map(|i| history.remove(i).outcome);
+    history.push(Played {
+        outcome: outcome.unwrap_or(played.outcome),
+        ..played
+    });
+    if history.len() > MAX_HISTORY {
+        history.drain(..history.len() - MAX_HISTORY);
+    }
+}
+
+/// Note a solve of `givens`, keeping the fastest if it was solved before.
+pub fn mark_solved(history: &mut [Played], givens: &str, secs: u64) {
+    if let Some(played) = history.iter_mut().find(|p| p.givens == givens) {
+        played.outcome = match played.outcome {
+            Outcome::Solved { secs: best } if best <= secs => played.outcome,
+            _ => Outcome::Solved { secs },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played(givens: &str) -> Played {
+        Played {
+            difficulty: Difficulty::Easy,
+            givens: givens.to_string(),
+            seed: None,
+            generator: GENERATOR_VERSION,
+            outcome: Outcome::Unfinished,
+        }
+    }
+
+    #[test]
+    fn replays_move_to_the_end_and_keep_their_best_solve() {
+        let mut history = vec![played("a"), played("b")];
+        mark_solved(&mut history, "a", 300);
+        mark_solved(&mut history, "a", 400);
+
+        remember(&mut history, played("a"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].givens, "a");
+        assert_eq!(history[1].outcome, Outcome::Solved { secs: 300 });
+
+        for i in 0..MAX_HISTORY {
+            remember(&mut history, played(&i.to_string()));
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert!(history.iter().all(|p| p.givens != "a" && p.givens != "b"));
+    }
+
+    #[test]
+    fn seeds_saved_before_versioning_are_not_shared() {
+        let old = r#"{"difficulty":"Hard","givens":"a","seed":13,"outcome":"Unfinished"}"#;
+        let played: Played = serde_json::from_str(old).unwrap();
+        assert_eq!(played.generator, 1);
+        assert_eq!(played.shareable_seed(), None);
+
+        let fresh = Played { seed: Some(13), generator: GENERATOR_VERSION, ..played };
+        assert_eq!(fresh.shareable_seed(), Some(13));
+    }
+}
@@ -1,11 +1,36 @@
+mod achievements;
 mod app;
+mod bot;
 mod game;
+mod ghost;
 mod hint;
+mod history;
 mod net;
+mod mp_menu;
+mod pencil;
+mod report;
+mod settings;
+mod tasks;
 mod ui;
 
 fn main() {
-    if let Err(e) = app::run() {
+    // `--offline` swaps the server for a scripted mock (UI work without a backend)
+    let network =
+        if std::env::args().skip(1).any(|a| a == "--offline") || std::env::var("SUDOKU_OFFLINE").is_ok() {
+            net::Backend::Offline
+        } else {
+            net::Backend::Online
+        };
+    // `--insecure-tls` accepts self-signed certificates (self-hosted servers)
+    if std::env::args().skip(1).any(|a| a == "--insecure-tls")
+        || std::env::var("SUDOKU_INSECURE_TLS").is_ok_and(|v| v == "1")
+    {
+        net::tls::enable_insecure();
+        eprintln!("WARNING: TLS certificate verification is disabled. Only use this with a server you control.");
+    }
+    // `--debug` opens with the network trace overlay (F12 toggles it any time)
+    let debug = std::env::args().skip(1).any(|a| a == "--debug");
+    if let Err(e) = app::run(debug, network) {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,244 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use sudoku_core::Difficulty;
+
+/// The multiplayer menu's items, in order
+pub const ITEMS: &[&str] = &[
+    "Create Room",
+    "Join Room",
+    "Quick Match",
+    "Leaderboard",
+    "Watch a Game",
+    "Profile",
+    "Back",
+];
+
+/// Index of "Quick Match" in `ITEMS`, where ←/→ change its difficulty
+pub const QUICK_MATCH: usize = 2;
+
+/// Length of the codes the server gives private rooms
+pub const ROOM_CODE_LEN: usize = 6;
+
+/// What has the keyboard in the multiplayer menu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MenuMode {
+    /// Moving through `ITEMS`
+    #[default]
+    Items,
+    /// Typing the code of a room to join
+    RoomCode,
+}
+
+/// What a key in the menu asks the app to do
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Run the item at this index in `ITEMS`
+    Activate(usize),
+    /// Join the room with this code
+    Join(String),
+    /// Step the quick-match difficulty on (true) or back (false)
+    StepQuickDifficulty(bool),
+    /// Back to the main menu
+    Leave,
+}
+
+/// The multiplayer menu's state. Keys go through `handle_key`, which moves
+/// between modes and hands anything needing the network back as a
+/// `MenuAction`.
+#[derive(Clone, Debug, Default)]
+pub struct MultiplayerMenu {
+    pub mode: MenuMode,
+    pub selection: usize,
+    /// Room code typed so far
+    pub room_input: String,
+    /// Why the last characters typed into the code were dropped
+    pub input_note: Option<String>,
+    /// Quick-match difficulty picked by hand; None follows the rating
+    pub quick_override: Option<Difficulty>,
+}
+
+impl MultiplayerMenu {
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<MenuAction> {
+        match self.mode {
+            MenuMode::Items => self.items_key(key),
+            MenuMode::RoomCode => self.room_code_key(key),
+        }
+    }
+
+    /// The difficulty Quick Match asks for: the one picked by hand, else
+    /// the one suiting `rating`, else `fallback` while the rating is unknown
+    pub fn quick_difficulty(&self, rating: Option<i32>, fallback: Difficulty) -> Difficulty {
+        self.quick_override
+            .or(rating.map(Difficulty::for_rating))
+            .unwrap_or(fallback)
+    }
+
+    /// Start typing a room code, from an empty box
+    pub fn enter_room_code(&mut self) {
+        self.mode = MenuMode::RoomCode;
+        self.room_input.clear();
+        self.input_note = None;
+    }
+
+    fn leave_room_code(&mut self) {
+        self.mode = MenuMode::Items;
+        self.room_input.clear();
+        self.input_note = None;
+    }
+
+    fn items_key(&mut self, key: KeyEvent) -> Option<MenuAction> {
+        match key.code {
+            KeyCode::Up => self.step(-1),
+            KeyCode::Down => self.step(1),
+            KeyCode::Enter => return Some(MenuAction::Activate(self.selection)),
+            KeyCode::Left if self.selection == QUICK_MATCH => {
+                return Some(MenuAction::StepQuickDifficulty(false));
+            }
+            KeyCode::Right if self.selection == QUICK_MATCH => {
+                return Some(MenuAction::StepQuickDifficulty(true));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(MenuAction::Leave),
+            _ => {}
+        }
+        None
+    }
+
+    fn room_code_key(&mut self, key: KeyEvent) -> Option<MenuAction> {
+        match key.code {
+            // Ctrl/Alt chords (e.g. a Ctrl+V the terminal passed through)
+            // aren't typing
+            KeyCode::Char(_)
+                if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {}
+            KeyCode::Char(c) => self.type_room_code(c),
+            KeyCode::Backspace => {
+                self.room_input.pop();
+                self.input_note = None;
+            }
+            KeyCode::Enter if self.room_input.len() == ROOM_CODE_LEN => {
+                let code = std::mem::take(&mut self.room_input);
+                self.leave_room_code();
+                return Some(MenuAction::Join(code));
+            }
+            KeyCode::Esc => self.leave_room_code(),
+            // Arrows give up on the code and carry on through the items
+            KeyCode::Up => {
+                self.leave_room_code();
+                self.step(-1);
+            }
+            KeyCode::Down => {
+                self.leave_room_code();
+                self.step(1);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn step(&mut self, delta: isize) {
+        let len = ITEMS.len() as isize;
+        self.selection = (self.selection as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// One typed (or pasted) character of the room code. Letters and digits
+    /// go in uppercased; anything else, or anything past the last character,
+    /// is dropped with a note so a messy paste doesn't silently turn into
+    /// the wrong code. Spaces around a pasted code are ignored.
+    fn type_room_code(&mut self, c: char) {
+        if c == ' ' {
+            return;
+        }
+        if !c.is_ascii_alphanumeric() {
+            self.input_note = Some("Invalid characters ignored".to_string());
+        } else if self.room_input.len() == ROOM_CODE_LEN {
+            self.input_note = Some(format!("Room codes are {} characters", ROOM_CODE_LEN));
+        } else {
+            self.room_input.push(c.to_ascii_uppercase());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(menu: &mut MultiplayerMenu, code: KeyCode) -> Option<MenuAction> {
+        menu.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_str(menu: &mut MultiplayerMenu, text: &str) {
+        for c in text.chars() {
+            press(menu, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn quick_match_suits_the_rating_until_picked_by_hand() {
+        let mut menu = MultiplayerMenu::default();
+        assert_eq!(menu.quick_difficulty(Some(1750), Difficulty::Easy), Difficulty::Hard);
+        assert_eq!(menu.quick_difficulty(Some(1200), Difficulty::Hard), Difficulty::Easy);
+        assert_eq!(menu.quick_difficulty(None, Difficulty::Medium), Difficulty::Medium);
+
+        // ←/→ only ask to change it on the Quick Match row
+        assert_eq!(press(&mut menu, KeyCode::Right), None);
+        menu.selection = QUICK_MATCH;
+        assert_eq!(press(&mut menu, KeyCode::Left), Some(MenuAction::StepQuickDifficulty(false)));
+        menu.quick_override = Some(Difficulty::Expert);
+        assert_eq!(menu.quick_difficulty(Some(1000), Difficulty::Easy), Difficulty::Expert);
+    }
+
+    #[test]
+    fn items_wrap_and_activate() {
+        let mut menu = MultiplayerMenu::default();
+        press(&mut menu, KeyCode::Up);
+        assert_eq!(menu.selection, ITEMS.len() - 1);
+        press(&mut menu, KeyCode::Down);
+        assert_eq!(menu.selection, 0);
+        assert_eq!(press(&mut menu, KeyCode::Enter), Some(MenuAction::Activate(0)));
+        assert_eq!(press(&mut menu, KeyCode::Char('q')), Some(MenuAction::Leave));
+    }
+
+    #[test]
+    fn room_code_entry_joins_cancels_or_moves_on() {
+        let mut menu = MultiplayerMenu { selection: 1, ..Default::default() };
+        menu.enter_room_code();
+        type_str(&mut menu, "abc12");
+        assert_eq!(press(&mut menu, KeyCode::Enter), None);
+        type_str(&mut menu, "3");
+        assert_eq!(
+            press(&mut menu, KeyCode::Enter),
+            Some(MenuAction::Join("ABC123".to_string()))
+        );
+        assert_eq!(menu.mode, MenuMode::Items);
+
+        menu.enter_room_code();
+        type_str(&mut menu, "xy");
+        assert_eq!(press(&mut menu, KeyCode::Esc), None);
+        assert_eq!(menu.mode, MenuMode::Items);
+        assert_eq!(menu.selection, 1);
+
+        // An arrow leaves the code box and moves straight on
+        menu.enter_room_code();
+        press(&mut menu, KeyCode::Down);
+        assert_eq!(menu.mode, MenuMode::Items);
+        assert!(menu.room_input.is_empty());
+        assert_eq!(menu.selection, 2);
+    }
+
+    #[test]
+    fn pasted_room_codes_keep_only_the_code() {
+        let mut menu = MultiplayerMenu::default();
+        menu.enter_room_code();
+        type_str(&mut menu, " abc123 ");
+        assert_eq!(menu.room_input, "ABC123");
+        assert_eq!(menu.input_note, None);
+
+        menu.enter_room_code();
+        type_str(&mut menu, "ab-c\u{e9}1\u{7}23x");
+        assert_eq!(menu.room_input, "ABC123");
+        assert!(menu.input_note.is_some());
+
+        // Ctrl+V isn't a V
+        menu.enter_room_code();
+        menu.handle_key(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        assert!(menu.room_input.is_empty());
+    }
+}
@@ -3,16 +3,23 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use sudoku_core::protocol::{
-    AuthPollResponse, ClientMessage, DeviceAuthResponse, LeaderboardEntry, PlayerProfile,
-    ServerMessage,
+    AuthPollResponse, ClientMessage, DeviceAuthResponse, HeadToHead, LeaderboardEntry,
+    MeResponse, PlayerProfile, RatingPoint, ServerMessage, SoloResult, SoloStats,
 };
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_tungstenite::tungstenite::Message;
 
+use super::trace::{NetTrace, TracedMessage};
+use super::{offline, tls, NetError};
+
 /// Timeout for HTTP requests (auth, leaderboard, etc.)
 const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
-/// Timeout for WebSocket connection establishment
-const WS_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to keep trying to reach the server, unless SUDOKU_CONNECT_TIMEOUT
+/// (seconds) says otherwise
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a dropped client's tasks get to close the socket before they're aborted
+const CLOSE_GRACE: Duration = Duration::from_secs(1);
 
 const DEFAULT_SERVER_URL: &str = "wss://sudoku-tui-server.onrender.com";
 const LOCAL_SERVER_URL: &str = "ws://localhost:8080";
@@ -51,9 +58,9 @@ fn check_local_server() -> bool {
     .is_ok()
 }
 
-/// Returns true if we should use dev-mode shortcuts (silent auth, no token persistence).
-/// Set SUDOKU_PROD_AUTH=1 to force production auth flow even against a local server.
-pub fn is_local() -> bool {
+/// Returns true if the server is a local dev one. Set SUDOKU_PROD_AUTH=1 to
+/// force production auth flow even against a local server.
+fn is_local_server() -> bool {
     if std::env::var("SUDOKU_PROD_AUTH").is_ok() {
         return false;
     }
@@ -61,6 +68,25 @@ pub fn is_local() -> bool {
     url.contains("localhost") || url.contains("127.0.0.1")
 }
 
+/// Bound on opening a connection (TCP, TLS and for WebSockets the upgrade),
+/// so an unreachable server fails with `NetError::Timeout` instead of
+/// waiting out the OS timeout.
+fn connect_timeout() -> Duration {
+    std::env::var("SUDOKU_CONNECT_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs)
+}
+
+fn http_client() -> Result<reqwest::Client, NetError> {
+    Ok(reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .connect_timeout(connect_timeout())
+        .danger_accept_invalid_certs(tls::is_insecure())
+        .build()?)
+}
+
 fn http_base_url() -> String {
     let ws_url = server_url();
     ws_url
@@ -68,10 +94,6 @@ fn http_base_url() -> String {
         .replace("ws://", "http://")
 }
 
-fn is_local_server() -> bool {
-    is_local()
-}
-
 fn auth_file_path() -> PathBuf {
     let config_dir = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -85,62 +107,244 @@ struct AuthData {
     username: String,
 }
 
+fn pending_auth_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("pending_auth.json")
+}
+
+/// Pending auths older than this are dropped on load, whatever their deadline says.
+const PENDING_AUTH_MAX_AGE_SECS: u64 = 60 * 60;
+
+/// A device-auth login that was started but not finished, kept on disk so a
+/// relaunch can resume polling. Times are unix seconds.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PendingAuth {
+    pub user_code: String,
+    pub verification_uri: String,
+    pub interval: u64,
+    pub started_at: u64,
+    pub expires_at: u64,
+}
+
+impl PendingAuth {
+    pub fn from_response(resp: &DeviceAuthResponse) -> Self {
+        let now = unix_now();
+        PendingAuth {
+            user_code: resp.user_code.clone(),
+            verification_uri: resp.verification_uri.clone(),
+            interval: resp.interval,
+            started_at: now,
+            expires_at: now + resp.expires_in,
+        }
+    }
+
+    /// Past its deadline, or started so long ago it can't still be good.
+    pub fn is_stale(&self, now: u64) -> bool {
+        now >= self.expires_at || now.saturating_sub(self.started_at) > PENDING_AUTH_MAX_AGE_SECS
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Stand-in for server text this client can't parse, so protocol drift
+/// shows up as an error instead of silently doing nothing.
+fn unrecognized_message(text: &str) -> ServerMessage {
+    let kind = serde_json::from_str::<serde_json::Value>(text)
+        .ok()
+        .and_then(|v| v.get("type")?.as_str().map(str::to_string));
+    let message = match kind {
+        Some(kind) => format!("Unrecognized server message \"{}\"; update your client?", kind),
+        None => "Unrecognized server message; update your client?".to_string(),
+    };
+    ServerMessage::Error {
+        message,
+        req_id: None,
+    }
+}
+
+/// Where network calls go: the real server, or the scripted mock behind
+/// `--offline`. Chosen once at startup; nothing above it can tell which.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Online,
+    Offline,
+}
+
+impl Backend {
+    /// Returns true if we should use dev-mode shortcuts (silent auth, no token persistence).
+    pub fn is_local(self) -> bool {
+        match self {
+            Backend::Online => is_local_server(),
+            Backend::Offline => true,
+        }
+    }
+
+    /// Connect with the given auth token
+    pub async fn connect(self, token: &str) -> Result<NetworkClient, NetError> {
+        match self {
+            Backend::Online => NetworkClient::connect(token).await,
+            Backend::Offline => Ok(offline::connect()),
+        }
+    }
+
+    /// Dev mode: authenticate and connect in one shot (no user interaction).
+    /// Returns (client, username, token).
+    pub async fn dev_auth_and_connect(self) -> Result<(NetworkClient, String, String), NetError> {
+        match self {
+            Backend::Online => NetworkClient::dev_auth_and_connect().await,
+            Backend::Offline => {
+                let name = offline::USERNAME.to_string();
+                Ok((offline::connect(), name.clone(), name))
+            }
+        }
+    }
+
+    pub async fn fetch_leaderboard(self) -> Result<Vec<LeaderboardEntry>, NetError> {
+        match self {
+            Backend::Online => NetworkClient::fetch_leaderboard().await,
+            Backend::Offline => Ok(offline::leaderboard()),
+        }
+    }
+
+    pub async fn fetch_me(self, token: &str) -> Result<MeResponse, NetError> {
+        match self {
+            Backend::Online => NetworkClient::fetch_me(token).await,
+            Backend::Offline => Ok(offline::me()),
+        }
+    }
+
+    pub async fn fetch_rating_history(self, username: &str) -> Result<Vec<RatingPoint>, NetError> {
+        match self {
+            Backend::Online => NetworkClient::fetch_rating_history(username).await,
+            Backend::Offline => Ok(offline::rating_history()),
+        }
+    }
+
+    pub async fn fetch_head_to_head(
+        self,
+        user_a: &str,
+        user_b: &str,
+    ) -> Result<HeadToHead, NetError> {
+        match self {
+            Backend::Online => NetworkClient::fetch_head_to_head(user_a, user_b).await,
+            Backend::Offline => Ok(offline::head_to_head()),
+        }
+    }
+
+    pub async fn record_solo(self, token: &str, result: &SoloResult) -> Result<(), NetError> {
+        match self {
+            Backend::Online => NetworkClient::record_solo(token, result).await,
+            Backend::Offline => Ok(()),
+        }
+    }
+
+    pub async fn fetch_solo_stats(self, token: &str) -> Result<SoloStats, NetError> {
+        match self {
+            Backend::Online => NetworkClient::fetch_solo_stats(token).await,
+            Backend::Offline => Ok(offline::solo_stats()),
+        }
+    }
+
+    /// The saved login, if there is one to use
+    pub fn load_token(self) -> Option<(String, String)> {
+        match self {
+            Backend::Online => NetworkClient::load_token(),
+            Backend::Offline => None,
+        }
+    }
+
+    /// A still-usable login that was left half done, if there is one to use
+    pub fn load_pending_auth(self) -> Option<PendingAuth> {
+        match self {
+            Backend::Online => NetworkClient::load_pending_auth(),
+            Backend::Offline => None,
+        }
+    }
+}
+
 pub struct NetworkClient {
     pub sender: mpsc::UnboundedSender<ClientMessage>,
     pub receiver: mpsc::UnboundedReceiver<ServerMessage>,
+    /// Tasks pumping the connection, stopped when the client is dropped
+    pub(super) tasks: Vec<JoinHandle<()>>,
+    /// Where sent messages are recorded for the debug overlay, once attached
+    pub(super) trace: Option<NetTrace>,
+}
+
+impl Drop for NetworkClient {
+    /// Dropping `sender` ends the sender task, which closes the WebSocket;
+    /// the server's reply then ends the receiver. Anything still running
+    /// after `CLOSE_GRACE` (or right away, outside a runtime) is aborted.
+    fn drop(&mut self) {
+        let tasks = std::mem::take(&mut self.tasks);
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tasks.iter().for_each(JoinHandle::abort);
+            return;
+        };
+        runtime.spawn(async move {
+            let aborts: Vec<_> = tasks.iter().map(JoinHandle::abort_handle).collect();
+            let finished = futures_util::future::join_all(tasks);
+            if tokio::time::timeout(CLOSE_GRACE, finished).await.is_err() {
+                aborts.iter().for_each(|task| task.abort());
+            }
+        });
+    }
 }
 
 impl NetworkClient {
     /// Connect to the server via WebSocket with the given auth token
-    pub async fn connect(token: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/ws?token={}", server_url(), token);
+    async fn connect(token: &str) -> Result<Self, NetError> {
+        Self::connect_url(&format!("{}/ws?token={}", server_url(), token)).await
+    }
 
-        // Build a rustls config that only advertises HTTP/1.1 in ALPN.
-        // Cloudflare/Render negotiate HTTP/2 by default, which breaks
-        // WebSocket upgrade (requires HTTP/1.1).
+    async fn connect_url(url: &str) -> Result<Self, NetError> {
         let connector = if url.starts_with("wss://") {
-            let roots = rustls::RootCertStore::from_iter(
-                webpki_roots::TLS_SERVER_ROOTS.iter().cloned(),
-            );
-            let config = rustls::ClientConfig::builder()
-                .with_root_certificates(roots)
-                .with_no_client_auth();
-            // config.alpn_protocols is empty by default = no ALPN = HTTP/1.1
-            Some(tokio_tungstenite::Connector::Rustls(Arc::new(config)))
+            Some(tokio_tungstenite::Connector::Rustls(Arc::new(
+                tls::ws_client_config(),
+            )))
         } else {
             None
         };
 
         let (ws_stream, _) = tokio::time::timeout(
-            WS_CONNECT_TIMEOUT,
-            tokio_tungstenite::connect_async_tls_with_config(&url, None, false, connector),
+            connect_timeout(),
+            tokio_tungstenite::connect_async_tls_with_config(url, None, false, connector),
         )
         .await
-        .map_err(|_| "Connection timed out — server may be starting up, try again")??;
+        .map_err(|_| NetError::Timeout)??;
         let (mut ws_sink, mut ws_stream_rx) = ws_stream.split();
 
         let (client_tx, mut client_rx) = mpsc::unbounded_channel::<ClientMessage>();
         let (server_tx, server_rx) = mpsc::unbounded_channel::<ServerMessage>();
 
-        // Sender task: forward client messages to WebSocket
-        tokio::spawn(async move {
+        // Sender task: forward client messages to WebSocket, then close it
+        // once the client is dropped
+        let sender_task = tokio::spawn(async move {
             while let Some(msg) = client_rx.recv().await {
                 let json = serde_json::to_string(&msg).unwrap();
                 if ws_sink.send(Message::Text(json.into())).await.is_err() {
                     break;
                 }
             }
+            let _ = ws_sink.close().await;
         });
 
         // Receiver task: forward WebSocket messages to channel
-        tokio::spawn(async move {
+        let receiver_task = tokio::spawn(async move {
             while let Some(Ok(msg)) = ws_stream_rx.next().await {
                 match msg {
                     Message::Text(text) => {
-                        if let Ok(server_msg) = serde_json::from_str::<ServerMessage>(&text) {
-                            if server_tx.send(server_msg).is_err() {
-                                break;
-                            }
+                        let server_msg = serde_json::from_str::<ServerMessage>(&text)
+                            .unwrap_or_else(|_| unrecognized_message(&text));
+                        if server_tx.send(server_msg).is_err() {
+                            break;
                         }
                     }
                     Message::Close(_) => break,
@@ -152,36 +356,46 @@ impl NetworkClient {
         Ok(Self {
             sender: client_tx,
             receiver: server_rx,
+            tasks: vec![sender_task, receiver_task],
+            trace: None,
         })
     }
 
-    pub fn send(&self, msg: ClientMessage) {
-        let _ = self.sender.send(msg);
+    /// Hand `msg` to the connection. Returns false if the connection has
+    /// already closed, so it went nowhere.
+    pub fn send(&self, msg: ClientMessage) -> bool {
+        if let Some(trace) = &self.trace {
+            trace.record(TracedMessage::Sent(msg.clone()));
+        }
+        self.sender.send(msg).is_ok()
+    }
+
+    /// Record every message sent from now on into `trace`
+    pub fn attach_trace(&mut self, trace: NetTrace) {
+        self.trace = Some(trace);
     }
 
     /// Dev mode: authenticate and connect in one shot (no user interaction).
-    /// Returns (client, username).
-    pub async fn dev_auth_and_connect(
-    ) -> Result<(Self, String), Box<dyn std::error::Error + Send + Sync>> {
+    /// Returns (client, username, token).
+    async fn dev_auth_and_connect(
+    ) -> Result<(Self, String, String), NetError> {
         let resp = Self::start_device_auth().await?;
         let poll = Self::poll_auth(&resp.user_code).await?;
         match poll {
             AuthPollResponse::Complete { token, username } => {
                 let client = Self::connect(&token).await?;
-                Ok((client, username))
+                Ok((client, username, token))
             }
-            _ => Err("Dev auth failed".into()),
+            _ => Err(NetError::Protocol("Dev auth failed".to_string())),
         }
     }
 
     /// Start the GitHub device auth flow
     pub async fn start_device_auth(
-    ) -> Result<DeviceAuthResponse, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<DeviceAuthResponse, NetError> {
         let url = format!("{}/auth/device", http_base_url());
-        let client = reqwest::Client::builder()
-            .timeout(HTTP_TIMEOUT)
-            .build()?;
-        let resp = client.post(&url).send().await?;
+        let client = http_client()?;
+        let resp = client.post(&url).send().await?.error_for_status()?;
         let body = resp.json::<DeviceAuthResponse>().await?;
         Ok(body)
     }
@@ -189,45 +403,125 @@ impl NetworkClient {
     /// Poll for auth completion
     pub async fn poll_auth(
         user_code: &str,
-    ) -> Result<AuthPollResponse, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<AuthPollResponse, NetError> {
         let url = format!("{}/auth/poll", http_base_url());
-        let client = reqwest::Client::builder()
-            .timeout(HTTP_TIMEOUT)
-            .build()?;
+        let client = http_client()?;
         let resp = client
             .post(&url)
             .json(&serde_json::json!({ "user_code": user_code }))
             .send()
-            .await?;
+            .await?
+            .error_for_status()?;
         let body = resp.json::<AuthPollResponse>().await?;
         Ok(body)
     }
 
+    /// Tell the server to forget a device code we've stopped polling
+    pub async fn cancel_device_auth(user_code: &str) -> Result<(), NetError> {
+        let url = format!("{}/auth/cancel", http_base_url());
+        let client = http_client()?;
+        client
+            .post(&url)
+            .json(&serde_json::json!({ "user_code": user_code }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
     /// Fetch leaderboard
-    pub async fn fetch_leaderboard(
-    ) -> Result<Vec<LeaderboardEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn fetch_leaderboard(
+    ) -> Result<Vec<LeaderboardEntry>, NetError> {
         let url = format!("{}/leaderboard", http_base_url());
-        let client = reqwest::Client::builder()
-            .timeout(HTTP_TIMEOUT)
-            .build()?;
-        let resp = client.get(&url).send().await?;
+        let client = http_client()?;
+        let resp = client.get(&url).send().await?.error_for_status()?;
         let entries = resp.json::<Vec<LeaderboardEntry>>().await?;
         Ok(entries)
     }
 
+    /// Fetch the logged-in player's own profile and rank
+    async fn fetch_me(
+        token: &str,
+    ) -> Result<MeResponse, NetError> {
+        let url = format!("{}/me", http_base_url());
+        let client = http_client()?;
+        let resp = client
+            .get(&url)
+            .query(&[("token", token)])
+            .send()
+            .await?
+            .error_for_status()?;
+        let me = resp.json::<MeResponse>().await?;
+        Ok(me)
+    }
+
     /// Fetch player profile
-    pub async fn fetch_profile(
+    async fn fetch_profile(
         username: &str,
-    ) -> Result<PlayerProfile, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<PlayerProfile, NetError> {
         let url = format!("{}/profile/{}", http_base_url(), username);
-        let client = reqwest::Client::builder()
-            .timeout(HTTP_TIMEOUT)
-            .build()?;
-        let resp = client.get(&url).send().await?;
+        let client = http_client()?;
+        let resp = client.get(&url).send().await?.error_for_status()?;
         let profile = resp.json::<PlayerProfile>().await?;
         Ok(profile)
     }
 
+    /// Fetch a player's rating after each of their matches, oldest first
+    async fn fetch_rating_history(
+        username: &str,
+    ) -> Result<Vec<RatingPoint>, NetError> {
+        let url = format!("{}/profile/{}/rating-history", http_base_url(), username);
+        let client = http_client()?;
+        let resp = client.get(&url).send().await?.error_for_status()?;
+        let history = resp.json::<Vec<RatingPoint>>().await?;
+        Ok(history)
+    }
+
+    /// Results between two players; `a_wins` are `user_a`'s
+    async fn fetch_head_to_head(
+        user_a: &str,
+        user_b: &str,
+    ) -> Result<HeadToHead, NetError> {
+        let url = format!("{}/h2h/{}/{}", http_base_url(), user_a, user_b);
+        let client = http_client()?;
+        let resp = client.get(&url).send().await?.error_for_status()?;
+        let h2h = resp.json::<HeadToHead>().await?;
+        Ok(h2h)
+    }
+
+    /// Record a finished single-player puzzle against the logged-in player
+    async fn record_solo(
+        token: &str,
+        result: &SoloResult,
+    ) -> Result<(), NetError> {
+        let url = format!("{}/solo/record", http_base_url());
+        let client = http_client()?;
+        client
+            .post(&url)
+            .query(&[("token", token)])
+            .json(result)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Fetch the logged-in player's single-player results from every machine
+    async fn fetch_solo_stats(
+        token: &str,
+    ) -> Result<SoloStats, NetError> {
+        let url = format!("{}/solo/stats", http_base_url());
+        let client = http_client()?;
+        let resp = client
+            .get(&url)
+            .query(&[("token", token)])
+            .send()
+            .await?
+            .error_for_status()?;
+        let stats = resp.json::<SoloStats>().await?;
+        Ok(stats)
+    }
+
     /// Save auth token to disk (skipped for local dev servers)
     pub fn save_token(token: &str, username: &str) -> std::io::Result<()> {
         if is_local_server() {
@@ -251,7 +545,7 @@ impl NetworkClient {
     }
 
     /// Load saved auth token from disk (skipped for local dev servers)
-    pub fn load_token() -> Option<(String, String)> {
+    fn load_token() -> Option<(String, String)> {
         if is_local_server() {
             return None;
         }
@@ -260,4 +554,98 @@ impl NetworkClient {
         let auth: AuthData = serde_json::from_str(&data).ok()?;
         Some((auth.token, auth.username))
     }
+
+    /// Remember an in-progress device auth (skipped for local dev servers)
+    pub fn save_pending_auth(pending: &PendingAuth) -> std::io::Result<()> {
+        if is_local_server() {
+            return Ok(());
+        }
+        let path = pending_auth_file_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(pending).unwrap();
+        std::fs::write(path, json)
+    }
+
+    /// Forget the in-progress device auth (on success, expiry or cancel)
+    pub fn clear_pending_auth() {
+        let _ = std::fs::remove_file(pending_auth_file_path());
+    }
+
+    /// Load a still-usable pending device auth; stale ones are deleted.
+    fn load_pending_auth() -> Option<PendingAuth> {
+        if is_local_server() {
+            return None;
+        }
+        let data = std::fs::read_to_string(pending_auth_file_path()).ok()?;
+        let pending: Option<PendingAuth> = serde_json::from_str(&data).ok();
+        match pending {
+            Some(p) if !p.is_stale(unix_now()) => Some(p),
+            _ => {
+                Self::clear_pending_auth();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    #[test]
+    fn pending_auths_go_stale_at_their_deadline_or_an_hour_in() {
+        let pending = PendingAuth {
+            user_code: "ABCD-1234".to_string(),
+            verification_uri: "https://github.com/login/device".to_string(),
+            interval: 5,
+            started_at: 1_000,
+            expires_at: 1_900,
+        };
+        assert!(!pending.is_stale(1_899));
+        assert!(pending.is_stale(1_900));
+
+        // A deadline too far out to trust still runs out after the hour
+        let pending = PendingAuth { expires_at: u64::MAX, ..pending };
+        assert!(!pending.is_stale(1_000 + PENDING_AUTH_MAX_AGE_SECS));
+        assert!(pending.is_stale(1_000 + PENDING_AUTH_MAX_AGE_SECS + 1));
+    }
+
+    #[tokio::test]
+    async fn dropped_clients_close_their_sockets_and_tasks() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let closed = Arc::new(AtomicUsize::new(0));
+        let server_closed = closed.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let closed = server_closed.clone();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    while let Some(Ok(msg)) = ws.next().await {
+                        if msg.is_close() {
+                            closed.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                });
+            }
+        });
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let baseline = metrics.num_alive_tasks();
+
+        for _ in 0..20 {
+            let client = NetworkClient::connect_url(&url).await.unwrap();
+            assert_eq!(client.tasks.len(), 2);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while metrics.num_alive_tasks() > baseline || closed.load(Ordering::SeqCst) < 20 {
+            assert!(Instant::now() < deadline, "client tasks or sockets left open");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(closed.load(Ordering::SeqCst), 20);
+    }
 }
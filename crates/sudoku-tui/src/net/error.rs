@@ -0,0 +1,103 @@
+use std::fmt;
+
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+/// Shown when the server refuses the upgrade because it hit its connection cap.
+pub const SERVER_FULL_MESSAGE: &str = "Server is full, try again shortly";
+
+/// Why a network call failed, so the app can pick a recovery (retry, re-auth,
+/// wait) instead of just printing a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetError {
+    /// The server didn't answer in time (often a cold start)
+    Timeout,
+    /// The token was rejected; the user has to log in again
+    Unauthorized,
+    /// The server is at its connection cap; the token is still fine
+    ServerFull,
+    /// The server answered, but not with anything we could use
+    Protocol(String),
+    /// The server couldn't be reached at all
+    Io(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Timeout => {
+                write!(f, "Connection timed out — server may be starting up, try again")
+            }
+            NetError::Unauthorized => write!(f, "Session expired or invalid"),
+            NetError::ServerFull => write!(f, "{}", SERVER_FULL_MESSAGE),
+            NetError::Protocol(msg) | NetError::Io(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+fn from_status(status: StatusCode) -> NetError {
+    match status {
+        StatusCode::SERVICE_UNAVAILABLE => NetError::ServerFull,
+        StatusCode::UNAUTHORIZED => NetError::Unauthorized,
+        status => NetError::Protocol(format!("Server rejected request ({})", status)),
+    }
+}
+
+/// The server answers a refused WebSocket upgrade with a plain HTTP status,
+/// so inspect it instead of surfacing tungstenite's generic error text.
+impl From<WsError> for NetError {
+    fn from(err: WsError) -> Self {
+        match err {
+            WsError::Http(resp) => from_status(resp.status()),
+            WsError::Io(e) if e.kind() == std::io::ErrorKind::TimedOut => NetError::Timeout,
+            WsError::Io(e) => NetError::Io(e.to_string()),
+            WsError::Tls(e) => NetError::Io(e.to_string()),
+            other => NetError::Protocol(other.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for NetError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return NetError::Timeout;
+        }
+        match err.status() {
+            // reqwest and tungstenite may pull in different http versions
+            Some(status) => StatusCode::from_u16(status.as_u16())
+                .map(from_status)
+                .unwrap_or_else(|_| NetError::Protocol(err.to_string())),
+            None if err.is_decode() => NetError::Protocol(err.to_string()),
+            None => NetError::Io(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::http::Response;
+
+    fn http_error(status: u16) -> WsError {
+        WsError::Http(Response::builder().status(status).body(None).unwrap())
+    }
+
+    #[test]
+    fn full_server_maps_to_friendly_message() {
+        let err = NetError::from(http_error(503));
+        assert_eq!(err, NetError::ServerFull);
+        assert_eq!(err.to_string(), SERVER_FULL_MESSAGE);
+    }
+
+    #[test]
+    fn other_statuses_are_distinguished() {
+        assert_eq!(NetError::from(http_error(401)), NetError::Unauthorized);
+        assert_eq!(
+            NetError::from(http_error(401)).to_string(),
+            "Session expired or invalid"
+        );
+        assert!(NetError::from(http_error(500)).to_string().contains("500"));
+    }
+}
@@ -1,3 +1,9 @@
 pub mod client;
+pub mod error;
+pub mod offline;
+pub mod outbox;
+pub mod tls;
+pub mod trace;
 
-pub use client::NetworkClient;
+pub use client::{Backend, NetworkClient};
+pub use error::NetError;
@@ -0,0 +1,529 @@
+//! `--offline` mode: a scripted stand-in for the server, so the multiplayer
+//! screens can be worked on without running the backend.
+//!
+//! The mock speaks the same `ClientMessage`/`ServerMessage` channels as a real
+//! connection, so nothing above `Backend` can tell the difference.
+
+use std::time::{Duration, Instant};
+
+use sudoku_core::elo::elo_change;
+use sudoku_core::protocol::{
+    ClientMessage, GameMode, HeadToHead, LeaderboardEntry, MeResponse, PlayerProfile, RatingPoint,
+    ServerMessage, SoloDifficultyStats, SoloStats,
+};
+use sudoku_core::puzzle::{generate_puzzle, GENERATOR_VERSION};
+use sudoku_core::{Board, Cell, Difficulty, SolutionBoard};
+use tokio::sync::mpsc;
+
+use super::NetworkClient;
+
+/// Name the offline player is "logged in" as.
+pub const USERNAME: &str = "offline_player";
+const STARTING_RATING: i32 = 1200;
+const OPPONENT_NAME: &str = "scripted_bot";
+const OPPONENT_RATING: i32 = 1250;
+const ROOM_CODE: &str = "OFFLIN";
+/// The scripted opponent fills one cell this often.
+const OPPONENT_MOVE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// A `NetworkClient` wired to the in-process mock server.
+pub fn connect() -> NetworkClient {
+    let (client_tx, client_rx) = mpsc::unbounded_channel::<ClientMessage>();
+    let (server_tx, server_rx) = mpsc::unbounded_channel::<ServerMessage>();
+    NetworkClient {
+        sender: client_tx,
+        receiver: server_rx,
+        tasks: vec![tokio::spawn(run(client_rx, server_tx))],
+        trace: None,
+    }
+}
+
+// ── Canned REST data ────────────────────────────────────────────────────
+
+pub fn leaderboard() -> Vec<LeaderboardEntry> {
+    let rows = [
+        ("grid_wizard", 1642, 48, 12),
+        ("naked_pair", 1518, 31, 17),
+        (OPPONENT_NAME, OPPONENT_RATING, 20, 18),
+        (USERNAME, STARTING_RATING, 3, 3),
+        ("pencil_pusher", 1104, 6, 15),
+    ];
+    rows.iter()
+        .enumerate()
+        .map(|(i, &(username, rating, wins, losses))| LeaderboardEntry {
+            rank: i as u32 + 1,
+            username: username.to_string(),
+            rating,
+            wins,
+            losses,
+            favorite_difficulty: Some(Difficulty::Medium),
+            favorite_mode: Some(GameMode::Race),
+        })
+        .collect()
+}
+
+pub fn profile(username: &str) -> Option<PlayerProfile> {
+    leaderboard()
+        .into_iter()
+        .find(|e| e.username == username)
+        .map(|e| PlayerProfile {
+            username: e.username,
+            avatar_url: String::new(),
+            rating: e.rating,
+            wins: e.wins,
+            losses: e.losses,
+            favorite_difficulty: e.favorite_difficulty,
+            favorite_mode: e.favorite_mode,
+        })
+}
+
+pub fn me() -> MeResponse {
+    let rank = leaderboard()
+        .iter()
+        .find(|e| e.username == USERNAME)
+        .map(|e| e.rank)
+        .unwrap_or(0);
+    MeResponse {
+        profile: profile(USERNAME).expect("offline player is on the canned leaderboard"),
+        rank,
+    }
+}
+
+pub fn rating_history() -> Vec<RatingPoint> {
+    [1216, 1201, 1185, 1199, 1214, 1200]
+        .iter()
+        .enumerate()
+        .map(|(i, &rating)| RatingPoint {
+            timestamp: format!("2025-01-{:02} 12:00:00", i + 1),
+            rating,
+            seed: None,
+            generator: GENERATOR_VERSION,
+        })
+        .collect()
+}
+
+pub fn head_to_head() -> HeadToHead {
+    HeadToHead {
+        a_wins: 3,
+        b_wins: 2,
+        games: 5,
+        avg_duration_secs: 312,
+    }
+}
+
+pub fn solo_stats() -> SoloStats {
+    SoloStats {
+        solved: 7,
+        flawless: 3,
+        by_difficulty: vec![
+            SoloDifficultyStats {
+                difficulty: Difficulty::Easy,
+                solved: 5,
+                flawless: 3,
+                best_secs: 184,
+                avg_secs: 251,
+            },
+            SoloDifficultyStats {
+                difficulty: Difficulty::Medium,
+                solved: 2,
+                flawless: 0,
+                best_secs: 402,
+                avg_secs: 437,
+            },
+        ],
+    }
+}
+
+// ── Mock server ─────────────────────────────────────────────────────────
+
+struct MockRoom {
+    mode: GameMode,
+    difficulty: Difficulty,
+    givens: Board,
+    solution: SolutionBoard,
+    /// Race: our board. Shared: the one board both sides play on.
+    mine: Board,
+    /// Race only: the scripted opponent's board.
+    opponent: Board,
+    /// Shared only: cells the scripted opponent claimed.
+    opponent_cells: Vec<(usize, usize)>,
+    started_at: Instant,
+}
+
+impl MockRoom {
+    fn new(mode: GameMode, difficulty: Difficulty) -> Self {
+        let (givens, solution) = generate_puzzle(difficulty);
+        MockRoom {
+            mode,
+            difficulty,
+            givens,
+            solution,
+            mine: givens,
+            opponent: givens,
+            opponent_cells: Vec::new(),
+            started_at: Instant::now(),
+        }
+    }
+
+    fn match_started(&self) -> ServerMessage {
+        ServerMessage::MatchStarted {
+            mode: self.mode,
+            difficulty: self.difficulty,
+            board: to_wire(&self.givens),
+            opponent_name: OPPONENT_NAME.to_string(),
+            opponent_rating: OPPONENT_RATING,
+            code: ROOM_CODE.to_string(),
+            handicap: None,
+        }
+    }
+
+    fn is_opponent_cell(&self, row: usize, col: usize) -> bool {
+        self.opponent_cells.contains(&(row, col))
+    }
+
+    /// Shared mode scores: (ours, opponent's) correct cells.
+    fn shared_scores(&self) -> (u32, u32) {
+        let mut mine = 0;
+        let mut theirs = 0;
+        for r in 0..9 {
+            for c in 0..9 {
+                if self.mine[r][c] == Cell::UserInput(self.solution[r][c]) {
+                    if self.is_opponent_cell(r, c) {
+                        theirs += 1;
+                    } else {
+                        mine += 1;
+                    }
+                }
+            }
+        }
+        (mine, theirs)
+    }
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<ClientMessage>, tx: mpsc::UnboundedSender<ServerMessage>) {
+    let mut room: Option<MockRoom> = None;
+    let mut rating = STARTING_RATING;
+    let mut ticker = tokio::time::interval(OPPONENT_MOVE_INTERVAL);
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(msg) => handle(msg, &mut room, &mut rating, &tx),
+                None => break,
+            },
+            _ = ticker.tick() => {
+                if let Some(r) = room.as_mut()
+                    && opponent_move(r, &tx)
+                {
+                    end_game(&mut room, &mut rating, false, &tx);
+                }
+            }
+        }
+    }
+}
+
+fn handle(
+    msg: ClientMessage,
+    room: &mut Option<MockRoom>,
+    rating: &mut i32,
+    tx: &mpsc::UnboundedSender<ServerMessage>,
+) {
+    match msg {
+        ClientMessage::Auth { .. } => {
+            let _ = tx.send(ServerMessage::AuthOk {
+                username: USERNAME.to_string(),
+                rating: *rating,
+            });
+        }
+        ClientMessage::CreateRoom {
+            mode,
+            difficulty,
+            req_id,
+            ..
+        } => {
+            let _ = tx.send(ServerMessage::RoomCreated {
+                code: ROOM_CODE.to_string(),
+                req_id,
+            });
+            let _ = tx.send(ServerMessage::WaitingForOpponent);
+            start(room, mode, difficulty, tx);
+        }
+        ClientMessage::JoinRoom { .. } => start(room, GameMode::Race, Difficulty::Easy, tx),
+        ClientMessage::QuickMatch { mode, difficulty } => {
+            let _ = tx.send(ServerMessage::WaitingForOpponent);
+            start(room, mode, difficulty, tx);
+        }
+        ClientMessage::PlaceNumber {
+            row,
+            col,
+            value,
+            req_id,
+        } => {
+            let Some(r) = room.as_mut() else { return };
+            if row >= 9 || col >= 9 || !(1..=9).contains(&value) || r.givens[row][col].is_given() {
+                let _ = tx.send(ServerMessage::MoveRejected {
+                    row,
+                    col,
+                    reason: "Invalid move".into(),
+                    req_id,
+                });
+                return;
+            }
+            if r.mode == GameMode::Shared && r.is_opponent_cell(row, col) {
+                let _ = tx.send(ServerMessage::MoveRejected {
+                    row,
+                    col,
+                    reason: "Cell already claimed".into(),
+                    req_id,
+                });
+                return;
+            }
+            r.mine[row][col] = Cell::UserInput(value);
+            let _ = tx.send(ServerMessage::MoveAccepted {
+                row,
+                col,
+                value,
+                req_id,
+            });
+
+            if r.mine.iter().flatten().all(|cell| cell.value().is_some()) {
+                let wrong = wrong_cells(&r.mine, &r.solution);
+                if wrong == 0 {
+                    let won = match r.mode {
+                        GameMode::Race => true,
+                        GameMode::Shared => {
+                            let (mine, theirs) = r.shared_scores();
+                            mine >= theirs
+                        }
+                    };
+                    end_game(room, rating, won, tx);
+                } else {
+                    let _ = tx.send(ServerMessage::BoardIncomplete { wrong_cells: wrong });
+                }
+            }
+        }
+        ClientMessage::EraseNumber { row, col } => {
+            if let Some(r) = room.as_mut()
+                && row < 9
+                && col < 9
+                && !r.givens[row][col].is_given()
+                && !r.is_opponent_cell(row, col)
+            {
+                r.mine[row][col] = Cell::Empty;
+            }
+        }
+        ClientMessage::RequestSync => {
+            if let Some(r) = room.as_ref() {
+                let owners = (0..9)
+                    .map(|row| {
+                        (0..9)
+                            .map(|col| match r.mine[row][col] {
+                                Cell::UserInput(_) if r.is_opponent_cell(row, col) => 2,
+                                Cell::UserInput(_) => 1,
+                                _ => 0,
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let _ = tx.send(ServerMessage::BoardSync {
+                    board: values_to_wire(&r.mine),
+                    owners,
+                    elapsed_secs: r.started_at.elapsed().as_secs(),
+                });
+            }
+        }
+        ClientMessage::Forfeit => end_game(room, rating, false, tx),
+        ClientMessage::Rematch { difficulty: wanted } => {
+            let (mode, difficulty) = room
+                .as_ref()
+                .map(|r| (r.mode, r.difficulty))
+                .unwrap_or((GameMode::Race, Difficulty::Easy));
+            start(room, mode, wanted.unwrap_or(difficulty), tx);
+        }
+        ClientMessage::Ping => {
+            let _ = tx.send(ServerMessage::Pong);
+        }
+        ClientMessage::Spectate { .. } | ClientMessage::SpectateRandom => {
+            let _ = tx.send(ServerMessage::Error {
+                message: "Nothing to watch offline".into(),
+                req_id: None,
+            });
+        }
+        // The mock opponent joins straight away, so there's no wait to adjust
+        ClientMessage::UpdateCursor { .. }
+        | ClientMessage::UpdateRoomSettings { .. }
+        | ClientMessage::ReportPuzzle { .. }
+        | ClientMessage::StopSpectating => {}
+    }
+}
+
+fn start(
+    room: &mut Option<MockRoom>,
+    mode: GameMode,
+    difficulty: Difficulty,
+    tx: &mpsc::UnboundedSender<ServerMessage>,
+) {
+    let new_room = MockRoom::new(mode, difficulty);
+    let _ = tx.send(new_room.match_started());
+    *room = Some(new_room);
+}
+
+/// Fill the opponent's next cell. Race mode works top-down, shared mode
+/// bottom-up so the two players meet in the middle. Returns true once the
+/// opponent has finished the puzzle.
+fn opponent_move(room: &mut MockRoom, tx: &mpsc::UnboundedSender<ServerMessage>) -> bool {
+    match room.mode {
+        GameMode::Race => {
+            let next = (0..81)
+                .map(|i| (i / 9, i % 9))
+                .find(|&(r, c)| room.opponent[r][c] == Cell::Empty);
+            let Some((r, c)) = next else { return false };
+            room.opponent[r][c] = Cell::UserInput(room.solution[r][c]);
+            let filled = room
+                .opponent
+                .iter()
+                .flatten()
+                .filter(|cell| matches!(cell, Cell::UserInput(_)))
+                .count() as u32;
+            let _ = tx.send(ServerMessage::OpponentProgress {
+                filled_count: filled,
+                momentum: 60.0 / OPPONENT_MOVE_INTERVAL.as_secs_f32(),
+            });
+            room.opponent.iter().flatten().all(|cell| cell.value().is_some())
+        }
+        GameMode::Shared => {
+            let next = (0..81)
+                .rev()
+                .map(|i| (i / 9, i % 9))
+                .find(|&(r, c)| room.mine[r][c] == Cell::Empty);
+            let Some((r, c)) = next else { return false };
+            let value = room.solution[r][c];
+            room.mine[r][c] = Cell::UserInput(value);
+            room.opponent_cells.push((r, c));
+            let _ = tx.send(ServerMessage::OpponentCursor { row: r, col: c });
+            let _ = tx.send(ServerMessage::OpponentPlaced { row: r, col: c, value });
+            if room.mine.iter().flatten().any(|cell| cell.value().is_none()) {
+                return false;
+            }
+            let (mine, theirs) = room.shared_scores();
+            wrong_cells(&room.mine, &room.solution) == 0 && theirs > mine
+        }
+    }
+}
+
+fn end_game(
+    room: &mut Option<MockRoom>,
+    rating: &mut i32,
+    won: bool,
+    tx: &mpsc::UnboundedSender<ServerMessage>,
+) {
+    let Some(r) = room.take() else { return };
+    let (your_score, opponent_score) = match r.mode {
+        GameMode::Race => (
+            correct_count(&r.mine, &r.solution),
+            correct_count(&r.opponent, &r.solution),
+        ),
+        GameMode::Shared => r.shared_scores(),
+    };
+    let change = elo_change(*rating, OPPONENT_RATING, won);
+    *rating += change;
+    let _ = tx.send(ServerMessage::GameEnd {
+        won,
+        your_score,
+        opponent_score,
+        elo_change: change,
+        new_rating: *rating,
+        solution: Some(r.solution),
+        seed: None,
+    });
+    if r.mode == GameMode::Race {
+        let _ = tx.send(ServerMessage::FinalBoards {
+            mine: values_to_wire(&r.mine),
+            opponent: values_to_wire(&r.opponent),
+        });
+    }
+}
+
+fn correct_count(board: &Board, solution: &SolutionBoard) -> u32 {
+    (0..81)
+        .filter(|&i| board[i / 9][i % 9] == Cell::UserInput(solution[i / 9][i % 9]))
+        .count() as u32
+}
+
+fn wrong_cells(board: &Board, solution: &SolutionBoard) -> u32 {
+    (0..81)
+        .filter(|&i| match board[i / 9][i % 9] {
+            Cell::UserInput(v) => v != solution[i / 9][i % 9],
+            _ => false,
+        })
+        .count() as u32
+}
+
+/// Givens only, 0 elsewhere — the `MatchStarted` wire format.
+fn to_wire(board: &Board) -> Vec<Vec<u8>> {
+    board
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| if cell.is_given() { cell.value().unwrap_or(0) } else { 0 })
+                .collect()
+        })
+        .collect()
+}
+
+/// Every value, 0 for empty.
+fn values_to_wire(board: &Board) -> Vec<Vec<u8>> {
+    board
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.value().unwrap_or(0)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn solving_the_race_wins_against_the_script() {
+        let mut client = connect();
+        client.send(ClientMessage::QuickMatch {
+            mode: GameMode::Race,
+            difficulty: Difficulty::Easy,
+        });
+        assert!(matches!(client.receiver.recv().await, Some(ServerMessage::WaitingForOpponent)));
+        let Some(ServerMessage::MatchStarted { board, .. }) = client.receiver.recv().await else {
+            panic!("expected MatchStarted");
+        };
+
+        let mut grid = [[0u8; 9]; 9];
+        for (r, row) in board.iter().enumerate() {
+            grid[r].copy_from_slice(row);
+        }
+        let givens = grid;
+        assert!(sudoku_core::puzzle::solve(&mut grid));
+        for r in 0..9 {
+            for c in 0..9 {
+                if givens[r][c] == 0 {
+                    client.send(ClientMessage::PlaceNumber {
+                        row: r,
+                        col: c,
+                        value: grid[r][c],
+                        req_id: None,
+                    });
+                }
+            }
+        }
+
+        loop {
+            match client.receiver.recv().await {
+                Some(ServerMessage::GameEnd { won, elo_change, .. }) => {
+                    assert!(won);
+                    assert!(elo_change > 0);
+                    break;
+                }
+                Some(_) => {}
+                None => panic!("mock server hung up"),
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+
+use sudoku_core::protocol::ClientMessage;
+
+use super::NetworkClient;
+
+/// Moves kept while the connection is down; past this new ones are refused
+pub const OUTBOX_LEN: usize = 32;
+
+/// Placements and erasures made while the connection is down, sent once it's
+/// back so a blip doesn't lose them. Replaying one the server already has is
+/// safe: a repeated placement is simply acked again.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    queued: VecDeque<ClientMessage>,
+}
+
+impl Outbox {
+    /// Hold `msg` until `flush`. Returns false, dropping it, once the queue
+    /// is full.
+    pub fn push(&mut self, msg: ClientMessage) -> bool {
+        if self.queued.len() >= OUTBOX_LEN {
+            return false;
+        }
+        self.queued.push_back(msg);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Send everything held through `client`, oldest first
+    pub fn flush(&mut self, client: &NetworkClient) {
+        for msg in self.queued.drain(..) {
+            let _ = client.send(msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::offline;
+    use sudoku_core::protocol::{GameMode, ServerMessage};
+    use sudoku_core::Difficulty;
+
+    #[tokio::test]
+    async fn moves_made_offline_reach_the_server_after_a_flush() {
+        let mut client = offline::connect();
+        client.send(ClientMessage::QuickMatch {
+            mode: GameMode::Race,
+            difficulty: Difficulty::Easy,
+        });
+        let _ = client.receiver.recv().await;
+        let Some(ServerMessage::MatchStarted { board, .. }) = client.receiver.recv().await else {
+            panic!("expected MatchStarted");
+        };
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| board[r][c] == 0)
+            .unwrap();
+
+        let mut outbox = Outbox::default();
+        let place = ClientMessage::PlaceNumber { row, col, value: 1, req_id: Some(7) };
+        for _ in 0..OUTBOX_LEN {
+            assert!(outbox.push(place.clone()));
+        }
+        assert!(!outbox.push(place));
+        assert_eq!(outbox.len(), OUTBOX_LEN);
+
+        outbox.flush(&client);
+        assert_eq!(outbox.len(), 0);
+        loop {
+            match client.receiver.recv().await {
+                Some(ServerMessage::MoveAccepted { req_id, .. }) => {
+                    assert_eq!(req_id, Some(7));
+                    break;
+                }
+                Some(_) => {}
+                None => panic!("connection closed"),
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use sudoku_core::protocol::{ClientMessage, ServerMessage};
+
+/// Messages kept for the debug overlay; older ones drop off the front
+const TRACE_LEN: usize = 50;
+
+/// A message as it went over the wire. Kept as-is and only formatted when
+/// the overlay draws it, so tracing costs a clone per message.
+#[derive(Clone, Debug)]
+pub enum TracedMessage {
+    Sent(ClientMessage),
+    Received(ServerMessage),
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    /// Seconds since the trace was created
+    pub secs: f64,
+    pub message: TracedMessage,
+}
+
+impl TraceEntry {
+    pub fn arrow(&self) -> &'static str {
+        match self.message {
+            TracedMessage::Sent(_) => "→",
+            TracedMessage::Received(_) => "←",
+        }
+    }
+
+    /// One-line summary like `PlaceNumber row=3 col=4 value=5 req_id=7`.
+    /// Boards and other lists shrink to `[…]`.
+    pub fn summary(&self) -> String {
+        let value = match &self.message {
+            TracedMessage::Sent(msg) => serde_json::to_value(msg),
+            TracedMessage::Received(msg) => serde_json::to_value(msg),
+        };
+        let Ok(serde_json::Value::Object(fields)) = value else {
+            return "?".to_string();
+        };
+        let mut parts = Vec::with_capacity(fields.len());
+        if let Some(kind) = fields.get("type").and_then(|t| t.as_str()) {
+            parts.push(kind.to_string());
+        }
+        for (key, value) in fields.iter().filter(|(key, _)| *key != "type") {
+            let value = match value {
+                serde_json::Value::Array(_) => "[…]".to_string(),
+                serde_json::Value::String(s) => format!("{:?}", s),
+                other => other.to_string(),
+            };
+            parts.push(format!("{}={}", key, value));
+        }
+        parts.join(" ")
+    }
+}
+
+/// The last few network messages in both directions, for the debug overlay.
+/// `Game` owns one and records what arrives; the `NetworkClient` it's
+/// attached to records what goes out. Clones share the same buffer.
+#[derive(Clone, Debug)]
+pub struct NetTrace {
+    started: Instant,
+    entries: Arc<Mutex<VecDeque<TraceEntry>>>,
+}
+
+impl Default for NetTrace {
+    fn default() -> Self {
+        NetTrace {
+            started: Instant::now(),
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(TRACE_LEN))),
+        }
+    }
+}
+
+impl NetTrace {
+    pub fn record(&self, message: TracedMessage) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() == TRACE_LEN {
+            entries.pop_front();
+        }
+        entries.push_back(TraceEntry {
+            secs: self.started.elapsed().as_secs_f64(),
+            message,
+        });
+    }
+
+    /// The newest `n` entries, oldest first
+    pub fn recent(&self, n: usize) -> Vec<TraceEntry> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        entries.iter().skip(entries.len().saturating_sub(n)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_newest_messages_in_compact_form() {
+        let trace = NetTrace::default();
+        for row in 0..TRACE_LEN + 5 {
+            trace.record(TracedMessage::Sent(ClientMessage::PlaceNumber {
+                row,
+                col: 4,
+                value: 5,
+                req_id: Some(7),
+            }));
+        }
+        trace.record(TracedMessage::Received(ServerMessage::BoardSync {
+            board: vec![vec![0; 9]; 9],
+            owners: vec![vec![0; 9]; 9],
+            elapsed_secs: 0,
+        }));
+
+        let recent = trace.recent(TRACE_LEN * 2);
+        assert_eq!(recent.len(), TRACE_LEN);
+        assert_eq!(recent[0].summary(), "PlaceNumber col=4 req_id=7 row=6 value=5");
+        assert_eq!(recent[0].arrow(), "→");
+
+        let last = trace.recent(1);
+        assert_eq!(last[0].summary(), "BoardSync board=[…] elapsed_secs=0 owners=[…]");
+        assert_eq!(last[0].arrow(), "←");
+    }
+}
@@ -0,0 +1,105 @@
+/// Who put a pencil mark in its cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkOrigin {
+    /// Typed by the player in pencil mode
+    Manual,
+    /// Filled in from the cell's candidates
+    Auto,
+}
+
+/// The pencil marks in one cell, in digit order, each with its origin.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PencilMarks(Vec<(u8, MarkOrigin)>);
+
+impl PencilMarks {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn contains(&self, digit: u8) -> bool {
+        self.origin(digit).is_some()
+    }
+
+    pub fn origin(&self, digit: u8) -> Option<MarkOrigin> {
+        self.0.iter().find(|&&(d, _)| d == digit).map(|&(_, origin)| origin)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, MarkOrigin)> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn digits(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0.iter().map(|&(d, _)| d)
+    }
+
+    /// The cell's digit if it has exactly one mark
+    pub fn single(&self) -> Option<u8> {
+        match self.0[..] {
+            [(digit, _)] => Some(digit),
+            _ => None,
+        }
+    }
+
+    /// Add `digit` with `origin`, replacing the origin if it's already there.
+    pub fn insert(&mut self, digit: u8, origin: MarkOrigin) {
+        match self.0.binary_search_by_key(&digit, |&(d, _)| d) {
+            Ok(i) => self.0[i].1 = origin,
+            Err(i) => self.0.insert(i, (digit, origin)),
+        }
+    }
+
+    pub fn remove(&mut self, digit: u8) {
+        self.0.retain(|&(d, _)| d != digit);
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(u8) -> bool) {
+        self.0.retain(|&(d, _)| keep(d));
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The player's toggle: remove `digit`, or add it as their own mark.
+    pub fn toggle(&mut self, digit: u8) {
+        if self.contains(digit) {
+            self.remove(digit);
+        } else {
+            self.insert(digit, MarkOrigin::Manual);
+        }
+    }
+
+    /// Marks for `candidates`, keeping the origin of any digit already here
+    /// so a refill doesn't pass the player's notes off as automatic.
+    pub fn filled_from(&self, candidates: &[u8]) -> PencilMarks {
+        let mut filled = PencilMarks::default();
+        for &digit in candidates {
+            filled.insert(digit, self.origin(digit).unwrap_or(MarkOrigin::Auto));
+        }
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refilling_keeps_manual_marks_manual() {
+        let mut marks = PencilMarks::default();
+        marks.toggle(7);
+        marks.toggle(2);
+        assert_eq!(marks.digits().collect::<Vec<_>>(), [2, 7]);
+
+        let filled = marks.filled_from(&[1, 2, 9]);
+        assert_eq!(
+            filled.iter().collect::<Vec<_>>(),
+            [(1, MarkOrigin::Auto), (2, MarkOrigin::Manual), (9, MarkOrigin::Auto)]
+        );
+        assert_eq!(filled.single(), None);
+    }
+}
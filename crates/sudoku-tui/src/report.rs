@@ -0,0 +1,44 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sudoku_core::board::givens_string;
+
+use crate::game::Game;
+
+/// Reasons offered by the report prompt, picked with 1-3.
+pub const REPORT_REASONS: &[&str] = &["Multiple solutions", "No solution", "Something else is wrong"];
+
+fn report_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("reports.log")
+}
+
+/// Append the current puzzle to the local report log, one tab-separated line:
+/// unix time, difficulty, grid size, givens (`.` for blanks), reason.
+pub fn save_local_report(game: &Game, reason: &str) -> std::io::Result<PathBuf> {
+    let path = report_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}\t{}",
+        timestamp,
+        game.difficulty.label(),
+        game.grid.label(),
+        givens_string(&game.board, game.grid.size),
+        reason
+    )?;
+    Ok(path)
+}
@@ -0,0 +1,323 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use sudoku_core::protocol::{Handicap, ProgressGranularity};
+
+use crate::bot::BotLevel;
+use crate::hint::{HintConfig, HintTechnique};
+
+/// Where the cursor goes after a number is placed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AutoAdvance {
+    Off,
+    /// One cell right, on to the next row at the end of one
+    Right,
+    /// The next empty cell in reading order
+    NextEmpty,
+}
+
+impl AutoAdvance {
+    pub fn all() -> &'static [AutoAdvance] {
+        &[AutoAdvance::Off, AutoAdvance::Right, AutoAdvance::NextEmpty]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AutoAdvance::Off => "Off",
+            AutoAdvance::Right => "Move right",
+            AutoAdvance::NextEmpty => "Next empty cell",
+        }
+    }
+
+    /// The next option in `all()`, wrapping around.
+    pub fn next(&self) -> AutoAdvance {
+        let all = AutoAdvance::all();
+        let idx = all.iter().position(|a| a == self).unwrap_or(0);
+        all[(idx + 1) % all.len()]
+    }
+}
+
+/// Player preferences, persisted next to the auth token in the config dir.
+/// Unknown or missing fields fall back to their defaults so older files keep loading.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub hints: HintConfig,
+    /// Number of puzzles in a marathon run
+    pub marathon_length: usize,
+    /// Step the difficulty up after each marathon puzzle instead of keeping it fixed
+    pub marathon_ladder: bool,
+    /// Show "Almost there" in the status bar near the end of a clean board
+    pub almost_there: bool,
+    /// Handicap requested for rooms you create; lands on the higher-rated player
+    pub room_handicap: Option<Handicap>,
+    /// How much race progress rooms you create share; quarters only is fog
+    pub room_progress: ProgressGranularity,
+    /// Pace and accuracy of the local race bot
+    pub bot_level: BotLevel,
+    /// Mirror the playing screens: side panel on the left, grid on the right
+    pub swap_hands: bool,
+    /// Number the columns along the top of the grid and the rows down its side
+    pub coordinates: bool,
+    /// Show the side panel next to the grid; `i` while playing toggles it
+    pub show_info_panel: bool,
+    /// Colour your entries green or red by whether they match the solution.
+    /// Single player only.
+    pub instant_feedback: bool,
+    /// Keep a mistake on the count even after undoing the wrong placement
+    pub permanent_mistakes: bool,
+    /// Refuse a digit already in its row, column or box instead of placing it.
+    /// Wrong digits that clash with nothing still go in.
+    pub strict_placement: bool,
+    /// Move the cursor on by itself after each placement
+    pub auto_advance: AutoAdvance,
+    /// Draw the pencil marks you typed brighter than filled-in candidates
+    pub bright_manual_marks: bool,
+    /// Draw `[i]` and `[x]` instead of emoji and other symbols some
+    /// terminals can't show or draw two columns wide
+    pub ascii_symbols: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hints: HintConfig::default(),
+            marathon_length: 5,
+            marathon_ladder: false,
+            almost_there: true,
+            room_handicap: None,
+            room_progress: ProgressGranularity::Exact,
+            bot_level: BotLevel::Steady,
+            swap_hands: false,
+            coordinates: false,
+            show_info_panel: true,
+            instant_feedback: false,
+            permanent_mistakes: false,
+            strict_placement: false,
+            auto_advance: AutoAdvance::Off,
+            bright_manual_marks: false,
+            ascii_symbols: false,
+        }
+    }
+}
+
+const MARATHON_LENGTHS: &[usize] = &[3, 5, 10];
+
+const ROOM_HANDICAPS: &[Option<Handicap>] = &[
+    None,
+    Some(Handicap::FewerGivens { count: 5 }),
+    Some(Handicap::TimePenalty { secs: 30 }),
+];
+
+/// One editable row on the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingItem {
+    NakedSingleHints,
+    HiddenSingleHints,
+    HintOrder,
+    RevealWhenStuck,
+    MarathonLength,
+    MarathonDifficulty,
+    AlmostThere,
+    RoomHandicap,
+    RoomProgress,
+    BotLevel,
+    SwapHands,
+    Coordinates,
+    InstantFeedback,
+    PermanentMistakes,
+    StrictPlacement,
+    AutoAdvance,
+    ManualMarks,
+    Symbols,
+}
+
+impl SettingItem {
+    pub const ALL: &'static [SettingItem] = &[
+        SettingItem::NakedSingleHints,
+        SettingItem::HiddenSingleHints,
+        SettingItem::HintOrder,
+        SettingItem::RevealWhenStuck,
+        SettingItem::MarathonLength,
+        SettingItem::MarathonDifficulty,
+        SettingItem::AlmostThere,
+        SettingItem::RoomHandicap,
+        SettingItem::RoomProgress,
+        SettingItem::BotLevel,
+        SettingItem::SwapHands,
+        SettingItem::Coordinates,
+        SettingItem::InstantFeedback,
+        SettingItem::PermanentMistakes,
+        SettingItem::StrictPlacement,
+        SettingItem::AutoAdvance,
+        SettingItem::ManualMarks,
+        SettingItem::Symbols,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            SettingItem::NakedSingleHints => "Naked Single hints",
+            SettingItem::HiddenSingleHints => "Hidden Single hints",
+            SettingItem::HintOrder => "Hint order",
+            SettingItem::RevealWhenStuck => "Reveal answer when stuck",
+            SettingItem::MarathonLength => "Marathon puzzles",
+            SettingItem::MarathonDifficulty => "Marathon difficulty",
+            SettingItem::AlmostThere => "Almost-there reminder",
+            SettingItem::RoomHandicap => "Room handicap",
+            SettingItem::RoomProgress => "Opponent progress",
+            SettingItem::BotLevel => "Race bot",
+            SettingItem::SwapHands => "Swap hands",
+            SettingItem::Coordinates => "Coordinates",
+            SettingItem::InstantFeedback => "Instant feedback",
+            SettingItem::PermanentMistakes => "Mistakes on undo",
+            SettingItem::StrictPlacement => "Clashing digits",
+            SettingItem::AutoAdvance => "After placing",
+            SettingItem::ManualMarks => "Your pencil marks",
+            SettingItem::Symbols => "Symbols",
+        }
+    }
+}
+
+fn settings_file_path() -> PathBuf {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sudoku-tui");
+    config_dir.join("settings.json")
+}
+
+fn on_off(value: bool) -> String {
+    let label = if value { "On" } else { "Off" };
+    label.to_string()
+}
+
+impl Settings {
+    /// Load settings from disk, falling back to defaults if missing or unreadable
+    pub fn load() -> Self {
+        std::fs::read_to_string(settings_file_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = settings_file_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(path, json)
+    }
+
+    pub fn value_label(&self, item: SettingItem) -> String {
+        match item {
+            SettingItem::NakedSingleHints => on_off(self.technique_enabled(HintTechnique::NakedSingle)),
+            SettingItem::HiddenSingleHints => on_off(self.technique_enabled(HintTechnique::HiddenSingle)),
+            SettingItem::HintOrder => {
+                let order: Vec<&str> = self.hints.enabled.iter().map(|t| t.label()).collect();
+                if order.is_empty() {
+                    "—".to_string()
+                } else {
+                    order.join(" → ")
+                }
+            }
+            SettingItem::RevealWhenStuck => on_off(self.hints.allow_reveal),
+            SettingItem::MarathonLength => self.marathon_length.to_string(),
+            SettingItem::MarathonDifficulty => {
+                let label = if self.marathon_ladder { "Laddering" } else { "Fixed" };
+                label.to_string()
+            }
+            SettingItem::AlmostThere => on_off(self.almost_there),
+            SettingItem::RoomHandicap => match self.room_handicap {
+                None => "Off".to_string(),
+                Some(Handicap::FewerGivens { count }) => format!("{} fewer givens", count),
+                Some(Handicap::TimePenalty { secs }) => format!("{}s late start", secs),
+            },
+            SettingItem::RoomProgress => match self.room_progress {
+                ProgressGranularity::Exact => "Exact".to_string(),
+                ProgressGranularity::Quartiles => "Fog (quarters)".to_string(),
+            },
+            SettingItem::BotLevel => format!(
+                "{} ({:.0}/min)",
+                self.bot_level.label(),
+                self.bot_level.cells_per_min()
+            ),
+            SettingItem::SwapHands => {
+                let label = if self.swap_hands { "Panel left" } else { "Panel right" };
+                label.to_string()
+            }
+            SettingItem::Coordinates => on_off(self.coordinates),
+            SettingItem::InstantFeedback => on_off(self.instant_feedback),
+            SettingItem::PermanentMistakes => {
+                let label = if self.permanent_mistakes { "Kept" } else { "Taken back" };
+                label.to_string()
+            }
+            SettingItem::StrictPlacement => {
+                let label = if self.strict_placement { "Refused" } else { "Allowed" };
+                label.to_string()
+            }
+            SettingItem::AutoAdvance => self.auto_advance.label().to_string(),
+            SettingItem::ManualMarks => {
+                let label = if self.bright_manual_marks { "Brighter" } else { "Like filled-in" };
+                label.to_string()
+            }
+            SettingItem::Symbols => {
+                let label = if self.ascii_symbols { "ASCII" } else { "Unicode" };
+                label.to_string()
+            }
+        }
+    }
+
+    /// Change the given setting to its next value, wrapping around.
+    pub fn cycle(&mut self, item: SettingItem) {
+        match item {
+            SettingItem::NakedSingleHints => self.toggle_technique(HintTechnique::NakedSingle),
+            SettingItem::HiddenSingleHints => self.toggle_technique(HintTechnique::HiddenSingle),
+            SettingItem::HintOrder => self.hints.enabled.reverse(),
+            SettingItem::RevealWhenStuck => self.hints.allow_reveal = !self.hints.allow_reveal,
+            SettingItem::MarathonLength => {
+                let next = MARATHON_LENGTHS
+                    .iter()
+                    .position(|&n| n == self.marathon_length)
+                    .map_or(0, |i| (i + 1) % MARATHON_LENGTHS.len());
+                self.marathon_length = MARATHON_LENGTHS[next];
+            }
+            SettingItem::MarathonDifficulty => self.marathon_ladder = !self.marathon_ladder,
+            SettingItem::AlmostThere => self.almost_there = !self.almost_there,
+            SettingItem::RoomHandicap => {
+                let next = ROOM_HANDICAPS
+                    .iter()
+                    .position(|&h| h == self.room_handicap)
+                    .map_or(0, |i| (i + 1) % ROOM_HANDICAPS.len());
+                self.room_handicap = ROOM_HANDICAPS[next];
+            }
+            SettingItem::RoomProgress => {
+                self.room_progress = match self.room_progress {
+                    ProgressGranularity::Exact => ProgressGranularity::Quartiles,
+                    ProgressGranularity::Quartiles => ProgressGranularity::Exact,
+                };
+            }
+            SettingItem::BotLevel => self.bot_level = self.bot_level.next(),
+            SettingItem::SwapHands => self.swap_hands = !self.swap_hands,
+            SettingItem::Coordinates => self.coordinates = !self.coordinates,
+            SettingItem::InstantFeedback => self.instant_feedback = !self.instant_feedback,
+            SettingItem::PermanentMistakes => self.permanent_mistakes = !self.permanent_mistakes,
+            SettingItem::StrictPlacement => self.strict_placement = !self.strict_placement,
+            SettingItem::AutoAdvance => self.auto_advance = self.auto_advance.next(),
+            SettingItem::ManualMarks => self.bright_manual_marks = !self.bright_manual_marks,
+            SettingItem::Symbols => self.ascii_symbols = !self.ascii_symbols,
+        }
+    }
+
+    fn technique_enabled(&self, technique: HintTechnique) -> bool {
+        self.hints.enabled.contains(&technique)
+    }
+
+    fn toggle_technique(&mut self, technique: HintTechnique) {
+        if self.technique_enabled(technique) {
+            self.hints.enabled.retain(|&t| t != technique);
+        } else {
+            self.hints.enabled.push(technique);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::future::Future;
+
+use futures_util::future::select_all;
+use tokio::task::{JoinError, JoinHandle};
+
+/// What a background task is for. Tasks of different kinds run side by
+/// side; each kind runs one at a time, so a slow leaderboard fetch doesn't
+/// hold up a connect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskKind {
+    /// Starting the device login
+    Auth,
+    /// Opening the game connection
+    Connect,
+    /// Getting back into a match whose connection dropped. The match screen
+    /// stays up meanwhile, so Esc must not give the match away.
+    Reconnect,
+    Leaderboard,
+    /// Our profile, rating history and head-to-head record
+    Profile,
+    /// Posting single-player results and fetching their totals
+    SoloSync,
+    /// Generating the puzzle for a new single-player game or bot race
+    Puzzle,
+}
+
+impl TaskKind {
+    /// Whether the player is left waiting on it behind a status line, so
+    /// Esc cancels it. The others run unnoticed.
+    pub fn is_foreground(self) -> bool {
+        matches!(self, TaskKind::Auth | TaskKind::Connect | TaskKind::Leaderboard)
+    }
+}
+
+/// The app's background tasks, at most one of each kind.
+pub struct Tasks<T> {
+    running: Vec<(TaskKind, JoinHandle<T>)>,
+}
+
+impl<T> Default for Tasks<T> {
+    fn default() -> Self {
+        Tasks { running: Vec::new() }
+    }
+}
+
+impl<T: Send + 'static> Tasks<T> {
+    pub fn is_running(&self, kind: TaskKind) -> bool {
+        self.running.iter().any(|(k, _)| *k == kind)
+    }
+
+    /// Run `task` as `kind`, replacing (and aborting) any task already
+    /// running as that kind.
+    pub fn spawn<F>(&mut self, kind: TaskKind, task: F)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        self.cancel(kind);
+        self.running.push((kind, tokio::spawn(task)));
+    }
+
+    /// Abort the task running as `kind`, if any. Returns whether there was one.
+    pub fn cancel(&mut self, kind: TaskKind) -> bool {
+        self.cancel_where(|k| k == kind)
+    }
+
+    /// Abort every foreground task. Returns whether there were any.
+    pub fn cancel_foreground(&mut self) -> bool {
+        self.cancel_where(TaskKind::is_foreground)
+    }
+
+    pub fn cancel_all(&mut self) {
+        self.cancel_where(|_| true);
+    }
+
+    fn cancel_where(&mut self, cancel: impl Fn(TaskKind) -> bool) -> bool {
+        let before = self.running.len();
+        self.running.retain(|(kind, handle)| {
+            let cancelled = cancel(*kind);
+            if cancelled {
+                handle.abort();
+            }
+            !cancelled
+        });
+        self.running.len() != before
+    }
+
+    /// The next task to finish, with its kind. Never resolves while
+    /// nothing is running. Cancel safe: dropping it loses no results.
+    pub async fn next(&mut self) -> (TaskKind, Result<T, JoinError>) {
+        if self.running.is_empty() {
+            return std::future::pending().await;
+        }
+        let (result, index, _) = select_all(self.running.iter_mut().map(|(_, handle)| handle)).await;
+        let (kind, _) = self.running.remove(index);
+        (kind, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn kinds_run_side_by_side_and_cancel_alone() {
+        let mut tasks = Tasks::default();
+        tasks.spawn(TaskKind::Connect, async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            "connected"
+        });
+        tasks.spawn(TaskKind::Profile, async { "profile" });
+        assert!(tasks.is_running(TaskKind::Connect));
+
+        // The quick one finishes without waiting on the slow one
+        let (kind, result) = tasks.next().await;
+        assert_eq!((kind, result.unwrap()), (TaskKind::Profile, "profile"));
+        assert!(!tasks.is_running(TaskKind::Profile));
+
+        tasks.spawn(TaskKind::SoloSync, async { "synced" });
+        tasks.spawn(TaskKind::Reconnect, async { "reconnected" });
+        assert!(tasks.cancel_foreground());
+        assert!(!tasks.is_running(TaskKind::Connect));
+        assert!(tasks.is_running(TaskKind::SoloSync));
+        assert!(tasks.is_running(TaskKind::Reconnect));
+        assert!(!tasks.cancel_foreground());
+    }
+}
@@ -6,15 +6,31 @@ use ratatui::{
     Frame,
 };
 
-use crate::game::{CellOwner, Game, GameState};
-use crate::hint::HintStage;
+use crate::achievements::Achievement;
+use crate::game::{CellFlag, CellOwner, EndView, Game, GameState, MultiplayerState};
+use crate::mp_menu::{self, MenuMode, ROOM_CODE_LEN};
+use crate::pencil::MarkOrigin;
+use crate::hint::{HintStage, HintTechnique};
+use crate::history::Outcome;
+use crate::net::trace::TracedMessage;
+use crate::report::REPORT_REASONS;
+use crate::settings::SettingItem;
 use sudoku_core::protocol::GameMode;
-use sudoku_core::{Cell, Difficulty};
+use sudoku_core::{Board, Cell, Difficulty, GridSpec};
 
 // ── Constants ────────────────────────────────────────────────────────────────
 
-const GRID_WIDTH: u16 = 73;
-const GRID_HEIGHT: u16 = 37;
+/// One full bright→dim→bright cycle of the conflict highlight
+const CONFLICT_PULSE_SECS: f32 = 1.6;
+
+// Each cell is 7 columns by 3 rows, with a one-character border between cells
+const CELL_WIDTH: u16 = 7;
+const CELL_HEIGHT: u16 = 3;
+
+// With coordinates on: row numbers and a space left of the board, column
+// numbers in a line above it
+const ROW_LABEL_WIDTH: u16 = 2;
+const COL_LABEL_HEIGHT: u16 = 1;
 
 // ── Public entry point ───────────────────────────────────────────────────────
 
@@ -30,13 +46,96 @@ pub fn draw(f: &mut Frame, game: &Game) {
         GameState::MultiplayerPlaying => draw_multiplayer_playing(f, game),
         GameState::MultiplayerEnd => draw_multiplayer_end(f, game),
         GameState::Leaderboard => draw_leaderboard(f, game),
+        GameState::Settings => draw_settings(f, game),
+        GameState::MarathonSummary => draw_marathon_summary(f, game),
+        GameState::Badges => draw_badges(f, game),
+        GameState::History => draw_history(f, game),
+        GameState::Spectating => draw_spectating(f, game),
+        GameState::Profile => draw_profile(f, game),
+    }
+
+    if let Some(text) = game.current_announcement() {
+        draw_announcement(f, game, text);
+    }
+
+    if game.show_net_trace {
+        draw_net_trace(f, game);
+    }
+
+    if game.show_reroll_confirm {
+        draw_reroll_confirm(f);
     }
 
     if game.show_quit_confirm {
-        draw_quit_confirm(f);
+        if game.state == GameState::MultiplayerPlaying && game.bot.is_none() {
+            draw_forfeit_confirm(f, game);
+        } else {
+            draw_quit_confirm(f, game);
+        }
     }
 }
 
+/// The server operator's announcement across the top line of any screen
+fn draw_announcement(f: &mut Frame, game: &Game, text: &str) {
+    let area = f.area();
+    let banner = Rect::new(area.x, area.y, area.width, 1.min(area.height));
+    f.render_widget(Clear, banner);
+    let line = Line::from(Span::styled(
+        format!(" {} {} ", glyph(game, "📢", "[!]"), text),
+        Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+    ));
+    f.render_widget(
+        Paragraph::new(line)
+            .alignment(Alignment::Center)
+            .style(Style::default().bg(Color::Yellow)),
+        banner,
+    );
+}
+
+/// Debug overlay: the latest network messages, newest at the bottom, in the
+/// bottom-right corner over whatever screen is up.
+fn draw_net_trace(f: &mut Frame, game: &Game) {
+    let area = f.area();
+    let width = area.width.min(72);
+    let height = area.height.min(16);
+    let popup = Rect::new(area.right() - width, area.bottom() - height, width, height);
+
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Network · F12 ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::DarkGray));
+
+    let entries = game.net_trace.recent(height.saturating_sub(2) as usize);
+    let lines: Vec<Line> = if entries.is_empty() {
+        vec![Line::from(Span::styled(
+            " No messages yet",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        entries
+            .iter()
+            .map(|entry| {
+                let color = match entry.message {
+                    TracedMessage::Sent(_) => Color::Cyan,
+                    TracedMessage::Received(_) => Color::Yellow,
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>8.3} ", entry.secs),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(format!("{} ", entry.arrow()), Style::default().fg(color)),
+                    Span::styled(entry.summary(), Style::default().fg(Color::White)),
+                ])
+            })
+            .collect()
+    };
+
+    f.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
 // ── Menu screen ──────────────────────────────────────────────────────────────
 
 fn draw_menu(f: &mut Frame, game: &Game) {
@@ -46,12 +145,12 @@ fn draw_menu(f: &mut Frame, game: &Game) {
         Constraint::Min(0),
         Constraint::Length(8),
         Constraint::Length(1),
-        Constraint::Length(3),
+        Constraint::Length(4),
         Constraint::Length(1),
-        Constraint::Length(10),
+        Constraint::Length(17),
         Constraint::Min(0),
     ])
-    .split(center_rect(60, 32, area));
+    .split(center_rect(60, 40, area));
 
     let title_lines = vec![
         Line::from(Span::styled(
@@ -95,9 +194,11 @@ fn draw_menu(f: &mut Frame, game: &Game) {
     let title = Paragraph::new(title_lines).alignment(Alignment::Center);
     f.render_widget(title, chunks[1]);
 
-    let diff_label = game.difficulty.label();
-    let diff_color = difficulty_color(game.difficulty);
-    let selector_line = Line::from(vec![
+    let (diff_label, diff_color) = match game.custom_givens {
+        Some(count) => (format!("{} givens", count), Color::White),
+        None => (game.difficulty.label().to_string(), difficulty_color(game.difficulty)),
+    };
+    let mut selector_spans = vec![
         Span::styled("◄  ", Style::default().fg(Color::DarkGray)),
         Span::styled(
             format!("  {}  ", diff_label),
@@ -106,7 +207,14 @@ fn draw_menu(f: &mut Frame, game: &Game) {
                 .add_modifier(Modifier::BOLD),
         ),
         Span::styled("  ►", Style::default().fg(Color::DarkGray)),
-    ]);
+    ];
+    if game.grid != GridSpec::CLASSIC {
+        selector_spans.push(Span::styled(
+            format!("  {}", game.grid.label()),
+            Style::default().fg(Color::White),
+        ));
+    }
+    let selector_line = Line::from(selector_spans);
     let selector = Paragraph::new(vec![
         Line::from(Span::styled(
             "Select Difficulty",
@@ -114,11 +222,12 @@ fn draw_menu(f: &mut Frame, game: &Game) {
         )),
         Line::from(""),
         selector_line,
+        difficulty_position(game),
     ])
     .alignment(Alignment::Center);
     f.render_widget(selector, chunks[3]);
 
-    let controls = Paragraph::new(vec![
+    let mut control_lines = vec![
         Line::from(Span::styled(
             "Controls",
             Style::default()
@@ -134,16 +243,82 @@ fn draw_menu(f: &mut Frame, game: &Game) {
             Span::styled("Enter", Style::default().fg(Color::Yellow)),
             Span::styled("  Start game", Style::default().fg(Color::Gray)),
         ]),
+        Line::from(vec![
+            Span::styled("g", Style::default().fg(Color::Yellow)),
+            Span::styled("      Grid size (9×9, 6×6, 4×4)", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("#", Style::default().fg(Color::Yellow)),
+            Span::styled("      Play a seed", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("c", Style::default().fg(Color::Yellow)),
+            Span::styled("      Custom givens (17-80)", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("r", Style::default().fg(Color::Yellow)),
+            Span::styled("      Marathon", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("b", Style::default().fg(Color::Yellow)),
+            Span::styled(
+                format!("      Race a bot ({})", game.settings.bot_level.label()),
+                Style::default().fg(Color::Gray),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("h", Style::default().fg(Color::Yellow)),
+            Span::styled("      Race your ghost", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("p", Style::default().fg(Color::Yellow)),
+            Span::styled("      Recently played", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(vec![
+            Span::styled("a", Style::default().fg(Color::Yellow)),
+            Span::styled("      Badges", Style::default().fg(Color::Gray)),
+        ]),
         Line::from(vec![
             Span::styled("m", Style::default().fg(Color::Yellow)),
             Span::styled("      Multiplayer", Style::default().fg(Color::Gray)),
         ]),
+        Line::from(vec![
+            Span::styled("s", Style::default().fg(Color::Yellow)),
+            Span::styled("      Settings", Style::default().fg(Color::Gray)),
+        ]),
         Line::from(vec![
             Span::styled("q", Style::default().fg(Color::Yellow)),
             Span::styled("      Quit", Style::default().fg(Color::Gray)),
         ]),
-    ])
-    .alignment(Alignment::Center);
+    ];
+    if let Some(ref input) = game.seed_input {
+        control_lines.push(Line::from(""));
+        control_lines.push(Line::from(vec![
+            Span::styled("Seed: ", Style::default().fg(Color::White)),
+            Span::styled(
+                format!("{}_", input),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("  Enter to play, Esc to cancel", Style::default().fg(Color::DarkGray)),
+        ]));
+    } else if let Some(ref input) = game.givens_input {
+        control_lines.push(Line::from(""));
+        control_lines.push(Line::from(vec![
+            Span::styled("Givens: ", Style::default().fg(Color::White)),
+            Span::styled(
+                format!("{}_", input),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("  Enter to play, Esc to cancel", Style::default().fg(Color::DarkGray)),
+        ]));
+    } else if let Some(ref notice) = game.notice {
+        control_lines.push(Line::from(""));
+        control_lines.push(Line::from(Span::styled(
+            notice.clone(),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    let controls = Paragraph::new(control_lines).alignment(Alignment::Center);
     f.render_widget(controls, chunks[5]);
 }
 
@@ -151,7 +326,7 @@ fn draw_menu(f: &mut Frame, game: &Game) {
 
 fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
     let area = f.area();
-    let popup = center_rect(40, 18, area);
+    let popup = center_rect(52, 22, area);
 
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
@@ -162,10 +337,43 @@ fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::Cyan));
 
-    let items = ["Create Room", "Join Room", "Quick Match", "Leaderboard", "Back"];
     let mut lines = vec![Line::from("")];
 
-    if game.joining_room {
+    if crate::net::tls::is_insecure() {
+        lines.push(Line::from(Span::styled(
+            format!(" {} Certificate checks are OFF", glyph(game, "⚠", "[!]")),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    if let (None, Some(name)) = (&game.my_profile, &game.connected_as) {
+        lines.push(connected_as_line(name, None));
+        lines.push(Line::from(""));
+    }
+    if let Some(ref me) = game.my_profile {
+        lines.push(connected_as_line(
+            &me.profile.username,
+            Some(format!(" (rating {}, rank #{})", me.profile.rating, me.rank)),
+        ));
+        let favorites: Vec<&str> = [
+            me.profile.favorite_difficulty.as_ref().map(|d| d.label()),
+            me.profile.favorite_mode.map(mode_label),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !favorites.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!(" Mostly plays {}", favorites.join("/")),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        lines.push(Line::from(""));
+    }
+
+    let menu = &game.mp_menu;
+    if menu.mode == MenuMode::RoomCode {
         lines.push(Line::from(Span::styled(
             "Enter room code:",
             Style::default()
@@ -175,8 +383,8 @@ fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
         lines.push(Line::from(""));
         let display_code = format!(
             "  {}{}",
-            game.room_input,
-            "_".repeat(6 - game.room_input.len())
+            menu.room_input,
+            "_".repeat(ROOM_CODE_LEN - menu.room_input.len())
         );
         lines.push(Line::from(Span::styled(
             display_code,
@@ -184,14 +392,17 @@ fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
         )));
-        lines.push(Line::from(""));
         lines.push(Line::from(Span::styled(
-            "  Enter to join, Esc to cancel",
+            format!("  {}", menu.input_note.as_deref().unwrap_or("")),
+            Style::default().fg(Color::DarkGray),
+        )));
+        lines.push(Line::from(Span::styled(
+            "  Enter to join, Esc or ↑↓ to cancel",
             Style::default().fg(Color::DarkGray),
         )));
     } else {
-        for (i, item) in items.iter().enumerate() {
-            let is_selected = i == game.menu_selection;
+        for (i, item) in mp_menu::ITEMS.iter().enumerate() {
+            let is_selected = i == menu.selection;
             let prefix = if is_selected { "▸ " } else { "  " };
             let style = if is_selected {
                 Style::default()
@@ -200,7 +411,18 @@ fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
             } else {
                 Style::default().fg(Color::White)
             };
-            lines.push(Line::from(Span::styled(format!("{}{}", prefix, item), style)));
+            let mut spans = vec![Span::styled(format!("{}{}", prefix, item), style)];
+            if i == mp_menu::QUICK_MATCH {
+                let difficulty = game.quick_match_difficulty();
+                spans.push(Span::styled(
+                    format!("  {}", difficulty.label()),
+                    Style::default().fg(difficulty_color(difficulty)),
+                ));
+                if is_selected {
+                    spans.push(Span::styled(" ←/→", Style::default().fg(Color::DarkGray)));
+                }
+            }
+            lines.push(Line::from(spans));
             lines.push(Line::from(""));
         }
     }
@@ -221,6 +443,18 @@ fn draw_multiplayer_menu(f: &mut Frame, game: &Game) {
             Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
         )));
         f.render_widget(err_line, err_area);
+    } else if let Some(ref notice) = game.notice {
+        let notice_area = Rect {
+            x: popup.x,
+            y: popup.y + popup.height,
+            width: popup.width,
+            height: 1,
+        };
+        let notice_line = Paragraph::new(Line::from(Span::styled(
+            format!(" {}", notice),
+            Style::default().fg(Color::LightGreen),
+        )));
+        f.render_widget(notice_line, notice_area);
     }
 }
 
@@ -278,7 +512,7 @@ fn draw_auth_screen(f: &mut Frame, game: &Game) {
 
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
-        " Press Esc to cancel",
+        " Press Esc to cancel, q to quit",
         Style::default().fg(Color::DarkGray),
     )));
 
@@ -290,7 +524,7 @@ fn draw_auth_screen(f: &mut Frame, game: &Game) {
 
 fn draw_lobby(f: &mut Frame, game: &Game) {
     let area = f.area();
-    let popup = center_rect(44, 10, area);
+    let popup = center_rect(44, 11, area);
 
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
@@ -320,6 +554,20 @@ fn draw_lobby(f: &mut Frame, game: &Game) {
         " Waiting for opponent...",
         Style::default().fg(Color::White),
     )));
+    if let Some((mode, difficulty)) = game.lobby_room {
+        let mode = mode_label(mode);
+        let waited = game.waiting_since.map_or(0, |t| t.elapsed().as_secs());
+        lines.push(Line::from(Span::styled(
+            format!(
+                " {} • {} • waiting {}:{:02}",
+                mode,
+                difficulty.label(),
+                waited / 60,
+                waited % 60
+            ),
+            Style::default().fg(Color::Gray),
+        )));
+    }
     lines.push(Line::from(""));
 
     // Spinner animation using elapsed time
@@ -341,8 +589,13 @@ fn draw_lobby(f: &mut Frame, game: &Game) {
     )));
 
     lines.push(Line::from(""));
+    let footer = if game.room_code.is_some() && game.lobby_room.is_some() {
+        " ←→ difficulty  m mode  Esc cancel"
+    } else {
+        " Press Esc to cancel"
+    };
     lines.push(Line::from(Span::styled(
-        " Press Esc to cancel",
+        footer,
         Style::default().fg(Color::DarkGray),
     )));
 
@@ -365,37 +618,66 @@ fn draw_playing(f: &mut Frame, game: &Game) {
     let main_area = outer[0];
     let bottom_area = outer[1];
 
-    let h_chunks = Layout::horizontal([
-        Constraint::Min(0),
-        Constraint::Length(GRID_WIDTH + 2),
-        Constraint::Length(2),
-        Constraint::Length(28),
-        Constraint::Min(0),
-    ])
-    .split(main_area);
+    let (grid_area, panel_area) = grid_and_panel(game, main_area, 28);
 
     let grid_v = Layout::vertical([
         Constraint::Min(0),
-        Constraint::Length(GRID_HEIGHT + 2),
+        Constraint::Length(board_height(game)),
         Constraint::Min(0),
     ])
-    .split(h_chunks[1]);
+    .split(grid_area);
 
     draw_grid(f, game, grid_v[1]);
 
+    let mut panel_height = if game.marathon.is_some() { 22 } else { 18 };
+    if game.seed.is_some() {
+        panel_height += 2;
+    }
+    if !selected_conflicts(game).is_empty() {
+        panel_height += 2;
+    }
     let panel_v = Layout::vertical([
         Constraint::Min(0),
-        Constraint::Length(18),
+        Constraint::Length(panel_height),
         Constraint::Min(0),
     ])
-    .split(h_chunks[3]);
+    .split(panel_area);
 
-    draw_info_panel(f, game, panel_v[1]);
+    if game.settings.show_info_panel {
+        draw_info_panel(f, game, panel_v[1]);
+    }
 
     if has_hint {
         draw_hint_bar(f, game, bottom_area);
     } else {
-        draw_key_hints(f, bottom_area);
+        draw_key_hints(f, game, bottom_area);
+    }
+}
+
+/// Centre the grid and a `panel_width` side panel in `area`, two columns
+/// apart. Returns (grid, panel); the panel goes left with "Swap hands" on.
+/// With the panel hidden the grid is centred alone and the panel is empty.
+fn grid_and_panel(game: &Game, area: Rect, panel_width: u16) -> (Rect, Rect) {
+    let grid = Constraint::Length(board_width(game));
+    if !game.settings.show_info_panel {
+        let chunks = Layout::horizontal([Constraint::Min(0), grid, Constraint::Min(0)]).split(area);
+        return (chunks[1], Rect::default());
+    }
+    let panel = Constraint::Length(panel_width);
+    let swap = game.settings.swap_hands;
+    let (left, right) = if swap { (panel, grid) } else { (grid, panel) };
+    let chunks = Layout::horizontal([
+        Constraint::Min(0),
+        left,
+        Constraint::Length(2),
+        right,
+        Constraint::Min(0),
+    ])
+    .split(area);
+    if swap {
+        (chunks[3], chunks[1])
+    } else {
+        (chunks[1], chunks[3])
     }
 }
 
@@ -414,21 +696,14 @@ fn draw_multiplayer_playing(f: &mut Frame, game: &Game) {
 
     if is_race {
         // Race mode: your board + opponent progress panel
-        let h_chunks = Layout::horizontal([
-            Constraint::Min(0),
-            Constraint::Length(GRID_WIDTH + 2),
-            Constraint::Length(2),
-            Constraint::Length(30),
-            Constraint::Min(0),
-        ])
-        .split(main_area);
+        let (grid_area, panel_area) = grid_and_panel(game, main_area, 30);
 
         let grid_v = Layout::vertical([
             Constraint::Min(0),
-            Constraint::Length(GRID_HEIGHT + 2),
+            Constraint::Length(board_height(game)),
             Constraint::Min(0),
         ])
-        .split(h_chunks[1]);
+        .split(grid_area);
 
         draw_grid(f, game, grid_v[1]);
 
@@ -437,26 +712,21 @@ fn draw_multiplayer_playing(f: &mut Frame, game: &Game) {
             Constraint::Length(20),
             Constraint::Min(0),
         ])
-        .split(h_chunks[3]);
+        .split(panel_area);
 
-        draw_race_panel(f, game, panel_v[1]);
+        if game.settings.show_info_panel {
+            draw_race_panel(f, game, panel_v[1]);
+        }
     } else {
         // Shared mode: single board with ownership colors + info panel
-        let h_chunks = Layout::horizontal([
-            Constraint::Min(0),
-            Constraint::Length(GRID_WIDTH + 2),
-            Constraint::Length(2),
-            Constraint::Length(30),
-            Constraint::Min(0),
-        ])
-        .split(main_area);
+        let (grid_area, panel_area) = grid_and_panel(game, main_area, 30);
 
         let grid_v = Layout::vertical([
             Constraint::Min(0),
-            Constraint::Length(GRID_HEIGHT + 2),
+            Constraint::Length(board_height(game)),
             Constraint::Min(0),
         ])
-        .split(h_chunks[1]);
+        .split(grid_area);
 
         draw_grid(f, game, grid_v[1]);
 
@@ -465,9 +735,11 @@ fn draw_multiplayer_playing(f: &mut Frame, game: &Game) {
             Constraint::Length(20),
             Constraint::Min(0),
         ])
-        .split(h_chunks[3]);
+        .split(panel_area);
 
-        draw_shared_panel(f, game, panel_v[1]);
+        if game.settings.show_info_panel {
+            draw_shared_panel(f, game, panel_v[1]);
+        }
     }
 
     draw_multiplayer_key_hints(f, game, bottom_area);
@@ -475,33 +747,48 @@ fn draw_multiplayer_playing(f: &mut Frame, game: &Game) {
 
 // ── Race mode panel ─────────────────────────────────────────────────────────
 
+/// "👀 3 watching" along the bottom edge of a multiplayer panel, if anyone is
+fn with_watchers<'a>(block: Block<'a>, game: &Game, mp: &MultiplayerState) -> Block<'a> {
+    if mp.spectators == 0 {
+        return block;
+    }
+    let eyes = glyph(game, "👀 ", "");
+    block.title_bottom(Line::from(format!(" {}{} watching ", eyes, mp.spectators)).right_aligned())
+}
+
+/// "Room ABC123" along the bottom edge of a multiplayer panel, so either
+/// player can read the code out however they were paired
+fn with_room_code<'a>(block: Block<'a>, game: &Game) -> Block<'a> {
+    match game.room_code {
+        Some(ref code) => block.title_bottom(Line::from(format!(" Room {} ", code))),
+        None => block,
+    }
+}
+
 fn draw_race_panel(f: &mut Frame, game: &Game, area: Rect) {
+    let mp = game.multiplayer.as_ref().unwrap();
+
     let block = Block::bordered()
         .title(" Race Mode ")
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::Cyan));
-
-    let mp = game.multiplayer.as_ref().unwrap();
+    let block = with_watchers(block, game, mp);
+    let block = with_room_code(block, game);
 
     let your_filled = game.filled_count();
     let total = 81u32;
 
     // Progress bars
     let your_pct = (your_filled as f32 / total as f32 * 20.0) as usize;
-    let opp_pct = (mp.opponent_filled as f32 / total as f32 * 20.0) as usize;
 
     let your_bar = format!(
         "{}{}",
         "█".repeat(your_pct),
         "░".repeat(20 - your_pct)
     );
-    let opp_bar = format!(
-        "{}{}",
-        "█".repeat(opp_pct),
-        "░".repeat(20 - opp_pct)
-    );
+    let (opp_bar, opp_label) = opponent_bar(mp, total);
 
-    let lines = vec![
+    let mut lines = vec![
         Line::from(""),
         Line::from(vec![
             Span::styled(" vs ", Style::default().fg(Color::Gray)),
@@ -546,12 +833,15 @@ fn draw_race_panel(f: &mut Frame, game: &Game, area: Rect) {
         Line::from(vec![
             Span::styled(" ", Style::default()),
             Span::styled(&opp_bar, Style::default().fg(Color::Magenta)),
-            Span::styled(
-                format!(" {}/{}", mp.opponent_filled, total),
-                Style::default().fg(Color::White),
-            ),
+            Span::styled(opp_label, Style::default().fg(Color::White)),
         ]),
-        Line::from(""),
+        match mp.opponent_correct {
+            Some(correct) => Line::from(Span::styled(
+                format!(" {} correct", correct),
+                Style::default().fg(Color::DarkGray),
+            )),
+            None => Line::from(""),
+        },
         Line::from(vec![
             Span::styled(" Momentum: ", Style::default().fg(Color::Gray)),
             Span::styled(
@@ -560,20 +850,45 @@ fn draw_race_panel(f: &mut Frame, game: &Game, area: Rect) {
             ),
         ]),
     ];
+    if let Some(count) = mp.opponent_mistakes {
+        lines.push(Line::from(vec![
+            Span::styled(" Mistakes: ", Style::default().fg(Color::Gray)),
+            Span::styled(format!("{}", count), Style::default().fg(Color::Red)),
+        ]));
+    }
 
     let paragraph = Paragraph::new(lines).block(block);
     f.render_widget(paragraph, area);
 }
 
+/// The opponent's progress bar and what it says after it. In a fog room only
+/// the quarter they've reached is known, so the bar shades that quarter.
+fn opponent_bar(mp: &MultiplayerState, total: u32) -> (String, String) {
+    match mp.opponent_quarter {
+        Some(quarter) => {
+            let done = quarter as usize * 5;
+            let bar = format!("{}{}{}", "█".repeat(done), "▒".repeat(5), "░".repeat(15 - done));
+            (bar, format!(" ~{}-{}%", done * 5, done * 5 + 25))
+        }
+        None => {
+            let pct = (mp.opponent_filled as f32 / total as f32 * 20.0) as usize;
+            let bar = format!("{}{}", "█".repeat(pct), "░".repeat(20 - pct));
+            (bar, format!(" {}/{}", mp.opponent_filled, total))
+        }
+    }
+}
+
 // ── Shared mode panel ───────────────────────────────────────────────────────
 
 fn draw_shared_panel(f: &mut Frame, game: &Game, area: Rect) {
+    let mp = game.multiplayer.as_ref().unwrap();
+
     let block = Block::bordered()
         .title(" Shared Board ")
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::Green));
-
-    let mp = game.multiplayer.as_ref().unwrap();
+    let block = with_watchers(block, game, mp);
+    let block = with_room_code(block, game);
 
     // Count cells per player
     let mut my_cells = 0u32;
@@ -658,7 +973,27 @@ fn draw_multiplayer_end(f: &mut Frame, game: &Game) {
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
 
-    let popup = center_rect(44, 16, area);
+    if let Some(mp) = game.multiplayer.as_ref()
+        && mp.end_view == EndView::Solution
+        && let Some(ref solution) = mp.solution
+    {
+        draw_final_board(f, game, solution, " Solution ", area);
+        return;
+    }
+    if let Some(mp) = game.multiplayer.as_ref()
+        && let Some(ref boards) = mp.final_boards
+        && mp.end_view != EndView::Summary
+    {
+        let (board, title) = if mp.end_view == EndView::MyBoard {
+            (&boards.mine, " Your final board ".to_string())
+        } else {
+            (&boards.opponent, format!(" {}'s final board ", mp.opponent_name))
+        };
+        draw_final_board(f, game, board, &title, area);
+        return;
+    }
+
+    let popup = center_rect(50, 16, area);
     f.render_widget(Clear, popup);
 
     let mp = game.multiplayer.as_ref();
@@ -713,17 +1048,43 @@ fn draw_multiplayer_end(f: &mut Frame, game: &Game) {
             Color::Red
         };
         let elo_sign = if r.elo_change >= 0 { "+" } else { "" };
-        lines.push(Line::from(vec![
-            Span::styled("  ELO: ", Style::default().fg(Color::Gray)),
-            Span::styled(
-                format!("{}{}", elo_sign, r.elo_change),
-                Style::default().fg(elo_color).add_modifier(Modifier::BOLD),
-            ),
-            Span::styled(
-                format!("  (now {})", r.new_rating),
+        if let Some(ref ghost) = game.ghost_race {
+            let verdict = if r.won {
+                let ahead = ghost.secs().saturating_sub(game.elapsed_secs);
+                format!("  You beat your ghost by {}:{:02}", ahead / 60, ahead % 60)
+            } else {
+                format!("  Your ghost finished in {}:{:02}", ghost.secs() / 60, ghost.secs() % 60)
+            };
+            lines.push(Line::from(Span::styled(
+                verdict,
+                Style::default().fg(if r.won { Color::Green } else { Color::DarkGray }),
+            )));
+        } else if game.bot.is_some() {
+            lines.push(Line::from(Span::styled(
+                "  Bot races are unrated",
                 Style::default().fg(Color::DarkGray),
-            ),
-        ]));
+            )));
+        } else {
+            lines.push(Line::from(vec![
+                Span::styled("  ELO: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!("{}{}", elo_sign, r.elo_change),
+                    Style::default().fg(elo_color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("  (now {})", r.new_rating),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            if let Some(m) = mp
+                && let Some((ours, theirs)) = m.series
+            {
+                lines.push(Line::from(Span::styled(
+                    format!("  Series vs {}: {}–{}", m.opponent_name, ours, theirs),
+                    Style::default().fg(Color::Gray),
+                )));
+            }
+        }
     }
 
     lines.push(Line::from(""));
@@ -731,13 +1092,36 @@ fn draw_multiplayer_end(f: &mut Frame, game: &Game) {
         format!("  Time: {}", game.format_time()),
         Style::default().fg(Color::White),
     )));
+    if let Some(m) = mp.filter(|_| game.bot.is_none()) {
+        let difficulty = m.rematch_difficulty.label();
+        let (text, color) = if m.rematch_requested {
+            (format!("  Rematch at {} asked — waiting…", difficulty), Color::DarkGray)
+        } else if m.rematch_offer.is_some() {
+            (
+                format!("  {} asks for {} — r to accept", m.opponent_name, difficulty),
+                Color::Yellow,
+            )
+        } else {
+            (format!("  Rematch at {}  (←/→ to change)", difficulty), Color::DarkGray)
+        };
+        lines.push(Line::from(Span::styled(text, Style::default().fg(color))));
+    }
     lines.push(Line::from(""));
-    lines.push(Line::from(vec![
+    let mut keys = vec![
         Span::styled("r", Style::default().fg(Color::Yellow)),
         Span::styled(" Rematch  ", Style::default().fg(Color::Gray)),
-        Span::styled("Enter/q", Style::default().fg(Color::Yellow)),
-        Span::styled(" Menu", Style::default().fg(Color::Gray)),
-    ]));
+    ];
+    if mp.is_some_and(|m| m.final_boards.is_some()) {
+        keys.push(Span::styled("b", Style::default().fg(Color::Yellow)));
+        keys.push(Span::styled(" Boards  ", Style::default().fg(Color::Gray)));
+    }
+    if mp.is_some_and(|m| m.solution.is_some()) {
+        keys.push(Span::styled("s", Style::default().fg(Color::Yellow)));
+        keys.push(Span::styled(" Solution  ", Style::default().fg(Color::Gray)));
+    }
+    keys.push(Span::styled("Enter/q", Style::default().fg(Color::Yellow)));
+    keys.push(Span::styled(" Menu", Style::default().fg(Color::Gray)));
+    lines.push(Line::from(keys));
 
     let paragraph = Paragraph::new(lines)
         .block(block)
@@ -745,6 +1129,111 @@ fn draw_multiplayer_end(f: &mut Frame, game: &Game) {
     f.render_widget(paragraph, popup);
 }
 
+/// Full-screen read-only view of one finished race board.
+fn draw_final_board(f: &mut Frame, game: &Game, board: &Board, title: &str, area: Rect) {
+    let outer = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(area);
+
+    let grid_h = Layout::horizontal([
+        Constraint::Min(0),
+        Constraint::Length(board_width(game)),
+        Constraint::Min(0),
+    ])
+    .split(outer[0]);
+    let grid_v = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(board_height(game)),
+        Constraint::Min(0),
+    ])
+    .split(grid_h[1]);
+
+    draw_board(f, game, board, title, true, grid_v[1]);
+
+    let showing_solution = game
+        .multiplayer
+        .as_ref()
+        .is_some_and(|mp| mp.end_view == EndView::Solution);
+    let (key, action) = if showing_solution {
+        (" s", " Hide solution  ")
+    } else {
+        (" b", " Next view  ")
+    };
+    let keys = Line::from(vec![
+        Span::styled(key, Style::default().fg(Color::Yellow)),
+        Span::styled(action, Style::default().fg(Color::Gray)),
+        Span::styled("Esc", Style::default().fg(Color::Yellow)),
+        Span::styled(" Back to results", Style::default().fg(Color::Gray)),
+    ]);
+    let bar = Paragraph::new(keys).style(Style::default().bg(Color::DarkGray));
+    f.render_widget(bar, outer[1]);
+}
+
+// ── Spectating ──────────────────────────────────────────────────────────────
+
+fn draw_spectating(f: &mut Frame, game: &Game) {
+    let area = f.area();
+    let Some(ref watching) = game.watching else {
+        return;
+    };
+
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let outer = Layout::vertical([
+        Constraint::Length(2),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .split(area);
+
+    let header = Paragraph::new(vec![
+        Line::from(Span::styled(
+            watching.players.join(" vs "),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            format!(
+                "{} · {} · {}",
+                mode_label(watching.mode),
+                watching.difficulty.label(),
+                game.format_time()
+            ),
+            Style::default().fg(Color::Gray),
+        )),
+    ])
+    .alignment(Alignment::Center);
+    f.render_widget(header, outer[0]);
+
+    let grid_h = Layout::horizontal([
+        Constraint::Min(0),
+        Constraint::Length(board_width(game)),
+        Constraint::Min(0),
+    ])
+    .split(outer[1]);
+    let grid_v = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(board_height(game)),
+        Constraint::Min(0),
+    ])
+    .split(grid_h[1]);
+    draw_board(
+        f,
+        game,
+        &game.board,
+        &format!(" Watching {} ", watching.code),
+        true,
+        grid_v[1],
+    );
+
+    let keys = Line::from(vec![
+        Span::styled(" r", Style::default().fg(Color::Yellow)),
+        Span::styled(" Refresh  ", Style::default().fg(Color::Gray)),
+        Span::styled("Esc", Style::default().fg(Color::Yellow)),
+        Span::styled(" Stop watching", Style::default().fg(Color::Gray)),
+    ]);
+    let bar = Paragraph::new(keys).style(Style::default().bg(Color::DarkGray));
+    f.render_widget(bar, outer[2]);
+}
+
 // ── Leaderboard screen ──────────────────────────────────────────────────────
 
 fn draw_leaderboard(f: &mut Frame, game: &Game) {
@@ -753,7 +1242,7 @@ fn draw_leaderboard(f: &mut Frame, game: &Game) {
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
 
-    let popup = center_rect(52, 28, area);
+    let popup = center_rect(68, 28, area);
     f.render_widget(Clear, popup);
 
     let block = Block::bordered()
@@ -765,14 +1254,14 @@ fn draw_leaderboard(f: &mut Frame, game: &Game) {
         Line::from(""),
         Line::from(vec![
             Span::styled(
-                "  #   Player               Rating  W/L",
+                "  #   Player               Rating  W/L      Plays most",
                 Style::default()
                     .fg(Color::White)
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
         Line::from(Span::styled(
-            "  ─────────────────────────────────────",
+            "  ─────────────────────────────────────────────────────────────",
             Style::default().fg(Color::DarkGray),
         )),
     ];
@@ -797,6 +1286,13 @@ fn draw_leaderboard(f: &mut Frame, game: &Game) {
             };
             let rating_str = format!("{:>6}", entry.rating);
             let wl_str = format!("{}/{}", entry.wins, entry.losses);
+            // Both unset until a player has finished a match
+            let favorite = match (entry.favorite_difficulty, entry.favorite_mode) {
+                (Some(difficulty), Some(mode)) => {
+                    format!("{} {}", difficulty.label(), mode_label(mode))
+                }
+                _ => "-".to_string(),
+            };
 
             let rank_color = match entry.rank {
                 1 => Color::Yellow,
@@ -815,7 +1311,8 @@ fn draw_leaderboard(f: &mut Frame, game: &Game) {
                 Span::styled("  ", Style::default()),
                 Span::styled(name, Style::default().fg(Color::Cyan)),
                 Span::styled(rating_str, Style::default().fg(Color::Green)),
-                Span::styled(format!("  {}", wl_str), Style::default().fg(Color::Gray)),
+                Span::styled(format!("  {:<9}", wl_str), Style::default().fg(Color::Gray)),
+                Span::styled(favorite, Style::default().fg(Color::DarkGray)),
             ]));
         }
 
@@ -843,22 +1340,331 @@ fn draw_leaderboard(f: &mut Frame, game: &Game) {
     f.render_widget(paragraph, popup);
 }
 
-// ── Grid rendering ───────────────────────────────────────────────────────────
+// ── Settings screen ──────────────────────────────────────────────────────────
 
-fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
-    let selected_val = game.selected_value();
+fn draw_settings(f: &mut Frame, game: &Game) {
+    let area = f.area();
 
-    let hint_highlighted: Vec<(usize, usize)> = game
-        .active_hint
-        .as_ref()
-        .map(|h| h.highlighted_cells.clone())
-        .unwrap_or_default();
-    let hint_target: Option<(usize, usize)> = game
-        .active_hint
-        .as_ref()
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let height = SettingItem::ALL.len() as u16 * 2 + 5;
+    let popup = center_rect(60, height, area);
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Settings ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Cyan));
+
+    let mut lines = vec![Line::from("")];
+    for (i, item) in SettingItem::ALL.iter().enumerate() {
+        let is_selected = i == game.settings_selection;
+        let prefix = if is_selected { "▸ " } else { "  " };
+        let label_style = if is_selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{}{:<28}", prefix, item.label()), label_style),
+            Span::styled(
+                game.settings.value_label(*item),
+                Style::default().fg(Color::Green),
+            ),
+        ]));
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        "  ↑/↓ select, Enter/←/→ change, Esc to save and go back",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let paragraph = Paragraph::new(lines).block(block);
+    f.render_widget(paragraph, popup);
+}
+
+// ── Marathon summary ─────────────────────────────────────────────────────────
+
+fn draw_marathon_summary(f: &mut Frame, game: &Game) {
+    let area = f.area();
+
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let Some(ref marathon) = game.marathon else {
+        return;
+    };
+    let splits = &marathon.splits;
+
+    let popup = center_rect(40, splits.len() as u16 + 11, area);
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Marathon Complete ")
+        .border_type(BorderType::Double)
+        .style(Style::default().fg(Color::Magenta));
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "  #   Difficulty   Split",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            "  ────────────────────────",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+
+    for (i, (difficulty, secs)) in splits.iter().enumerate() {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:>2}  ", i + 1), Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                format!(" {:<11}", difficulty.label()),
+                Style::default().fg(difficulty_color(*difficulty)),
+            ),
+            Span::styled(format!("  {}", format_secs(*secs)), Style::default().fg(Color::White)),
+        ]));
+    }
+
+    let total = marathon.total_secs();
+    lines.push(Line::from(Span::styled(
+        "  ────────────────────────",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Line::from(vec![
+        Span::styled("  Total             ", Style::default().fg(Color::Gray)),
+        Span::styled(
+            format_secs(total),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ]));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  Press Enter for menu, Q to quit",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let paragraph = Paragraph::new(lines).block(block);
+    f.render_widget(paragraph, popup);
+}
+
+// ── Profile screen ───────────────────────────────────────────────────────────
+
+fn draw_profile(f: &mut Frame, game: &Game) {
+    let area = f.area();
+
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let popup = center_rect(52, 16, area);
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Profile ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Cyan));
+
+    let mut lines = vec![Line::from("")];
+    let Some(ref me) = game.my_profile else {
+        lines.push(Line::from(Span::styled(
+            "  Loading...",
+            Style::default().fg(Color::DarkGray),
+        )));
+        f.render_widget(Paragraph::new(lines).block(block), popup);
+        return;
+    };
+
+    lines.push(Line::from(Span::styled(
+        format!("  {}", me.profile.username),
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "  Rating {} · rank #{} · {}W {}L",
+            me.profile.rating, me.rank, me.profile.wins, me.profile.losses
+        ),
+        Style::default().fg(Color::White),
+    )));
+    lines.push(Line::from(""));
+
+    if game.rating_history.len() >= 2 {
+        let recent =
+            &game.rating_history[game.rating_history.len().saturating_sub(SPARKLINE_GAMES)..];
+        let trend = recent[recent.len() - 1] - recent[0];
+        let trend_color = if trend >= 0 { Color::Green } else { Color::Red };
+        lines.push(Line::from(Span::styled(
+            format!("  Rating over your last {} games", recent.len()),
+            Style::default().fg(Color::Gray),
+        )));
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {}", sparkline(recent)), Style::default().fg(trend_color)),
+            Span::styled(format!("  {:+}", trend), Style::default().fg(trend_color)),
+        ]));
+        let (low, high) = (recent.iter().min().unwrap(), recent.iter().max().unwrap());
+        lines.push(Line::from(Span::styled(
+            format!("  Low {} · high {}", low, high),
+            Style::default().fg(Color::DarkGray),
+        )));
+    } else {
+        lines.push(Line::from(Span::styled(
+            "  Play a couple of rated games to chart your rating",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  Press Enter to go back",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    f.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
+// ── Badges screen ────────────────────────────────────────────────────────────
+
+fn draw_badges(f: &mut Frame, game: &Game) {
+    let area = f.area();
+
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let synced_rows = game.solo_stats.is_some() as u16;
+    let popup = center_rect(64, Achievement::ALL.len() as u16 * 3 + 6 + synced_rows, area);
+    f.render_widget(Clear, popup);
+
+    let earned = game.stats.earned.len();
+    let block = Block::bordered()
+        .title(format!(" Badges {}/{} ", earned, Achievement::ALL.len()))
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Yellow));
+
+    let mut lines = vec![Line::from("")];
+    for badge in Achievement::ALL {
+        let (mark, style) = if game.stats.has(*badge) {
+            let star = glyph(game, "★", "*");
+            (star, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        } else {
+            (glyph(game, "·", "."), Style::default().fg(Color::DarkGray))
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} ", mark), style),
+            Span::styled(badge.name(), style),
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!("    {}", badge.description()),
+            Style::default().fg(Color::Gray),
+        )));
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        format!(
+            "  {} solved · {}-day streak",
+            game.stats.solved, game.stats.streak_days
+        ),
+        Style::default().fg(Color::White),
+    )));
+    if let Some(synced) = &game.solo_stats {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {} solved · {} flawless on all your machines",
+                synced.solved, synced.flawless
+            ),
+            Style::default().fg(Color::Cyan),
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  Press Enter to go back",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    f.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
+// ── History screen ───────────────────────────────────────────────────────────
+
+fn draw_history(f: &mut Frame, game: &Game) {
+    let area = f.area();
+
+    let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
+    f.render_widget(bg, area);
+
+    let rows = game.history.len().max(1) as u16;
+    let popup = center_rect(56, rows + 6, area);
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Recently Played ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Cyan));
+
+    let mut lines = vec![Line::from("")];
+    if game.history.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  Nothing played yet",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    // Newest first, matching how `replay` counts
+    for (i, played) in game.history.iter().rev().enumerate() {
+        let selected = i == game.history_selection;
+        let marker = if selected { glyph(game, "▸", ">") } else { " " };
+        let outcome = match played.outcome {
+            Outcome::Solved { secs } => format!("Solved {}", format_secs(secs)),
+            Outcome::Unfinished => "Unfinished".to_string(),
+        };
+        let seed = played
+            .shareable_seed()
+            .map(|seed| format!("{} #{}", played.difficulty.label(), seed))
+            .unwrap_or_default();
+        let style = if selected {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {} {:<8}", marker, played.difficulty.label()), style),
+            Span::styled(format!("{:<14}", outcome), style),
+            Span::styled(seed, Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  ↑/↓ select, Enter to replay, Esc to go back",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    f.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
+// ── Grid rendering ───────────────────────────────────────────────────────────
+
+fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
+    draw_board(f, game, &game.board, " Sudoku ", false, area);
+}
+
+/// Render `board` using the game's highlighting. A read-only board skips
+/// everything tied to live play: cursor, conflicts, hints, pencil marks and flags.
+fn draw_board(f: &mut Frame, game: &Game, board: &Board, title: &str, read_only: bool, area: Rect) {
+    let selected_val = if read_only { None } else { game.selected_value() };
+
+    let active_hint = game.active_hint.as_ref().filter(|_| !read_only);
+    let hint_highlighted: Vec<(usize, usize)> = active_hint
+        .map(|h| h.highlighted_cells.clone())
+        .unwrap_or_default();
+    let hint_target: Option<(usize, usize)> = active_hint
+        .filter(|h| h.technique != HintTechnique::Stuck)
         .map(|h| (h.target_row, h.target_col));
     let hint_reveal_value: Option<u8> = if game.hint_stage == HintStage::RevealValue {
-        game.active_hint.as_ref().map(|h| h.value)
+        active_hint.map(|h| h.value)
     } else {
         None
     };
@@ -866,24 +1672,29 @@ fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
     let opponent_cursor = game
         .multiplayer
         .as_ref()
-        .and_then(|m| m.opponent_cursor);
+        .and_then(|m| m.opponent_cursor)
+        .filter(|_| !read_only);
 
-    let mut lines: Vec<Line> = Vec::with_capacity(GRID_HEIGHT as usize);
+    let conflict_bg = conflict_color(game);
 
-    for visual_row in 0..GRID_HEIGHT {
+    let grid = &game.grid;
+    let height = grid_height(grid);
+    let mut lines: Vec<Line> = Vec::with_capacity(height as usize);
+
+    for visual_row in 0..height {
         let mut spans: Vec<Span> = Vec::new();
-        let row_kind = classify_row(visual_row);
+        let row_kind = classify_row(grid, visual_row);
 
         match row_kind {
             RowKind::ThickBorder(border_idx) => {
-                spans.push(thick_horizontal_line(border_idx));
+                spans.push(thick_horizontal_line(grid, border_idx));
             }
             RowKind::ThinBorder => {
-                spans.push(thin_horizontal_line());
+                spans.push(thin_horizontal_line(grid));
             }
             RowKind::CellRow(grid_row, sub_row) => {
-                for seg in 0..19 {
-                    let col_kind = classify_col(seg);
+                for seg in 0..grid.size * 2 + 1 {
+                    let col_kind = classify_col(grid, seg);
                     match col_kind {
                         ColKind::ThickBorder => {
                             spans.push(Span::styled(
@@ -898,15 +1709,20 @@ fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
                             ));
                         }
                         ColKind::Cell(grid_col) => {
-                            let cell = game.board[grid_row][grid_col];
-                            let is_selected =
-                                grid_row == game.selected_row && grid_col == game.selected_col;
+                            let cell = board[grid_row][grid_col];
+                            let is_selected = !read_only
+                                && grid_row == game.selected_row
+                                && grid_col == game.selected_col;
                             let is_opponent_cursor =
                                 opponent_cursor == Some((grid_row, grid_col));
-                            let is_conflict = game.show_conflicts
+                            let is_in_selection =
+                                !read_only && game.in_selection(grid_row, grid_col);
+                            let is_conflict = !read_only
+                                && game.show_conflicts
                                 && game.conflicts.contains(&(grid_row, grid_col));
                             let is_hint_highlight =
                                 hint_highlighted.contains(&(grid_row, grid_col));
+                            let is_flashing = !read_only && game.is_flashing(grid_row, grid_col);
                             let is_hint_target = hint_target == Some((grid_row, grid_col));
                             let is_same_number = if let Some(sv) = selected_val {
                                 cell.value() == Some(sv) && !is_selected
@@ -918,6 +1734,7 @@ fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
                             let cell_owner = game
                                 .multiplayer
                                 .as_ref()
+                                .filter(|_| !read_only)
                                 .map(|m| m.cell_owner[grid_row][grid_col])
                                 .unwrap_or(CellOwner::None);
 
@@ -925,38 +1742,84 @@ fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
                                 Color::Yellow
                             } else if is_opponent_cursor {
                                 Color::Magenta
+                            } else if is_in_selection {
+                                Color::Blue
                             } else if is_hint_target {
                                 Color::Green
                             } else if is_conflict {
-                                Color::Red
+                                conflict_bg
                             } else if is_hint_highlight {
                                 Color::Magenta
+                            } else if is_flashing {
+                                Color::Green
                             } else if is_same_number {
                                 Color::DarkGray
                             } else {
                                 Color::Reset
                             };
 
-                            // Cell text color based on ownership
-                            let ownership_fg = match cell_owner {
-                                CellOwner::Mine => Some(Color::Cyan),
-                                CellOwner::Opponent => Some(Color::Green),
-                                _ => None,
+                            // Entry text color: right/wrong with instant
+                            // feedback, otherwise who placed it
+                            let entry_fg = match game.entry_correct(grid_row, grid_col) {
+                                Some(true) => Some(Color::Green),
+                                Some(false) => Some(Color::Red),
+                                None => match cell_owner {
+                                    CellOwner::Mine => Some(Color::Cyan),
+                                    CellOwner::Opponent => Some(Color::Green),
+                                    _ => None,
+                                },
                             };
 
                             let reveal =
                                 if is_hint_target { hint_reveal_value } else { None };
 
-                            let cell_span = render_cell(
+                            let pencil_marks: Vec<(u8, Color)> = if read_only {
+                                Vec::new()
+                            } else {
+                                game.pencil_marks[grid_row][grid_col]
+                                    .iter()
+                                    .map(|(digit, origin)| (digit, mark_color(game, origin)))
+                                    .collect()
+                            };
+                            let mut cell_spans = render_cell(
                                 cell,
-                                &game.pencil_marks[grid_row][grid_col],
+                                &pencil_marks,
                                 bg,
                                 is_selected,
                                 sub_row,
                                 reveal,
-                                ownership_fg,
+                                entry_fg,
                             );
-                            spans.push(cell_span);
+                            if !read_only
+                                && sub_row == 1
+                                && cell == Cell::Empty
+                                && game.is_peeking()
+                            {
+                                cell_spans = vec![Span::styled(
+                                    format!("   {}   ", game.solution[grid_row][grid_col]),
+                                    Style::default().fg(Color::DarkGray).bg(bg),
+                                )];
+                            }
+                            let is_out_of_focus = !read_only
+                                && game
+                                    .locked_digit
+                                    .is_some_and(|d| !game.cell_shows_digit(grid_row, grid_col, d));
+                            if is_out_of_focus && bg == Color::Reset {
+                                for span in &mut cell_spans {
+                                    span.style = span
+                                        .style
+                                        .fg(Color::DarkGray)
+                                        .remove_modifier(Modifier::BOLD);
+                                }
+                            }
+                            match game.cell_flags[grid_row][grid_col] {
+                                Some(flag) if sub_row == 0 && !read_only => {
+                                    let first = cell_spans.remove(0);
+                                    spans.extend(with_corner_marker(first, flag_color(flag)));
+                                    spans.extend(cell_spans);
+                                }
+                                _ => spans.extend(cell_spans),
+                            }
                         }
                     }
                 }
@@ -967,26 +1830,79 @@ fn draw_grid(f: &mut Frame, game: &Game, area: Rect) {
     }
 
     let block = Block::bordered()
-        .title(" Sudoku ")
+        .title(title.to_string())
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::White));
 
+    let area = if game.settings.coordinates {
+        draw_coordinates(f, grid, area)
+    } else {
+        area
+    };
     let grid_paragraph = Paragraph::new(lines).block(block);
     f.render_widget(grid_paragraph, area);
 }
 
+/// Number the columns above the board and the rows to its left, each label
+/// lined up with the middle of its cells. Returns what's left for the board.
+fn draw_coordinates(f: &mut Frame, grid: &GridSpec, area: Rect) -> Rect {
+    let style = Style::default().fg(Color::DarkGray);
+    // The board's block border, then the grid's own outer line
+    let inset = 2;
+
+    let mut top = " ".repeat((ROW_LABEL_WIDTH + inset) as usize);
+    for col in 0..grid.size {
+        let label = format!("{:^width$}", col + 1, width = CELL_WIDTH as usize);
+        top.push_str(&label);
+        top.push(' ');
+    }
+    let top_area = Rect { height: COL_LABEL_HEIGHT.min(area.height), ..area };
+    f.render_widget(Paragraph::new(Span::styled(top, style)), top_area);
+
+    let board = Rect {
+        x: area.x + ROW_LABEL_WIDTH,
+        y: area.y + COL_LABEL_HEIGHT,
+        width: area.width.saturating_sub(ROW_LABEL_WIDTH),
+        height: area.height.saturating_sub(COL_LABEL_HEIGHT),
+    };
+    let mut side: Vec<Line> = vec![Line::from(""); (inset + grid_height(grid)) as usize];
+    for row in 0..grid.size {
+        let y = inset + row as u16 * (CELL_HEIGHT + 1) + CELL_HEIGHT / 2;
+        side[y as usize] = Line::from(Span::styled(format!("{}", row + 1), style));
+    }
+    let side_area = Rect {
+        x: area.x,
+        y: board.y,
+        width: ROW_LABEL_WIDTH.min(area.width),
+        height: board.height,
+    };
+    f.render_widget(Paragraph::new(side), side_area);
+    board
+}
+
+/// Pencil marks are dim grey, except that the player's own stand out from
+/// filled-in candidates when the setting asks for it.
+fn mark_color(game: &Game, origin: MarkOrigin) -> Color {
+    match origin {
+        MarkOrigin::Manual if game.settings.bright_manual_marks => Color::Gray,
+        _ => Color::DarkGray,
+    }
+}
+
+/// One sub-row of a cell, seven columns wide. Usually a single span; pencil
+/// marks get one per digit so each can carry its own colour.
 fn render_cell(
     cell: Cell,
-    pencil_marks: &[u8],
+    pencil_marks: &[(u8, Color)],
     bg: Color,
     is_selected: bool,
     sub_row: usize,
     reveal: Option<u8>,
-    ownership_fg: Option<Color>,
-) -> Span<'static> {
+    entry_fg: Option<Color>,
+) -> Vec<Span<'static>> {
     let fg_for_bg = if bg == Color::Yellow || bg == Color::Green {
         Color::Black
-    } else if bg == Color::Red || bg == Color::Magenta {
+    } else if bg == Color::Red || bg == Color::Magenta || matches!(bg, Color::Rgb(..)) {
         Color::White
     } else {
         Color::Reset
@@ -996,7 +1912,7 @@ fn render_cell(
 
     if let Some(v) = reveal {
         if cell == Cell::Empty {
-            return if sub_row == 1 {
+            return vec![if sub_row == 1 {
                 Span::styled(
                     format!("   {}   ", v),
                     Style::default()
@@ -1006,11 +1922,11 @@ fn render_cell(
                 )
             } else {
                 Span::styled(blank, Style::default().bg(bg))
-            };
+            }];
         }
     }
 
-    match cell {
+    let span = match cell {
         Cell::Given(v) => {
             if sub_row == 1 {
                 let fg = if fg_for_bg != Color::Reset {
@@ -1034,49 +1950,76 @@ fn render_cell(
                 let fg = if fg_for_bg != Color::Reset {
                     fg_for_bg
                 } else {
-                    ownership_fg.unwrap_or(Color::Cyan)
+                    entry_fg.unwrap_or(Color::Cyan)
                 };
                 Span::styled(format!("   {}   ", v), Style::default().fg(fg).bg(bg))
             } else {
                 Span::styled(blank, Style::default().bg(bg))
             }
         }
-        Cell::Empty => {
-            if pencil_marks.is_empty() {
-                if is_selected && sub_row == 1 {
-                    Span::styled(
-                        "   ·   ",
-                        Style::default().fg(Color::DarkGray).bg(bg),
-                    )
-                } else {
-                    Span::styled(blank, Style::default().bg(bg))
-                }
+        Cell::Empty if pencil_marks.is_empty() => {
+            if is_selected && sub_row == 1 {
+                Span::styled(
+                    "   ·   ",
+                    Style::default().fg(Color::DarkGray).bg(bg),
+                )
             } else {
-                let base = (sub_row * 3 + 1) as u8;
-                let c0 = if pencil_marks.contains(&base) {
-                    (b'0' + base) as char
-                } else {
-                    ' '
-                };
-                let c1 = if pencil_marks.contains(&(base + 1)) {
-                    (b'0' + base + 1) as char
-                } else {
-                    ' '
-                };
-                let c2 = if pencil_marks.contains(&(base + 2)) {
-                    (b'0' + base + 2) as char
-                } else {
-                    ' '
-                };
-                let text = format!(" {} {} {} ", c0, c1, c2);
-                let fg = if fg_for_bg != Color::Reset {
-                    fg_for_bg
-                } else {
-                    Color::DarkGray
+                Span::styled(blank, Style::default().bg(bg))
+            }
+        }
+        Cell::Empty => {
+            // " 1 2 3 ", one span per digit slot
+            let base = (sub_row * 3 + 1) as u8;
+            let mut spans = vec![Span::styled(" ", Style::default().bg(bg))];
+            for digit in base..base + 3 {
+                let mark = pencil_marks.iter().find(|&&(d, _)| d == digit);
+                let (text, color) = match mark {
+                    Some(&(d, color)) => (format!("{}", d), color),
+                    None => (" ".to_string(), Color::Reset),
                 };
-                Span::styled(text, Style::default().fg(fg).bg(bg))
+                let fg = if fg_for_bg != Color::Reset { fg_for_bg } else { color };
+                spans.push(Span::styled(text, Style::default().fg(fg).bg(bg)));
+                spans.push(Span::styled(" ", Style::default().bg(bg)));
             }
+            return spans;
         }
+    };
+    vec![span]
+}
+
+/// Conflict background, pulsing between bright and dim red since conflicts
+/// were switched on. Only the brightness moves; the hue stays put so the cue
+/// never depends on telling colours apart.
+fn conflict_color(game: &Game) -> Color {
+    let Some(shown_at) = game.conflicts_shown_at else {
+        return Color::Red;
+    };
+    let phase = shown_at.elapsed().as_secs_f32() / CONFLICT_PULSE_SECS;
+    // 1.0 at the start of each cycle, easing down to 0.55 halfway through
+    let wave = (phase * std::f32::consts::TAU).cos() * 0.5 + 0.5;
+    let intensity = 0.55 + 0.45 * wave;
+    let channel = |full: f32| (full * intensity) as u8;
+    Color::Rgb(channel(220.0), channel(40.0), channel(40.0))
+}
+
+/// Overlay a flag marker on the top-left corner of a cell's first sub-row.
+/// That column is always padding, so values and pencil marks stay intact.
+fn with_corner_marker(span: Span<'static>, color: Color) -> [Span<'static>; 2] {
+    let bg = span.style.bg.unwrap_or(Color::Reset);
+    let fg = if bg == color { Color::Black } else { color };
+    let rest: String = span.content.chars().skip(1).collect();
+    [
+        Span::styled("◤", Style::default().fg(fg).bg(bg)),
+        Span::styled(rest, span.style),
+    ]
+}
+
+fn flag_color(flag: CellFlag) -> Color {
+    match flag {
+        CellFlag::Red => Color::LightRed,
+        CellFlag::Green => Color::LightGreen,
+        CellFlag::Blue => Color::LightBlue,
+        CellFlag::Yellow => Color::LightYellow,
     }
 }
 
@@ -1089,26 +2032,39 @@ enum RowKind {
     CellRow(usize, usize),
 }
 
-fn classify_row(visual: u16) -> RowKind {
-    match visual {
-        0 => RowKind::ThickBorder(0),
-        12 => RowKind::ThickBorder(1),
-        24 => RowKind::ThickBorder(2),
-        36 => RowKind::ThickBorder(3),
-        4 | 8 | 16 | 20 | 28 | 32 => RowKind::ThinBorder,
-        _ => {
-            let section = (visual / 12) as usize;
-            let offset = visual % 12;
-            let (cell_in_section, sub_row) = if offset <= 3 {
-                (0, (offset - 1) as usize)
-            } else if offset <= 7 {
-                (1, (offset - 5) as usize)
-            } else {
-                (2, (offset - 9) as usize)
-            };
-            let grid_row = section * 3 + cell_in_section;
-            RowKind::CellRow(grid_row, sub_row)
+/// Space the board takes on screen: the grid, its block border, and the
+/// labels when coordinates are on.
+fn board_width(game: &Game) -> u16 {
+    let labels = if game.settings.coordinates { ROW_LABEL_WIDTH } else { 0 };
+    grid_width(&game.grid) + 2 + labels
+}
+
+fn board_height(game: &Game) -> u16 {
+    let labels = if game.settings.coordinates { COL_LABEL_HEIGHT } else { 0 };
+    grid_height(&game.grid) + 2 + labels
+}
+
+fn grid_width(grid: &GridSpec) -> u16 {
+    grid.size as u16 * (CELL_WIDTH + 1) + 1
+}
+
+fn grid_height(grid: &GridSpec) -> u16 {
+    grid.size as u16 * (CELL_HEIGHT + 1) + 1
+}
+
+/// Border rows are numbered by box boundary: 0 is the top edge and
+/// `size / box_rows` the bottom edge.
+fn classify_row(grid: &GridSpec, visual: u16) -> RowKind {
+    let index = (visual / (CELL_HEIGHT + 1)) as usize;
+    let offset = (visual % (CELL_HEIGHT + 1)) as usize;
+    if offset == 0 {
+        if index.is_multiple_of(grid.box_rows) {
+            RowKind::ThickBorder((index / grid.box_rows) as u8)
+        } else {
+            RowKind::ThinBorder
         }
+    } else {
+        RowKind::CellRow(index, offset - 1)
     }
 }
 
@@ -1118,40 +2074,36 @@ enum ColKind {
     Cell(usize),
 }
 
-fn classify_col(seg: usize) -> ColKind {
-    match seg {
-        0 | 6 | 12 | 18 => ColKind::ThickBorder,
-        2 | 4 | 8 | 10 | 14 | 16 => ColKind::ThinBorder,
-        1 => ColKind::Cell(0),
-        3 => ColKind::Cell(1),
-        5 => ColKind::Cell(2),
-        7 => ColKind::Cell(3),
-        9 => ColKind::Cell(4),
-        11 => ColKind::Cell(5),
-        13 => ColKind::Cell(6),
-        15 => ColKind::Cell(7),
-        17 => ColKind::Cell(8),
-        _ => ColKind::ThinBorder,
+/// Even segments are borders, odd segments are cells.
+fn classify_col(grid: &GridSpec, seg: usize) -> ColKind {
+    if seg % 2 == 1 {
+        ColKind::Cell(seg / 2)
+    } else if (seg / 2).is_multiple_of(grid.box_cols) {
+        ColKind::ThickBorder
+    } else {
+        ColKind::ThinBorder
     }
 }
 
-fn thick_horizontal_line(border_idx: u8) -> Span<'static> {
+fn thick_horizontal_line(grid: &GridSpec, border_idx: u8) -> Span<'static> {
+    let last = (grid.size / grid.box_rows) as u8;
     let (left, thick_cross, thin_cross, right) = match border_idx {
         0 => ('╔', '╦', '╤', '╗'),
-        3 => ('╚', '╩', '╧', '╝'),
+        b if b == last => ('╚', '╩', '╧', '╝'),
         _ => ('╠', '╬', '╪', '╣'),
     };
 
+    let boxes = grid.size / grid.box_cols;
     let mut s = String::with_capacity(80);
     s.push(left);
-    for box_idx in 0..3 {
-        for cell_idx in 0..3 {
+    for box_idx in 0..boxes {
+        for cell_idx in 0..grid.box_cols {
             s.push_str("═══════");
-            if cell_idx < 2 {
+            if cell_idx < grid.box_cols - 1 {
                 s.push(thin_cross);
             }
         }
-        if box_idx < 2 {
+        if box_idx < boxes - 1 {
             s.push(thick_cross);
         }
     }
@@ -1160,17 +2112,18 @@ fn thick_horizontal_line(border_idx: u8) -> Span<'static> {
     Span::styled(s, Style::default().fg(Color::White))
 }
 
-fn thin_horizontal_line() -> Span<'static> {
+fn thin_horizontal_line(grid: &GridSpec) -> Span<'static> {
+    let boxes = grid.size / grid.box_cols;
     let mut s = String::with_capacity(80);
     s.push('║');
-    for box_idx in 0..3 {
-        for cell_idx in 0..3 {
+    for box_idx in 0..boxes {
+        for cell_idx in 0..grid.box_cols {
             s.push_str("───────");
-            if cell_idx < 2 {
+            if cell_idx < grid.box_cols - 1 {
                 s.push('┼');
             }
         }
-        if box_idx < 2 {
+        if box_idx < boxes - 1 {
             s.push('║');
         }
     }
@@ -1187,7 +2140,10 @@ fn draw_info_panel(f: &mut Frame, game: &Game, area: Rect) {
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::White));
 
-    let diff_color = difficulty_color(game.difficulty);
+    let (diff_label, diff_color) = match game.custom_givens {
+        Some(_) => ("Custom", Color::White),
+        None => (game.difficulty.label(), difficulty_color(game.difficulty)),
+    };
 
     let pencil_indicator = if game.pencil_mode {
         Span::styled(
@@ -1201,15 +2157,34 @@ fn draw_info_panel(f: &mut Frame, game: &Game, area: Rect) {
         Span::styled("OFF", Style::default().fg(Color::DarkGray))
     };
 
-    let lines = vec![
+    let flag_indicator = if game.flag_mode {
+        Span::styled(
+            " 1-4 ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled("OFF", Style::default().fg(Color::DarkGray))
+    };
+
+    let grid_label = if game.grid == GridSpec::CLASSIC {
+        String::new()
+    } else {
+        format!(" {}", game.grid.label())
+    };
+
+    let mut lines = vec![
         Line::from(vec![
             Span::styled(" Difficulty: ", Style::default().fg(Color::Gray)),
             Span::styled(
-                game.difficulty.label(),
+                diff_label,
                 Style::default()
                     .fg(diff_color)
                     .add_modifier(Modifier::BOLD),
             ),
+            Span::styled(grid_label, Style::default().fg(Color::White)),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -1246,34 +2221,122 @@ fn draw_info_panel(f: &mut Frame, game: &Game, area: Rect) {
             Span::styled(" Pencil:     ", Style::default().fg(Color::Gray)),
             pencil_indicator,
         ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(" Flags:      ", Style::default().fg(Color::Gray)),
+            flag_indicator,
+        ]),
     ];
 
+    if let Some(seed) = seed_label(game) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Seed:       ", Style::default().fg(Color::Gray)),
+            Span::styled(seed, Style::default().fg(Color::White)),
+        ]));
+    }
+
+    if let Some(count) = game.custom_givens {
+        // Digging can stop short of the count asked for; say so
+        let givens = game.board.iter().flatten().filter(|c| matches!(c, Cell::Given(_))).count();
+        let label = if givens == count {
+            format!("{} (custom)", count)
+        } else {
+            format!("{} (asked {})", givens, count)
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Givens:     ", Style::default().fg(Color::Gray)),
+            Span::styled(label, Style::default().fg(Color::White)),
+        ]));
+    }
+
+    if let Some(ref marathon) = game.marathon {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Marathon:   ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format!("{}/{}", marathon.current(), marathon.length),
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Total:      ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format_secs(marathon.total_secs() + game.get_elapsed_secs()),
+                Style::default().fg(Color::White),
+            ),
+        ]));
+    }
+
+    let offenders = selected_conflicts(game);
+    if !offenders.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Clashes:    ", Style::default().fg(Color::Gray)),
+            Span::styled(format_cells(&offenders), Style::default().fg(Color::Red)),
+        ]));
+    }
+
     let paragraph = Paragraph::new(lines).block(block);
     f.render_widget(paragraph, area);
 }
 
-// ── Hint bar ─────────────────────────────────────────────────────────────────
-
+// ── Hint bar ─────────────────────────────────────────────────────────────────
+
+/// `unicode`, or `ascii` when the player has asked for plain symbols. The
+/// ASCII form keeps every character one column wide so bars stay aligned.
+fn glyph(game: &Game, unicode: &'static str, ascii: &'static str) -> &'static str {
+    if game.settings.ascii_symbols { ascii } else { unicode }
+}
+
+/// What the hint bar says for the active hint, and in which colour
+fn hint_bar_text(game: &Game) -> Option<(String, Color)> {
+    let hint = game.active_hint.as_ref()?;
+    let sep = glyph(game, "│", "|");
+    // Explanations are written with em dashes; those go too
+    let explanation = if game.settings.ascii_symbols {
+        hint.explanation.replace('—', "-")
+    } else {
+        hint.explanation.clone()
+    };
+    let text = match game.hint_stage {
+        _ if hint.technique == HintTechnique::Stuck => (
+            format!(
+                " {} {}  {}  Esc to dismiss",
+                glyph(game, "🧩", "[?]"),
+                explanation,
+                sep
+            ),
+            Color::Yellow,
+        ),
+        HintStage::ShowTechnique => (
+            format!(
+                " {} {}  {}  Press ? again to reveal value, Esc to dismiss",
+                glyph(game, "💡", "[i]"),
+                explanation,
+                sep
+            ),
+            Color::Cyan,
+        ),
+        HintStage::RevealValue => (
+            format!(
+                " {} R{}C{} = {}  {}  Press ? to place it, Esc to dismiss",
+                glyph(game, "✓", "[x]"),
+                hint.target_row + 1,
+                hint.target_col + 1,
+                hint.value,
+                sep
+            ),
+            Color::Green,
+        ),
+    };
+    Some(text)
+}
+
 fn draw_hint_bar(f: &mut Frame, game: &Game, area: Rect) {
-    if let Some(ref hint) = game.active_hint {
-        let (stage_text, stage_color) = match game.hint_stage {
-            HintStage::ShowTechnique => (
-                format!(
-                    " 💡 {}  │  Press ? again to reveal value, Esc to dismiss",
-                    hint.explanation
-                ),
-                Color::Cyan,
-            ),
-            HintStage::RevealValue => (
-                format!(
-                    " ✓ R{}C{} = {}  │  Press ? to place it, Esc to dismiss",
-                    hint.target_row + 1,
-                    hint.target_col + 1,
-                    hint.value
-                ),
-                Color::Green,
-            ),
-        };
+    if let Some((stage_text, stage_color)) = hint_bar_text(game) {
 
         let lines = vec![
             Line::from(""),
@@ -1295,9 +2358,70 @@ fn draw_hint_bar(f: &mut Frame, game: &Game, area: Rect) {
 
 // ── Key hints (bottom status bar) ────────────────────────────────────────────
 
-fn draw_key_hints(f: &mut Frame, area: Rect) {
-    let hints = Line::from(vec![
-        Span::styled(" ←↑↓→", Style::default().fg(Color::Yellow)),
+fn draw_key_hints(f: &mut Frame, game: &Game, area: Rect) {
+    if game.awaiting_report_reason {
+        draw_report_prompt(f, area);
+        return;
+    }
+
+    if game.awaiting_fill_unit {
+        let prompt = Line::from(vec![
+            Span::styled(" Fill candidates for: ", Style::default().fg(Color::White)),
+            Span::styled("r", Style::default().fg(Color::Yellow)),
+            Span::styled(" Row  ", Style::default().fg(Color::Gray)),
+            Span::styled("c", Style::default().fg(Color::Yellow)),
+            Span::styled(" Column  ", Style::default().fg(Color::Gray)),
+            Span::styled("b", Style::default().fg(Color::Yellow)),
+            Span::styled(" Box  ", Style::default().fg(Color::Gray)),
+            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::styled(" Cancel", Style::default().fg(Color::Gray)),
+        ]);
+        let bar = Paragraph::new(prompt).style(Style::default().bg(Color::DarkGray));
+        f.render_widget(bar, area);
+        return;
+    }
+
+    if game.awaiting_lock_digit {
+        let prompt = Line::from(vec![
+            Span::styled(" Focus on digit: ", Style::default().fg(Color::White)),
+            Span::styled(format!("1-{}", game.grid.size), Style::default().fg(Color::Yellow)),
+            Span::styled("  ", Style::default().fg(Color::Gray)),
+            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::styled(" Cancel", Style::default().fg(Color::Gray)),
+        ]);
+        let bar = Paragraph::new(prompt).style(Style::default().bg(Color::DarkGray));
+        f.render_widget(bar, area);
+        return;
+    }
+
+    if let Some(digit) = game.locked_digit {
+        let focus = Line::from(vec![
+            Span::styled(
+                format!(" FOCUS {} ", digit),
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(glyph(game, " ←↑↓→", " Arrows"), Style::default().fg(Color::Yellow)),
+            Span::styled(" Next candidate  ", Style::default().fg(Color::Gray)),
+            Span::styled("Enter", Style::default().fg(Color::Yellow)),
+            Span::styled(format!(" Place {}  ", digit), Style::default().fg(Color::Gray)),
+            Span::styled("1-9", Style::default().fg(Color::Yellow)),
+            Span::styled(" Switch digit  ", Style::default().fg(Color::Gray)),
+            Span::styled("u", Style::default().fg(Color::Yellow)),
+            Span::styled(" Undo  ", Style::default().fg(Color::Gray)),
+            Span::styled("Esc", Style::default().fg(Color::Yellow)),
+            Span::styled(" Unlock", Style::default().fg(Color::Gray)),
+        ]);
+        let bar = Paragraph::new(focus).style(Style::default().bg(Color::DarkGray));
+        f.render_widget(bar, area);
+        return;
+    }
+
+    let mut hints = panel_stand_in(game);
+    hints.extend([
+        Span::styled(glyph(game, " ←↑↓→", " Arrows"), Style::default().fg(Color::Yellow)),
         Span::styled(" Move  ", Style::default().fg(Color::Gray)),
         Span::styled("1-9", Style::default().fg(Color::Yellow)),
         Span::styled(" Place  ", Style::default().fg(Color::Gray)),
@@ -1305,22 +2429,124 @@ fn draw_key_hints(f: &mut Frame, area: Rect) {
         Span::styled(" Erase  ", Style::default().fg(Color::Gray)),
         Span::styled("p", Style::default().fg(Color::Yellow)),
         Span::styled(" Pencil  ", Style::default().fg(Color::Gray)),
+        Span::styled("f", Style::default().fg(Color::Yellow)),
+        Span::styled(" Flag  ", Style::default().fg(Color::Gray)),
+        Span::styled("a", Style::default().fg(Color::Yellow)),
+        Span::styled(" Notes  ", Style::default().fg(Color::Gray)),
+        Span::styled("l", Style::default().fg(Color::Yellow)),
+        Span::styled(" Focus  ", Style::default().fg(Color::Gray)),
         Span::styled("u", Style::default().fg(Color::Yellow)),
         Span::styled(" Undo  ", Style::default().fg(Color::Gray)),
         Span::styled("?", Style::default().fg(Color::Yellow)),
         Span::styled(" Hint  ", Style::default().fg(Color::Gray)),
+        Span::styled("h", Style::default().fg(Color::Yellow)),
+        Span::styled(" Log  ", Style::default().fg(Color::Gray)),
+        Span::styled("g", Style::default().fg(Color::Yellow)),
+        Span::styled(" Peek  ", Style::default().fg(Color::Gray)),
         Span::styled("v", Style::default().fg(Color::Yellow)),
         Span::styled(" Check  ", Style::default().fg(Color::Gray)),
+        Span::styled("n", Style::default().fg(Color::Yellow)),
+        Span::styled(" New  ", Style::default().fg(Color::Gray)),
+        Span::styled("c", Style::default().fg(Color::Yellow)),
+        Span::styled(" Copy seed  ", Style::default().fg(Color::Gray)),
         Span::styled("Spc", Style::default().fg(Color::Yellow)),
         Span::styled(" Pause  ", Style::default().fg(Color::Gray)),
+        Span::styled("i", Style::default().fg(Color::Yellow)),
+        Span::styled(" Panel  ", Style::default().fg(Color::Gray)),
         Span::styled("q", Style::default().fg(Color::Yellow)),
         Span::styled(" Quit", Style::default().fg(Color::Gray)),
     ]);
 
-    let bar = Paragraph::new(hints).style(Style::default().bg(Color::DarkGray));
+    let bar = Paragraph::new(Line::from(hints)).style(Style::default().bg(Color::DarkGray));
+    f.render_widget(bar, area);
+
+    let offenders = selected_conflicts(game);
+    if game.notice.is_some() {
+        draw_notice(f, game, area);
+    } else if !offenders.is_empty() {
+        let note = Paragraph::new(Line::from(Span::styled(
+            format!("conflicts with {} ", format_cells(&offenders)),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )))
+        .alignment(Alignment::Right);
+        f.render_widget(note, area);
+    } else if game.settings.almost_there
+        && let Some(left) = game.almost_solved_remaining()
+    {
+        let noun = if left == 1 { "cell" } else { "cells" };
+        let note = Paragraph::new(Line::from(Span::styled(
+            format!("Almost there — {} {} left! ", left, noun),
+            Style::default().fg(Color::LightGreen),
+        )))
+        .alignment(Alignment::Right);
+        f.render_widget(note, area);
+    }
+}
+
+/// With the side panel hidden, the time and mistakes it would have shown,
+/// to lead the key hints bar. Mistakes only count where the solution is known.
+fn panel_stand_in(game: &Game) -> Vec<Span<'static>> {
+    if game.settings.show_info_panel {
+        return vec![];
+    }
+    let mut spans = vec![Span::styled(
+        format!(" {} ", game.format_time()),
+        Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+    )];
+    if game.solution_known {
+        let color = if game.mistakes > 0 { Color::Red } else { Color::Green };
+        spans.push(Span::styled(
+            format!("{} {} ", glyph(game, "✗", "x"), game.mistakes),
+            Style::default().fg(color),
+        ));
+    }
+    spans.push(Span::styled(glyph(game, "│", "|"), Style::default().fg(Color::Gray)));
+    spans
+}
+
+fn draw_report_prompt(f: &mut Frame, area: Rect) {
+    let mut spans = vec![Span::styled(" Report puzzle: ", Style::default().fg(Color::White))];
+    for (i, reason) in REPORT_REASONS.iter().enumerate() {
+        spans.push(Span::styled(format!("{}", i + 1), Style::default().fg(Color::Yellow)));
+        spans.push(Span::styled(format!(" {}  ", reason), Style::default().fg(Color::Gray)));
+    }
+    spans.push(Span::styled("Esc", Style::default().fg(Color::Yellow)));
+    spans.push(Span::styled(" Cancel", Style::default().fg(Color::Gray)));
+    let bar = Paragraph::new(Line::from(spans)).style(Style::default().bg(Color::DarkGray));
     f.render_widget(bar, area);
 }
 
+/// Peers clashing with the selected cell, once conflicts are being shown and
+/// the selection is one of them.
+fn selected_conflicts(game: &Game) -> Vec<(usize, usize)> {
+    let (r, c) = (game.selected_row, game.selected_col);
+    if !game.show_conflicts || !game.conflicts.contains(&(r, c)) {
+        return vec![];
+    }
+    game.conflict_reason(r, c)
+}
+
+/// "R3C5, R1C2" — 1-based cell coordinates
+fn format_cells(cells: &[(usize, usize)]) -> String {
+    cells
+        .iter()
+        .map(|(r, c)| format!("R{}C{}", r + 1, c + 1))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Right-aligned confirmation on top of the key hints bar
+fn draw_notice(f: &mut Frame, game: &Game, area: Rect) {
+    if let Some(ref notice) = game.notice {
+        let note = Paragraph::new(Line::from(Span::styled(
+            format!("{} ", notice),
+            Style::default().fg(Color::LightGreen),
+        )))
+        .alignment(Alignment::Right);
+        f.render_widget(note, area);
+    }
+}
+
 fn draw_multiplayer_key_hints(f: &mut Frame, game: &Game, area: Rect) {
     // If there's an error message, show it instead of key hints
     if let Some(ref err) = game.error_message {
@@ -1335,6 +2561,56 @@ fn draw_multiplayer_key_hints(f: &mut Frame, game: &Game, area: Rect) {
         return;
     }
 
+    if game.awaiting_report_reason {
+        draw_report_prompt(f, area);
+        return;
+    }
+
+    if let (Some(_), Some(mp)) = (game.reconnect_deadline(), game.multiplayer.as_ref()) {
+        let held = match mp.outbox.len() {
+            0 => String::new(),
+            1 => " — 1 move held".to_string(),
+            n => format!(" — {} moves held", n),
+        };
+        let bar = Paragraph::new(Line::from(Span::styled(
+            format!(" Connection lost — reconnecting…{} ", held),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+        )));
+        f.render_widget(bar, area);
+        return;
+    }
+
+    if let Some(secs) = game.multiplayer.as_ref().and_then(|mp| mp.opponent_grace_remaining()) {
+        let bar = Paragraph::new(Line::from(Span::styled(
+            format!(
+                " Opponent disconnected — waiting for them to return ({}:{:02})… ",
+                secs / 60,
+                secs % 60
+            ),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+        )));
+        f.render_widget(bar, area);
+        return;
+    }
+
+    if let Some(secs) = game.multiplayer.as_ref().and_then(|mp| mp.idle_seconds_remaining()) {
+        let bar = Paragraph::new(Line::from(Span::styled(
+            format!(" Idle — room forfeits in {}s. Make a move to stay in! ", secs),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )));
+        f.render_widget(bar, area);
+        return;
+    }
+
     let pencil_span = if game.pencil_mode {
         Span::styled(
             " PENCIL ",
@@ -1347,9 +2623,10 @@ fn draw_multiplayer_key_hints(f: &mut Frame, game: &Game, area: Rect) {
         Span::styled("", Style::default())
     };
 
-    let hints = Line::from(vec![
+    let mut hints = panel_stand_in(game);
+    hints.extend([
         pencil_span,
-        Span::styled(" ←↑↓→", Style::default().fg(Color::Yellow)),
+        Span::styled(glyph(game, " ←↑↓→", " Arrows"), Style::default().fg(Color::Yellow)),
         Span::styled(" Move  ", Style::default().fg(Color::Gray)),
         Span::styled("1-9", Style::default().fg(Color::Yellow)),
         Span::styled(" Place  ", Style::default().fg(Color::Gray)),
@@ -1357,12 +2634,17 @@ fn draw_multiplayer_key_hints(f: &mut Frame, game: &Game, area: Rect) {
         Span::styled(" Erase  ", Style::default().fg(Color::Gray)),
         Span::styled("p", Style::default().fg(Color::Yellow)),
         Span::styled(" Pencil  ", Style::default().fg(Color::Gray)),
+        Span::styled("!", Style::default().fg(Color::Yellow)),
+        Span::styled(" Report  ", Style::default().fg(Color::Gray)),
+        Span::styled("i", Style::default().fg(Color::Yellow)),
+        Span::styled(" Panel  ", Style::default().fg(Color::Gray)),
         Span::styled("q", Style::default().fg(Color::Yellow)),
         Span::styled(" Forfeit", Style::default().fg(Color::Gray)),
     ]);
 
-    let bar = Paragraph::new(hints).style(Style::default().bg(Color::DarkGray));
+    let bar = Paragraph::new(Line::from(hints)).style(Style::default().bg(Color::DarkGray));
     f.render_widget(bar, area);
+    draw_notice(f, game, area);
 }
 
 // ── Paused screen ────────────────────────────────────────────────────────────
@@ -1373,7 +2655,7 @@ fn draw_paused(f: &mut Frame, game: &Game) {
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
 
-    let popup = center_rect(34, 9, area);
+    let popup = center_rect(34, 10, area);
     f.render_widget(Clear, popup);
 
     let block = Block::bordered()
@@ -1384,7 +2666,7 @@ fn draw_paused(f: &mut Frame, game: &Game) {
     let text = Paragraph::new(vec![
         Line::from(""),
         Line::from(Span::styled(
-            "⏸  PAUSED",
+            glyph(game, "⏸  PAUSED", "||  PAUSED"),
             Style::default()
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
@@ -1405,11 +2687,58 @@ fn draw_paused(f: &mut Frame, game: &Game) {
             ),
             Span::styled(" to resume", Style::default().fg(Color::Gray)),
         ]),
+        Line::from(vec![
+            Span::styled("h", Style::default().fg(Color::Yellow)),
+            Span::styled(" hint log", Style::default().fg(Color::Gray)),
+        ]),
     ])
     .block(block)
     .alignment(Alignment::Center);
 
     f.render_widget(text, popup);
+
+    if game.show_hint_log {
+        draw_hint_log(f, game);
+    }
+}
+
+fn draw_hint_log(f: &mut Frame, game: &Game) {
+    let area = f.area();
+    let popup = center_rect(64, 18, area);
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(format!(" Hints this game ({}) ", game.hint_log.len()))
+        .title_bottom(Line::from(" ↑/↓ scroll · h/Esc close ").right_aligned())
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Magenta));
+
+    let lines: Vec<Line> = if game.hint_log.is_empty() {
+        vec![Line::from(Span::styled(
+            " No hints used yet",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        game.hint_log
+            .iter()
+            .enumerate()
+            .skip(game.hint_log_scroll)
+            .flat_map(|(i, explanation)| {
+                [
+                    Line::from(vec![
+                        Span::styled(format!(" {}. ", i + 1), Style::default().fg(Color::Yellow)),
+                        Span::styled(explanation.clone(), Style::default().fg(Color::White)),
+                    ]),
+                    Line::from(""),
+                ]
+            })
+            .collect()
+    };
+
+    let text = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    f.render_widget(text, popup);
 }
 
 // ── Won screen ───────────────────────────────────────────────────────────────
@@ -1420,7 +2749,13 @@ fn draw_won(f: &mut Frame, game: &Game) {
     let bg = Paragraph::new("").style(Style::default().bg(Color::Black));
     f.render_widget(bg, area);
 
-    let popup = center_rect(40, 13, area);
+    let badge_lines = if game.new_badges.is_empty() {
+        0
+    } else {
+        game.new_badges.len() as u16 + 1
+    };
+    let seed_lines = u16::from(game.seed.is_some());
+    let popup = center_rect(40, 15 + seed_lines + badge_lines, area);
     f.render_widget(Clear, popup);
 
     let block = Block::bordered()
@@ -1428,7 +2763,7 @@ fn draw_won(f: &mut Frame, game: &Game) {
         .border_type(BorderType::Double)
         .style(Style::default().fg(Color::Green));
 
-    let text = Paragraph::new(vec![
+    let mut lines = vec![
         Line::from(""),
         Line::from(Span::styled(
             "CONGRATULATIONS!",
@@ -1469,6 +2804,17 @@ fn draw_won(f: &mut Frame, game: &Game) {
                 Style::default().fg(Color::White),
             ),
         ]),
+        Line::from(vec![
+            Span::styled("  Peeks:      ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format!("{}", game.peeks),
+                Style::default().fg(if game.peeks == 0 {
+                    Color::White
+                } else {
+                    Color::Red
+                }),
+            ),
+        ]),
         Line::from(vec![
             Span::styled("  Difficulty: ", Style::default().fg(Color::Gray)),
             Span::styled(
@@ -1478,23 +2824,46 @@ fn draw_won(f: &mut Frame, game: &Game) {
                     .add_modifier(Modifier::BOLD),
             ),
         ]),
+    ];
+    // Shared as a number, so a friend can play the same board with '#'
+    if let Some(seed) = seed_label(game) {
+        lines.push(Line::from(vec![
+            Span::styled("  Seed:       ", Style::default().fg(Color::Gray)),
+            Span::styled(seed, Style::default().fg(Color::White)),
+        ]));
+    }
+    if !game.new_badges.is_empty() {
+        lines.push(Line::from(""));
+        for badge in &game.new_badges {
+            lines.push(Line::from(Span::styled(
+                format!("{} New badge: {}", glyph(game, "★", "*"), badge.name()),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )));
+        }
+    }
+    lines.extend([
         Line::from(""),
         Line::from(Span::styled(
             "Press Enter for new game, Q to quit",
             Style::default().fg(Color::DarkGray),
         )),
-    ])
-    .block(block)
-    .alignment(Alignment::Center);
+        Line::from(Span::styled(
+            "H to race this solve's ghost",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ]);
+    let text = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center);
 
     f.render_widget(text, popup);
 }
 
 // ── Quit confirmation dialog ─────────────────────────────────────────────────
 
-fn draw_quit_confirm(f: &mut Frame) {
+fn draw_quit_confirm(f: &mut Frame, game: &Game) {
     let area = f.area();
-    let popup = center_rect(36, 7, area);
+    let popup = center_rect(36, if game.auth_polling { 8 } else { 7 }, area);
 
     f.render_widget(Clear, popup);
 
@@ -1503,12 +2872,20 @@ fn draw_quit_confirm(f: &mut Frame) {
         .border_type(BorderType::Rounded)
         .style(Style::default().fg(Color::Red));
 
-    let text = Paragraph::new(vec![
+    let mut lines = vec![
         Line::from(""),
         Line::from(Span::styled(
             "Are you sure you want to quit?",
             Style::default().fg(Color::White),
         )),
+    ];
+    if game.auth_polling {
+        lines.push(Line::from(Span::styled(
+            "Your login will be cancelled.",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines.extend([
         Line::from(""),
         Line::from(vec![
             Span::styled(
@@ -1533,6 +2910,104 @@ fn draw_quit_confirm(f: &mut Frame) {
             ),
             Span::styled(" No", Style::default().fg(Color::Gray)),
         ]),
+    ]);
+
+    let text = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center);
+
+    f.render_widget(text, popup);
+}
+
+fn draw_reroll_confirm(f: &mut Frame) {
+    let popup = center_rect(36, 7, f.area());
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" New puzzle? ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Yellow));
+
+    let lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Drop this game for a fresh one?",
+            Style::default().fg(Color::White),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                "Y",
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("/", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "Enter",
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" Yes   ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "Any key",
+                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" No", Style::default().fg(Color::Gray)),
+        ]),
+    ];
+
+    let text = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center);
+    f.render_widget(text, popup);
+}
+
+/// Quitting a rated match is a forfeit, so say what it costs.
+fn draw_forfeit_confirm(f: &mut Frame, game: &Game) {
+    let area = f.area();
+    let popup = center_rect(44, 8, area);
+
+    f.render_widget(Clear, popup);
+
+    let block = Block::bordered()
+        .title(" Forfeit? ")
+        .border_type(BorderType::Rounded)
+        .style(Style::default().fg(Color::Red));
+
+    let stakes = match game.forfeit_cost() {
+        Some(cost) => format!("Forfeiting will cost you ~{} rating", cost),
+        None => "Forfeiting counts as a loss".to_string(),
+    };
+
+    let text = Paragraph::new(vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Leave the match and forfeit?",
+            Style::default().fg(Color::White),
+        )),
+        Line::from(Span::styled(stakes, Style::default().fg(Color::Yellow))),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                "Y",
+                Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("/", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "Enter",
+                Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" Forfeit   ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "Any key",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" Keep playing", Style::default().fg(Color::Gray)),
+        ]),
     ])
     .block(block)
     .alignment(Alignment::Center);
@@ -1542,6 +3017,26 @@ fn draw_quit_confirm(f: &mut Frame) {
 
 // ── Layout helpers ───────────────────────────────────────────────────────────
 
+/// How many recent games the profile screen's rating sparkline covers
+const SPARKLINE_GAMES: usize = 20;
+
+/// One block character per value, scaled between the slice's min and max.
+fn sparkline(values: &[i32]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+        return String::new();
+    };
+    let span = (max - min).max(1) as usize;
+    values
+        .iter()
+        .map(|&v| BARS[(v - min) as usize * (BARS.len() - 1) / span])
+        .collect()
+}
+
+fn format_secs(secs: u64) -> String {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
 fn center_rect(width: u16, height: u16, area: Rect) -> Rect {
     let vert = Layout::vertical([
         Constraint::Min(0),
@@ -1560,6 +3055,59 @@ fn center_rect(width: u16, height: u16, area: Rect) -> Rect {
     horiz[1]
 }
 
+/// "Connected as <name>", with the player's standing once `/me` has answered
+fn connected_as_line(name: &str, standing: Option<String>) -> Line<'static> {
+    let mut spans = vec![
+        Span::styled(" Connected as ", Style::default().fg(Color::Gray)),
+        Span::styled(
+            name.to_string(),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ),
+    ];
+    if let Some(standing) = standing {
+        spans.push(Span::styled(standing, Style::default().fg(Color::Gray)));
+    }
+    Line::from(spans)
+}
+
+fn mode_label(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Race => "Race",
+        GameMode::Shared => "Shared",
+    }
+}
+
+/// A dot per difficulty under the selector, the chosen one filled, so it's
+/// plain how far along the scale it sits. Blank for a custom givens count.
+fn difficulty_position(game: &Game) -> Line<'static> {
+    if game.custom_givens.is_some() {
+        return Line::from("");
+    }
+    let dots: Vec<Span> = Difficulty::all()
+        .iter()
+        .map(|&d| {
+            if d == game.difficulty {
+                Span::styled(glyph(game, "● ", "* "), Style::default().fg(difficulty_color(d)))
+            } else {
+                Span::styled(glyph(game, "○ ", ". "), Style::default().fg(Color::DarkGray))
+            }
+        })
+        .collect();
+    Line::from(dots)
+}
+
+/// The puzzle's seed with what else a friend has to pick before typing it in
+/// after '#': the same number at another difficulty or grid is another board
+fn seed_label(game: &Game) -> Option<String> {
+    let seed = game.seed?;
+    let setting = match game.custom_givens {
+        Some(count) => format!("{} givens", count),
+        None if game.grid == GridSpec::CLASSIC => game.difficulty.label().to_string(),
+        None => format!("{} {}", game.grid.label(), game.difficulty.label()),
+    };
+    Some(format!("{} #{}", setting, seed))
+}
+
 fn difficulty_color(d: Difficulty) -> Color {
     match d {
         Difficulty::Easy => Color::Green,
@@ -1568,3 +3116,155 @@ fn difficulty_color(d: Difficulty) -> Color {
         Difficulty::Expert => Color::Red,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_symbols_keep_the_hint_bar_plain() {
+        let mut game = Game::new();
+        game.settings.ascii_symbols = true;
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.request_hint();
+        let (text, _) = hint_bar_text(&game).unwrap();
+        assert!(text.is_ascii(), "{text}");
+
+        game.request_hint();
+        let (text, _) = hint_bar_text(&game).unwrap();
+        assert!(text.starts_with(" [x] R"), "{text}");
+        assert!(text.is_ascii(), "{text}");
+
+        game.settings.ascii_symbols = false;
+        let (text, _) = hint_bar_text(&game).unwrap();
+        assert!(text.starts_with(" ✓ R"), "{text}");
+    }
+
+    #[test]
+    fn hiding_the_panel_centres_the_grid_alone() {
+        let mut game = Game::new();
+        game.start_new_game();
+        game.finish_pending_puzzle();
+        game.settings.show_info_panel = false;
+        let area = Rect::new(0, 0, 80, 30);
+        let (grid, panel) = grid_and_panel(&game, area, 28);
+        let right_margin = area.width - grid.right();
+        assert!(grid.x.abs_diff(right_margin) <= 1, "{grid:?}");
+        assert_eq!(panel.area(), 0);
+        assert!(!panel_stand_in(&game).is_empty());
+
+        // Narrower than grid and panel together, and still draws
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(44, 30)).unwrap();
+        terminal.draw(|f| draw_playing(f, &game)).unwrap();
+    }
+
+    #[test]
+    fn seeds_say_what_they_were_drawn_at() {
+        let mut game = Game::new();
+        assert_eq!(seed_label(&game), None);
+        game.difficulty = Difficulty::Medium;
+        game.seed = Some(12345);
+        assert_eq!(seed_label(&game).as_deref(), Some("Medium #12345"));
+        game.grid = game.grid.next();
+        assert_eq!(
+            seed_label(&game),
+            Some(format!("{} Medium #12345", game.grid.label()))
+        );
+        game.set_custom_givens(30);
+        assert_eq!(seed_label(&game).as_deref(), Some("30 givens #12345"));
+    }
+
+    #[test]
+    fn menu_dots_mark_the_chosen_difficulty() {
+        let mut game = Game::new();
+        game.difficulty = Difficulty::Hard;
+        let dots: Vec<String> = difficulty_position(&game)
+            .spans
+            .iter()
+            .map(|s| s.content.trim().to_string())
+            .collect();
+        assert_eq!(dots.len(), Difficulty::all().len());
+        assert_eq!(dots.iter().position(|d| d == "●"), Some(2));
+        assert_eq!(dots.iter().filter(|d| *d == "○").count(), dots.len() - 1);
+
+        game.custom_givens = Some(30);
+        assert!(difficulty_position(&game).spans.is_empty());
+    }
+
+    #[test]
+    fn the_menu_says_who_we_are_connected_as() {
+        let mut game = Game::new();
+        game.state = GameState::MultiplayerMenu;
+        game.connected_as = Some("alice".to_string());
+        let screen = |game: &Game| {
+            let mut terminal =
+                ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 30)).unwrap();
+            terminal.draw(|f| draw_multiplayer_menu(f, game)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            buffer.content.iter().map(|c| c.symbol()).collect::<String>()
+        };
+        // The profile fetch failed: just the name
+        assert!(screen(&game).contains("Connected as alice "));
+
+        game.my_profile = Some(crate::net::offline::me());
+        game.my_profile.as_mut().unwrap().profile.username = "alice".to_string();
+        let text = screen(&game);
+        assert!(text.contains("Connected as alice (rating 1200, rank #4)"), "{text}");
+        assert_eq!(text.matches("Connected as").count(), 1);
+    }
+
+    #[test]
+    fn the_profile_charts_recent_ratings() {
+        let mut game = Game::new();
+        game.state = GameState::Profile;
+        game.my_profile = Some(crate::net::offline::me());
+        game.rating_history = vec![1200, 1216, 1190, 1240];
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|f| draw(f, &game)).unwrap();
+        let text: String =
+            terminal.backend().buffer().content.iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("Rating over your last 4 games"), "{text}");
+        assert!(text.contains(&format!("{}  +40", sparkline(&game.rating_history))));
+        assert!(text.contains("Low 1190 · high 1240"));
+    }
+
+    #[test]
+    fn the_leaderboard_shows_what_each_player_plays_most() {
+        let mut game = Game::new();
+        game.state = GameState::Leaderboard;
+        game.leaderboard_entries = crate::net::offline::leaderboard();
+        game.leaderboard_entries[1].favorite_difficulty = None;
+        game.leaderboard_entries[1].favorite_mode = None;
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|f| draw(f, &game)).unwrap();
+        let rows: Vec<String> = terminal
+            .backend()
+            .buffer()
+            .content
+            .chunks(80)
+            .map(|row| row.iter().map(|c| c.symbol()).collect())
+            .collect();
+        let row = |name: &str| rows.iter().find(|r| r.contains(name)).unwrap().clone();
+        assert!(row("grid_wizard").contains("48/12    Medium Race"), "{rows:#?}");
+        assert!(row("naked_pair").contains("31/17    -"), "{rows:#?}");
+    }
+
+    #[test]
+    fn fog_rooms_shade_the_opponents_quarter() {
+        let mut game = Game::new();
+        let (board, solution) = sudoku_core::puzzle::generate_puzzle(Difficulty::Easy);
+        game.start_multiplayer_game(board, Some(solution), GameMode::Race, "opp".into(), 1200);
+        let mp = game.multiplayer.as_mut().unwrap();
+        mp.opponent_filled = 27;
+        assert_eq!(opponent_bar(mp, 81).1, " 27/81");
+
+        mp.opponent_quarter = Some(2);
+        let (bar, label) = opponent_bar(mp, 81);
+        assert_eq!(bar.chars().count(), 20);
+        assert_eq!(bar.chars().filter(|&c| c == '▒').count(), 5);
+        assert_eq!(label, " ~50-75%");
+    }
+}